@@ -0,0 +1,176 @@
+//! Compares `decode_rgb8_parallel` with the serial `decode_rgb8` on a large LZW-compressed RGB
+//! image of many strips. Run with `cargo bench --bench parallel_decode`, adding
+//! `--features rayon` to decode on rayon's thread pool rather than one thread per CPU. Prints the
+//! fastest of several runs of each, and the speedup: about 1x on a single CPU, where the two
+//! do the same work, and bounded above by the number of CPUs.
+
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+use tiffy::baseline::decode::{decode_rgb8, decode_rgb8_parallel};
+use tiffy::baseline::tags;
+use tiffy::compression::decompress_lzw;
+use tiffy::lowlevel::{read_ifds_auto, IFDField, LittleEndian, TiffBuilder, IFD};
+
+const WIDTH: u32 = 2048;
+const HEIGHT: u32 = 2048;
+const ROWS_PER_STRIP: u32 = 32;
+const RUNS: u32 = 5;
+
+/// Compress `input` with TIFF-style LZW, as `decompress_lzw` expects it.
+fn compress_lzw(input: &[u8]) -> Vec<u8> {
+    const CLEAR_CODE: u32 = 256;
+    const EOI_CODE: u32 = 257;
+
+    let mut writer = CodeWriter::default();
+    let mut dictionary = std::collections::HashMap::new();
+    let mut width = 9;
+    writer.write(CLEAR_CODE, width);
+    let mut current: Option<u32> = None;
+    for &byte in input {
+        let prefix = match current {
+            None => {
+                current = Some(u32::from(byte));
+                continue;
+            }
+            Some(prefix) => prefix,
+        };
+        if let Some(&code) = dictionary.get(&(prefix, byte)) {
+            current = Some(code);
+            continue;
+        }
+        writer.write(prefix, width);
+        let next = 258 + dictionary.len() as u32;
+        dictionary.insert((prefix, byte), next);
+        // Widen one code early, as libtiff does
+        if next + 1 >= 1 << width {
+            width += 1;
+        }
+        // Start over before the codes would need more than 12 bits
+        if next == 4093 {
+            writer.write(CLEAR_CODE, width);
+            dictionary.clear();
+            width = 9;
+        }
+        current = Some(u32::from(byte));
+    }
+    if let Some(code) = current {
+        writer.write(code, width);
+        let next = 258 + dictionary.len() as u32;
+        if next + 1 >= 1 << width {
+            width += 1;
+        }
+    }
+    writer.write(EOI_CODE, width);
+    writer.finish()
+}
+
+/// Packs variable-width codes, most significant bit first.
+#[derive(Default)]
+struct CodeWriter {
+    output: Vec<u8>,
+    buffer: u32,
+    buffered_bits: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u32, width: u32) {
+        self.buffer = (self.buffer << width) | code;
+        self.buffered_bits += width;
+        while self.buffered_bits >= 8 {
+            self.buffered_bits -= 8;
+            self.output.push((self.buffer >> self.buffered_bits) as u8);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.buffered_bits > 0 {
+            self.output
+                .push((self.buffer << (8 - self.buffered_bits)) as u8);
+        }
+        self.output
+    }
+}
+
+/// A smooth, noisy RGB test pattern, so that it compresses neither too well nor too badly.
+fn pixels() -> Vec<u8> {
+    let mut state = 0x2545_F491_u32;
+    let mut pixels = Vec::with_capacity((WIDTH * HEIGHT * 3) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let noise = (state & 3) as u8;
+            pixels.extend_from_slice(&[(x / 8) as u8 ^ noise, (y / 8) as u8, ((x + y) / 16) as u8]);
+        }
+    }
+    pixels
+}
+
+/// Time the fastest of `RUNS` calls of `decode`.
+fn fastest(mut decode: impl FnMut() -> Vec<u8>) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(decode());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let pixels = pixels();
+    let strip_len = (WIDTH * ROWS_PER_STRIP * 3) as usize;
+    let strips: Vec<Vec<u8>> = pixels.chunks(strip_len).map(compress_lzw).collect();
+    for (strip, raw) in strips.iter().zip(pixels.chunks(strip_len)) {
+        assert_eq!(decompress_lzw(strip).unwrap(), raw);
+    }
+
+    let mut ifd = IFD::new();
+    let entries = [
+        (tags::IMAGE_WIDTH, IFDField::Long(vec![WIDTH].into())),
+        (tags::IMAGE_LENGTH, IFDField::Long(vec![HEIGHT].into())),
+        (tags::BITS_PER_SAMPLE, IFDField::Short(vec![8; 3].into())),
+        (tags::COMPRESSION, IFDField::Short(vec![5].into())),
+        (
+            tags::PHOTOMETRIC_INTERPRETATION,
+            IFDField::Short(vec![2].into()),
+        ),
+        (tags::SAMPLES_PER_PIXEL, IFDField::Short(vec![3].into())),
+        (
+            tags::ROWS_PER_STRIP,
+            IFDField::Long(vec![ROWS_PER_STRIP].into()),
+        ),
+    ];
+    ifd.entries.extend(entries);
+    let buf = TiffBuilder::new().add_page(ifd, strips).build_le().unwrap();
+    let (_, ifds) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+    let ifd = &ifds.into_classic().unwrap()[0];
+
+    let serial = fastest(|| {
+        decode_rgb8::<LittleEndian, _>(&mut Cursor::new(&buf), ifd)
+            .unwrap()
+            .2
+    });
+    let parallel = fastest(|| decode_rgb8_parallel::<LittleEndian>(&buf, ifd).unwrap().2);
+    assert_eq!(
+        decode_rgb8_parallel::<LittleEndian>(&buf, ifd).unwrap().2,
+        pixels
+    );
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!(
+        "{}x{} RGB, {} LZW strips, {} CPUs",
+        WIDTH,
+        HEIGHT,
+        HEIGHT / ROWS_PER_STRIP,
+        threads
+    );
+    println!("decode_rgb8:          {:?}", serial);
+    println!("decode_rgb8_parallel: {:?}", parallel);
+    println!(
+        "speedup:              {:.2}x",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
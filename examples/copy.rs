@@ -1,11 +1,11 @@
-use failure::Fallible;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom};
 use tiffy::baseline::tags;
+use tiffy::errors::TiffError;
 use tiffy::lowlevel::{IFDField, MetadataReader, MetadataWriter, NativeEndian};
 
 /// Rewrite (copy) an image's tags and data
-fn main() -> Fallible<()> {
+fn main() -> Result<(), TiffError> {
     // Parse arguments
     let mut args = std::env::args();
     let (source_path, dest_path) = match (args.next(), args.next(), args.next()) {
@@ -37,7 +37,7 @@ fn main() -> Fallible<()> {
         // For each original strip offset and length, copy the data to the output image
         for (offset, length) in strip_offsets.iter().zip(strip_lengths.iter()) {
             source_file.seek(SeekFrom::Start(*offset as u64))?;
-            let strip_offset = dest_file.seek(SeekFrom::Current(0))? as u32;
+            let strip_offset = dest_file.stream_position()? as u32;
             std::io::copy(
                 &mut source_file.by_ref().take(*length as u64),
                 &mut dest_file,
@@ -53,10 +53,9 @@ fn main() -> Fallible<()> {
         // Careful about blindly copying tags between files, Unrecognized tags will write out the
         // _literal_ information from the tag instead of reordering it and setting a new pointer in
         // the file. This library _does_ give you enough rope to hang yourself if you so choose.
-        new_ifd.entries.retain(|_, field| match field {
-            IFDField::Unrecognized { .. } => false,
-            _ => true,
-        });
+        new_ifd
+            .entries
+            .retain(|_, field| !matches!(field, IFDField::Unrecognized { .. }));
 
         // Set the strip offsets and lengths on the output image (they are different from the original)
         *new_ifd.entries.get_mut(&tags::STRIP_OFFSETS).unwrap() =
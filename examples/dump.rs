@@ -0,0 +1,21 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use tiffy::errors::TiffError;
+use tiffy::lowlevel::dump_tiff;
+
+/// Print the structure of an image's header and IFDs
+fn main() -> Result<(), TiffError> {
+    // Parse arguments
+    let mut args = std::env::args();
+    let path = match (args.next(), args.next()) {
+        (Some(_), Some(path)) => path,
+        (Some(program_name), _) => {
+            eprintln!("Usage: {} <source>", program_name);
+            return Ok(());
+        }
+        _ => panic!("Program has no path"),
+    };
+
+    let mut file = BufReader::new(File::open(path)?);
+    dump_tiff(&mut file, &mut io::stdout().lock())
+}
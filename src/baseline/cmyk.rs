@@ -0,0 +1,120 @@
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, photometric::Photometric, tags},
+    errors::TiffError,
+    lowlevel::RawIFD,
+};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Names given to the inks of a separated image with four inks and no InkNames tag.
+pub const DEFAULT_INK_NAMES: [&str; 4] = ["Cyan", "Magenta", "Yellow", "Black"];
+
+/// The inks a separated image's samples are printed with, read by `ink_info`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InkInfo {
+    /// Number of inks, from the NumberOfInks tag.
+    pub number_of_inks: u16,
+    /// Name of each ink in the order of the samples, from the InkNames tag. Empty if the image
+    /// has no InkNames tag and other than four inks.
+    pub ink_names: Vec<String>,
+}
+
+/// Read the NumberOfInks and InkNames tags of `ifd`, returning `None` unless it is a separated
+/// (CMYK) image. A missing NumberOfInks defaults to the number of names, or 4 if there are none,
+/// and four inks without names are named by `DEFAULT_INK_NAMES`. Fails if the image has no
+/// PhotometricInterpretation tag, or InkNames isn't valid ASCII.
+#[cfg(feature = "std")]
+pub fn ink_info<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<InkInfo>, TiffError> {
+    if Photometric::from_ifd::<E, R>(reader, ifd)? != Photometric::Cmyk {
+        return Ok(None);
+    }
+    let ink_names = match ifd.get(tags::INK_NAMES) {
+        Some(field) => Some(field.as_ascii_multi::<E, R>(reader)?),
+        None => None,
+    };
+    let default_count = ink_names.as_ref().map_or(4, Vec::len) as u64;
+    let number_of_inks =
+        read_optional_value::<E, R>(reader, ifd, tags::NUMBER_OF_INKS, default_count)? as u16;
+    let ink_names = ink_names.unwrap_or_else(|| match number_of_inks {
+        4 => DEFAULT_INK_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        _ => Vec::new(),
+    });
+    Ok(Some(InkInfo {
+        number_of_inks,
+        ink_names,
+    }))
+}
+
+/// Convert interleaved 8-bit CMYK samples in `buf` to interleaved 8-bit RGB, using the naive
+/// formula `R = 255 * (1 - C) * (1 - K)` and likewise for green and blue. This ignores how the
+/// inks actually combine on paper, so the colors are only approximate; callers needing accurate
+/// colors should decode the CMYK samples themselves and convert them with the image's ICC
+/// profile, as read by `read_icc_profile`. Any bytes after the last whole pixel are ignored.
+pub fn cmyk_to_rgb(buf: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(buf.len() / 4 * 3);
+    for pixel in buf.chunks_exact(4) {
+        let black = 255 - u16::from(pixel[3]);
+        rgb.extend(pixel[..3].iter().map(|&ink| {
+            // Rounded to nearest
+            (((255 - u16::from(ink)) * black + 127) / 255) as u8
+        }));
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_inks_to_plausible_rgb() {
+        let cmyk = [
+            0, 0, 0, 0, // paper
+            0, 0, 0, 255, // black
+            255, 0, 0, 0, // cyan
+            0, 255, 255, 0, // red
+            0, 0, 0, 128, // half black
+            0, 128, 255, 64, // a dark orange
+            1, 2, // a partial pixel
+        ];
+        let expected = [
+            [255, 255, 255],
+            [0, 0, 0],
+            [0, 255, 255],
+            [255, 0, 0],
+            [127, 127, 127],
+            [191, 95, 0],
+        ];
+        assert_eq!(cmyk_to_rgb(&cmyk), expected.concat());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decode_rgb8_converts_cmyk_strip() {
+        use crate::baseline::decode::decode_rgb8;
+        use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+        use crate::lowlevel::{Endianness, LittleEndian};
+        use std::io::Cursor;
+
+        let strip = vec![0, 255, 255, 0, 255, 255, 0, 0];
+        let (buf, ifd) =
+            single_page_file(image_ifd(2, 1, &[8; 4], 5), vec![strip], Endianness::Little);
+
+        assert_eq!(
+            decode_rgb8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap(),
+            (2, 1, vec![255, 0, 0, 0, 0, 255])
+        );
+    }
+}
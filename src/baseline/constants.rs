@@ -81,3 +81,10 @@ pub mod photometic_interpretation {
         }
     }
 }
+
+/// Resolution unit magic
+pub mod resolution_unit {
+    pub const NONE: u16 = 0x0001;
+    pub const INCH: u16 = 0x0002;
+    pub const CENTIMETER: u16 = 0x0003;
+}
@@ -0,0 +1,231 @@
+#[cfg(feature = "std")]
+use crate::baseline::tags;
+use crate::errors::FieldExtractionError;
+#[cfg(feature = "std")]
+use crate::errors::TiffError;
+#[cfg(feature = "std")]
+use crate::lowlevel::RawIFD;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// A date and time as stored in the DateTime field, in the format `YYYY:MM:DD HH:MM:SS`.
+/// TIFF records no time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Parse a date and time in the format `YYYY:MM:DD HH:MM:SS`, without the NUL terminator.
+    /// Fails if `text` is in any other format, or any of the values are out of range.
+    pub fn parse(text: &str) -> Result<Self, FieldExtractionError> {
+        let invalid = |reason| FieldExtractionError::InvalidDateTime { reason };
+        let bytes = text.as_bytes();
+        if bytes.len() != 19 {
+            return Err(invalid("expected 19 characters"));
+        }
+        if bytes[4] != b':' || bytes[7] != b':' || bytes[10] != b' ' {
+            return Err(invalid("date is not separated by colons"));
+        }
+        if bytes[13] != b':' || bytes[16] != b':' {
+            return Err(invalid("time is not separated by colons"));
+        }
+
+        let number = |start: usize, len: usize| {
+            bytes[start..start + len]
+                .iter()
+                .try_fold(0u16, |value, &digit| match digit {
+                    b'0'..=b'9' => Some(value * 10 + u16::from(digit - b'0')),
+                    _ => None,
+                })
+                .ok_or(invalid("expected a digit"))
+        };
+        let date_time = Self {
+            year: number(0, 4)?,
+            month: number(5, 2)? as u8,
+            day: number(8, 2)? as u8,
+            hour: number(11, 2)? as u8,
+            minute: number(14, 2)? as u8,
+            second: number(17, 2)? as u8,
+        };
+
+        if !(1..=12).contains(&date_time.month) {
+            return Err(invalid("month out of range"));
+        }
+        if !(1..=31).contains(&date_time.day) {
+            return Err(invalid("day out of range"));
+        }
+        // Allow for leap seconds
+        if date_time.hour > 23 || date_time.minute > 59 || date_time.second > 60 {
+            return Err(invalid("time out of range"));
+        }
+        Ok(date_time)
+    }
+
+    /// Convert to chrono's NaiveDateTime, which like TIFF has no time zone. A leap second is
+    /// represented as chrono does, by a second of 59 with 1000 or more milliseconds. Fails if
+    /// the date doesn't exist, such as the 30th of February.
+    #[cfg(feature = "chrono")]
+    pub fn to_naive_datetime(&self) -> Result<chrono::NaiveDateTime, FieldExtractionError> {
+        let invalid = |reason| FieldExtractionError::InvalidDateTime { reason };
+        let date = chrono::NaiveDate::from_ymd_opt(
+            i32::from(self.year),
+            u32::from(self.month),
+            u32::from(self.day),
+        )
+        .ok_or(invalid("date does not exist"))?;
+        let (second, milli) = match self.second {
+            60 => (59, 1000),
+            second => (second, 0),
+        };
+        let time = chrono::NaiveTime::from_hms_milli_opt(
+            u32::from(self.hour),
+            u32::from(self.minute),
+            u32::from(second),
+            milli,
+        )
+        .ok_or(invalid("time out of range"))?;
+        Ok(date.and_time(time))
+    }
+}
+
+/// Shows the date and time in the format it is stored in, `YYYY:MM:DD HH:MM:SS`.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Read the DateTime field of `ifd`, the date and time the image was created. Returns `None` if
+/// the field is missing or empty. Fails if it is not a valid date and time.
+#[cfg(feature = "std")]
+pub fn read_datetime<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<DateTime>, TiffError> {
+    let text = match ifd.get(tags::DATE_TIME) {
+        Some(field) => field.as_ascii::<E, R>(reader)?,
+        None => return Ok(None),
+    };
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(DateTime::parse(&text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        let date_time = DateTime::parse("2024:02:29 23:59:60").unwrap();
+        assert_eq!(
+            date_time,
+            DateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 23,
+                minute: 59,
+                second: 60,
+            }
+        );
+        assert_eq!(date_time.to_string(), "2024:02:29 23:59:60");
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        for (text, reason) in [
+            ("2024:02:29 23:59", "expected 19 characters"),
+            ("2024-02-29 23:59:59", "date is not separated by colons"),
+            ("2024:02:29 23-59-59", "time is not separated by colons"),
+            ("2024:0x:29 23:59:59", "expected a digit"),
+            ("2024:13:29 23:59:59", "month out of range"),
+            ("2024:02:00 23:59:59", "day out of range"),
+            ("2024:02:29 24:00:00", "time out of range"),
+        ] {
+            match DateTime::parse(text) {
+                Err(FieldExtractionError::InvalidDateTime { reason: actual }) => {
+                    assert_eq!(actual, reason, "{}", text)
+                }
+                other => panic!("expected InvalidDateTime for {}, got {:?}", text, other),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_datetime_reads_field_or_none() {
+        use crate::lowlevel::tiff_builder::single_page_file;
+        use crate::lowlevel::{Endianness, IFDField, LittleEndian, IFD};
+        use std::io::Cursor;
+
+        let read = |text: Option<&str>| {
+            let mut ifd = IFD::new();
+            ifd.entries
+                .insert(tags::IMAGE_WIDTH, IFDField::Long(vec![1].into()));
+            if let Some(text) = text {
+                let field = IFDField::Ascii(vec![text.to_owned()].into());
+                ifd.entries.insert(tags::DATE_TIME, field);
+            }
+            let (buf, ifd) = single_page_file(ifd, Vec::new(), Endianness::Little);
+            read_datetime::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd)
+        };
+        assert_eq!(
+            read(Some("1999:12:31 08:00:01")).unwrap(),
+            Some(DateTime::parse("1999:12:31 08:00:01").unwrap())
+        );
+        assert_eq!(read(None).unwrap(), None);
+        assert_eq!(read(Some("")).unwrap(), None);
+        assert!(matches!(
+            read(Some("1999:12:31")),
+            Err(TiffError::Field(
+                FieldExtractionError::InvalidDateTime { .. }
+            ))
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn converts_to_chrono() {
+        use chrono::{NaiveDate, Timelike};
+
+        let date_time = DateTime::parse("2023:07:04 12:30:45").unwrap();
+        assert_eq!(
+            date_time.to_naive_datetime().unwrap(),
+            NaiveDate::from_ymd_opt(2023, 7, 4)
+                .unwrap()
+                .and_hms_opt(12, 30, 45)
+                .unwrap()
+        );
+
+        let leap = DateTime::parse("2016:12:31 23:59:60").unwrap();
+        let leap = leap.to_naive_datetime().unwrap();
+        assert_eq!((leap.second(), leap.nanosecond()), (59, 1_000_000_000));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn rejects_nonexistent_date_for_chrono() {
+        let date_time = DateTime::parse("2023:02:30 00:00:00").unwrap();
+        assert!(matches!(
+            date_time.to_naive_datetime(),
+            Err(FieldExtractionError::InvalidDateTime {
+                reason: "date does not exist"
+            })
+        ));
+    }
+}
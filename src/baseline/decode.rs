@@ -0,0 +1,1227 @@
+use crate::baseline::ifd_values::read_optional_value;
+use crate::baseline::{
+    cmyk::cmyk_to_rgb,
+    dimensions::image_dimensions,
+    fill_order::{reverse_bits_in_place, FillOrder},
+    photometric::Photometric,
+    repack::planar_to_chunky,
+    samples::{bits_per_sample, samples_per_pixel, ExtraSamples, PlanarConfig, SampleFormat},
+    strips::{rows_per_strip, StripInfo},
+    tags,
+    tiles::TileInfo,
+    ycbcr::{ycbcr_to_rgb, YCbCrParams},
+};
+use crate::compression::jpeg::with_jpeg_tables;
+use crate::compression::{
+    apply_predictor_float, apply_predictor_horizontal, read_jpeg_tables, Compression,
+    DecompressParams, FaxOptions,
+};
+use crate::errors::{CompressionError, ImageDataError, TiffError};
+use crate::lowlevel::{raw_field_values::read_bytes_at, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::borrow::Cow;
+use std::io::{Cursor, Seek};
+#[cfg(not(feature = "rayon"))]
+use std::thread;
+
+/// Decode an RGB image with 8 bits per sample, uncompressed or compressed with PackBits, LZW,
+/// Deflate or (with the `jpeg` feature) JPEG, and stored in either strips or tiles. Returns its
+/// width, height and pixels as interleaved RGB samples, row by row. YCbCr images are converted
+/// to RGB, but only if their chroma isn't subsampled or they are JPEG-compressed, and so are
+/// CMYK images with four samples, approximately, as `cmyk_to_rgb` does. Fails if `ifd`
+/// describes any other kind of image.
+pub fn decode_rgb8<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    decode_rgb8_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode an RGB image as `decode_rgb8` does, bounded in size by `limits`.
+pub fn decode_rgb8_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let photometric = check_color_format::<E, R>(reader, ifd)?;
+    let ycbcr = read_ycbcr_params::<E, R>(reader, ifd, photometric)?;
+    let samples = color_samples(photometric);
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, samples, 8, limits)?;
+    Ok((width, height, convert_to_rgb(data, photometric, ycbcr)))
+}
+
+/// Decode an RGB image as `decode_rgb8` does, from `buf` holding the whole file, decompressing
+/// its strips or tiles concurrently: on rayon's thread pool with the `rayon` feature, or on one
+/// thread per available CPU without it. Only decompression is spread across threads, so the
+/// speedup over `decode_rgb8` is at most the number of CPUs; none is gained on a single CPU, as
+/// the `parallel_decode` benchmark shows. Fails as `decode_rgb8` does, or with
+/// `TiffError::OffsetOutOfBounds` if a strip or tile lies outside `buf`.
+pub fn decode_rgb8_parallel<E: ByteOrder>(
+    buf: &[u8],
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    decode_rgb8_parallel_with_limits::<E>(buf, ifd, DecodeLimits::default())
+}
+
+/// Decode an RGB image as `decode_rgb8_parallel` does, bounded in size by `limits`.
+pub fn decode_rgb8_parallel_with_limits<E: ByteOrder>(
+    buf: &[u8],
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let mut reader = Cursor::new(buf);
+    let photometric = check_color_format::<E, _>(&mut reader, ifd)?;
+    let ycbcr = read_ycbcr_params::<E, _>(&mut reader, ifd, photometric)?;
+    let samples = color_samples(photometric);
+    let layout =
+        ImageLayout::from_ifd::<E, _>(&mut reader, ifd, samples, 8, limits.max_decoded_len)?;
+
+    let raw_chunks = (0..layout.chunk_count())
+        .map(|index| {
+            let (offset, len) = (layout.offsets[index], layout.byte_counts[index]);
+            offset
+                .checked_add(len)
+                .and_then(|end| buf.get(offset as usize..end as usize))
+                .ok_or(TiffError::OffsetOutOfBounds {
+                    offset,
+                    file_len: buf.len() as u64,
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let chunks = decode_chunks_parallel::<E>(&layout, &raw_chunks)?;
+    let data = layout.assemble(&chunks);
+    Ok((
+        layout.width,
+        layout.height,
+        convert_to_rgb(data, photometric, ycbcr),
+    ))
+}
+
+/// Decompress `raw_chunks`, every strip or tile of `layout` in order, on rayon's thread pool,
+/// returning them in the same order.
+#[cfg(feature = "rayon")]
+fn decode_chunks_parallel<E: ByteOrder>(
+    layout: &ImageLayout,
+    raw_chunks: &[&[u8]],
+) -> Result<Vec<Vec<u8>>, TiffError> {
+    use rayon::prelude::*;
+
+    raw_chunks
+        .par_iter()
+        .enumerate()
+        .map(|(index, raw)| layout.decode_chunk::<E>(index, raw))
+        .collect()
+}
+
+/// Decompress `raw_chunks`, every strip or tile of `layout` in order, on one thread per
+/// available CPU, returning them in the same order.
+#[cfg(not(feature = "rayon"))]
+fn decode_chunks_parallel<E: ByteOrder>(
+    layout: &ImageLayout,
+    raw_chunks: &[&[u8]],
+) -> Result<Vec<Vec<u8>>, TiffError> {
+    // Give each thread a contiguous run of strips or tiles, so they come back in order
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let per_thread = raw_chunks.len().div_ceil(threads).max(1);
+    let chunks = thread::scope(|scope| {
+        let handles: Vec<_> = raw_chunks
+            .chunks(per_thread)
+            .enumerate()
+            .map(|(run, raws)| {
+                scope.spawn(move || {
+                    raws.iter()
+                        .enumerate()
+                        .map(|(i, raw)| layout.decode_chunk::<E>(run * per_thread + i, raw))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("decoding thread panicked"))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+/// Decode an RGB image as `decode_rgb8` does, or an RGBA image whose fourth sample is alpha
+/// according to its ExtraSamples tag. Returns its width, height and pixels as interleaved RGBA
+/// samples, row by row, with unassociated (straight) alpha: premultiplied color samples are
+/// divided by their alpha, and images without alpha are made opaque.
+pub fn decode_rgba8<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    decode_rgba8_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode an RGB or RGBA image as `decode_rgba8` does, bounded in size by `limits`.
+pub fn decode_rgba8_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let alpha = match samples_per_pixel::<E, R>(reader, ifd)? {
+        4 => ExtraSamples::from_ifd::<E, R>(reader, ifd)?
+            .first()
+            .copied()
+            .filter(|extra| extra.is_alpha()),
+        _ => None,
+    };
+    let alpha = match alpha {
+        Some(alpha) => alpha,
+        None => {
+            // Adding alpha makes the pixels a third larger
+            let (width, height) = image_dimensions::<E, R>(reader, ifd)?;
+            limits.check((u64::from(width) * u64::from(height)).saturating_mul(4))?;
+            let (width, height, rgb) = decode_rgb8_with_limits::<E, R>(reader, ifd, limits)?;
+            let mut rgba = Vec::with_capacity(rgb.len() / 3 * 4);
+            for pixel in rgb.chunks_exact(3) {
+                rgba.extend_from_slice(pixel);
+                rgba.push(u8::MAX);
+            }
+            return Ok((width, height, rgba));
+        }
+    };
+
+    check_format::<E, R>(
+        reader,
+        ifd,
+        &[Photometric::Rgb],
+        4,
+        8,
+        SampleFormat::Unsigned,
+    )?;
+    let (width, height, mut rgba) = read_image_data::<E, R>(reader, ifd, 4, 8, limits)?;
+    if alpha == ExtraSamples::AssociatedAlpha {
+        for pixel in rgba.chunks_exact_mut(4) {
+            let alpha = u32::from(pixel[3]);
+            for sample in &mut pixel[..3] {
+                *sample = match alpha {
+                    0 => 0,
+                    alpha => ((u32::from(*sample) * 255 + alpha / 2) / alpha).min(255) as u8,
+                };
+            }
+        }
+    }
+    Ok((width, height, rgba))
+}
+
+/// Decode a grayscale (WhiteIsZero or BlackIsZero) image with one 8-bit sample per pixel,
+/// compressed and stored as for `decode_rgb8`. Returns its width, height and pixels row by row,
+/// inverted if necessary so that larger values are always brighter.
+pub fn decode_gray8<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    decode_gray8_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a grayscale image as `decode_gray8` does, bounded in size by `limits`.
+pub fn decode_gray8_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let photometric = check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 8, SampleFormat::Unsigned)?;
+    let (width, height, mut data) = read_image_data::<E, R>(reader, ifd, 1, 8, limits)?;
+    if photometric == Photometric::WhiteIsZero {
+        data.iter_mut().for_each(|sample| *sample = !*sample);
+    }
+    Ok((width, height, data))
+}
+
+/// Decode a grayscale image with one 16-bit sample per pixel, as `decode_gray8` does. Samples
+/// are read in the byte order `E` of the file.
+pub fn decode_gray16<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u16>), TiffError> {
+    decode_gray16_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a grayscale image as `decode_gray16` does, bounded in size by `limits`.
+pub fn decode_gray16_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u16>), TiffError> {
+    let photometric = check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 16, SampleFormat::Unsigned)?;
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, 1, 16, limits)?;
+    let mut samples = vec![0; data.len() / 2];
+    E::read_u16_into(&data, &mut samples);
+    if photometric == Photometric::WhiteIsZero {
+        samples.iter_mut().for_each(|sample| *sample = !*sample);
+    }
+    Ok((width, height, samples))
+}
+
+/// Decode a grayscale image with one 32-bit IEEE floating point sample per pixel, such as a
+/// GeoTIFF elevation model, compressed and stored as for `decode_rgb8`. Returns its width, height
+/// and samples row by row. Samples are read in the byte order `E` of the file, and returned as
+/// stored, even for WhiteIsZero images.
+pub fn decode_f32<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<f32>), TiffError> {
+    decode_f32_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a floating point image as `decode_f32` does, bounded in size by `limits`.
+pub fn decode_f32_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<f32>), TiffError> {
+    check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 32, SampleFormat::Float)?;
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, 1, 32, limits)?;
+    let mut samples = vec![0.0; data.len() / 4];
+    E::read_f32_into(&data, &mut samples);
+    Ok((width, height, samples))
+}
+
+/// Decode a grayscale image with one 16-bit two's complement signed sample per pixel, such as
+/// a GeoTIFF elevation model, as `decode_f32` does. Fails unless SampleFormat says the samples
+/// are signed, so that unsigned data is never reinterpreted.
+pub fn decode_i16<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<i16>), TiffError> {
+    decode_i16_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a signed image as `decode_i16` does, bounded in size by `limits`.
+pub fn decode_i16_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<i16>), TiffError> {
+    check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 16, SampleFormat::Signed)?;
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, 1, 16, limits)?;
+    let mut samples = vec![0; data.len() / 2];
+    E::read_i16_into(&data, &mut samples);
+    Ok((width, height, samples))
+}
+
+/// Decode a grayscale image with one 32-bit two's complement signed sample per pixel, as
+/// `decode_i16` does.
+pub fn decode_i32<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<i32>), TiffError> {
+    decode_i32_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a signed image as `decode_i32` does, bounded in size by `limits`.
+pub fn decode_i32_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<i32>), TiffError> {
+    check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 32, SampleFormat::Signed)?;
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, 1, 32, limits)?;
+    let mut samples = vec![0; data.len() / 4];
+    E::read_i32_into(&data, &mut samples);
+    Ok((width, height, samples))
+}
+
+/// Decode the strips of the image described by `ifd` one at a time, calling `f` with the index
+/// of each strip, the row of the image it starts at, and its decoded rows, so that only one
+/// strip is held in memory at once. Strips are decompressed and have any predictor undone as
+/// `decode_gray8` and the other decoders do, but are otherwise passed on as stored: each row
+/// starts on a byte boundary, and samples keep the byte order `E`. The strips of a planar
+/// image come one plane after another, with their starting rows counted within the plane.
+/// Stops at the first error returned by `f`. Fails with `ImageDataError::NotStripped` if the
+/// image is tiled, or `ImageDataError::UnsupportedFormat` if its samples differ in size.
+pub fn for_each_decoded_strip<E, R, F>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    mut f: F,
+) -> Result<(), TiffError>
+where
+    E: ByteOrder,
+    R: ReadBytesExt + Seek,
+    F: FnMut(usize, u32, &[u8]) -> Result<(), TiffError>,
+{
+    let (samples, bits) = uniform_sample_size::<E, R>(reader, ifd)?;
+    let layout = ImageLayout::from_ifd::<E, R>(reader, ifd, samples, bits, MAX_DECODED_LEN)?;
+    let rows_per_strip = match layout.chunking {
+        Chunking::Strips { rows_per_strip } => rows_per_strip,
+        Chunking::Tiles(_) => return Err(ImageDataError::NotStripped.into()),
+    };
+    for index in 0..layout.chunk_count() {
+        let raw = read_bytes_at(reader, layout.offsets[index], layout.byte_counts[index])?;
+        let strip = layout.decode_chunk::<E>(index, &raw)?;
+        let first_row = (index % layout.chunks_per_plane()) as u32 * rows_per_strip;
+        f(index, first_row, &strip)?;
+    }
+    Ok(())
+}
+
+/// Number of bytes the image data of `ifd` takes once decompressed, as passed to
+/// `for_each_decoded_strip`: before any conversion, with each row starting on a byte boundary.
+/// Fails with `ImageDataError::ImageTooLarge` if the whole image, or any one strip or tile, is
+/// more than `max_bytes`, so that images too large to decode can be rejected before anything is
+/// allocated for them. Fails as `for_each_decoded_strip` does if its samples differ in size.
+pub fn decoded_len<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    max_bytes: u64,
+) -> Result<u64, TiffError> {
+    let (samples, bits) = uniform_sample_size::<E, R>(reader, ifd)?;
+    ImageLayout::from_ifd::<E, R>(reader, ifd, samples, bits, max_bytes)?.check_len(max_bytes)
+}
+
+/// Read the number of samples in each pixel of `ifd`, and the number of bits in each sample.
+/// Fails with `ImageDataError::UnsupportedFormat` if the samples differ in size, or have more
+/// than 64 bits.
+fn uniform_sample_size<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(usize, u8), TiffError> {
+    let samples = samples_per_pixel::<E, R>(reader, ifd)?;
+    let bits = bits_per_sample::<E, R>(reader, ifd)?;
+    let first_bits = bits.first().copied().unwrap_or(1);
+    if let Some(&other_bits) = bits.iter().find(|&&bits| bits != first_bits || bits > 64) {
+        return Err(ImageDataError::UnsupportedFormat {
+            photometric: Photometric::from_ifd::<E, R>(reader, ifd)?.as_u16(),
+            samples_per_pixel: samples,
+            bits_per_sample: other_bits,
+        }
+        .into());
+    }
+    Ok((usize::from(samples), first_bits as u8))
+}
+
+/// Decode a bilevel (WhiteIsZero or BlackIsZero) image with one 1-bit sample per pixel,
+/// compressed as for `decode_rgb8` or with any of the CCITT fax schemes. Returns its width,
+/// height and pixels row by row, one byte per pixel, black as 0 and white as 255. The padding
+/// bits at the end of each row are dropped.
+pub fn decode_bilevel<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    decode_bilevel_with_limits::<E, R>(reader, ifd, DecodeLimits::default())
+}
+
+/// Decode a bilevel image as `decode_bilevel` does, bounded in size by `limits`.
+pub fn decode_bilevel_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let photometric = check_format::<E, R>(reader, ifd, GRAYSCALE, 1, 1, SampleFormat::Unsigned)?;
+    // One byte per pixel may not fit where one bit per pixel did
+    let (width, height) = image_dimensions::<E, R>(reader, ifd)?;
+    let pixel_count = u64::from(width) * u64::from(height);
+    limits.check(pixel_count)?;
+
+    let (width, height, data) = read_image_data::<E, R>(reader, ifd, 1, 1, limits)?;
+    // Brightness of pixels whose bits are 0 and 1
+    let (zero, one) = match photometric {
+        Photometric::WhiteIsZero => (255, 0),
+        _ => (0, 255),
+    };
+
+    let row_len = (width as usize).div_ceil(8);
+    let mut pixels = Vec::with_capacity(pixel_count as usize);
+    for row in data.chunks_exact(row_len.max(1)).take(height as usize) {
+        pixels.extend((0..width as usize).map(|x| {
+            if row[x / 8] & (0x80 >> (x % 8)) == 0 {
+                zero
+            } else {
+                one
+            }
+        }));
+    }
+    Ok((width, height, pixels))
+}
+
+/// Photometric interpretations of grayscale images.
+const GRAYSCALE: &[Photometric] = &[Photometric::WhiteIsZero, Photometric::BlackIsZero];
+
+/// Fail unless the image described by `ifd` has one of the `photometrics`, and pixels of
+/// `samples_per_pixel` samples of `bits_per_sample` bits each in `sample_format`. Returns its
+/// photometric interpretation.
+fn check_format<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    photometrics: &[Photometric],
+    expected_samples_per_pixel: u16,
+    expected_bits_per_sample: u16,
+    expected_sample_format: SampleFormat,
+) -> Result<Photometric, TiffError> {
+    let photometric = Photometric::from_ifd::<E, R>(reader, ifd)?;
+    let samples = samples_per_pixel::<E, R>(reader, ifd)?;
+    let bits = bits_per_sample::<E, R>(reader, ifd)?
+        .into_iter()
+        .find(|&bits| bits != expected_bits_per_sample)
+        .unwrap_or(expected_bits_per_sample);
+    if !photometrics.contains(&photometric)
+        || samples != expected_samples_per_pixel
+        || bits != expected_bits_per_sample
+    {
+        return Err(ImageDataError::UnsupportedFormat {
+            photometric: photometric.as_u16(),
+            samples_per_pixel: samples,
+            bits_per_sample: bits,
+        }
+        .into());
+    }
+    let sample_format = SampleFormat::from_ifd::<E, R>(reader, ifd)?;
+    if sample_format != expected_sample_format {
+        return Err(ImageDataError::UnsupportedSampleFormat {
+            sample_format: sample_format.as_u16(),
+        }
+        .into());
+    }
+
+    Ok(photometric)
+}
+
+/// Check that `ifd` holds an 8-bit unsigned image `decode_rgb8` can convert to RGB, with three
+/// samples per pixel or four for CMYK. Returns its photometric interpretation.
+fn check_color_format<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Photometric, TiffError> {
+    let samples = color_samples(Photometric::from_ifd::<E, R>(reader, ifd)?);
+    check_format::<E, R>(
+        reader,
+        ifd,
+        &[Photometric::Rgb, Photometric::YCbCr, Photometric::Cmyk],
+        samples as u16,
+        8,
+        SampleFormat::Unsigned,
+    )
+}
+
+/// Number of samples per pixel of a `photometric` image decoded by `decode_rgb8`.
+fn color_samples(photometric: Photometric) -> usize {
+    match photometric {
+        Photometric::Cmyk => 4,
+        _ => 3,
+    }
+}
+
+/// Convert decoded samples of a `photometric` image to RGB, undoing the YCbCr encoding `ycbcr`
+/// read by `read_ycbcr_params` if there is one.
+fn convert_to_rgb(
+    mut data: Vec<u8>,
+    photometric: Photometric,
+    ycbcr: Option<YCbCrParams>,
+) -> Vec<u8> {
+    if let Some(params) = ycbcr {
+        ycbcr_to_rgb(
+            &mut data,
+            params.coefficients,
+            &params.reference_black_white,
+        );
+    }
+    match photometric {
+        Photometric::Cmyk => cmyk_to_rgb(&data),
+        _ => data,
+    }
+}
+
+/// Read how the samples of `ifd` are encoded if `photometric` is YCbCr, or `None` otherwise,
+/// or if the image is JPEG-compressed, as the JPEG decoder converts it to RGB itself. Fails if
+/// the chroma is subsampled, which isn't supported.
+fn read_ycbcr_params<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    photometric: Photometric,
+) -> Result<Option<YCbCrParams>, TiffError> {
+    if photometric != Photometric::YCbCr
+        || Compression::from_ifd::<E, R>(reader, ifd)? == Compression::Jpeg
+    {
+        return Ok(None);
+    }
+    let params = YCbCrParams::from_ifd::<E, R>(reader, ifd)?;
+    params.check_not_subsampled()?;
+    Ok(Some(params))
+}
+
+/// Read and decompress all of the image data of `ifd`, from its strips or tiles, into rows of
+/// pixels of `samples_per_pixel` samples of `bits_per_sample` bits each, undoing any predictor.
+/// Each row starts on a byte boundary. Returns the image width, height and data. Fails with
+/// `ImageDataError::ImageTooLarge` before reading any of it if it would exceed `limits`.
+pub(crate) fn read_image_data<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    samples_per_pixel: usize,
+    bits_per_sample: u8,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let layout = ImageLayout::from_ifd::<E, R>(
+        reader,
+        ifd,
+        samples_per_pixel,
+        bits_per_sample,
+        limits.max_decoded_len,
+    )?;
+    let chunks = (0..layout.chunk_count())
+        .map(|index| {
+            let raw = read_bytes_at(reader, layout.offsets[index], layout.byte_counts[index])?;
+            layout.decode_chunk::<E>(index, &raw)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((layout.width, layout.height, layout.assemble(&chunks)))
+}
+
+/// Most bytes an image or any of its strips or tiles may take once decoded, the largest
+/// allocation possible.
+pub(crate) const MAX_DECODED_LEN: u64 = isize::MAX as u64;
+
+/// Bounds on the size of a decoded image, so that a small untrusted file can't claim
+/// dimensions which make decoding allocate more memory than the caller can spare. The default
+/// allows anything which could be allocated at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Most bytes the decoded image may take, both as decompressed and as returned, and the
+    /// most any one of its strips or tiles may take once decompressed. Larger images fail with
+    /// `ImageDataError::ImageTooLarge` before anything is allocated for them. Values beyond
+    /// the largest allocation possible are treated as that.
+    pub max_decoded_len: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_decoded_len: MAX_DECODED_LEN,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Fail with `ImageDataError::ImageTooLarge` if `bytes` is more than these limits allow.
+    fn check(&self, bytes: u64) -> Result<(), TiffError> {
+        let limit = self.max_decoded_len.min(MAX_DECODED_LEN);
+        match bytes {
+            bytes if bytes > limit => Err(ImageDataError::ImageTooLarge { bytes, limit }.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How an image's data is divided into strips or tiles, and how to decompress them and
+/// reassemble the image.
+struct ImageLayout {
+    width: u32,
+    height: u32,
+    /// Samples in each pixel of a strip or tile: all of them for chunky data, or one for planar
+    /// data.
+    samples_per_pixel: usize,
+    /// Number of planes, each stored in its own strips or tiles, one after another.
+    planes: usize,
+    bits_per_sample: u8,
+    compression: Compression,
+    predictor: u16,
+    fill_order: FillOrder,
+    fax_options: FaxOptions,
+    /// The JPEGTables shared by every strip or tile of a JPEG-compressed image, if there are
+    /// any.
+    jpeg_tables: Option<Vec<u8>>,
+    /// File position of each strip or tile.
+    offsets: Vec<u64>,
+    /// Length in bytes of each strip or tile, after compression.
+    byte_counts: Vec<u64>,
+    chunking: Chunking,
+}
+
+/// Whether an image is divided into strips or tiles.
+enum Chunking {
+    Strips { rows_per_strip: u32 },
+    Tiles(TileInfo),
+}
+
+impl ImageLayout {
+    /// Read the layout of the image data of `ifd`, which has pixels of `samples_per_pixel`
+    /// samples of `bits_per_sample` bits each. Fails if the number of strips doesn't match the
+    /// image, or there are too few tiles to cover it, or with `ImageDataError::ImageTooLarge` if
+    /// it or any of its strips or tiles would take more than `max_len` bytes once decoded.
+    fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+        samples_per_pixel: usize,
+        bits_per_sample: u8,
+        max_len: u64,
+    ) -> Result<Self, TiffError> {
+        let (width, height) = image_dimensions::<E, R>(reader, ifd)?;
+        let compression = Compression::from_ifd::<E, R>(reader, ifd)?;
+        let predictor = read_optional_value::<E, R>(reader, ifd, tags::PREDICTOR, 1)? as u16;
+        let fill_order = FillOrder::from_ifd::<E, R>(reader, ifd)?;
+        let fax_options = FaxOptions::from_ifd::<E, R>(reader, ifd)?;
+        let jpeg_tables = match compression {
+            Compression::Jpeg => read_jpeg_tables::<E, R>(reader, ifd)?,
+            _ => None,
+        };
+        let (planes, samples_per_pixel) = match PlanarConfig::from_ifd::<E, R>(reader, ifd)? {
+            PlanarConfig::Chunky => (1, samples_per_pixel),
+            PlanarConfig::Planar if bits_per_sample.is_multiple_of(8) => (samples_per_pixel, 1),
+            // Interleaving samples smaller than a byte isn't supported
+            PlanarConfig::Planar => {
+                return Err(ImageDataError::UnsupportedPlanarConfiguration {
+                    planar_configuration: PlanarConfig::Planar.as_u16(),
+                }
+                .into())
+            }
+        };
+
+        let (offsets, byte_counts, chunking) = if ifd.get(tags::TILE_OFFSETS).is_none() {
+            let StripInfo {
+                offsets,
+                byte_counts,
+            } = StripInfo::from_ifd::<E, R>(reader, ifd)?;
+            let rows_per_strip = rows_per_strip::<E, R>(reader, ifd)?;
+            (offsets, byte_counts, Chunking::Strips { rows_per_strip })
+        } else {
+            let mut tiles = TileInfo::from_ifd::<E, R>(reader, ifd)?;
+            // Tiles are placed in the image a whole number of bytes apart
+            let tile_row_bits =
+                u64::from(tiles.tile_width) * samples_per_pixel as u64 * u64::from(bits_per_sample);
+            if !tile_row_bits.is_multiple_of(8) {
+                return Err(ImageDataError::InvalidTileSize {
+                    width: tiles.tile_width,
+                    length: tiles.tile_length,
+                }
+                .into());
+            }
+            let expected = tiles.tile_count() * planes;
+            if tiles.offsets.len() < expected {
+                return Err(ImageDataError::TooFewTiles {
+                    expected,
+                    actual: tiles.offsets.len(),
+                }
+                .into());
+            }
+            let mut offsets = core::mem::take(&mut tiles.offsets);
+            let mut byte_counts = core::mem::take(&mut tiles.byte_counts);
+            offsets.truncate(expected);
+            byte_counts.truncate(expected);
+            (offsets, byte_counts, Chunking::Tiles(tiles))
+        };
+
+        let layout = Self {
+            width,
+            height,
+            samples_per_pixel,
+            planes,
+            bits_per_sample,
+            compression,
+            predictor,
+            fill_order,
+            fax_options,
+            jpeg_tables,
+            offsets,
+            byte_counts,
+            chunking,
+        };
+        // The lengths computed while decoding must fit in a `usize`, even on 32-bit targets
+        layout.check_len(max_len.min(MAX_DECODED_LEN))?;
+        Ok(layout)
+    }
+
+    /// Length in bytes of the whole image once decoded. Fails with
+    /// `ImageDataError::ImageTooLarge` if it, or the length of any one decompressed strip or
+    /// tile, is more than `limit`.
+    fn check_len(&self, limit: u64) -> Result<u64, TiffError> {
+        let (chunk_width, chunk_rows) = match &self.chunking {
+            Chunking::Strips { rows_per_strip } => (self.width, *rows_per_strip),
+            Chunking::Tiles(tiles) => (tiles.tile_width, tiles.tile_length),
+        };
+        // Saturating, as anything which overflows is far beyond any limit
+        let image_len = self
+            .row_len_u64(self.width)
+            .saturating_mul(u64::from(self.height))
+            .saturating_mul(self.planes as u64);
+        let chunk_len = self
+            .row_len_u64(chunk_width)
+            .saturating_mul(u64::from(chunk_rows));
+        match image_len.max(chunk_len) {
+            bytes if bytes > limit => Err(ImageDataError::ImageTooLarge { bytes, limit }.into()),
+            _ => Ok(image_len),
+        }
+    }
+
+    /// Number of strips or tiles covering the image, in all of its planes.
+    fn chunk_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Number of strips or tiles covering each plane of the image.
+    fn chunks_per_plane(&self) -> usize {
+        self.chunk_count() / self.planes
+    }
+
+    /// Length in bytes of a row of `width` pixels. This fits in a `usize` for the widths of the
+    /// image and its strips or tiles, as checked by `from_ifd`.
+    fn row_len(&self, width: u32) -> usize {
+        self.row_len_u64(width) as usize
+    }
+
+    /// Length in bytes of a row of `width` pixels, computed without overflowing.
+    fn row_len_u64(&self, width: u32) -> u64 {
+        (u64::from(width) * self.samples_per_pixel as u64 * u64::from(self.bits_per_sample))
+            .div_ceil(8)
+    }
+
+    /// Width in pixels and number of rows of the strip or tile at `index`, once decompressed.
+    fn chunk_size(&self, index: usize) -> (u32, u32) {
+        let index = index % self.chunks_per_plane();
+        match &self.chunking {
+            // The last strip may hold fewer rows
+            Chunking::Strips { rows_per_strip } => (
+                self.width,
+                (*rows_per_strip).min(self.height - index as u32 * rows_per_strip),
+            ),
+            // Tiles at the edges are padded to the full size
+            Chunking::Tiles(tiles) => (tiles.tile_width, tiles.tile_length),
+        }
+    }
+
+    /// Decompress `raw`, the strip or tile at `index`, and undo any predictor. Data stored
+    /// least significant bit first is reversed beforehand, as the decompressors expect the
+    /// most significant bit first, and JPEG data has any JPEGTables spliced in.
+    fn decode_chunk<E: ByteOrder>(&self, index: usize, raw: &[u8]) -> Result<Vec<u8>, TiffError> {
+        let raw = match self.fill_order {
+            FillOrder::MsbFirst => Cow::Borrowed(raw),
+            FillOrder::LsbFirst => {
+                let mut bytes = raw.to_vec();
+                reverse_bits_in_place(&mut bytes);
+                Cow::Owned(bytes)
+            }
+        };
+        let raw = match self.compression {
+            Compression::Jpeg => with_jpeg_tables(self.jpeg_tables.as_deref(), &raw)?,
+            _ => Cow::Borrowed(&*raw),
+        };
+
+        let (width, rows) = self.chunk_size(index);
+        let params = DecompressParams {
+            width: width as usize,
+            rows: rows as usize,
+            fax_options: self.fax_options,
+        };
+        let mut chunk =
+            self.compression
+                .decompress(&raw, self.row_len(width) * rows as usize, &params)?;
+        match self.predictor {
+            1 => {}
+            2 => apply_predictor_horizontal::<E>(
+                &mut chunk,
+                width as usize,
+                self.samples_per_pixel,
+                self.bits_per_sample,
+            )?,
+            3 => apply_predictor_float::<E>(
+                &mut chunk,
+                width as usize,
+                self.samples_per_pixel,
+                self.bits_per_sample,
+            )?,
+            predictor => return Err(CompressionError::UnknownPredictor { predictor }.into()),
+        }
+        Ok(chunk)
+    }
+
+    /// Arrange the decoded strips or tiles, in order, into the rows of the whole image,
+    /// interleaving the samples of planar data.
+    fn assemble(&self, chunks: &[Vec<u8>]) -> Vec<u8> {
+        // An empty image has no strips or tiles to divide between planes
+        if self.planes == 1 || chunks.is_empty() {
+            return self.assemble_plane(chunks);
+        }
+
+        let planes: Vec<u8> = chunks
+            .chunks(self.chunks_per_plane())
+            .flat_map(|plane| self.assemble_plane(plane))
+            .collect();
+        planar_to_chunky(
+            &planes,
+            self.width as usize,
+            self.height as usize,
+            self.planes,
+            usize::from(self.bits_per_sample / 8),
+        )
+    }
+
+    /// Arrange the decoded strips or tiles of one plane, in order, into its rows.
+    fn assemble_plane(&self, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let tiles = match &self.chunking {
+            Chunking::Strips { .. } => return chunks.concat(),
+            Chunking::Tiles(tiles) => tiles,
+        };
+
+        let (image_row_len, tile_row_len) =
+            (self.row_len(self.width), self.row_len(tiles.tile_width));
+        let mut data = vec![0; image_row_len * self.height as usize];
+        for (index, tile) in chunks.iter().enumerate() {
+            let (col, row) = (
+                index as u32 % tiles.tiles_across,
+                index as u32 / tiles.tiles_across,
+            );
+
+            // Crop the padding of tiles at the right and bottom edges
+            let start = col as usize * tile_row_len;
+            let len = tile_row_len.min(image_row_len - start);
+            let first_row = row * tiles.tile_length;
+            let rows = tiles.tile_length.min(self.height - first_row) as usize;
+            for (tile_row, image_row) in tile
+                .chunks(tile_row_len)
+                .take(rows)
+                .zip(data[first_row as usize * image_row_len..].chunks_mut(image_row_len))
+            {
+                image_row[start..start + len].copy_from_slice(&tile_row[..len]);
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TiffDocument;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{BigEndian, Endianness, IFDField, LittleEndian};
+
+    /// A little-endian file holding one uncompressed image of `samples` 8-bit samples per
+    /// pixel (or one 1-bit sample if `samples` is 0), in a single strip of `strip`, along with
+    /// its IFD.
+    fn file(width: u32, height: u32, samples: u16, strip: Vec<u8>) -> (Vec<u8>, RawIFD) {
+        file_with(width, height, samples, Vec::new(), vec![strip])
+    }
+
+    /// A file as made by `file`, but with the fields of `extra` added or replacing its own, and
+    /// in the strips `strips`.
+    fn file_with(
+        width: u32,
+        height: u32,
+        samples: u16,
+        extra: Vec<(u16, IFDField)>,
+        strips: Vec<Vec<u8>>,
+    ) -> (Vec<u8>, RawIFD) {
+        let (photometric, bits) = match samples {
+            0 => (1, vec![1]),
+            1 => (1, vec![8]),
+            _ => (2, vec![8; usize::from(samples)]),
+        };
+        let mut ifd = image_ifd(width, height, &bits, photometric);
+        ifd.entries.extend(extra);
+        single_page_file(ifd, strips, Endianness::Little)
+    }
+
+    fn limits(max_decoded_len: u64) -> DecodeLimits {
+        DecodeLimits { max_decoded_len }
+    }
+
+    fn assert_too_large<T: core::fmt::Debug>(result: Result<T, TiffError>, expected: u64) {
+        match result {
+            Err(TiffError::ImageData(ImageDataError::ImageTooLarge { bytes, .. })) => {
+                assert_eq!(bytes, expected)
+            }
+            other => panic!("expected ImageTooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_image_within_limits() {
+        let (buf, ifd) = file(4, 2, 1, (0..8).collect());
+        let decoded =
+            decode_gray8_with_limits::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, limits(8));
+        assert_eq!(decoded.unwrap(), (4, 2, (0..8).collect()));
+    }
+
+    #[test]
+    fn custom_limit_rejects_larger_image() {
+        let (buf, ifd) = file(4, 2, 1, (0..8).collect());
+        let decoded =
+            decode_gray8_with_limits::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, limits(7));
+        assert_too_large(decoded, 8);
+        assert!(decode_gray8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).is_ok());
+
+        let (buf, ifd) = file(2, 1, 3, vec![0; 6]);
+        let decoded = decode_rgb8_parallel_with_limits::<LittleEndian>(&buf, &ifd, limits(5));
+        assert_too_large(decoded, 6);
+    }
+
+    /// A file in the byte order `E` holding one grayscale image of two samples of `bits` bits,
+    /// given as `samples` in that byte order, with `sample_format` as its SampleFormat tag if
+    /// there is one. Returns it with its IFD.
+    fn signed_file<E: ByteOrder>(
+        bits: u16,
+        sample_format: Option<u16>,
+        samples: Vec<u8>,
+    ) -> (Vec<u8>, RawIFD) {
+        let mut ifd = image_ifd(2, 1, &[bits], 1);
+        if let Some(sample_format) = sample_format {
+            let field = IFDField::Short(vec![sample_format].into());
+            ifd.entries.insert(tags::SAMPLE_FORMAT, field);
+        }
+        single_page_file(ifd, vec![samples], Endianness::of::<E>())
+    }
+
+    #[test]
+    fn decodes_signed_samples_in_either_byte_order() {
+        // Elevations below sea level must not wrap around to large positive values
+        let elevations16: [i16; 2] = [-432, 8848];
+        let elevations32: [i32; 2] = [-10_994, 100_000];
+
+        let mut le16 = vec![0; 4];
+        LittleEndian::write_i16_into(&elevations16, &mut le16);
+        let (buf, ifd) = signed_file::<LittleEndian>(16, Some(2), le16);
+        let decoded = decode_i16::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (2, 1, elevations16.to_vec()));
+
+        let mut be16 = vec![0; 4];
+        BigEndian::write_i16_into(&elevations16, &mut be16);
+        let (buf, ifd) = signed_file::<BigEndian>(16, Some(2), be16);
+        let decoded = decode_i16::<BigEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (2, 1, elevations16.to_vec()));
+
+        let mut le32 = vec![0; 8];
+        LittleEndian::write_i32_into(&elevations32, &mut le32);
+        let (buf, ifd) = signed_file::<LittleEndian>(32, Some(2), le32);
+        let decoded = decode_i32::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (2, 1, elevations32.to_vec()));
+
+        let mut be32 = vec![0; 8];
+        BigEndian::write_i32_into(&elevations32, &mut be32);
+        let (buf, ifd) = signed_file::<BigEndian>(32, Some(2), be32);
+        let decoded = decode_i32::<BigEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (2, 1, elevations32.to_vec()));
+    }
+
+    #[test]
+    fn signed_decoders_reject_missing_or_unsigned_sample_format() {
+        for sample_format in [None, Some(1)] {
+            let (buf, ifd) = signed_file::<LittleEndian>(16, sample_format, vec![0; 4]);
+            assert!(matches!(
+                decode_i16::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+                Err(TiffError::ImageData(
+                    ImageDataError::UnsupportedSampleFormat { sample_format: 1 }
+                ))
+            ));
+
+            let (buf, ifd) = signed_file::<LittleEndian>(32, sample_format, vec![0; 8]);
+            assert!(matches!(
+                decode_i32::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+                Err(TiffError::ImageData(
+                    ImageDataError::UnsupportedSampleFormat { sample_format: 1 }
+                ))
+            ));
+        }
+
+        // Nor may the bit depth differ from the decoder's
+        let (buf, ifd) = signed_file::<LittleEndian>(32, Some(2), vec![0; 8]);
+        assert!(matches!(
+            decode_i16::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::UnsupportedFormat {
+                bits_per_sample: 32,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn parallel_matches_serial_across_strips() {
+        // More strips than there are likely to be threads, one row each
+        let (width, height) = (3, 40);
+        let pixels: Vec<u8> = (0..width * height * 3).map(|i| i as u8).collect();
+        let strips = pixels.chunks(width as usize * 3).map(<[u8]>::to_vec);
+        let rows_per_strip = vec![(tags::ROWS_PER_STRIP, IFDField::Long(vec![1].into()))];
+        let (buf, ifd) = file_with(width, height, 3, rows_per_strip, strips.collect());
+
+        let serial = decode_rgb8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        let parallel = decode_rgb8_parallel::<LittleEndian>(&buf, &ifd).unwrap();
+        assert_eq!(serial, (width, height, pixels));
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn limit_covers_converted_pixels() {
+        // The RGB data fits, but not once alpha is added
+        let (buf, ifd) = file(2, 1, 3, vec![0; 6]);
+        let mut reader = Cursor::new(&buf);
+        assert!(decode_rgb8_with_limits::<LittleEndian, _>(&mut reader, &ifd, limits(6)).is_ok());
+        assert_too_large(
+            decode_rgba8_with_limits::<LittleEndian, _>(&mut reader, &ifd, limits(6)),
+            8,
+        );
+
+        // Bilevel pixels take a byte each once decoded, rather than a bit
+        let (buf, ifd) = file(16, 1, 0, vec![0xff, 0x00]);
+        let mut reader = Cursor::new(&buf);
+        assert_too_large(
+            decode_bilevel_with_limits::<LittleEndian, _>(&mut reader, &ifd, limits(15)),
+            16,
+        );
+        let (_, _, pixels) =
+            decode_bilevel_with_limits::<LittleEndian, _>(&mut reader, &ifd, limits(16)).unwrap();
+        assert_eq!(pixels, [[255; 8], [0; 8]].concat());
+    }
+
+    #[test]
+    fn rejects_dimensions_at_u32_max_before_allocating() {
+        let max = u64::from(u32::MAX);
+        let (buf, ifd) = file(u32::MAX, u32::MAX, 1, vec![0; 16]);
+        let mut reader = Cursor::new(&buf);
+        assert_too_large(
+            decode_gray8::<LittleEndian, _>(&mut reader, &ifd),
+            max * max,
+        );
+
+        // The product of the dimensions overflows a u32, and saturates with more samples
+        let (buf, ifd) = file(u32::MAX, u32::MAX, 3, vec![0; 16]);
+        let mut reader = Cursor::new(&buf);
+        assert_too_large(decode_rgb8::<LittleEndian, _>(&mut reader, &ifd), u64::MAX);
+        assert_too_large(decode_rgb8_parallel::<LittleEndian>(&buf, &ifd), u64::MAX);
+
+        let (buf, ifd) = file(u32::MAX, u32::MAX, 0, vec![0; 16]);
+        let mut reader = Cursor::new(&buf);
+        assert_too_large(
+            decode_bilevel::<LittleEndian, _>(&mut reader, &ifd),
+            max * max,
+        );
+    }
+
+    #[test]
+    fn rejects_image_beyond_a_32_bit_address_space() {
+        // Fine on a 64-bit target, but more than a 32-bit one can allocate
+        let (buf, ifd) = file(u32::MAX, 2, 1, vec![0; 16]);
+        let mut reader = Cursor::new(&buf);
+        let limits = limits(i32::MAX as u64);
+        assert_too_large(
+            decode_gray8_with_limits::<LittleEndian, _>(&mut reader, &ifd, limits),
+            u64::from(u32::MAX) * 2,
+        );
+    }
+
+    #[test]
+    fn document_applies_its_decode_limits() {
+        let (buf, _) = file(4, 2, 1, (0..8).collect());
+        let mut document = TiffDocument::read_from(Cursor::new(buf)).unwrap();
+        assert_eq!(document.decode_limits(), DecodeLimits::default());
+        assert!(document.decode_gray8(0).is_ok());
+
+        document.set_decode_limits(limits(4));
+        assert_too_large(document.decode_gray8(0), 8);
+    }
+
+    #[test]
+    fn reinterleaves_planar_rgb() {
+        // One strip per row of each plane, so the planes must also be taken in order
+        let extra = vec![
+            (tags::PLANAR_CONFIGURATION, IFDField::Short(vec![2].into())),
+            (tags::ROWS_PER_STRIP, IFDField::Long(vec![1].into())),
+        ];
+        let strips = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![7, 8],
+            vec![9, 10],
+            vec![11, 12],
+        ];
+        let (buf, ifd) = file_with(2, 2, 3, extra, strips);
+        let decoded = decode_rgb8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        let expected = vec![1, 5, 9, 2, 6, 10, 3, 7, 11, 4, 8, 12];
+        assert_eq!(decoded, (2, 2, expected));
+
+        // Without the tag, the same samples are chunky
+        let (buf, ifd) = file(2, 2, 3, (1..=12).collect());
+        let decoded = decode_rgb8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (2, 2, (1..=12).collect()));
+    }
+
+    #[test]
+    fn bilevel_drops_row_padding_at_odd_widths() {
+        // Rows of 13 pixels, padded to two bytes with bits that must not be read as pixels
+        let strip = vec![0b1111_0000, 0b1111_0111, 0b0000_0000, 0b0000_1111];
+        let row = |bits: &str| -> Vec<u8> {
+            bits.bytes()
+                .map(|bit| if bit == b'1' { 255 } else { 0 })
+                .collect()
+        };
+        let expected = [row("1111000011110"), row("0000000000001")].concat();
+        let inverted: Vec<u8> = expected.iter().map(|&pixel| !pixel).collect();
+
+        let (buf, ifd) = file(13, 2, 0, strip.clone());
+        let decoded = decode_bilevel::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (13, 2, expected.clone()));
+
+        let white_is_zero = vec![(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            IFDField::Short(vec![0].into()),
+        )];
+        let (buf, ifd) = file_with(13, 2, 0, white_is_zero, vec![strip.clone()]);
+        let decoded = decode_bilevel::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (13, 2, inverted));
+
+        let lsb_first = vec![(tags::FILL_ORDER, IFDField::Short(vec![2].into()))];
+        let reversed = strip.iter().map(|byte| byte.reverse_bits()).collect();
+        let (buf, ifd) = file_with(13, 2, 0, lsb_first, vec![reversed]);
+        let decoded = decode_bilevel::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (13, 2, expected));
+    }
+
+    #[test]
+    fn bilevel_tiles_are_cropped_or_rejected_if_not_byte_aligned() {
+        let tiled = |tile_width: u32, tile_count: usize| {
+            vec![
+                (tags::TILE_WIDTH, IFDField::Long(vec![tile_width].into())),
+                (tags::TILE_LENGTH, IFDField::Long(vec![16].into())),
+                (
+                    tags::TILE_OFFSETS,
+                    IFDField::Long(vec![8; tile_count].into()),
+                ),
+                (
+                    tags::TILE_BYTE_COUNTS,
+                    IFDField::Long(vec![32; tile_count].into()),
+                ),
+            ]
+        };
+        // One 16 by 16 tile, of which only the first 10 pixels of the first row are in the image
+        let mut tile = vec![0; 32];
+        tile[..2].copy_from_slice(&[0b1010_1010, 0b1111_1111]);
+        let (buf, ifd) = file_with(10, 1, 0, tiled(16, 1), vec![tile.clone()]);
+        let decoded = decode_bilevel::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        let expected = [255, 0, 255, 0, 255, 0, 255, 0, 255, 255];
+        assert_eq!(decoded, (10, 1, expected.to_vec()));
+
+        // Tiles 3 pixels wide would start partway through a byte
+        let (buf, ifd) = file_with(10, 1, 0, tiled(3, 4), vec![tile]);
+        assert!(matches!(
+            decode_bilevel::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::InvalidTileSize {
+                width: 3,
+                length: 16
+            }))
+        ));
+    }
+
+    #[test]
+    fn rgba_honours_both_alpha_modes() {
+        let alpha = |extra: u16| vec![(tags::EXTRA_SAMPLES, IFDField::Short(vec![extra].into()))];
+        // An opaque, a half transparent and a fully transparent pixel
+        let strip = vec![200, 100, 50, 255, 100, 50, 0, 128, 0, 0, 0, 0];
+
+        // Unassociated alpha is passed through as stored
+        let (buf, ifd) = file_with(3, 1, 4, alpha(2), vec![strip.clone()]);
+        let decoded = decode_rgba8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (3, 1, strip.clone()));
+
+        // Associated alpha was premultiplied, so the color is divided by it again
+        let (buf, ifd) = file_with(3, 1, 4, alpha(1), vec![strip]);
+        let decoded = decode_rgba8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        let expected = vec![200, 100, 50, 255, 199, 100, 0, 128, 0, 0, 0, 0];
+        assert_eq!(decoded, (3, 1, expected));
+
+        // Without alpha, RGB is made opaque
+        let (buf, ifd) = file(1, 1, 3, vec![1, 2, 3]);
+        let decoded = decode_rgba8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!(decoded, (1, 1, vec![1, 2, 3, 255]));
+    }
+}
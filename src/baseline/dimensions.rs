@@ -0,0 +1,17 @@
+use crate::baseline::{ifd_values::read_required_u32, tags};
+use crate::errors::TiffError;
+use crate::lowlevel::RawIFD;
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// Read the ImageWidth and ImageLength tags of `ifd`, returning the width and height of the
+/// image in pixels. Either may be stored as a SHORT or a LONG. Fails if either tag is missing, as
+/// neither has a default, or doesn't fit in a u32.
+pub fn image_dimensions<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(u32, u32), TiffError> {
+    let width = read_required_u32::<E, R>(reader, ifd, tags::IMAGE_WIDTH)?;
+    let height = read_required_u32::<E, R>(reader, ifd, tags::IMAGE_LENGTH)?;
+    Ok((width, height))
+}
@@ -0,0 +1,18 @@
+use crate::baseline::tags::DNG_VERSION;
+use crate::lowlevel::{RawIFD, TagType};
+
+/// Returns true if `ifd` is the main IFD of an Adobe DNG file, which is identified by the
+/// presence of DNGVersion.
+pub fn is_dng(ifd: &RawIFD) -> bool {
+    ifd.contains_tag(DNG_VERSION)
+}
+
+/// Read the four-part DNGVersion of `ifd`, such as `[1, 4, 0, 0]` for DNG 1.4. Returns `None`
+/// if `ifd` isn't DNG, or the field isn't the 4 BYTEs the specification requires.
+pub fn dng_version(ifd: &RawIFD) -> Option<[u8; 4]> {
+    let field = ifd.get(DNG_VERSION)?;
+    if field.tag_type_enum()? != TagType::Byte || field.count != 4 {
+        return None;
+    }
+    Some(field.value_or_offset)
+}
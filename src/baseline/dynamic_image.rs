@@ -0,0 +1,128 @@
+use crate::baseline::{
+    decode::{decode_gray16, decode_gray8, decode_rgb8, decode_rgba8},
+    samples::{bits_per_sample, samples_per_pixel, ExtraSamples},
+};
+use crate::errors::TiffError;
+use crate::lowlevel::RawIFD;
+use byteorder::{ByteOrder, ReadBytesExt};
+use image::{DynamicImage, ImageBuffer};
+use std::io::Seek;
+
+/// Decode the image of `ifd` into the `image` crate's DynamicImage, choosing the variant by its
+/// samples: Luma16 for a single 16-bit sample, Luma8 for any other single sample, Rgba8 for four
+/// samples the last of which is alpha according to the ExtraSamples tag, and Rgb8 otherwise.
+/// Each is decoded as `decode_gray16`, `decode_gray8`, `decode_rgba8` or `decode_rgb8` does, and
+/// fails as they do, with `ImageDataError::UnsupportedFormat` naming the photometric
+/// interpretation, samples per pixel and bits per sample of an image none of them can decode.
+pub fn to_dynamic_image<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<DynamicImage, TiffError> {
+    let image = match samples_per_pixel::<E, R>(reader, ifd)? {
+        1 => match bits_per_sample::<E, R>(reader, ifd)?.first() {
+            Some(16) => {
+                let (width, height, data) = decode_gray16::<E, R>(reader, ifd)?;
+                DynamicImage::ImageLuma16(buffer(width, height, data))
+            }
+            _ => {
+                let (width, height, data) = decode_gray8::<E, R>(reader, ifd)?;
+                DynamicImage::ImageLuma8(buffer(width, height, data))
+            }
+        },
+        4 if has_alpha::<E, R>(reader, ifd)? => {
+            let (width, height, data) = decode_rgba8::<E, R>(reader, ifd)?;
+            DynamicImage::ImageRgba8(buffer(width, height, data))
+        }
+        _ => {
+            let (width, height, data) = decode_rgb8::<E, R>(reader, ifd)?;
+            DynamicImage::ImageRgb8(buffer(width, height, data))
+        }
+    };
+    Ok(image)
+}
+
+/// Whether the first extra sample of `ifd` is alpha.
+fn has_alpha<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<bool, TiffError> {
+    Ok(ExtraSamples::from_ifd::<E, R>(reader, ifd)?
+        .first()
+        .is_some_and(|extra| extra.is_alpha()))
+}
+
+/// Wrap `data`, a decoded image of `width` by `height` pixels, in an ImageBuffer.
+fn buffer<P: image::Pixel>(
+    width: u32,
+    height: u32,
+    data: Vec<P::Subpixel>,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    // The decoders always return exactly one pixel's samples for every pixel
+    ImageBuffer::from_raw(width, height, data).expect("decoded image has the wrong length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::tags;
+    use crate::errors::ImageDataError;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{Endianness, IFDField, LittleEndian};
+    use std::io::Cursor;
+
+    /// Decode a little-endian 2x1 image with `photometric` pixels of one sample per entry of
+    /// `bits`, holding `strip`, and with `extra` as its ExtraSamples tag if there is one.
+    fn decode(
+        photometric: u16,
+        bits: Vec<u16>,
+        extra: Option<u16>,
+        strip: Vec<u8>,
+    ) -> Result<DynamicImage, TiffError> {
+        let mut ifd = image_ifd(2, 1, &bits, photometric);
+        if let Some(extra) = extra {
+            let field = IFDField::Short(vec![extra].into());
+            ifd.entries.insert(tags::EXTRA_SAMPLES, field);
+        }
+        let (buf, ifd) = single_page_file(ifd, vec![strip], Endianness::Little);
+        to_dynamic_image::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd)
+    }
+
+    #[test]
+    fn converts_gray8() {
+        let image = decode(1, vec![8], None, vec![10, 200]).unwrap();
+        assert_eq!(image.as_luma8().unwrap().as_raw(), &[10, 200]);
+    }
+
+    #[test]
+    fn converts_gray16() {
+        let image = decode(1, vec![16], None, vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(image.as_luma16().unwrap().as_raw(), &[0x0201, 0x0403]);
+    }
+
+    #[test]
+    fn converts_rgb8() {
+        let image = decode(2, vec![8; 3], None, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(image.as_rgb8().unwrap().as_raw(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn converts_rgba8_with_unassociated_alpha() {
+        let strip = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image = decode(2, vec![8; 4], Some(2), strip.clone()).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.as_rgba8().unwrap().as_raw(), &strip);
+    }
+
+    #[test]
+    fn rejects_unsupported_format() {
+        // A palette-color image has no DynamicImage variant
+        assert!(matches!(
+            decode(3, vec![8], None, vec![0, 1]),
+            Err(TiffError::ImageData(ImageDataError::UnsupportedFormat {
+                photometric: 3,
+                samples_per_pixel: 1,
+                bits_per_sample: 8,
+            }))
+        ));
+    }
+}
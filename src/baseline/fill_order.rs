@@ -0,0 +1,144 @@
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    errors::{ImageDataError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// The order of the bits within each byte of image data, as given by its FillOrder tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FillOrder {
+    /// Earlier pixels are in the more significant bits, which is the default.
+    MsbFirst,
+    /// Earlier pixels are in the less significant bits, as written by some fax software.
+    LsbFirst,
+}
+
+impl FillOrder {
+    /// Interpret a FillOrder value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(FillOrder::MsbFirst),
+            2 => Some(FillOrder::LsbFirst),
+            _ => None,
+        }
+    }
+
+    /// The FillOrder value for this order.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            FillOrder::MsbFirst => 1,
+            FillOrder::LsbFirst => 2,
+        }
+    }
+
+    /// Read the FillOrder tag of `ifd`, or `MsbFirst` if it is missing. Fails if it is
+    /// unrecognized.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::FILL_ORDER, 1)?;
+        Self::from_u16(value as u16).ok_or_else(|| {
+            ImageDataError::UnknownFillOrder {
+                value: value as u16,
+            }
+            .into()
+        })
+    }
+}
+
+/// Reverse the order of the bits within each byte of `buf`, converting between the two fill
+/// orders.
+pub fn reverse_bits_in_place(buf: &mut [u8]) {
+    buf.iter_mut().for_each(|byte| *byte = byte.reverse_bits());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 24x16 bilevel WhiteIsZero image, written by libtiff as a single Group 4 strip with
+    /// FillOrder 2. Pixel `(x, y)` is black if `(x / 3 + y / 2) % 3 == 0`.
+    #[cfg(feature = "std")]
+    const LIBTIFF_G4_LSB_FIRST: &[u8] = include_bytes!("../../testdata/libtiff_g4_lsb_first.tif");
+
+    #[test]
+    fn u16_round_trip() {
+        for order in [FillOrder::MsbFirst, FillOrder::LsbFirst] {
+            assert_eq!(FillOrder::from_u16(order.as_u16()), Some(order));
+        }
+        assert_eq!(FillOrder::from_u16(0), None);
+        assert_eq!(FillOrder::from_u16(3), None);
+    }
+
+    #[test]
+    fn reverses_bits_of_each_byte() {
+        let mut buf = [0b1000_0000, 0b0000_0110, 0b1100_1010];
+        reverse_bits_in_place(&mut buf);
+        assert_eq!(buf, [0b0000_0001, 0b0110_0000, 0b0101_0011]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decodes_libtiff_lsb_first_fax() {
+        use crate::baseline::decode::decode_bilevel;
+        use crate::lowlevel::{read_ifds_auto, LittleEndian};
+        use std::io::Cursor;
+
+        let mut reader = Cursor::new(LIBTIFF_G4_LSB_FIRST);
+        let (_, ifds) = read_ifds_auto(&mut reader).unwrap();
+        let mut ifd = ifds.into_classic().unwrap()[0].clone();
+        assert_eq!(
+            FillOrder::from_ifd::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            FillOrder::LsbFirst
+        );
+
+        let expected: Vec<u8> = (0..16)
+            .flat_map(|y| (0..24).map(move |x| if (x / 3 + y / 2) % 3 == 0 { 0 } else { 255 }))
+            .collect();
+        assert_eq!(
+            decode_bilevel::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            (24, 16, expected.clone())
+        );
+
+        // Read as MSB-first, the same strip is garbage
+        ifd.get_mut(tags::FILL_ORDER).unwrap().value_or_offset = [1, 0, 0, 0];
+        let decoded = decode_bilevel::<LittleEndian, _>(&mut reader, &ifd);
+        assert_ne!(decoded.ok(), Some((24, 16, expected)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_ifd_defaults_to_msb_first() {
+        use crate::lowlevel::tiff_builder::single_page_file;
+        use crate::lowlevel::{Endianness, IFDField, LittleEndian, IFD};
+        use std::io::Cursor;
+
+        let read = |fill_order: Option<u16>| {
+            let mut ifd = IFD::new();
+            ifd.entries
+                .insert(tags::IMAGE_WIDTH, IFDField::Long(vec![1].into()));
+            if let Some(fill_order) = fill_order {
+                let field = IFDField::Short(vec![fill_order].into());
+                ifd.entries.insert(tags::FILL_ORDER, field);
+            }
+            let (buf, ifd) = single_page_file(ifd, Vec::new(), Endianness::Little);
+            FillOrder::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd)
+        };
+        assert_eq!(read(None).unwrap(), FillOrder::MsbFirst);
+        assert_eq!(read(Some(1)).unwrap(), FillOrder::MsbFirst);
+        assert_eq!(read(Some(2)).unwrap(), FillOrder::LsbFirst);
+        assert!(matches!(
+            read(Some(3)),
+            Err(TiffError::ImageData(ImageDataError::UnknownFillOrder {
+                value: 3
+            }))
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::errors::{FieldExtractionError, TiffError};
+use crate::lowlevel::RawIFD;
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::convert::TryFrom;
+use std::io::Seek;
+
+/// Read the unsigned array in the `tag` field of `ifd`, failing if it is missing.
+pub(crate) fn read_required_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tag: u16,
+) -> Result<Vec<u64>, TiffError> {
+    ifd.get(tag)
+        .ok_or(FieldExtractionError::MissingTag { tag })?
+        .as_unsigned_array::<E, R>(reader)
+}
+
+/// Read the first unsigned value in the `tag` field of `ifd`, failing if it is missing or empty.
+pub(crate) fn read_required_value<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tag: u16,
+) -> Result<u64, TiffError> {
+    read_required_array::<E, R>(reader, ifd, tag)?
+        .first()
+        .copied()
+        .ok_or_else(|| FieldExtractionError::InsufficientData.into())
+}
+
+/// Read the first unsigned value in the `tag` field of `ifd` as `read_required_value` does,
+/// failing with `FieldExtractionError::ValueOutOfRange` if it doesn't fit in a u32.
+pub(crate) fn read_required_u32<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tag: u16,
+) -> Result<u32, TiffError> {
+    let value = read_required_value::<E, R>(reader, ifd, tag)?;
+    u32::try_from(value).map_err(|_| FieldExtractionError::ValueOutOfRange { tag, value }.into())
+}
+
+/// Read the first unsigned value in the `tag` field of `ifd`, or `default` if it is missing.
+pub(crate) fn read_optional_value<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tag: u16,
+    default: u64,
+) -> Result<u64, TiffError> {
+    match ifd.get(tag) {
+        Some(field) => field
+            .as_unsigned_array::<E, R>(reader)?
+            .first()
+            .copied()
+            .ok_or_else(|| FieldExtractionError::InsufficientData.into()),
+        None => Ok(default),
+    }
+}
@@ -0,0 +1,273 @@
+use crate::baseline::tags::{self, tag_name};
+use crate::errors::IFDError;
+use crate::lowlevel::{RawIFD, TagType};
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+/// A non-fatal problem found in an IFD by `RawIFD::lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Tag of the offending field.
+    pub tag: u16,
+
+    /// What is wrong with it.
+    pub message: String,
+}
+
+/// Shows the tag by name where known, e.g. `ImageWidth (256): type ASCII, expected SHORT or LONG`.
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match tag_name(self.tag) {
+            Some(name) => write!(f, "{} ({}): {}", name, self.tag, self.message),
+            None => write!(f, "Tag {}: {}", self.tag, self.message),
+        }
+    }
+}
+
+/// No useful field holds more values than this, so larger counts suggest a corrupt entry.
+const IMPLAUSIBLE_COUNT: u32 = 1 << 28;
+
+const SHORT_OR_LONG: &[TagType] = &[TagType::Short, TagType::Long];
+const SHORT: &[TagType] = &[TagType::Short];
+const LONG: &[TagType] = &[TagType::Long];
+const RATIONAL: &[TagType] = &[TagType::Rational];
+const SHORT_OR_RATIONAL: &[TagType] = &[TagType::Short, TagType::Rational];
+const SHORT_LONG_OR_RATIONAL: &[TagType] = &[TagType::Short, TagType::Long, TagType::Rational];
+const SRATIONAL: &[TagType] = &[TagType::SRational];
+const BYTE: &[TagType] = &[TagType::Byte];
+const ASCII: &[TagType] = &[TagType::Ascii];
+/// DNG allows text in either ASCII or (for UTF-8) BYTE fields
+const TEXT: &[TagType] = &[TagType::Ascii, TagType::Byte];
+const DOUBLE: &[TagType] = &[TagType::Double];
+const BYTES: &[TagType] = &[TagType::Byte, TagType::Undefined];
+const IFD: &[TagType] = &[TagType::Long, TagType::IFD];
+
+/// The types and (if fixed) count the specifications give each well-known tag, sorted by tag.
+const TAG_SPECS: &[(u16, &[TagType], Option<u32>)] = &[
+    (tags::NEW_SUBFILE_TYPE, LONG, Some(1)),
+    (tags::SUBFILE_TYPE, SHORT, Some(1)),
+    (tags::IMAGE_WIDTH, SHORT_OR_LONG, Some(1)),
+    (tags::IMAGE_LENGTH, SHORT_OR_LONG, Some(1)),
+    (tags::BITS_PER_SAMPLE, SHORT, None),
+    (tags::COMPRESSION, SHORT, Some(1)),
+    (tags::PHOTOMETRIC_INTERPRETATION, SHORT, Some(1)),
+    (tags::THRESHHOLDING, SHORT, Some(1)),
+    (tags::CELL_WIDTH, SHORT, Some(1)),
+    (tags::CELL_LENGTH, SHORT, Some(1)),
+    (tags::FILL_ORDER, SHORT, Some(1)),
+    (tags::DOCUMENT_NAME, ASCII, None),
+    (tags::IMAGE_DESCRIPTION, ASCII, None),
+    (tags::MAKE, ASCII, None),
+    (tags::MODEL, ASCII, None),
+    (tags::STRIP_OFFSETS, SHORT_OR_LONG, None),
+    (tags::ORIENTATION, SHORT, Some(1)),
+    (tags::SAMPLES_PER_PIXEL, SHORT, Some(1)),
+    (tags::ROWS_PER_STRIP, SHORT_OR_LONG, Some(1)),
+    (tags::STRIP_BYTE_COUNTS, SHORT_OR_LONG, None),
+    (tags::MIN_SAMPLE_VALUE, SHORT, None),
+    (tags::MAX_SAMPLE_VALUE, SHORT, None),
+    (tags::X_RESOLUTION, RATIONAL, Some(1)),
+    (tags::Y_RESOLUTION, RATIONAL, Some(1)),
+    (tags::PLANAR_CONFIGURATION, SHORT, Some(1)),
+    (tags::PAGE_NAME, ASCII, None),
+    (tags::X_POSITION, RATIONAL, Some(1)),
+    (tags::Y_POSITION, RATIONAL, Some(1)),
+    (tags::FREE_OFFSETS, LONG, None),
+    (tags::FREE_BYTE_COUNTS, LONG, None),
+    (tags::GRAY_RESPONSE_UNIT, SHORT, Some(1)),
+    (tags::GRAY_RESPONSE_CURVE, SHORT, None),
+    (tags::T4_OPTIONS, LONG, Some(1)),
+    (tags::T6_OPTIONS, LONG, Some(1)),
+    (tags::RESOLUTION_UNIT, SHORT, Some(1)),
+    (tags::PAGE_NUMBER, SHORT, Some(2)),
+    (tags::TRANSFER_FUNCTION, SHORT, None),
+    (tags::SOFTWARE, ASCII, None),
+    (tags::DATE_TIME, ASCII, Some(20)),
+    (tags::ARTIST, ASCII, None),
+    (tags::HOST_COMPUTER, ASCII, None),
+    (tags::PREDICTOR, SHORT, Some(1)),
+    (tags::WHITE_POINT, RATIONAL, Some(2)),
+    (tags::PRIMARY_CHROMATICITIES, RATIONAL, Some(6)),
+    (tags::COLOR_MAP, SHORT, None),
+    (tags::HALFTONE_HINTS, SHORT, Some(2)),
+    (tags::TILE_WIDTH, SHORT_OR_LONG, Some(1)),
+    (tags::TILE_LENGTH, SHORT_OR_LONG, Some(1)),
+    (tags::TILE_OFFSETS, LONG, None),
+    (tags::TILE_BYTE_COUNTS, SHORT_OR_LONG, None),
+    (tags::SUB_IFDS, IFD, None),
+    (tags::INK_SET, SHORT, Some(1)),
+    (tags::INK_NAMES, ASCII, None),
+    (tags::NUMBER_OF_INKS, SHORT, Some(1)),
+    (tags::TARGET_PRINTER, ASCII, None),
+    (tags::EXTRA_SAMPLES, SHORT, None),
+    (tags::SAMPLE_FORMAT, SHORT, None),
+    (tags::JPEG_TABLES, BYTES, None),
+    (tags::Y_CBCR_COEFFICIENTS, RATIONAL, Some(3)),
+    (tags::Y_CBCR_SUB_SAMPLING, SHORT, Some(2)),
+    (tags::Y_CBCR_POSITIONING, SHORT, Some(1)),
+    (tags::REFERENCE_BLACK_WHITE, RATIONAL, Some(6)),
+    (tags::XMP, BYTES, None),
+    (tags::CFA_REPEAT_PATTERN_DIM, SHORT, Some(2)),
+    (tags::CFA_PATTERN, BYTES, None),
+    (tags::COPYRIGHT, ASCII, None),
+    (tags::MODEL_PIXEL_SCALE, DOUBLE, Some(3)),
+    (tags::MODEL_TIEPOINT, DOUBLE, None),
+    (tags::MODEL_TRANSFORMATION, DOUBLE, Some(16)),
+    (tags::PHOTOSHOP, BYTES, None),
+    (tags::EXIF_IFD, IFD, Some(1)),
+    (tags::ICC_PROFILE, BYTES, None),
+    (tags::GEO_KEY_DIRECTORY, SHORT, None),
+    (tags::GEO_DOUBLE_PARAMS, DOUBLE, None),
+    (tags::GEO_ASCII_PARAMS, ASCII, None),
+    (tags::GPS_IFD, IFD, Some(1)),
+    (tags::GDAL_METADATA, ASCII, None),
+    (tags::GDAL_NODATA, ASCII, None),
+    (tags::DNG_VERSION, BYTE, Some(4)),
+    (tags::DNG_BACKWARD_VERSION, BYTE, Some(4)),
+    (tags::UNIQUE_CAMERA_MODEL, ASCII, None),
+    (tags::LOCALIZED_CAMERA_MODEL, TEXT, None),
+    (tags::CFA_PLANE_COLOR, BYTE, None),
+    (tags::CFA_LAYOUT, SHORT, Some(1)),
+    (tags::LINEARIZATION_TABLE, SHORT, None),
+    (tags::BLACK_LEVEL_REPEAT_DIM, SHORT, Some(2)),
+    (tags::BLACK_LEVEL, SHORT_LONG_OR_RATIONAL, None),
+    (tags::BLACK_LEVEL_DELTA_H, SRATIONAL, None),
+    (tags::BLACK_LEVEL_DELTA_V, SRATIONAL, None),
+    (tags::WHITE_LEVEL, SHORT_OR_LONG, None),
+    (tags::DEFAULT_SCALE, RATIONAL, Some(2)),
+    (tags::DEFAULT_CROP_ORIGIN, SHORT_LONG_OR_RATIONAL, Some(2)),
+    (tags::DEFAULT_CROP_SIZE, SHORT_LONG_OR_RATIONAL, Some(2)),
+    (tags::COLOR_MATRIX_1, SRATIONAL, None),
+    (tags::COLOR_MATRIX_2, SRATIONAL, None),
+    (tags::CAMERA_CALIBRATION_1, SRATIONAL, None),
+    (tags::CAMERA_CALIBRATION_2, SRATIONAL, None),
+    (tags::REDUCTION_MATRIX_1, SRATIONAL, None),
+    (tags::REDUCTION_MATRIX_2, SRATIONAL, None),
+    (tags::ANALOG_BALANCE, RATIONAL, None),
+    (tags::AS_SHOT_NEUTRAL, SHORT_OR_RATIONAL, None),
+    (tags::AS_SHOT_WHITE_XY, RATIONAL, Some(2)),
+    (tags::BASELINE_EXPOSURE, SRATIONAL, Some(1)),
+    (tags::BASELINE_NOISE, RATIONAL, Some(1)),
+    (tags::BASELINE_SHARPNESS, RATIONAL, Some(1)),
+    (tags::BAYER_GREEN_SPLIT, LONG, Some(1)),
+    (tags::LINEAR_RESPONSE_LIMIT, RATIONAL, Some(1)),
+    (tags::CAMERA_SERIAL_NUMBER, ASCII, None),
+    (tags::LENS_INFO, RATIONAL, Some(4)),
+    (tags::CHROMA_BLUR_RADIUS, RATIONAL, Some(1)),
+    (tags::ANTI_ALIAS_STRENGTH, RATIONAL, Some(1)),
+    (tags::SHADOW_SCALE, RATIONAL, Some(1)),
+    (tags::DNG_PRIVATE_DATA, BYTE, None),
+    (tags::MAKER_NOTE_SAFETY, SHORT, Some(1)),
+    (tags::CALIBRATION_ILLUMINANT_1, SHORT, Some(1)),
+    (tags::CALIBRATION_ILLUMINANT_2, SHORT, Some(1)),
+    (tags::BEST_QUALITY_SCALE, RATIONAL, Some(1)),
+    (tags::RAW_DATA_UNIQUE_ID, BYTE, Some(16)),
+    (tags::ORIGINAL_RAW_FILE_NAME, TEXT, None),
+    (tags::ACTIVE_AREA, SHORT_OR_LONG, Some(4)),
+    (tags::MASKED_AREAS, SHORT_OR_LONG, None),
+    (tags::CAMERA_CALIBRATION_SIGNATURE, TEXT, None),
+    (tags::PROFILE_CALIBRATION_SIGNATURE, TEXT, None),
+    (tags::PROFILE_NAME, TEXT, None),
+    (tags::FORWARD_MATRIX_1, SRATIONAL, None),
+    (tags::FORWARD_MATRIX_2, SRATIONAL, None),
+];
+
+/// Join the names of `types` for a message, e.g. `SHORT or LONG`.
+fn type_names(types: &[TagType]) -> String {
+    types
+        .iter()
+        .map(|tag_type| tag_type.name())
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+impl RawIFD {
+    /// Check this IFD for problems which readers can often tolerate, returning a warning for
+    /// each rather than failing at the first: tags which are duplicated or out of order (as
+    /// `validate` rejects), unknown or BigTIFF-only types, zero or implausibly large counts, and
+    /// types or counts which don't match the specification of a well-known tag. Warnings are in
+    /// the order of the fields they concern, with those about ordering first.
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        if let Err(IFDError::UnorderedTags {
+            duplicates,
+            out_of_order,
+        }) = self.validate()
+        {
+            for tag in out_of_order {
+                lints.push(Lint {
+                    tag,
+                    message: String::from("out of order"),
+                });
+            }
+            for tag in duplicates {
+                lints.push(Lint {
+                    tag,
+                    message: String::from("appears more than once"),
+                });
+            }
+        }
+
+        for field in self {
+            let mut lint = |message: String| {
+                lints.push(Lint {
+                    tag: field.tag,
+                    message,
+                })
+            };
+
+            if field.count == 0 {
+                lint(String::from("has no values"));
+            } else if field.count > IMPLAUSIBLE_COUNT {
+                lint(format!("implausibly many values ({})", field.count));
+            }
+
+            let tag_type = match field.tag_type_enum() {
+                Some(tag_type) => tag_type,
+                None => {
+                    lint(format!("unknown type {}", field.tag_type));
+                    continue;
+                }
+            };
+            if let TagType::Long8 | TagType::SLong8 | TagType::IFD8 = tag_type {
+                lint(format!("type {} is only valid in BigTIFF", tag_type.name()));
+            }
+            if field
+                .value_byte_len()
+                .is_none_or(|len| len > u32::MAX as usize)
+            {
+                lint(String::from("value is larger than a TIFF file can hold"));
+            }
+
+            let spec = TAG_SPECS
+                .binary_search_by_key(&field.tag, |(tag, _, _)| *tag)
+                .ok()
+                .map(|idx| TAG_SPECS[idx]);
+            if let Some((_, types, count)) = spec {
+                if !types.contains(&tag_type) {
+                    lint(format!(
+                        "type {}, expected {}",
+                        tag_type.name(),
+                        type_names(types)
+                    ));
+                }
+                if let Some(count) = count {
+                    if field.count != 0 && field.count != count {
+                        lint(format!("{} values, expected {}", field.count, count));
+                    }
+                }
+            }
+        }
+        lints
+    }
+}
+
+/// Lint every IFD in `ifds` (such as the pages of a `TiffDocument`) as `RawIFD::lint` does,
+/// pairing each warning with the index of the IFD it concerns.
+pub fn lint_document<'a, I: IntoIterator<Item = &'a RawIFD>>(ifds: I) -> Vec<(usize, Lint)> {
+    ifds.into_iter()
+        .enumerate()
+        .flat_map(|(index, ifd)| ifd.lint().into_iter().map(move |lint| (index, lint)))
+        .collect()
+}
@@ -0,0 +1,157 @@
+use crate::baseline::{
+    datetime::{read_datetime, DateTime},
+    orientation::Orientation,
+    resolution::Resolution,
+    tags,
+};
+use crate::errors::{FieldExtractionError, TiffError};
+use crate::lowlevel::RawIFD;
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// The descriptive fields most often wanted from a photograph or scan, read together by
+/// `BasicMetadata::from_ifd`. Each is `None` if the image doesn't have it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicMetadata {
+    /// Manufacturer of the scanner or camera, from the Make tag.
+    pub make: Option<String>,
+    /// Model of the scanner or camera, from the Model tag.
+    pub model: Option<String>,
+    /// Program which created the image, from the Software tag.
+    pub software: Option<String>,
+    /// When the image was created, from the DateTime tag.
+    pub datetime: Option<DateTime>,
+    /// Description of the subject of the image, from the ImageDescription tag.
+    pub image_description: Option<String>,
+    /// How the image should be turned for display, from the Orientation tag.
+    pub orientation: Option<Orientation>,
+    /// Physical size of the pixels, if either the XResolution or YResolution tag is present.
+    pub resolution: Option<Resolution>,
+}
+
+impl BasicMetadata {
+    /// Read the descriptive fields of `ifd`, without applying the defaults of tags such as
+    /// Orientation, so that missing tags can be told apart. Fails if any field present is
+    /// malformed, such as text which isn't valid UTF-8 or an unrecognized orientation.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let mut read_text = |tag| {
+            ifd.get(tag)
+                .map(|field| field.as_ascii::<E, R>(reader))
+                .transpose()
+        };
+        let make = read_text(tags::MAKE)?;
+        let model = read_text(tags::MODEL)?;
+        let software = read_text(tags::SOFTWARE)?;
+        let image_description = read_text(tags::IMAGE_DESCRIPTION)?;
+
+        let datetime = read_datetime::<E, R>(reader, ifd)?;
+        let orientation = match ifd.get(tags::ORIENTATION) {
+            Some(_) => Some(Orientation::from_ifd::<E, R>(reader, ifd)?),
+            None => None,
+        };
+        let resolution =
+            if ifd.get(tags::X_RESOLUTION).is_some() || ifd.get(tags::Y_RESOLUTION).is_some() {
+                Some(Resolution::from_ifd::<E, R>(reader, ifd)?)
+            } else {
+                None
+            };
+
+        Ok(Self {
+            make,
+            model,
+            software,
+            datetime,
+            image_description,
+            orientation,
+            resolution,
+        })
+    }
+}
+
+/// Read the embedded ICC color profile of `ifd`, as the raw bytes of the profile, ready to be
+/// handed to a color management system. Returns `None` if the image has no profile.
+pub fn read_icc_profile<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<Vec<u8>>, TiffError> {
+    ifd.get(tags::ICC_PROFILE)
+        .map(|field| field.read_value_bytes::<E, R>(reader))
+        .transpose()
+}
+
+/// Read the XMP packet embedded in `ifd` as a string of XML, without any trailing NUL padding.
+/// Returns `None` if the image has no XMP metadata. Fails if the packet is not valid UTF-8.
+pub fn read_xmp<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<String>, TiffError> {
+    let mut bytes = match ifd.get(tags::XMP) {
+        Some(field) => field.read_value_bytes::<E, R>(reader)?,
+        None => return Ok(None),
+    };
+    while bytes.last() == Some(&b'\0') {
+        bytes.pop();
+    }
+    String::from_utf8(bytes)
+        .map(Some)
+        .map_err(|_| FieldExtractionError::InvalidText { tag: tags::XMP }.into())
+}
+
+/// Read the IPTC-NAA metadata block embedded in `ifd` as raw bytes. Some writers declare the
+/// block as LONGs rather than BYTEs, so its length is taken from the declared type and count,
+/// and the bytes are returned as stored in the file, without any byte swapping. Returns `None`
+/// if the image has no IPTC metadata.
+pub fn read_iptc<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<Vec<u8>>, TiffError> {
+    ifd.get(tags::IPTC)
+        .map(|field| field.read_value_bytes::<E, R>(reader))
+        .transpose()
+}
+
+/// Where a page belongs in a scanned document, read by `page_info`. Each is `None` if the page
+/// doesn't have it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageInfo {
+    /// Name of the document the page was scanned from, from the DocumentName tag.
+    pub document_name: Option<String>,
+    /// Name of the page, from the PageName tag.
+    pub page_name: Option<String>,
+    /// Zero-based number of the page and the total number of pages in the document, from the
+    /// PageNumber tag. A total of zero means it is unknown.
+    pub page_number: Option<(u16, u16)>,
+}
+
+/// Read the DocumentName, PageName and PageNumber tags of `ifd`, with which the pages of a
+/// multi-page file can be put in order and split into documents. Fails if any present is
+/// malformed, such as text which isn't valid UTF-8 or a PageNumber without exactly two values.
+pub fn page_info<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<PageInfo, TiffError> {
+    let mut read_text = |tag| {
+        ifd.get(tag)
+            .map(|field| field.as_ascii::<E, R>(reader))
+            .transpose()
+    };
+    let document_name = read_text(tags::DOCUMENT_NAME)?;
+    let page_name = read_text(tags::PAGE_NAME)?;
+
+    let page_number = match ifd.get(tags::PAGE_NUMBER) {
+        Some(field) => match field.as_u16_array::<E, R>(reader)?[..] {
+            [page, total] => Some((page, total)),
+            _ => return Err(FieldExtractionError::InsufficientData.into()),
+        },
+        None => None,
+    };
+
+    Ok(PageInfo {
+        document_name,
+        page_name,
+        page_number,
+    })
+}
@@ -3,3 +3,93 @@ pub mod constants;
 
 /// Integer values of tags
 pub mod tags;
+
+/// Reading the values of required fields
+#[cfg(feature = "std")]
+pub(crate) mod ifd_values;
+
+/// Reading the width and height of images
+#[cfg(feature = "std")]
+pub mod dimensions;
+
+/// Reading the size and format of every page, without decoding them
+#[cfg(feature = "std")]
+pub mod probe;
+
+/// Locating and reading strips of image data
+#[cfg(feature = "std")]
+pub mod strips;
+
+/// Locating and reading tiles of image data
+#[cfg(feature = "std")]
+pub mod tiles;
+
+/// Reading the number and size of samples in each pixel
+#[cfg(feature = "std")]
+pub mod samples;
+
+/// Interpreting the color space of pixels
+pub mod photometric;
+
+/// Reading the order of bits within bytes
+pub mod fill_order;
+
+/// Converting pixels between chunky and planar layouts
+pub mod repack;
+
+/// Unpacking samples which don't fill a whole number of bytes
+pub mod unpack;
+
+/// Reading what kind of image each IFD holds
+pub mod subfile;
+
+/// Decoding whole images into pixels
+#[cfg(feature = "std")]
+pub mod decode;
+
+/// Converting decoded images to the image crate's DynamicImage
+#[cfg(feature = "image")]
+pub mod dynamic_image;
+
+/// Reorganizing images between strips and tiles
+#[cfg(feature = "std")]
+pub mod rechunk;
+
+/// Finding and decoding thumbnails
+#[cfg(feature = "std")]
+pub mod thumbnail;
+
+/// Extracting the embedded JPEG stream of old-style JPEG images
+#[cfg(feature = "std")]
+pub mod old_jpeg;
+
+/// Expanding palette-color images to RGB
+pub mod palette;
+
+/// Converting YCbCr images to RGB
+pub mod ycbcr;
+
+/// Converting CMYK images to RGB
+pub mod cmyk;
+
+/// Extracting embedded color profiles and metadata
+#[cfg(feature = "std")]
+pub mod metadata;
+
+/// Parsing the date and time an image was created
+pub mod datetime;
+
+/// Reading the physical size of pixels
+pub mod resolution;
+
+/// Turning images the right way up for display
+pub mod orientation;
+
+/// Checking that IFDs have the tags readers require
+pub(crate) mod required_tags;
+
+/// Identifying Adobe DNG raw files
+pub mod dng;
+
+/// Reporting suspicious fields without rejecting the IFD
+pub mod lint;
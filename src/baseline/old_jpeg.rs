@@ -0,0 +1,26 @@
+use crate::baseline::ifd_values::{read_optional_value, read_required_value};
+use crate::baseline::tags;
+use crate::compression::Compression;
+use crate::errors::TiffError;
+use crate::lowlevel::{raw_field_values::read_bytes_at, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// Extract the JPEG stream embedded in an old-style JPEG (Compression 6) image, located by its
+/// JPEGInterchangeFormat and JPEGInterchangeFormatLength tags, so that it can be handed to an
+/// external JPEG decoder. The bytes are returned as stored, without checking they are valid.
+/// Returns `None` if `ifd` isn't compressed with old-style JPEG. Fails if either tag is missing,
+/// as in the variants spreading their JPEG data and tables across strips, which aren't supported.
+pub fn read_old_jpeg_stream<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<Vec<u8>>, TiffError> {
+    let compression = read_optional_value::<E, R>(reader, ifd, tags::COMPRESSION, 1)?;
+    if compression != u64::from(Compression::OldJpeg.as_u16()) {
+        return Ok(None);
+    }
+
+    let offset = read_required_value::<E, R>(reader, ifd, tags::JPEG_INTERCHANGE_FORMAT)?;
+    let len = read_required_value::<E, R>(reader, ifd, tags::JPEG_INTERCHANGE_FORMAT_LNGTH)?;
+    read_bytes_at(reader, offset, len).map(Some)
+}
@@ -0,0 +1,239 @@
+use crate::errors::{ImageDataError, TiffError};
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    lowlevel::RawIFD,
+};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Where the first row and column of an image belong when it is displayed, as given by its
+/// Orientation tag. Each variant is named for the visual position of the first row, then the
+/// first column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// The image is stored as displayed, which is the default.
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+    LeftTop,
+    RightTop,
+    RightBottom,
+    LeftBottom,
+}
+
+impl Orientation {
+    /// Interpret an Orientation value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Some(match value {
+            1 => Orientation::TopLeft,
+            2 => Orientation::TopRight,
+            3 => Orientation::BottomRight,
+            4 => Orientation::BottomLeft,
+            5 => Orientation::LeftTop,
+            6 => Orientation::RightTop,
+            7 => Orientation::RightBottom,
+            8 => Orientation::LeftBottom,
+            _ => return None,
+        })
+    }
+
+    /// The Orientation value for this orientation.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            Orientation::TopLeft => 1,
+            Orientation::TopRight => 2,
+            Orientation::BottomRight => 3,
+            Orientation::BottomLeft => 4,
+            Orientation::LeftTop => 5,
+            Orientation::RightTop => 6,
+            Orientation::RightBottom => 7,
+            Orientation::LeftBottom => 8,
+        }
+    }
+
+    /// Read the Orientation tag of `ifd`, or `TopLeft` if it is missing. Fails if it is
+    /// unrecognized.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::ORIENTATION, 1)?;
+        Self::from_u16(value as u16).ok_or_else(|| {
+            ImageDataError::UnknownOrientation {
+                value: value as u16,
+            }
+            .into()
+        })
+    }
+
+    /// Degrees (0, 90, 180 or 270) by which the stored image must be rotated clockwise for
+    /// display, before flipping it if `needs_horizontal_flip`.
+    pub fn clockwise_rotation(self) -> u16 {
+        match self {
+            Orientation::TopLeft | Orientation::TopRight => 0,
+            Orientation::RightTop | Orientation::LeftTop => 90,
+            Orientation::BottomRight | Orientation::BottomLeft => 180,
+            Orientation::LeftBottom | Orientation::RightBottom => 270,
+        }
+    }
+
+    /// Whether the stored image must be rotated by 90 degrees clockwise, and nothing more, for
+    /// display.
+    pub fn needs_90cw_rotation(self) -> bool {
+        self == Orientation::RightTop
+    }
+
+    /// Whether the image must be mirrored left to right for display, after rotating it by
+    /// `clockwise_rotation`.
+    pub fn needs_horizontal_flip(self) -> bool {
+        matches!(
+            self,
+            Orientation::TopRight
+                | Orientation::BottomLeft
+                | Orientation::LeftTop
+                | Orientation::RightBottom
+        )
+    }
+
+    /// Whether the image's width and height are exchanged for display.
+    pub fn swaps_dimensions(self) -> bool {
+        !self.clockwise_rotation().is_multiple_of(180)
+    }
+}
+
+/// Reorient `pixels`, an image of `width` by `height` pixels of `samples` bytes each stored with
+/// `orientation`, so that it is the right way up for display. Returns the new width, height and
+/// pixels; the width and height are exchanged if `orientation` swaps them. Fails with
+/// `ImageDataError::PixelBufferLength` unless `pixels` holds exactly `width * height * samples`
+/// bytes.
+pub fn apply_orientation(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    samples: usize,
+    orientation: Orientation,
+) -> Result<(u32, u32, Vec<u8>), TiffError> {
+    let expected = u64::from(width)
+        .checked_mul(u64::from(height))
+        .and_then(|len| len.checked_mul(samples as u64))
+        .unwrap_or(u64::MAX);
+    if expected != pixels.len() as u64 {
+        return Err(ImageDataError::PixelBufferLength {
+            expected,
+            actual: pixels.len(),
+        }
+        .into());
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = if orientation.swaps_dimensions() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    if pixels.is_empty() {
+        return Ok((new_width as u32, new_height as u32, Vec::new()));
+    }
+
+    let mut output = vec![0; pixels.len()];
+    for (index, pixel) in pixels.chunks_exact(samples).enumerate() {
+        let (row, col) = (index / width, index % width);
+        let (x, y) = match orientation {
+            Orientation::TopLeft => (col, row),
+            Orientation::TopRight => (width - 1 - col, row),
+            Orientation::BottomRight => (width - 1 - col, height - 1 - row),
+            Orientation::BottomLeft => (col, height - 1 - row),
+            Orientation::LeftTop => (row, col),
+            Orientation::RightTop => (height - 1 - row, col),
+            Orientation::RightBottom => (height - 1 - row, width - 1 - col),
+            Orientation::LeftBottom => (row, width - 1 - col),
+        };
+        let start = (y * new_width + x) * samples;
+        output[start..start + samples].copy_from_slice(pixel);
+    }
+    Ok((new_width as u32, new_height as u32, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3 by 2 image, one byte per pixel:
+    //   1 2 3
+    //   4 5 6
+    const PIXELS: [u8; 6] = [1, 2, 3, 4, 5, 6];
+
+    #[test]
+    fn apply_orientation_flips_and_rotates() {
+        let orient = |orientation| apply_orientation(&PIXELS, 3, 2, 1, orientation).unwrap();
+        assert_eq!(orient(Orientation::TopLeft), (3, 2, vec![1, 2, 3, 4, 5, 6]));
+        assert_eq!(
+            orient(Orientation::TopRight),
+            (3, 2, vec![3, 2, 1, 6, 5, 4])
+        );
+        assert_eq!(
+            orient(Orientation::BottomRight),
+            (3, 2, vec![6, 5, 4, 3, 2, 1])
+        );
+        assert_eq!(
+            orient(Orientation::BottomLeft),
+            (3, 2, vec![4, 5, 6, 1, 2, 3])
+        );
+        assert_eq!(orient(Orientation::LeftTop), (2, 3, vec![1, 4, 2, 5, 3, 6]));
+        assert_eq!(
+            orient(Orientation::RightTop),
+            (2, 3, vec![4, 1, 5, 2, 6, 3])
+        );
+        assert_eq!(
+            orient(Orientation::RightBottom),
+            (2, 3, vec![6, 3, 5, 2, 4, 1])
+        );
+        assert_eq!(
+            orient(Orientation::LeftBottom),
+            (2, 3, vec![3, 6, 2, 5, 1, 4])
+        );
+    }
+
+    #[test]
+    fn apply_orientation_keeps_samples_together() {
+        let pixels = [1, 10, 2, 20];
+        assert_eq!(
+            apply_orientation(&pixels, 2, 1, 2, Orientation::TopRight).unwrap(),
+            (2, 1, vec![2, 20, 1, 10])
+        );
+    }
+
+    #[test]
+    fn apply_orientation_rejects_wrong_buffer_length() {
+        let result = apply_orientation(&PIXELS[..5], 3, 2, 1, Orientation::RightTop);
+        assert!(matches!(
+            result,
+            Err(TiffError::ImageData(ImageDataError::PixelBufferLength {
+                expected: 6,
+                actual: 5
+            }))
+        ));
+        let result = apply_orientation(&PIXELS, 0, 2, 1, Orientation::BottomRight);
+        assert!(matches!(
+            result,
+            Err(TiffError::ImageData(
+                ImageDataError::PixelBufferLength { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn apply_orientation_accepts_empty_image() {
+        assert_eq!(
+            apply_orientation(&[], 0, 5, 3, Orientation::LeftTop).unwrap(),
+            (5, 0, Vec::new())
+        );
+    }
+}
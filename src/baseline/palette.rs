@@ -0,0 +1,123 @@
+#[cfg(feature = "std")]
+use crate::{
+    baseline::tags,
+    errors::{FieldExtractionError, ImageDataError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Read the ColorMap tag of `ifd` as the red, green and blue values of each palette index.
+/// The map is stored as every red value, then every green value, then every blue value, so it
+/// is regrouped here. Fails if the tag is missing or not a multiple of three values long.
+#[cfg(feature = "std")]
+pub fn read_color_map<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Vec<[u16; 3]>, TiffError> {
+    let values = ifd
+        .get(tags::COLOR_MAP)
+        .ok_or(FieldExtractionError::MissingTag {
+            tag: tags::COLOR_MAP,
+        })?
+        .as_u16_array::<E, R>(reader)?;
+    if values.len() % 3 != 0 {
+        return Err(ImageDataError::InvalidColorMap { len: values.len() }.into());
+    }
+
+    let (reds, rest) = values.split_at(values.len() / 3);
+    let (greens, blues) = rest.split_at(values.len() / 3);
+    Ok(reds
+        .iter()
+        .zip(greens)
+        .zip(blues)
+        .map(|((&red, &green), &blue)| [red, green, blue])
+        .collect())
+}
+
+/// Look up each of `indices` in `color_map`, producing interleaved 8-bit RGB samples. The 16-bit
+/// map entries are scaled down by keeping their high byte. Indices past the end of the map are
+/// treated as black.
+pub fn expand_palette(indices: &[u8], color_map: &[[u16; 3]]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(indices.len() * 3);
+    for &index in indices {
+        let color = color_map.get(index as usize).copied().unwrap_or([0; 3]);
+        rgb.extend(color.iter().map(|&sample| (sample >> 8) as u8));
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    use crate::lowlevel::tiff_builder::single_page_file;
+    #[cfg(feature = "std")]
+    use crate::lowlevel::{read_ifds_auto, Endianness, IFDField, LittleEndian, IFD};
+    #[cfg(feature = "std")]
+    use std::io::Cursor;
+
+    /// An 8x4 palette image written by libtiff, whose 256-entry ColorMap gives index `i` a red
+    /// of `i * 257`, a green of `(255 - i) * 257` and a blue of `(i * 7 % 256) << 8`.
+    #[cfg(feature = "std")]
+    const LIBTIFF_PALETTE: &[u8] = include_bytes!("../../testdata/libtiff_palette.tif");
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn expands_libtiff_palette_image() {
+        use crate::baseline::decode::for_each_decoded_strip;
+
+        let mut reader = Cursor::new(LIBTIFF_PALETTE);
+        let (_, ifds) = read_ifds_auto(&mut reader).unwrap();
+        let ifd = &ifds.into_classic().unwrap()[0];
+
+        let color_map = read_color_map::<LittleEndian, _>(&mut reader, ifd).unwrap();
+        assert_eq!(color_map.len(), 256);
+        for (i, &color) in color_map.iter().enumerate() {
+            let i = i as u16;
+            assert_eq!(color, [i * 257, (255 - i) * 257, (i * 7 % 256) << 8]);
+        }
+
+        let mut indices = Vec::new();
+        for_each_decoded_strip::<LittleEndian, _, _>(&mut reader, ifd, |_, _, strip| {
+            indices.extend_from_slice(strip);
+            Ok(())
+        })
+        .unwrap();
+        let expected: Vec<u8> = (0..4u32)
+            .flat_map(|y| (0..8u32).map(move |x| (x * 37 + y * 11) % 256))
+            .flat_map(|i| [i, 255 - i, i * 7 % 256])
+            .map(|sample| sample as u8)
+            .collect();
+        assert_eq!(expand_palette(&indices, &color_map), expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_color_map_rejects_partial_map() {
+        let mut ifd = IFD::new();
+        ifd.entries
+            .insert(tags::COLOR_MAP, IFDField::Short(vec![0; 5].into()));
+        let (buf, ifd) = single_page_file(ifd, Vec::new(), Endianness::Little);
+        assert!(matches!(
+            read_color_map::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::InvalidColorMap {
+                len: 5
+            }))
+        ));
+    }
+
+    #[test]
+    fn expand_palette_treats_missing_indices_as_black() {
+        let color_map = [[0xFFFF, 0x1234, 0x00FF]];
+        assert_eq!(
+            expand_palette(&[0, 1], &color_map),
+            vec![0xFF, 0x12, 0x00, 0, 0, 0]
+        );
+    }
+}
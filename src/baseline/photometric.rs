@@ -0,0 +1,173 @@
+use crate::baseline::constants::photometic_interpretation::*;
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_required_value, tags},
+    errors::{ImageDataError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// The color space of an image's pixels, as given by its PhotometricInterpretation tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Photometric {
+    /// Bilevel or grayscale where 0 is white, so larger values are darker.
+    WhiteIsZero,
+    /// Bilevel or grayscale where 0 is black, so larger values are brighter.
+    BlackIsZero,
+    Rgb,
+    /// Samples are indices into the ColorMap.
+    Palette,
+    /// A 1-bit mask marking which pixels of another image are opaque.
+    TransparencyMask,
+    Cmyk,
+    YCbCr,
+    CieLab,
+}
+
+impl Photometric {
+    /// Interpret a PhotometricInterpretation value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Some(match value {
+            WHITEISZERO => Photometric::WhiteIsZero,
+            BLACKISZERO => Photometric::BlackIsZero,
+            RGB => Photometric::Rgb,
+            RGB_PALETTE => Photometric::Palette,
+            TRANSPARENCY_MASK => Photometric::TransparencyMask,
+            CMYK => Photometric::Cmyk,
+            YCBCR => Photometric::YCbCr,
+            CIELAB => Photometric::CieLab,
+            _ => return None,
+        })
+    }
+
+    /// The PhotometricInterpretation value for this color space.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            Photometric::WhiteIsZero => WHITEISZERO,
+            Photometric::BlackIsZero => BLACKISZERO,
+            Photometric::Rgb => RGB,
+            Photometric::Palette => RGB_PALETTE,
+            Photometric::TransparencyMask => TRANSPARENCY_MASK,
+            Photometric::Cmyk => CMYK,
+            Photometric::YCbCr => YCBCR,
+            Photometric::CieLab => CIELAB,
+        }
+    }
+
+    /// Read the PhotometricInterpretation tag of `ifd`. Fails if it is missing or unrecognized.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_required_value::<E, R>(reader, ifd, tags::PHOTOMETRIC_INTERPRETATION)?;
+        Self::from_u16(value as u16).ok_or_else(|| {
+            ImageDataError::UnknownPhotometric {
+                value: value as u16,
+            }
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Photometric; 8] = [
+        Photometric::WhiteIsZero,
+        Photometric::BlackIsZero,
+        Photometric::Rgb,
+        Photometric::Palette,
+        Photometric::TransparencyMask,
+        Photometric::Cmyk,
+        Photometric::YCbCr,
+        Photometric::CieLab,
+    ];
+
+    #[test]
+    fn u16_round_trip() {
+        for photometric in ALL {
+            assert_eq!(
+                Photometric::from_u16(photometric.as_u16()),
+                Some(photometric)
+            );
+        }
+        assert_eq!(
+            Photometric::from_u16(WHITEISZERO),
+            Some(Photometric::WhiteIsZero)
+        );
+        assert_eq!(
+            Photometric::from_u16(BLACKISZERO),
+            Some(Photometric::BlackIsZero)
+        );
+        assert_eq!(Photometric::from_u16(7), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_ifd_distinguishes_white_and_black_is_zero() {
+        use crate::baseline::decode::decode_gray8;
+        use crate::errors::FieldExtractionError;
+        use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+        use crate::lowlevel::{Endianness, IFDField, LittleEndian};
+        use std::io::Cursor;
+
+        // A 2x1 8-bit grayscale image of a black and a light pixel, if zero is black
+        let file = |photometric: Option<u16>| {
+            let mut ifd = image_ifd(2, 1, &[8], 1);
+            match photometric {
+                Some(photometric) => {
+                    let field = IFDField::Short(vec![photometric].into());
+                    ifd.entries.insert(tags::PHOTOMETRIC_INTERPRETATION, field);
+                }
+                None => {
+                    ifd.entries.remove(&tags::PHOTOMETRIC_INTERPRETATION);
+                }
+            }
+            single_page_file(ifd, vec![vec![0, 200]], Endianness::Little)
+        };
+
+        // The same samples are inverted for WhiteIsZero, so larger values are always brighter
+        let (buf, ifd) = file(Some(WHITEISZERO));
+        let mut reader = Cursor::new(&buf);
+        assert_eq!(
+            Photometric::from_ifd::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            Photometric::WhiteIsZero
+        );
+        assert_eq!(
+            decode_gray8::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            (2, 1, vec![255, 55])
+        );
+
+        let (buf, ifd) = file(Some(BLACKISZERO));
+        let mut reader = Cursor::new(&buf);
+        assert_eq!(
+            Photometric::from_ifd::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            Photometric::BlackIsZero
+        );
+        assert_eq!(
+            decode_gray8::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            (2, 1, vec![0, 200])
+        );
+
+        let (buf, ifd) = file(Some(7));
+        assert!(matches!(
+            Photometric::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::UnknownPhotometric {
+                value: 7
+            }))
+        ));
+
+        let (buf, ifd) = file(None);
+        assert!(matches!(
+            Photometric::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::Field(FieldExtractionError::MissingTag {
+                tag: tags::PHOTOMETRIC_INTERPRETATION
+            }))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::baseline::{
+    dimensions::image_dimensions,
+    photometric::Photometric,
+    samples::{bits_per_sample, samples_per_pixel},
+};
+use crate::compression::Compression;
+use crate::errors::TiffError;
+use crate::lowlevel::{read_ifds_auto, BigEndian, Endianness, LittleEndian, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// The size and format of a page, read by `ImageInfo::from_ifd` or for every page by `probe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// Number of bits in each sample, with one entry per sample, or a single entry for every
+    /// sample if that is all the file gives.
+    pub bits_per_sample: Vec<u16>,
+    /// Number of samples in each pixel.
+    pub samples_per_pixel: u16,
+    /// Color space of the pixels.
+    pub photometric: Photometric,
+    /// How the image data is compressed.
+    pub compression: Compression,
+}
+
+impl ImageInfo {
+    /// Read the size and format of the image described by `ifd` from its tags alone, without
+    /// reading any image data. Missing tags take their defaults, except for the dimensions and
+    /// PhotometricInterpretation, which are required. Fails if any of the tags are malformed, or
+    /// the photometric interpretation or compression is unrecognized.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let (width, height) = image_dimensions::<E, R>(reader, ifd)?;
+        Ok(Self {
+            width,
+            height,
+            bits_per_sample: bits_per_sample::<E, R>(reader, ifd)?,
+            samples_per_pixel: samples_per_pixel::<E, R>(reader, ifd)?,
+            photometric: Photometric::from_ifd::<E, R>(reader, ifd)?,
+            compression: Compression::from_ifd::<E, R>(reader, ifd)?,
+        })
+    }
+}
+
+/// Read the header and IFDs of a classic TIFF file from `reader`, in whichever byte order it
+/// declares, and describe each page in the main chain as `ImageInfo::from_ifd` does. Only the
+/// IFDs and the values of the few tags involved are read, never the image data, so this is
+/// cheap even for huge files. Assumes the cursor is positioned at the beginning of the file.
+/// Fails with `TiffError::UnexpectedBigTiff` if the file is a BigTIFF.
+pub fn probe<R: ReadBytesExt + Seek>(reader: &mut R) -> Result<Vec<ImageInfo>, TiffError> {
+    let (endianness, ifds) = read_ifds_auto(reader)?;
+    let ifds = ifds.into_classic()?;
+    ifds.iter()
+        .map(|ifd| match endianness {
+            Endianness::Little => ImageInfo::from_ifd::<LittleEndian, R>(reader, ifd),
+            Endianness::Big => ImageInfo::from_ifd::<BigEndian, R>(reader, ifd),
+        })
+        .collect()
+}
@@ -0,0 +1,411 @@
+use crate::baseline::{
+    decode::{read_image_data, DecodeLimits},
+    ifd_values::read_optional_value,
+    samples::{bits_per_sample, samples_per_pixel},
+    tags,
+};
+use crate::errors::{ImageDataError, TiffError};
+use crate::lowlevel::{metadata_writer::classic_offset, RawIFD, RawIFDFieldBuilder, TiffWriter};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Seek, Write};
+
+/// Tags describing how image data is stored, rather than what it holds, which are replaced when
+/// the data is reorganized.
+const STORAGE_TAGS: &[u16] = &[
+    tags::COMPRESSION,
+    tags::FILL_ORDER,
+    tags::ROWS_PER_STRIP,
+    tags::PLANAR_CONFIGURATION,
+    tags::T4_OPTIONS,
+    tags::T6_OPTIONS,
+    tags::PREDICTOR,
+    tags::TILE_WIDTH,
+    tags::TILE_LENGTH,
+    tags::JPEG_PROC,
+    tags::JPEG_RESTART_INTERVAL,
+    tags::JPEG_LOSSLESS_PREDICTORS,
+    tags::JPEG_POINT_TRANSFORMS,
+];
+
+/// Tags pointing to other IFDs, which aren't copied along with the image.
+const IFD_TAGS: &[u16] = &[tags::SUB_IFDS, tags::EXIF_IFD, tags::GPS_IFD];
+
+/// An image reorganized into strips or tiles by `restrip_to_tiles` or `retile_to_strips`, ready
+/// for `write_to`. Its fields are encoded in the byte order of the file it was read from.
+#[derive(Debug, Clone)]
+pub struct Rechunked {
+    /// The IFD describing the image, with the offsets of its strips or tiles still zero.
+    pub ifd: RawIFD,
+    /// The data of each field of `ifd` which isn't inline, keyed by tag.
+    pub field_data: HashMap<u16, Vec<u8>>,
+    /// The uncompressed strips or tiles, in order.
+    pub chunks: Vec<Vec<u8>>,
+    /// The StripOffsets or TileOffsets tag, to be set once the chunks are placed.
+    offsets_tag: u16,
+}
+
+impl Rechunked {
+    /// Write the strips or tiles with `writer`, then the IFD locating them. `E` must be the
+    /// byte order of both the file the image was read from and `writer`. Returns the position
+    /// within the file of the IFD. Fails with `TiffError::OffsetOverflow` if any strip or tile
+    /// would lie beyond what a classic TIFF can address.
+    pub fn write_to<E: ByteOrder, W: Write + Seek>(
+        mut self,
+        writer: &mut TiffWriter<W>,
+    ) -> Result<u64, TiffError> {
+        let mut offsets = Vec::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            let position = writer.write_data(chunk)?;
+            offsets.push(classic_offset(position)?);
+            // The whole chunk must lie within reach of a classic offset
+            classic_offset(position + chunk.len() as u64)?;
+        }
+        RawIFDFieldBuilder::<E>::long(self.offsets_tag, &offsets)?
+            .set_into(&mut self.ifd, &mut self.field_data);
+        writer.add_ifd(&self.ifd, &self.field_data)
+    }
+}
+
+/// Decode the image of `ifd`, from strips or tiles with any supported compression, and cut it
+/// into uncompressed tiles `tile_width` by `tile_length` pixels, with those at the right and
+/// bottom edges padded with zeros. The new IFD keeps the other fields of `ifd`, with their data
+/// read from `reader`, except that planar images become chunky and the SubIFDs, Exif IFD and
+/// GPS IFD tags are dropped, as the IFDs they refer to aren't copied. Fails with
+/// `ImageDataError::InvalidTileSize` unless both dimensions are multiples of 16, as TIFF
+/// requires, or if the image can't be decoded.
+pub fn restrip_to_tiles<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tile_width: u32,
+    tile_length: u32,
+) -> Result<Rechunked, TiffError> {
+    restrip_to_tiles_with_limits::<E, R>(
+        reader,
+        ifd,
+        tile_width,
+        tile_length,
+        DecodeLimits::default(),
+    )
+}
+
+/// Cut the image of `ifd` into tiles as `restrip_to_tiles` does, decoding it bounded in size by
+/// `limits`.
+pub fn restrip_to_tiles_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    tile_width: u32,
+    tile_length: u32,
+    limits: DecodeLimits,
+) -> Result<Rechunked, TiffError> {
+    if tile_width == 0
+        || tile_length == 0
+        || !tile_width.is_multiple_of(16)
+        || !tile_length.is_multiple_of(16)
+    {
+        return Err(ImageDataError::InvalidTileSize {
+            width: tile_width,
+            length: tile_length,
+        }
+        .into());
+    }
+    let (width, height, bits_per_pixel, data) = read_image::<E, R>(reader, ifd, limits)?;
+
+    let row_len = (width as usize * bits_per_pixel).div_ceil(8);
+    // Tile widths are multiples of 16, so tile rows are whole bytes and start on byte boundaries
+    let tile_row_len = tile_width as usize * bits_per_pixel / 8;
+    let mut tiles = Vec::new();
+    for tile_row in 0..height.div_ceil(tile_length) {
+        for tile_col in 0..width.div_ceil(tile_width) {
+            let mut tile = vec![0; tile_row_len * tile_length as usize];
+            let start = tile_col as usize * tile_row_len;
+            let len = tile_row_len.min(row_len - start);
+            let first_row = tile_row as usize * tile_length as usize;
+            let rows = data[first_row * row_len..]
+                .chunks(row_len)
+                .take(tile_length as usize);
+            for (tile_row, row) in tile.chunks_mut(tile_row_len).zip(rows) {
+                tile_row[..len].copy_from_slice(&row[start..start + len]);
+            }
+            tiles.push(tile);
+        }
+    }
+
+    let (mut new_ifd, mut field_data) = copy_fields::<E, R>(reader, ifd)?;
+    let byte_counts = chunk_byte_counts(&tiles)?;
+    for built in [
+        RawIFDFieldBuilder::<E>::short(tags::COMPRESSION, &[1]),
+        RawIFDFieldBuilder::<E>::long(tags::TILE_WIDTH, &[tile_width]),
+        RawIFDFieldBuilder::<E>::long(tags::TILE_LENGTH, &[tile_length]),
+        RawIFDFieldBuilder::<E>::long(tags::TILE_OFFSETS, &vec![0; tiles.len()]),
+        RawIFDFieldBuilder::<E>::long(tags::TILE_BYTE_COUNTS, &byte_counts),
+    ] {
+        built?.set_into(&mut new_ifd, &mut field_data);
+    }
+    Ok(Rechunked {
+        ifd: new_ifd,
+        field_data,
+        chunks: tiles,
+        offsets_tag: tags::TILE_OFFSETS,
+    })
+}
+
+/// Decode the image of `ifd` as `restrip_to_tiles` does, and cut it into uncompressed strips
+/// of `rows_per_strip` rows, the last of which may be shorter. Fails with
+/// `ImageDataError::InvalidRowsPerStrip` if `rows_per_strip` is zero, or if the image can't be
+/// decoded.
+pub fn retile_to_strips<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    rows_per_strip: u32,
+) -> Result<Rechunked, TiffError> {
+    retile_to_strips_with_limits::<E, R>(reader, ifd, rows_per_strip, DecodeLimits::default())
+}
+
+/// Cut the image of `ifd` into strips as `retile_to_strips` does, decoding it bounded in size by
+/// `limits`.
+pub fn retile_to_strips_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    rows_per_strip: u32,
+    limits: DecodeLimits,
+) -> Result<Rechunked, TiffError> {
+    if rows_per_strip == 0 {
+        return Err(ImageDataError::InvalidRowsPerStrip { rows_per_strip }.into());
+    }
+    let (width, _, bits_per_pixel, data) = read_image::<E, R>(reader, ifd, limits)?;
+
+    let strip_len = (width as usize * bits_per_pixel).div_ceil(8) * rows_per_strip as usize;
+    let strips: Vec<Vec<u8>> = data.chunks(strip_len.max(1)).map(<[u8]>::to_vec).collect();
+
+    let (mut new_ifd, mut field_data) = copy_fields::<E, R>(reader, ifd)?;
+    let byte_counts = chunk_byte_counts(&strips)?;
+    for built in [
+        RawIFDFieldBuilder::<E>::short(tags::COMPRESSION, &[1]),
+        RawIFDFieldBuilder::<E>::long(tags::ROWS_PER_STRIP, &[rows_per_strip]),
+        RawIFDFieldBuilder::<E>::long(tags::STRIP_OFFSETS, &vec![0; strips.len()]),
+        RawIFDFieldBuilder::<E>::long(tags::STRIP_BYTE_COUNTS, &byte_counts),
+    ] {
+        built?.set_into(&mut new_ifd, &mut field_data);
+    }
+    Ok(Rechunked {
+        ifd: new_ifd,
+        field_data,
+        chunks: strips,
+        offsets_tag: tags::STRIP_OFFSETS,
+    })
+}
+
+/// Length of each of `chunks`, for StripByteCounts or TileByteCounts. Fails with
+/// `ImageDataError::ChunkTooLarge` if any is too long for a LONG.
+fn chunk_byte_counts(chunks: &[Vec<u8>]) -> Result<Vec<u32>, TiffError> {
+    chunks
+        .iter()
+        .map(|chunk| {
+            u32::try_from(chunk.len())
+                .map_err(|_| ImageDataError::ChunkTooLarge { len: chunk.len() }.into())
+        })
+        .collect()
+}
+
+/// Decode the whole image of `ifd`, bounded in size by `limits`, into chunky rows, each
+/// starting on a byte boundary. Returns its width, height, bits per pixel and data.
+fn read_image<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    limits: DecodeLimits,
+) -> Result<(u32, u32, usize, Vec<u8>), TiffError> {
+    let samples = samples_per_pixel::<E, R>(reader, ifd)?;
+    let bits = bits_per_sample::<E, R>(reader, ifd)?;
+    let first_bits = bits.first().copied().unwrap_or(1);
+    // Pixels without any bits, and samples of differing sizes or too large for any decoder,
+    // aren't supported
+    if samples == 0
+        || first_bits == 0
+        || bits.iter().any(|&bits| bits != first_bits)
+        || first_bits > u16::from(u8::MAX)
+    {
+        return Err(ImageDataError::UnsupportedFormat {
+            photometric: read_optional_value::<E, R>(
+                reader,
+                ifd,
+                tags::PHOTOMETRIC_INTERPRETATION,
+                0,
+            )? as u16,
+            samples_per_pixel: samples,
+            bits_per_sample: first_bits,
+        }
+        .into());
+    }
+    let (width, height, data) =
+        read_image_data::<E, R>(reader, ifd, usize::from(samples), first_bits as u8, limits)?;
+    Ok((
+        width,
+        height,
+        usize::from(samples) * usize::from(first_bits),
+        data,
+    ))
+}
+
+/// Copy the fields of `ifd` which describe what the image holds, reading the data of those
+/// which aren't inline from `reader`.
+fn copy_fields<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<(RawIFD, HashMap<u16, Vec<u8>>), TiffError> {
+    let data_tags = ifd.data_tags();
+    let mut new_ifd = RawIFD {
+        entries: Vec::with_capacity(ifd.len()),
+    };
+    let mut field_data = HashMap::new();
+    for field in ifd {
+        if data_tags.contains(&field.tag)
+            || STORAGE_TAGS.contains(&field.tag)
+            || IFD_TAGS.contains(&field.tag)
+        {
+            continue;
+        }
+        if field.is_inline() == Some(false) {
+            field_data.insert(field.tag, field.read_value_bytes::<E, R>(reader)?);
+        }
+        new_ifd.set_field(*field);
+    }
+    Ok((new_ifd, field_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::decode::decode_gray8;
+    use crate::lowlevel::metadata_writer::Sink;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{read_ifds_auto, Endianness, IFDField, LittleEndian};
+    use std::io::Cursor;
+
+    /// A little-endian file holding a 20 x 20 grayscale gradient in one strip, with its IFD.
+    fn gradient() -> (Vec<u8>, RawIFD, Vec<u8>) {
+        gradient_with(Vec::new())
+    }
+
+    /// A file as made by `gradient`, but with the fields of `extra` added or replacing its own.
+    fn gradient_with(extra: Vec<(u16, IFDField)>) -> (Vec<u8>, RawIFD, Vec<u8>) {
+        let pixels: Vec<u8> = (0..400).map(|i| i as u8).collect();
+        let mut ifd = image_ifd(20, 20, &[8], 1);
+        ifd.entries.extend(extra);
+        let (buf, ifd) = single_page_file(ifd, vec![pixels.clone()], Endianness::Little);
+        (buf, ifd, pixels)
+    }
+
+    fn first_ifd(buf: &[u8]) -> RawIFD {
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(buf)).unwrap();
+        ifds.into_classic().unwrap()[0].clone()
+    }
+
+    /// Write `rechunked` as the only page of a new file, and decode it again.
+    fn rewrite(rechunked: Rechunked) -> (u32, u32, Vec<u8>) {
+        let mut writer =
+            TiffWriter::write_header(Cursor::new(Vec::new()), Endianness::Little).unwrap();
+        rechunked.write_to::<LittleEndian, _>(&mut writer).unwrap();
+        let buf = writer.finish().unwrap().into_inner();
+        decode_gray8::<LittleEndian, _>(&mut Cursor::new(&buf), &first_ifd(&buf)).unwrap()
+    }
+
+    #[test]
+    fn restrip_and_retile_round_trip() {
+        let (buf, ifd, pixels) = gradient();
+        let tiled =
+            restrip_to_tiles::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, 16, 16).unwrap();
+        assert_eq!(tiled.chunks.len(), 4);
+        assert!(tiled.chunks.iter().all(|tile| tile.len() == 256));
+        // The padding of the edge tiles is zero
+        assert_eq!(&tiled.chunks[1][4..16], &[0; 12]);
+        assert_eq!(rewrite(tiled), (20, 20, pixels.clone()));
+
+        let stripped =
+            retile_to_strips::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, 8).unwrap();
+        let lens: Vec<_> = stripped.chunks.iter().map(Vec::len).collect();
+        assert_eq!(lens, [160, 160, 80]);
+        assert_eq!(rewrite(stripped), (20, 20, pixels));
+    }
+
+    #[test]
+    fn rejects_pixels_without_bits() {
+        for (tag, value) in [(tags::BITS_PER_SAMPLE, 0), (tags::SAMPLES_PER_PIXEL, 0)] {
+            let (buf, ifd, _) = gradient_with(vec![(tag, IFDField::Short(vec![value].into()))]);
+            assert!(matches!(
+                restrip_to_tiles::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, 16, 16),
+                Err(TiffError::ImageData(
+                    ImageDataError::UnsupportedFormat { .. }
+                ))
+            ));
+            assert!(matches!(
+                retile_to_strips::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, 8),
+                Err(TiffError::ImageData(
+                    ImageDataError::UnsupportedFormat { .. }
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn rechunking_applies_decode_limits() {
+        let (buf, ifd, _) = gradient();
+        let limits = |max_decoded_len| DecodeLimits { max_decoded_len };
+        let too_large = |result: Result<Rechunked, TiffError>| {
+            matches!(
+                result,
+                Err(TiffError::ImageData(ImageDataError::ImageTooLarge {
+                    bytes: 400,
+                    ..
+                }))
+            )
+        };
+
+        let tiled = restrip_to_tiles_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            &ifd,
+            16,
+            16,
+            limits(399),
+        );
+        assert!(too_large(tiled));
+        let stripped = retile_to_strips_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            &ifd,
+            8,
+            limits(399),
+        );
+        assert!(too_large(stripped));
+
+        let stripped = retile_to_strips_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            &ifd,
+            8,
+            limits(400),
+        );
+        assert_eq!(stripped.unwrap().chunks.len(), 3);
+    }
+
+    #[test]
+    fn chunk_byte_counts_are_chunk_lengths() {
+        let chunks = vec![vec![0; 3], Vec::new(), vec![0; 70000]];
+        assert_eq!(chunk_byte_counts(&chunks).unwrap(), [3, 0, 70000]);
+    }
+
+    #[test]
+    fn write_to_rejects_chunks_past_classic_offsets() {
+        let (buf, ifd, _) = gradient();
+        let tiled =
+            restrip_to_tiles::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd, 16, 16).unwrap();
+        // The header ends just short of 4 GiB, so the tiles can't all be addressed
+        let sink = Sink {
+            position: u64::from(u32::MAX) - 8 - 300,
+        };
+        let mut writer = TiffWriter::write_header(sink, Endianness::Little).unwrap();
+        assert!(matches!(
+            tiled.write_to::<LittleEndian, _>(&mut writer),
+            Err(TiffError::OffsetOverflow { .. })
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Rearrange `buf`, an image `width` by `height` pixels of `samples` interleaved samples of
+/// `bytes_per_sample` bytes each (PlanarConfiguration 1), into one plane per sample, one after
+/// another (PlanarConfiguration 2). Samples are moved whole, so multi-byte samples keep their
+/// byte order. Samples missing from the end of a short `buf` are treated as zero, and any
+/// excess is ignored.
+pub fn chunky_to_planar(
+    buf: &[u8],
+    width: usize,
+    height: usize,
+    samples: usize,
+    bytes_per_sample: usize,
+) -> Vec<u8> {
+    let pixels = width * height;
+    let mut planar = vec![0; pixels * samples * bytes_per_sample];
+    if bytes_per_sample == 0 {
+        return planar;
+    }
+    let plane_len = pixels * bytes_per_sample;
+    for (index, sample) in buf
+        .chunks_exact(bytes_per_sample)
+        .take(pixels * samples)
+        .enumerate()
+    {
+        let (pixel, plane) = (index / samples, index % samples);
+        let start = plane * plane_len + pixel * bytes_per_sample;
+        planar[start..start + bytes_per_sample].copy_from_slice(sample);
+    }
+    planar
+}
+
+/// Rearrange `buf`, an image `width` by `height` pixels stored as `samples` planes of samples
+/// of `bytes_per_sample` bytes each, into interleaved pixels. The inverse of
+/// `chunky_to_planar`, which describes the treatment of short buffers.
+pub fn planar_to_chunky(
+    buf: &[u8],
+    width: usize,
+    height: usize,
+    samples: usize,
+    bytes_per_sample: usize,
+) -> Vec<u8> {
+    let pixels = width * height;
+    let mut chunky = vec![0; pixels * samples * bytes_per_sample];
+    if bytes_per_sample == 0 {
+        return chunky;
+    }
+    for (index, sample) in buf
+        .chunks_exact(bytes_per_sample)
+        .take(pixels * samples)
+        .enumerate()
+    {
+        let (plane, pixel) = (index / pixels, index % pixels);
+        let start = (pixel * samples + plane) * bytes_per_sample;
+        chunky[start..start + bytes_per_sample].copy_from_slice(sample);
+    }
+    chunky
+}
@@ -0,0 +1,62 @@
+use crate::baseline::{photometric::Photometric, tags};
+use crate::lowlevel::RawIFD;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Tags every image needs, besides those locating its strips or tiles.
+const COMMON_TAGS: &[u16] = &[
+    tags::IMAGE_WIDTH,
+    tags::IMAGE_LENGTH,
+    tags::COMPRESSION,
+    tags::PHOTOMETRIC_INTERPRETATION,
+];
+
+/// Tags locating the image data of a stripped image.
+const STRIP_TAGS: &[u16] = &[
+    tags::STRIP_OFFSETS,
+    tags::ROWS_PER_STRIP,
+    tags::STRIP_BYTE_COUNTS,
+];
+
+/// Tags locating the image data of a tiled image.
+const TILE_TAGS: &[u16] = &[
+    tags::TILE_WIDTH,
+    tags::TILE_LENGTH,
+    tags::TILE_OFFSETS,
+    tags::TILE_BYTE_COUNTS,
+];
+
+impl RawIFD {
+    /// Check that this IFD has every tag a reader requires for an image of type `photometric`,
+    /// returning the missing tags in ascending order otherwise. The image is taken to be tiled if
+    /// it has any tile tags, and stripped otherwise.
+    pub fn validate_baseline(&self, photometric: Photometric) -> Result<(), Vec<u16>> {
+        let layout_tags = if TILE_TAGS.iter().any(|&tag| self.contains_tag(tag)) {
+            TILE_TAGS
+        } else {
+            STRIP_TAGS
+        };
+        let photometric_tags: &[u16] = match photometric {
+            // A transparency mask is always 1 bit per pixel, the default
+            Photometric::TransparencyMask => &[],
+            Photometric::WhiteIsZero | Photometric::BlackIsZero => &[tags::BITS_PER_SAMPLE],
+            Photometric::Palette => &[tags::BITS_PER_SAMPLE, tags::COLOR_MAP],
+            Photometric::Rgb | Photometric::Cmyk | Photometric::YCbCr | Photometric::CieLab => {
+                &[tags::BITS_PER_SAMPLE, tags::SAMPLES_PER_PIXEL]
+            }
+        };
+
+        let mut missing: Vec<u16> = COMMON_TAGS
+            .iter()
+            .chain(layout_tags)
+            .chain(photometric_tags)
+            .copied()
+            .filter(|&tag| !self.contains_tag(tag))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        missing.sort_unstable();
+        Err(missing)
+    }
+}
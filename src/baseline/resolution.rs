@@ -0,0 +1,102 @@
+use crate::baseline::constants::resolution_unit::*;
+use crate::lowlevel::Rational;
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    errors::{FieldExtractionError, ImageDataError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Centimeters in an inch.
+const CM_PER_INCH: f64 = 2.54;
+
+/// The unit of an image's XResolution and YResolution, as given by its ResolutionUnit tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResolutionUnit {
+    /// No absolute unit, so the resolutions only give the aspect ratio of the pixels.
+    None,
+    Inch,
+    Centimeter,
+}
+
+impl ResolutionUnit {
+    /// Interpret a ResolutionUnit value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Some(match value {
+            NONE => ResolutionUnit::None,
+            INCH => ResolutionUnit::Inch,
+            CENTIMETER => ResolutionUnit::Centimeter,
+            _ => return None,
+        })
+    }
+
+    /// The ResolutionUnit value for this unit.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            ResolutionUnit::None => NONE,
+            ResolutionUnit::Inch => INCH,
+            ResolutionUnit::Centimeter => CENTIMETER,
+        }
+    }
+}
+
+/// The number of pixels per `unit` in each direction of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Resolution {
+    pub x: Rational,
+    pub y: Rational,
+    pub unit: ResolutionUnit,
+}
+
+impl Resolution {
+    /// Resolution assumed when an image doesn't specify one: 72 pixels per inch.
+    pub const DEFAULT: Self = Self {
+        x: Rational { num: 72, den: 1 },
+        y: Rational { num: 72, den: 1 },
+        unit: ResolutionUnit::Inch,
+    };
+
+    /// Read the XResolution, YResolution and ResolutionUnit tags of `ifd`, defaulting any which
+    /// are missing as in `Resolution::DEFAULT`. Fails if the unit is unrecognized.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let mut read_rational = |tag, default| -> Result<Rational, TiffError> {
+            match ifd.get(tag) {
+                Some(field) => field
+                    .as_rational_array::<E, R>(reader)?
+                    .first()
+                    .copied()
+                    .ok_or_else(|| FieldExtractionError::InsufficientData.into()),
+                None => Ok(default),
+            }
+        };
+        let x = read_rational(tags::X_RESOLUTION, Self::DEFAULT.x)?;
+        let y = read_rational(tags::Y_RESOLUTION, Self::DEFAULT.y)?;
+
+        let value = read_optional_value::<E, R>(reader, ifd, tags::RESOLUTION_UNIT, INCH.into())?;
+        let unit = ResolutionUnit::from_u16(value as u16).ok_or(
+            ImageDataError::UnknownResolutionUnit {
+                value: value as u16,
+            },
+        )?;
+        Ok(Self { x, y, unit })
+    }
+
+    /// The horizontal and vertical resolution in dots (pixels) per inch, converted from
+    /// centimeters if necessary. Returns `None` if the unit is `ResolutionUnit::None`.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        let scale = match self.unit {
+            ResolutionUnit::None => return None,
+            ResolutionUnit::Inch => 1.0,
+            ResolutionUnit::Centimeter => CM_PER_INCH,
+        };
+        Some((self.x.as_f64() * scale, self.y.as_f64() * scale))
+    }
+}
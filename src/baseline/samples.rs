@@ -0,0 +1,192 @@
+use crate::baseline::{ifd_values::read_optional_value, tags};
+use crate::errors::{ImageDataError, TiffError};
+use crate::lowlevel::RawIFD;
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// Read the BitsPerSample tag of `ifd`, with one entry per sample. Defaults to a single 1-bit
+/// sample if the tag is missing.
+pub fn bits_per_sample<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Vec<u16>, TiffError> {
+    match ifd.get(tags::BITS_PER_SAMPLE) {
+        Some(field) => Ok(field
+            .as_unsigned_array::<E, R>(reader)?
+            .into_iter()
+            .map(|bits| bits as u16)
+            .collect()),
+        None => Ok(vec![1]),
+    }
+}
+
+/// Read the SamplesPerPixel tag of `ifd`. Defaults to 1 if the tag is missing.
+pub fn samples_per_pixel<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<u16, TiffError> {
+    match ifd.get(tags::SAMPLES_PER_PIXEL) {
+        Some(field) => Ok(field
+            .as_unsigned_array::<E, R>(reader)?
+            .first()
+            .copied()
+            .unwrap_or(1) as u16),
+        None => Ok(1),
+    }
+}
+
+/// What an extra sample (beyond those the photometric interpretation calls for) holds, as given
+/// by the ExtraSamples tag, which has one entry per extra sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtraSamples {
+    /// Data of no particular meaning.
+    Unspecified,
+    /// Alpha which the color samples have already been multiplied by (premultiplied alpha).
+    AssociatedAlpha,
+    /// Alpha which is independent of the color samples.
+    UnassociatedAlpha,
+}
+
+impl ExtraSamples {
+    /// Interpret an ExtraSamples value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(ExtraSamples::Unspecified),
+            1 => Some(ExtraSamples::AssociatedAlpha),
+            2 => Some(ExtraSamples::UnassociatedAlpha),
+            _ => None,
+        }
+    }
+
+    /// The ExtraSamples value for this meaning.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            ExtraSamples::Unspecified => 0,
+            ExtraSamples::AssociatedAlpha => 1,
+            ExtraSamples::UnassociatedAlpha => 2,
+        }
+    }
+
+    /// Returns true if the sample is alpha, of either kind.
+    pub fn is_alpha(self) -> bool {
+        self != ExtraSamples::Unspecified
+    }
+
+    /// Read the ExtraSamples tag of `ifd`, giving the meaning of each extra sample in order.
+    /// Returns an empty Vec if the tag is missing. Fails if any value is unrecognized.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Vec<Self>, TiffError> {
+        let values = match ifd.get(tags::EXTRA_SAMPLES) {
+            Some(field) => field.as_unsigned_array::<E, R>(reader)?,
+            None => return Ok(Vec::new()),
+        };
+        values
+            .into_iter()
+            .map(|value| {
+                Self::from_u16(value as u16).ok_or_else(|| {
+                    ImageDataError::UnknownExtraSample {
+                        value: value as u16,
+                    }
+                    .into()
+                })
+            })
+            .collect()
+    }
+}
+
+/// How the bits of each sample are interpreted, as given by the SampleFormat tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+    /// Unsigned integers, which is the default.
+    Unsigned,
+    /// Two's complement signed integers.
+    Signed,
+    /// IEEE floating point numbers.
+    Float,
+    /// Data of no particular format.
+    Undefined,
+}
+
+impl SampleFormat {
+    /// Interpret a SampleFormat value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(SampleFormat::Unsigned),
+            2 => Some(SampleFormat::Signed),
+            3 => Some(SampleFormat::Float),
+            4 => Some(SampleFormat::Undefined),
+            _ => None,
+        }
+    }
+
+    /// The SampleFormat value for this format.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            SampleFormat::Unsigned => 1,
+            SampleFormat::Signed => 2,
+            SampleFormat::Float => 3,
+            SampleFormat::Undefined => 4,
+        }
+    }
+
+    /// Read the SampleFormat tag of `ifd`, or `Unsigned` if it is missing. The tag has an entry
+    /// per sample, but only the first is read, as images whose samples differ in format aren't
+    /// supported. Fails if it is unrecognized.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::SAMPLE_FORMAT, 1)?;
+        Self::from_u16(value as u16).ok_or_else(|| {
+            ImageDataError::UnknownSampleFormat {
+                value: value as u16,
+            }
+            .into()
+        })
+    }
+}
+
+/// How the samples of each pixel are arranged, as given by the PlanarConfiguration tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlanarConfig {
+    /// The samples of each pixel are stored together, which is the default.
+    Chunky,
+    /// Each sample is stored in its own plane, with separate strips or tiles.
+    Planar,
+}
+
+impl PlanarConfig {
+    /// Interpret a PlanarConfiguration value, returning `None` if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(PlanarConfig::Chunky),
+            2 => Some(PlanarConfig::Planar),
+            _ => None,
+        }
+    }
+
+    /// The PlanarConfiguration value for this arrangement.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            PlanarConfig::Chunky => 1,
+            PlanarConfig::Planar => 2,
+        }
+    }
+
+    /// Read the PlanarConfiguration tag of `ifd`, or `Chunky` if it is missing. Fails if it is
+    /// unrecognized.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::PLANAR_CONFIGURATION, 1)?;
+        Self::from_u16(value as u16).ok_or_else(|| {
+            ImageDataError::UnsupportedPlanarConfiguration {
+                planar_configuration: value as u16,
+            }
+            .into()
+        })
+    }
+}
@@ -0,0 +1,308 @@
+use crate::baseline::ifd_values::{read_optional_value, read_required_array};
+use crate::baseline::{
+    decode::MAX_DECODED_LEN,
+    dimensions::image_dimensions,
+    samples::{bits_per_sample, samples_per_pixel, PlanarConfig},
+    tags,
+};
+use crate::errors::{ImageDataError, TiffError};
+use crate::lowlevel::{raw_field_values::read_bytes_at, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// Largest RowsPerStrip, the default, meaning the whole image is one strip.
+const ROWS_PER_STRIP_DEFAULT: u64 = u32::MAX as u64;
+
+/// The locations of an image's strips, from its StripOffsets and StripByteCounts tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripInfo {
+    /// File position of each strip.
+    pub offsets: Vec<u64>,
+    /// Length in bytes of each strip, after compression.
+    pub byte_counts: Vec<u64>,
+}
+
+impl StripInfo {
+    /// Read the strip locations described by `ifd`, which may be stored as SHORTs or LONGs.
+    /// Fails if either tag is missing, or unless both describe exactly the `strip_count` strips
+    /// needed to cover the image, for each sample if it is planar, so that a corrupt file is
+    /// rejected here rather than partway through decoding.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let offsets = read_required_array::<E, R>(reader, ifd, tags::STRIP_OFFSETS)?;
+        let byte_counts = read_required_array::<E, R>(reader, ifd, tags::STRIP_BYTE_COUNTS)?;
+        let planes = match PlanarConfig::from_ifd::<E, R>(reader, ifd)? {
+            PlanarConfig::Chunky => 1,
+            PlanarConfig::Planar => usize::from(samples_per_pixel::<E, R>(reader, ifd)?),
+        };
+        let expected = strip_count::<E, R>(reader, ifd)? as usize * planes;
+        if offsets.len() != expected || byte_counts.len() != expected {
+            return Err(ImageDataError::StripCountMismatch {
+                offsets: offsets.len(),
+                byte_counts: byte_counts.len(),
+                expected,
+            }
+            .into());
+        }
+
+        Ok(Self {
+            offsets,
+            byte_counts,
+        })
+    }
+
+    /// Number of strips in the image.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns true if the image has no strips.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Read the raw (still compressed) bytes of the strip at `index`. The cursor position of
+    /// `reader` is restored afterwards.
+    pub fn read_strip<R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+        index: usize,
+    ) -> Result<Vec<u8>, TiffError> {
+        match (self.offsets.get(index), self.byte_counts.get(index)) {
+            (Some(&offset), Some(&byte_count)) => read_bytes_at(reader, offset, byte_count),
+            _ => Err(ImageDataError::StripOutOfRange {
+                index,
+                count: self.len(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Read the bytes of the strip at `index` of `strip_info` exactly as stored, without
+/// decompressing or otherwise interpreting them, so they can be copied into another file. Fails
+/// if there is no such strip, or it runs past the end of the file. The cursor position of
+/// `reader` is restored afterwards.
+pub fn read_strip_raw<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    strip_info: &StripInfo,
+    index: usize,
+) -> Result<Vec<u8>, TiffError> {
+    strip_info.read_strip(reader, index)
+}
+
+/// Read the RowsPerStrip tag of `ifd`. The value is clamped to between 1 and the image height,
+/// as the default when the tag is missing, and common in practice, is 2^32 - 1 to mean that the
+/// whole image is one strip.
+pub fn rows_per_strip<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<u32, TiffError> {
+    let (_, height) = image_dimensions::<E, R>(reader, ifd)?;
+    let rows_per_strip =
+        read_optional_value::<E, R>(reader, ifd, tags::ROWS_PER_STRIP, ROWS_PER_STRIP_DEFAULT)?;
+    Ok(rows_per_strip.clamp(1, u64::from(height.max(1))) as u32)
+}
+
+/// Number of strips needed to cover each plane of the image described by `ifd`, from its height
+/// and `rows_per_strip`. This is one for an image without the RowsPerStrip tag, or none for an
+/// image with no rows.
+pub fn strip_count<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<u32, TiffError> {
+    let (_, height) = image_dimensions::<E, R>(reader, ifd)?;
+    Ok(height.div_ceil(rows_per_strip::<E, R>(reader, ifd)?))
+}
+
+/// Read every strip of the image described by `ifd`, decompress each with `decode`, and stitch
+/// the results together, top to bottom, into the rows of the whole image. `decode` is given the
+/// raw bytes of a strip and the number of rows in it, which is fewer than RowsPerStrip for the
+/// last strip if the image height isn't a multiple of it. Any data `decode` returns beyond those
+/// rows, such as padding to a whole strip, is dropped. Rows are assumed to be of chunky pixels,
+/// each starting on a byte boundary. Fails if the number of strips doesn't match the image, or a
+/// strip decodes to too few rows.
+pub fn assemble_strips<E, R, F>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    mut decode: F,
+) -> Result<Vec<u8>, TiffError>
+where
+    E: ByteOrder,
+    R: ReadBytesExt + Seek,
+    F: FnMut(&[u8], u32) -> Result<Vec<u8>, TiffError>,
+{
+    let (width, height) = image_dimensions::<E, R>(reader, ifd)?;
+    let samples = usize::from(samples_per_pixel::<E, R>(reader, ifd)?);
+    let bits = bits_per_sample::<E, R>(reader, ifd)?;
+    // Writers often give a single BitsPerSample for every sample
+    let bits_per_pixel = match bits.len() {
+        len if len == samples => bits.iter().map(|&bits| u64::from(bits)).sum(),
+        _ => u64::from(bits.first().copied().unwrap_or(1)) * samples as u64,
+    };
+    // Computed without overflowing, so that the lengths below fit in a `usize`
+    let row_len = u64::from(width).saturating_mul(bits_per_pixel).div_ceil(8);
+    let image_len = row_len.saturating_mul(u64::from(height));
+    if image_len > MAX_DECODED_LEN {
+        return Err(ImageDataError::ImageTooLarge {
+            bytes: image_len,
+            limit: MAX_DECODED_LEN,
+        }
+        .into());
+    }
+    let row_len = row_len as usize;
+
+    let rows_per_strip = rows_per_strip::<E, R>(reader, ifd)?;
+    let strips = StripInfo::from_ifd::<E, R>(reader, ifd)?;
+
+    // Only the strips of the first plane, if the image is planar. The image grows as strips
+    // are decoded, rather than trusting its dimensions to allocate all of it up front
+    let mut image = Vec::new();
+    for index in 0..height.div_ceil(rows_per_strip) as usize {
+        let rows = rows_per_strip.min(height - index as u32 * rows_per_strip);
+        let mut strip = decode(&strips.read_strip(reader, index)?, rows)?;
+        let expected = row_len * rows as usize;
+        if strip.len() < expected {
+            return Err(ImageDataError::ShortStrip {
+                index,
+                expected,
+                actual: strip.len(),
+            }
+            .into());
+        }
+        strip.truncate(expected);
+        image.append(&mut strip);
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{Endianness, IFDField, LittleEndian};
+    use std::io::Cursor;
+
+    /// A little-endian file holding a 3x5 8-bit grayscale image whose pixels count up from 0, in
+    /// `strips`, with `rows_per_strip` as its RowsPerStrip tag if there is one. Returns it with
+    /// its IFD.
+    fn file_in_strips(rows_per_strip: Option<u32>, strips: Vec<Vec<u8>>) -> (Vec<u8>, RawIFD) {
+        let mut ifd = image_ifd(3, 5, &[8], 1);
+        match rows_per_strip {
+            Some(rows_per_strip) => {
+                let field = IFDField::Long(vec![rows_per_strip].into());
+                ifd.entries.insert(tags::ROWS_PER_STRIP, field);
+            }
+            None => {
+                ifd.entries.remove(&tags::ROWS_PER_STRIP);
+            }
+        }
+        single_page_file(ifd, strips, Endianness::Little)
+    }
+
+    /// The image of `file_in_strips` in strips of two rows, so that the last strip has only one.
+    fn file() -> (Vec<u8>, RawIFD) {
+        let strips = vec![(0..6).collect(), (6..12).collect(), (12..15).collect()];
+        file_in_strips(Some(2), strips)
+    }
+
+    #[test]
+    fn assembles_strips_with_short_last_strip() {
+        let (buf, ifd) = file();
+        let mut rows = Vec::new();
+        let image =
+            assemble_strips::<LittleEndian, _, _>(&mut Cursor::new(&buf), &ifd, |raw, n| {
+                rows.push(n);
+                // Pad every strip to a whole RowsPerStrip, as some codecs do
+                let mut strip = raw.to_vec();
+                strip.resize(6, 0xEE);
+                Ok(strip)
+            })
+            .unwrap();
+        assert_eq!(rows, [2, 2, 1]);
+        assert_eq!(image, (0..15).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn assemble_strips_rejects_short_strip() {
+        let (buf, ifd) = file();
+        let result =
+            assemble_strips::<LittleEndian, _, _>(&mut Cursor::new(&buf), &ifd, |raw, _| {
+                Ok(raw[..raw.len() - 1].to_vec())
+            });
+        assert!(matches!(
+            result,
+            Err(TiffError::ImageData(ImageDataError::ShortStrip {
+                index: 0,
+                expected: 6,
+                actual: 5
+            }))
+        ));
+    }
+
+    #[test]
+    fn strip_count_rounds_up() {
+        let (buf, ifd) = file();
+        let mut reader = Cursor::new(&buf);
+        assert_eq!(
+            rows_per_strip::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            2
+        );
+        assert_eq!(
+            strip_count::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn missing_rows_per_strip_means_one_strip() {
+        let (buf, ifd) = file_in_strips(None, vec![(0..15).collect()]);
+        let mut reader = Cursor::new(&buf);
+        assert_eq!(
+            rows_per_strip::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            5
+        );
+        assert_eq!(
+            strip_count::<LittleEndian, _>(&mut reader, &ifd).unwrap(),
+            1
+        );
+        assert_eq!(
+            StripInfo::from_ifd::<LittleEndian, _>(&mut reader, &ifd)
+                .unwrap()
+                .len(),
+            1
+        );
+        let image =
+            assemble_strips::<LittleEndian, _, _>(&mut reader, &ifd, |raw, _| Ok(raw.to_vec()));
+        assert_eq!(image.unwrap(), (0..15).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn from_ifd_rejects_strip_count_mismatch() {
+        // Two strips where RowsPerStrip needs three
+        let (buf, ifd) = file_in_strips(Some(2), vec![(0..6).collect(), (6..15).collect()]);
+        assert!(matches!(
+            StripInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::StripCountMismatch {
+                offsets: 2,
+                byte_counts: 2,
+                expected: 3
+            }))
+        ));
+
+        // Three offsets, but only two byte counts
+        let (buf, mut ifd) = file();
+        ifd.get_mut(tags::STRIP_BYTE_COUNTS).unwrap().count = 2;
+        assert!(matches!(
+            StripInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd),
+            Err(TiffError::ImageData(ImageDataError::StripCountMismatch {
+                offsets: 3,
+                byte_counts: 2,
+                expected: 3
+            }))
+        ));
+        assert!(StripInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &file().1).is_ok());
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    errors::TiffError,
+    lowlevel::RawIFD,
+};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// The kind of image an IFD holds, as given by the NewSubfileType tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SubfileType {
+    /// A reduced-resolution version of another image in the file, such as a thumbnail.
+    pub reduced_resolution: bool,
+    /// A single page of a multi-page image.
+    pub single_page: bool,
+    /// A transparency mask for another image in the file.
+    pub transparency_mask: bool,
+}
+
+impl SubfileType {
+    /// Interpret a NewSubfileType value. Unknown bits are ignored.
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            reduced_resolution: value & 1 != 0,
+            single_page: value & 2 != 0,
+            transparency_mask: value & 4 != 0,
+        }
+    }
+
+    /// The NewSubfileType value for this kind of image.
+    pub fn as_u32(self) -> u32 {
+        u32::from(self.reduced_resolution)
+            | u32::from(self.single_page) << 1
+            | u32::from(self.transparency_mask) << 2
+    }
+
+    /// Interpret a value of the deprecated SubfileType tag: 1 for a full-resolution image, 2 for
+    /// a reduced-resolution image, or 3 for a single page of a multi-page image. Other values
+    /// are treated as 1.
+    pub fn from_old_u16(value: u16) -> Self {
+        Self {
+            reduced_resolution: value == 2,
+            single_page: value == 3,
+            transparency_mask: false,
+        }
+    }
+
+    /// Read the NewSubfileType tag of `ifd`, or if only the deprecated SubfileType tag is
+    /// present, translate that instead. With neither, no flags are set, as for a single
+    /// full-resolution image.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        if !ifd.contains_tag(tags::NEW_SUBFILE_TYPE) && ifd.contains_tag(tags::SUBFILE_TYPE) {
+            let value = read_optional_value::<E, R>(reader, ifd, tags::SUBFILE_TYPE, 1)?;
+            return Ok(Self::from_old_u16(value as u16));
+        }
+        let value = read_optional_value::<E, R>(reader, ifd, tags::NEW_SUBFILE_TYPE, 0)?;
+        Ok(Self::from_u32(value as u32))
+    }
+}
@@ -48,6 +48,7 @@ pub const TILE_WIDTH: u16 = 322;
 pub const TILE_LENGTH: u16 = 323;
 pub const TILE_OFFSETS: u16 = 324;
 pub const TILE_BYTE_COUNTS: u16 = 325;
+pub const SUB_IFDS: u16 = 330;
 pub const INK_SET: u16 = 332;
 pub const INK_NAMES: u16 = 333;
 pub const NUMBER_OF_INKS: u16 = 334;
@@ -58,6 +59,7 @@ pub const SAMPLE_FORMAT: u16 = 339;
 pub const S_MIN_SAMPLE_VALUE: u16 = 340;
 pub const S_MAX_SAMPLE_VALUE: u16 = 341;
 pub const TRANSFER_RANGE: u16 = 342;
+pub const JPEG_TABLES: u16 = 347;
 pub const JPEG_PROC: u16 = 512;
 pub const JPEG_INTERCHANGE_FORMAT: u16 = 513;
 pub const JPEG_INTERCHANGE_FORMAT_LNGTH: u16 = 514;
@@ -71,4 +73,231 @@ pub const Y_CBCR_COEFFICIENTS: u16 = 529;
 pub const Y_CBCR_SUB_SAMPLING: u16 = 530;
 pub const Y_CBCR_POSITIONING: u16 = 531;
 pub const REFERENCE_BLACK_WHITE: u16 = 532;
+pub const XMP: u16 = 700;
+pub const CFA_REPEAT_PATTERN_DIM: u16 = 33421;
+pub const CFA_PATTERN: u16 = 33422;
 pub const COPYRIGHT: u16 = 33432;
+pub const MODEL_PIXEL_SCALE: u16 = 33550;
+pub const IPTC: u16 = 33723;
+pub const MODEL_TIEPOINT: u16 = 33922;
+pub const MODEL_TRANSFORMATION: u16 = 34264;
+pub const PHOTOSHOP: u16 = 34377;
+pub const EXIF_IFD: u16 = 34665;
+pub const ICC_PROFILE: u16 = 34675;
+pub const GEO_KEY_DIRECTORY: u16 = 34735;
+pub const GEO_DOUBLE_PARAMS: u16 = 34736;
+pub const GEO_ASCII_PARAMS: u16 = 34737;
+pub const GPS_IFD: u16 = 34853;
+pub const GDAL_METADATA: u16 = 42112;
+pub const GDAL_NODATA: u16 = 42113;
+pub const DNG_VERSION: u16 = 50706;
+pub const DNG_BACKWARD_VERSION: u16 = 50707;
+pub const UNIQUE_CAMERA_MODEL: u16 = 50708;
+pub const LOCALIZED_CAMERA_MODEL: u16 = 50709;
+pub const CFA_PLANE_COLOR: u16 = 50710;
+pub const CFA_LAYOUT: u16 = 50711;
+pub const LINEARIZATION_TABLE: u16 = 50712;
+pub const BLACK_LEVEL_REPEAT_DIM: u16 = 50713;
+pub const BLACK_LEVEL: u16 = 50714;
+pub const BLACK_LEVEL_DELTA_H: u16 = 50715;
+pub const BLACK_LEVEL_DELTA_V: u16 = 50716;
+pub const WHITE_LEVEL: u16 = 50717;
+pub const DEFAULT_SCALE: u16 = 50718;
+pub const DEFAULT_CROP_ORIGIN: u16 = 50719;
+pub const DEFAULT_CROP_SIZE: u16 = 50720;
+pub const COLOR_MATRIX_1: u16 = 50721;
+pub const COLOR_MATRIX_2: u16 = 50722;
+pub const CAMERA_CALIBRATION_1: u16 = 50723;
+pub const CAMERA_CALIBRATION_2: u16 = 50724;
+pub const REDUCTION_MATRIX_1: u16 = 50725;
+pub const REDUCTION_MATRIX_2: u16 = 50726;
+pub const ANALOG_BALANCE: u16 = 50727;
+pub const AS_SHOT_NEUTRAL: u16 = 50728;
+pub const AS_SHOT_WHITE_XY: u16 = 50729;
+pub const BASELINE_EXPOSURE: u16 = 50730;
+pub const BASELINE_NOISE: u16 = 50731;
+pub const BASELINE_SHARPNESS: u16 = 50732;
+pub const BAYER_GREEN_SPLIT: u16 = 50733;
+pub const LINEAR_RESPONSE_LIMIT: u16 = 50734;
+pub const CAMERA_SERIAL_NUMBER: u16 = 50735;
+pub const LENS_INFO: u16 = 50736;
+pub const CHROMA_BLUR_RADIUS: u16 = 50737;
+pub const ANTI_ALIAS_STRENGTH: u16 = 50738;
+pub const SHADOW_SCALE: u16 = 50739;
+pub const DNG_PRIVATE_DATA: u16 = 50740;
+pub const MAKER_NOTE_SAFETY: u16 = 50741;
+pub const CALIBRATION_ILLUMINANT_1: u16 = 50778;
+pub const CALIBRATION_ILLUMINANT_2: u16 = 50779;
+pub const BEST_QUALITY_SCALE: u16 = 50780;
+pub const RAW_DATA_UNIQUE_ID: u16 = 50781;
+pub const ORIGINAL_RAW_FILE_NAME: u16 = 50827;
+pub const ACTIVE_AREA: u16 = 50829;
+pub const MASKED_AREAS: u16 = 50830;
+pub const CAMERA_CALIBRATION_SIGNATURE: u16 = 50931;
+pub const PROFILE_CALIBRATION_SIGNATURE: u16 = 50932;
+pub const PROFILE_NAME: u16 = 50936;
+pub const FORWARD_MATRIX_1: u16 = 50964;
+pub const FORWARD_MATRIX_2: u16 = 50965;
+
+/// Names of the tags above as written in their specifications, sorted by tag
+static TAG_NAMES: &[(u16, &str)] = &[
+    (NEW_SUBFILE_TYPE, "NewSubfileType"),
+    (SUBFILE_TYPE, "SubfileType"),
+    (IMAGE_WIDTH, "ImageWidth"),
+    (IMAGE_LENGTH, "ImageLength"),
+    (BITS_PER_SAMPLE, "BitsPerSample"),
+    (COMPRESSION, "Compression"),
+    (PHOTOMETRIC_INTERPRETATION, "PhotometricInterpretation"),
+    (THRESHHOLDING, "Threshholding"),
+    (CELL_WIDTH, "CellWidth"),
+    (CELL_LENGTH, "CellLength"),
+    (FILL_ORDER, "FillOrder"),
+    (DOCUMENT_NAME, "DocumentName"),
+    (IMAGE_DESCRIPTION, "ImageDescription"),
+    (MAKE, "Make"),
+    (MODEL, "Model"),
+    (STRIP_OFFSETS, "StripOffsets"),
+    (ORIENTATION, "Orientation"),
+    (SAMPLES_PER_PIXEL, "SamplesPerPixel"),
+    (ROWS_PER_STRIP, "RowsPerStrip"),
+    (STRIP_BYTE_COUNTS, "StripByteCounts"),
+    (MIN_SAMPLE_VALUE, "MinSampleValue"),
+    (MAX_SAMPLE_VALUE, "MaxSampleValue"),
+    (X_RESOLUTION, "XResolution"),
+    (Y_RESOLUTION, "YResolution"),
+    (PLANAR_CONFIGURATION, "PlanarConfiguration"),
+    (PAGE_NAME, "PageName"),
+    (X_POSITION, "XPosition"),
+    (Y_POSITION, "YPosition"),
+    (FREE_OFFSETS, "FreeOffsets"),
+    (FREE_BYTE_COUNTS, "FreeByteCounts"),
+    (GRAY_RESPONSE_UNIT, "GrayResponseUnit"),
+    (GRAY_RESPONSE_CURVE, "GrayResponseCurve"),
+    (T4_OPTIONS, "T4Options"),
+    (T6_OPTIONS, "T6Options"),
+    (RESOLUTION_UNIT, "ResolutionUnit"),
+    (PAGE_NUMBER, "PageNumber"),
+    (TRANSFER_FUNCTION, "TransferFunction"),
+    (SOFTWARE, "Software"),
+    (DATE_TIME, "DateTime"),
+    (ARTIST, "Artist"),
+    (HOST_COMPUTER, "HostComputer"),
+    (PREDICTOR, "Predictor"),
+    (WHITE_POINT, "WhitePoint"),
+    (PRIMARY_CHROMATICITIES, "PrimaryChromaticities"),
+    (COLOR_MAP, "ColorMap"),
+    (HALFTONE_HINTS, "HalftoneHints"),
+    (TILE_WIDTH, "TileWidth"),
+    (TILE_LENGTH, "TileLength"),
+    (TILE_OFFSETS, "TileOffsets"),
+    (TILE_BYTE_COUNTS, "TileByteCounts"),
+    (SUB_IFDS, "SubIFDs"),
+    (INK_SET, "InkSet"),
+    (INK_NAMES, "InkNames"),
+    (NUMBER_OF_INKS, "NumberOfInks"),
+    (DOT_RANGE, "DotRange"),
+    (TARGET_PRINTER, "TargetPrinter"),
+    (EXTRA_SAMPLES, "ExtraSamples"),
+    (SAMPLE_FORMAT, "SampleFormat"),
+    (S_MIN_SAMPLE_VALUE, "SMinSampleValue"),
+    (S_MAX_SAMPLE_VALUE, "SMaxSampleValue"),
+    (TRANSFER_RANGE, "TransferRange"),
+    (JPEG_TABLES, "JPEGTables"),
+    (JPEG_PROC, "JPEGProc"),
+    (JPEG_INTERCHANGE_FORMAT, "JPEGInterchangeFormat"),
+    (JPEG_INTERCHANGE_FORMAT_LNGTH, "JPEGInterchangeFormatLngth"),
+    (JPEG_RESTART_INTERVAL, "JPEGRestartInterval"),
+    (JPEG_LOSSLESS_PREDICTORS, "JPEGLosslessPredictors"),
+    (JPEG_POINT_TRANSFORMS, "JPEGPointTransforms"),
+    (JPEGQ_TABLES, "JPEGQTables"),
+    (JPEGDC_TABLES, "JPEGDCTables"),
+    (JPEGAC_TABLES, "JPEGACTables"),
+    (Y_CBCR_COEFFICIENTS, "YCbCrCoefficients"),
+    (Y_CBCR_SUB_SAMPLING, "YCbCrSubSampling"),
+    (Y_CBCR_POSITIONING, "YCbCrPositioning"),
+    (REFERENCE_BLACK_WHITE, "ReferenceBlackWhite"),
+    (XMP, "XMP"),
+    (CFA_REPEAT_PATTERN_DIM, "CFARepeatPatternDim"),
+    (CFA_PATTERN, "CFAPattern"),
+    (COPYRIGHT, "Copyright"),
+    (MODEL_PIXEL_SCALE, "ModelPixelScale"),
+    (IPTC, "IPTC"),
+    (MODEL_TIEPOINT, "ModelTiepoint"),
+    (MODEL_TRANSFORMATION, "ModelTransformation"),
+    (PHOTOSHOP, "Photoshop"),
+    (EXIF_IFD, "ExifIFD"),
+    (ICC_PROFILE, "ICCProfile"),
+    (GEO_KEY_DIRECTORY, "GeoKeyDirectory"),
+    (GEO_DOUBLE_PARAMS, "GeoDoubleParams"),
+    (GEO_ASCII_PARAMS, "GeoAsciiParams"),
+    (GPS_IFD, "GPSIFD"),
+    (GDAL_METADATA, "GDALMetadata"),
+    (GDAL_NODATA, "GDALNoData"),
+    (DNG_VERSION, "DNGVersion"),
+    (DNG_BACKWARD_VERSION, "DNGBackwardVersion"),
+    (UNIQUE_CAMERA_MODEL, "UniqueCameraModel"),
+    (LOCALIZED_CAMERA_MODEL, "LocalizedCameraModel"),
+    (CFA_PLANE_COLOR, "CFAPlaneColor"),
+    (CFA_LAYOUT, "CFALayout"),
+    (LINEARIZATION_TABLE, "LinearizationTable"),
+    (BLACK_LEVEL_REPEAT_DIM, "BlackLevelRepeatDim"),
+    (BLACK_LEVEL, "BlackLevel"),
+    (BLACK_LEVEL_DELTA_H, "BlackLevelDeltaH"),
+    (BLACK_LEVEL_DELTA_V, "BlackLevelDeltaV"),
+    (WHITE_LEVEL, "WhiteLevel"),
+    (DEFAULT_SCALE, "DefaultScale"),
+    (DEFAULT_CROP_ORIGIN, "DefaultCropOrigin"),
+    (DEFAULT_CROP_SIZE, "DefaultCropSize"),
+    (COLOR_MATRIX_1, "ColorMatrix1"),
+    (COLOR_MATRIX_2, "ColorMatrix2"),
+    (CAMERA_CALIBRATION_1, "CameraCalibration1"),
+    (CAMERA_CALIBRATION_2, "CameraCalibration2"),
+    (REDUCTION_MATRIX_1, "ReductionMatrix1"),
+    (REDUCTION_MATRIX_2, "ReductionMatrix2"),
+    (ANALOG_BALANCE, "AnalogBalance"),
+    (AS_SHOT_NEUTRAL, "AsShotNeutral"),
+    (AS_SHOT_WHITE_XY, "AsShotWhiteXY"),
+    (BASELINE_EXPOSURE, "BaselineExposure"),
+    (BASELINE_NOISE, "BaselineNoise"),
+    (BASELINE_SHARPNESS, "BaselineSharpness"),
+    (BAYER_GREEN_SPLIT, "BayerGreenSplit"),
+    (LINEAR_RESPONSE_LIMIT, "LinearResponseLimit"),
+    (CAMERA_SERIAL_NUMBER, "CameraSerialNumber"),
+    (LENS_INFO, "LensInfo"),
+    (CHROMA_BLUR_RADIUS, "ChromaBlurRadius"),
+    (ANTI_ALIAS_STRENGTH, "AntiAliasStrength"),
+    (SHADOW_SCALE, "ShadowScale"),
+    (DNG_PRIVATE_DATA, "DNGPrivateData"),
+    (MAKER_NOTE_SAFETY, "MakerNoteSafety"),
+    (CALIBRATION_ILLUMINANT_1, "CalibrationIlluminant1"),
+    (CALIBRATION_ILLUMINANT_2, "CalibrationIlluminant2"),
+    (BEST_QUALITY_SCALE, "BestQualityScale"),
+    (RAW_DATA_UNIQUE_ID, "RawDataUniqueID"),
+    (ORIGINAL_RAW_FILE_NAME, "OriginalRawFileName"),
+    (ACTIVE_AREA, "ActiveArea"),
+    (MASKED_AREAS, "MaskedAreas"),
+    (CAMERA_CALIBRATION_SIGNATURE, "CameraCalibrationSignature"),
+    (PROFILE_CALIBRATION_SIGNATURE, "ProfileCalibrationSignature"),
+    (PROFILE_NAME, "ProfileName"),
+    (FORWARD_MATRIX_1, "ForwardMatrix1"),
+    (FORWARD_MATRIX_2, "ForwardMatrix2"),
+];
+
+/// Return the name of known tags, such as `ImageWidth` for 256
+pub fn tag_name(tag: u16) -> Option<&'static str> {
+    TAG_NAMES
+        .binary_search_by_key(&tag, |(tag, _)| *tag)
+        .ok()
+        .map(|idx| TAG_NAMES[idx].1)
+}
+
+/// Tags within the GPS IFD, which are numbered independently of those above
+pub mod gps {
+    pub const GPS_VERSION_ID: u16 = 0;
+    pub const GPS_LATITUDE_REF: u16 = 1;
+    pub const GPS_LATITUDE: u16 = 2;
+    pub const GPS_LONGITUDE_REF: u16 = 3;
+    pub const GPS_LONGITUDE: u16 = 4;
+    pub const GPS_ALTITUDE_REF: u16 = 5;
+    pub const GPS_ALTITUDE: u16 = 6;
+}
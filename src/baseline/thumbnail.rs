@@ -0,0 +1,47 @@
+use crate::baseline::{decode::decode_rgb8, dimensions::image_dimensions, subfile::SubfileType};
+use crate::errors::TiffError;
+use crate::lowlevel::{read_sub_ifds, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// Find the smallest reduced-resolution image, such as a thumbnail, among `ifds` and the IFDs
+/// referenced by their SubIFDs tags. These are marked by the ReducedImage bit of NewSubfileType,
+/// or the deprecated SubfileType tag; see `SubfileType::from_ifd`.
+/// A copy of the IFD is returned, as it may have been read from a SubIFD rather than being one
+/// of `ifds`. Returns `None` if there is no such image.
+pub fn find_thumbnail_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifds: &[RawIFD],
+) -> Result<Option<RawIFD>, TiffError> {
+    let mut smallest: Option<(u64, RawIFD)> = None;
+    for ifd in ifds {
+        let sub_ifds = read_sub_ifds::<E, R>(reader, ifd)?;
+        for candidate in core::iter::once(ifd).chain(&sub_ifds) {
+            if !SubfileType::from_ifd::<E, R>(reader, candidate)?.reduced_resolution {
+                continue;
+            }
+
+            let (width, height) = image_dimensions::<E, R>(reader, candidate)?;
+            let area = u64::from(width) * u64::from(height);
+            if smallest
+                .as_ref()
+                .is_none_or(|(smallest, _)| area < *smallest)
+            {
+                smallest = Some((area, candidate.clone()));
+            }
+        }
+    }
+    Ok(smallest.map(|(_, ifd)| ifd))
+}
+
+/// Find the thumbnail among `ifds` as `find_thumbnail_ifd` does, and decode it as for
+/// `decode_rgb8`. Returns `None` if there is no thumbnail.
+pub fn decode_thumbnail<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifds: &[RawIFD],
+) -> Result<Option<(u32, u32, Vec<u8>)>, TiffError> {
+    match find_thumbnail_ifd::<E, R>(reader, ifds)? {
+        Some(ifd) => decode_rgb8::<E, R>(reader, &ifd).map(Some),
+        None => Ok(None),
+    }
+}
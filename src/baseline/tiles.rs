@@ -0,0 +1,275 @@
+use crate::baseline::ifd_values::{read_required_array, read_required_u32};
+use crate::baseline::{dimensions::image_dimensions, tags};
+use crate::errors::{ImageDataError, TiffError};
+use crate::lowlevel::{raw_field_values::read_bytes_at, RawIFD};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::io::Seek;
+
+/// The layout and locations of an image's tiles, from its TileWidth, TileLength, TileOffsets
+/// and TileByteCounts tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileInfo {
+    /// Width in pixels of the whole image.
+    pub image_width: u32,
+    /// Height in pixels of the whole image.
+    pub image_length: u32,
+    /// Width in pixels of each tile.
+    pub tile_width: u32,
+    /// Height in pixels of each tile.
+    pub tile_length: u32,
+    /// Number of tiles in each row of tiles.
+    pub tiles_across: u32,
+    /// Number of rows of tiles.
+    pub tiles_down: u32,
+    /// File position of each tile, row by row.
+    pub offsets: Vec<u64>,
+    /// Length in bytes of each tile, after compression.
+    pub byte_counts: Vec<u64>,
+}
+
+impl TileInfo {
+    /// Read the tile layout and locations described by `ifd`. Fails if any of the tags are
+    /// missing, the tile size isn't a multiple of 16 (as TIFF requires) or is too large for a
+    /// u32, or there are too few tiles to cover the image.
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let (image_width, image_length) = image_dimensions::<E, R>(reader, ifd)?;
+        let tile_width = read_required_u32::<E, R>(reader, ifd, tags::TILE_WIDTH)?;
+        let tile_length = read_required_u32::<E, R>(reader, ifd, tags::TILE_LENGTH)?;
+        if tile_width == 0
+            || tile_length == 0
+            || !tile_width.is_multiple_of(16)
+            || !tile_length.is_multiple_of(16)
+        {
+            return Err(ImageDataError::InvalidTileSize {
+                width: tile_width,
+                length: tile_length,
+            }
+            .into());
+        }
+
+        let offsets = read_required_array::<E, R>(reader, ifd, tags::TILE_OFFSETS)?;
+        let byte_counts = read_required_array::<E, R>(reader, ifd, tags::TILE_BYTE_COUNTS)?;
+        if offsets.len() != byte_counts.len() {
+            return Err(ImageDataError::TileCountMismatch {
+                offsets: offsets.len(),
+                byte_counts: byte_counts.len(),
+            }
+            .into());
+        }
+
+        // Tiles at the right and bottom edges may extend past the image
+        let tiles_across = image_width.div_ceil(tile_width);
+        let tiles_down = image_length.div_ceil(tile_length);
+        let expected = tiles_across as usize * tiles_down as usize;
+        if offsets.len() < expected {
+            return Err(ImageDataError::TooFewTiles {
+                expected,
+                actual: offsets.len(),
+            }
+            .into());
+        }
+
+        Ok(Self {
+            image_width,
+            image_length,
+            tile_width,
+            tile_length,
+            tiles_across,
+            tiles_down,
+            offsets,
+            byte_counts,
+        })
+    }
+
+    /// Number of tiles needed to cover the image (per sample plane, if planes are separate).
+    pub fn tile_count(&self) -> usize {
+        self.tiles_across as usize * self.tiles_down as usize
+    }
+
+    /// Read the raw (still compressed) bytes of the tile at column `col` and row `row`. Tiles
+    /// at the right and bottom edges are padded to the full tile size, so once decompressed,
+    /// the caller should crop them to the image. The cursor position of `reader` is restored
+    /// afterwards.
+    pub fn read_tile<R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+        col: u32,
+        row: u32,
+    ) -> Result<Vec<u8>, TiffError> {
+        if col >= self.tiles_across || row >= self.tiles_down {
+            return Err(ImageDataError::TileOutOfRange { col, row }.into());
+        }
+        let index = row as usize * self.tiles_across as usize + col as usize;
+        read_bytes_at(reader, self.offsets[index], self.byte_counts[index])
+    }
+}
+
+/// Read the bytes of the tile at `index` of `tile_info` exactly as stored, without
+/// decompressing or otherwise interpreting them, so they can be copied into another file. Tiles
+/// are numbered as in TileOffsets, so when sample planes are stored separately, indices past
+/// `tile_count` reach the tiles of the later planes. Fails if there is no such tile, or it runs
+/// past the end of the file. The cursor position of `reader` is restored afterwards.
+pub fn read_tile_raw<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    tile_info: &TileInfo,
+    index: usize,
+) -> Result<Vec<u8>, TiffError> {
+    match (
+        tile_info.offsets.get(index),
+        tile_info.byte_counts.get(index),
+    ) {
+        (Some(&offset), Some(&byte_count)) => read_bytes_at(reader, offset, byte_count),
+        _ => {
+            // An image of zero width has no tiles across, so no column to report
+            let across = tile_info.tiles_across as usize;
+            Err(ImageDataError::TileOutOfRange {
+                col: index.checked_rem(across).unwrap_or(0) as u32,
+                row: index
+                    .checked_div(across)
+                    .unwrap_or(index)
+                    .min(u32::MAX as usize) as u32,
+            }
+            .into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{Endianness, IFDField, LittleEndian};
+    use std::io::Cursor;
+
+    fn tile_info(image_width: u32, offsets: Vec<u64>, byte_counts: Vec<u64>) -> TileInfo {
+        TileInfo {
+            image_width,
+            image_length: 16,
+            tile_width: 16,
+            tile_length: 16,
+            tiles_across: image_width.div_ceil(16),
+            tiles_down: 1,
+            offsets,
+            byte_counts,
+        }
+    }
+
+    #[test]
+    fn read_tile_raw_reads_stored_bytes() {
+        let mut reader = Cursor::new((0..20).collect::<Vec<u8>>());
+        let tiles = tile_info(32, vec![4, 10], vec![3, 2]);
+        assert_eq!(
+            read_tile_raw(&mut reader, &tiles, 0).unwrap(),
+            vec![4, 5, 6]
+        );
+        assert_eq!(read_tile_raw(&mut reader, &tiles, 1).unwrap(), vec![10, 11]);
+        assert_eq!(reader.position(), 0);
+    }
+
+    #[test]
+    fn read_tile_raw_rejects_overrun() {
+        let mut reader = Cursor::new(vec![0; 20]);
+        let tiles = tile_info(16, vec![15], vec![6]);
+        assert!(matches!(
+            read_tile_raw(&mut reader, &tiles, 0),
+            Err(TiffError::OffsetOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn read_tile_raw_rejects_missing_tile() {
+        let mut reader = Cursor::new(vec![0; 20]);
+        let tiles = tile_info(32, vec![0, 0], vec![1, 1]);
+        assert!(matches!(
+            read_tile_raw(&mut reader, &tiles, 5),
+            Err(TiffError::ImageData(ImageDataError::TileOutOfRange {
+                col: 1,
+                row: 2
+            }))
+        ));
+    }
+
+    #[test]
+    fn read_tile_raw_rejects_any_tile_of_empty_image() {
+        let mut reader = Cursor::new(vec![0; 20]);
+        let tiles = tile_info(0, Vec::new(), Vec::new());
+        assert!(matches!(
+            read_tile_raw(&mut reader, &tiles, 3),
+            Err(TiffError::ImageData(ImageDataError::TileOutOfRange {
+                col: 0,
+                row: 3
+            }))
+        ));
+    }
+
+    /// A little-endian file whose only IFD describes a 20x20 grayscale image in tiles of
+    /// `tile_width` by `tile_length`, given as `field`, along with that IFD.
+    fn tiled_file(
+        tile_width: u32,
+        tile_length: u32,
+        field: fn(u32) -> IFDField,
+    ) -> (Vec<u8>, RawIFD) {
+        let mut ifd = image_ifd(20, 20, &[8], 1);
+        ifd.entries.extend([
+            (tags::TILE_WIDTH, field(tile_width)),
+            (tags::TILE_LENGTH, field(tile_length)),
+            (
+                tags::TILE_OFFSETS,
+                IFDField::Long(vec![8, 24, 40, 56].into()),
+            ),
+            (tags::TILE_BYTE_COUNTS, IFDField::Long(vec![16; 4].into())),
+        ]);
+        single_page_file(ifd, Vec::new(), Endianness::Little)
+    }
+
+    fn long(value: u32) -> IFDField {
+        IFDField::Long(vec![value].into())
+    }
+
+    fn short(value: u32) -> IFDField {
+        IFDField::Short(vec![value as u16].into())
+    }
+
+    #[test]
+    fn from_ifd_reads_tile_layout() {
+        for field in [long, short] {
+            let (buf, ifd) = tiled_file(16, 16, field);
+            let tiles = TileInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd);
+            let tiles = tiles.unwrap();
+            assert_eq!((tiles.tile_width, tiles.tile_length), (16, 16));
+            assert_eq!((tiles.tiles_across, tiles.tiles_down), (2, 2));
+            assert_eq!(tiles.offsets, [8, 24, 40, 56]);
+            assert_eq!(tiles.tile_count(), 4);
+        }
+    }
+
+    #[test]
+    fn from_ifd_reads_full_width_long_tile_size() {
+        // The largest multiple of 16 that fits in a u32
+        let size = u32::MAX - 15;
+        let (buf, ifd) = tiled_file(size, size, long);
+        let tiles = TileInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd).unwrap();
+        assert_eq!((tiles.tile_width, tiles.tile_length), (size, size));
+        assert_eq!((tiles.tiles_across, tiles.tiles_down), (1, 1));
+    }
+
+    #[test]
+    fn from_ifd_rejects_zero_or_unaligned_tile_size() {
+        for (width, length) in [(0, 16), (16, 0), (24, 16), (16, 8), (17, 17)] {
+            let (buf, ifd) = tiled_file(width, length, long);
+            match TileInfo::from_ifd::<LittleEndian, _>(&mut Cursor::new(&buf), &ifd) {
+                Err(TiffError::ImageData(ImageDataError::InvalidTileSize {
+                    width: w,
+                    length: l,
+                })) => assert_eq!((w, l), (width, length)),
+                other => panic!(
+                    "expected InvalidTileSize for {}x{}, got {:?}",
+                    width, length, other
+                ),
+            }
+        }
+    }
+}
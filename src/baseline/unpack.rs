@@ -0,0 +1,115 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use byteorder::ByteOrder;
+
+/// Unpack `buf`, rows of `samples_per_row` samples of `bits_per_sample` bits each, into one
+/// `u16` per sample, keeping the values as stored rather than scaling them. Samples narrower than
+/// a byte, or of an odd width such as 12 bits, are packed with no gaps and read most significant
+/// bit first, so they may straddle byte boundaries; each row starts on a byte boundary, with any
+/// bits left over at the end of a row ignored. 16-bit samples are read in the byte order `E`.
+/// Any depth from 1 to 16 bits is supported, and no samples are returned for other depths.
+/// Samples cut off by the end of a short `buf` are dropped.
+pub fn unpack_samples<E: ByteOrder>(
+    buf: &[u8],
+    bits_per_sample: u8,
+    samples_per_row: usize,
+) -> Vec<u16> {
+    let bits = usize::from(bits_per_sample);
+    if !(1..=16).contains(&bits) || samples_per_row == 0 {
+        return Vec::new();
+    }
+    let row_len = (samples_per_row * bits).div_ceil(8);
+
+    let mut samples = Vec::with_capacity(buf.len() / row_len * samples_per_row);
+    for row in buf.chunks(row_len) {
+        let count = samples_per_row.min(row.len() * 8 / bits);
+        match bits {
+            8 => samples.extend(row[..count].iter().map(|&sample| u16::from(sample))),
+            16 => samples.extend(row[..count * 2].chunks_exact(2).map(E::read_u16)),
+            _ => {
+                for index in 0..count {
+                    // Gather the bytes the sample spans, at most three, most significant first
+                    let (first_bit, last_bit) = (index * bits, index * bits + bits - 1);
+                    let window = row[first_bit / 8..=last_bit / 8]
+                        .iter()
+                        .fold(0u32, |window, &byte| window << 8 | u32::from(byte));
+                    let shift = 7 - last_bit % 8;
+                    samples.push((window >> shift) as u16 & ((1 << bits) - 1) as u16);
+                }
+            }
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{BigEndian, LittleEndian};
+
+    #[test]
+    fn unpacks_12_bit_samples_across_byte_boundaries() {
+        // Two rows of three samples, each row padded with four bits to a whole five bytes
+        let buf = [
+            [0xAB, 0xC1, 0x23, 0xFF, 0xFF],
+            [0x00, 0x18, 0x00, 0x7F, 0xF0],
+        ]
+        .concat();
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&buf, 12, 3),
+            vec![0xABC, 0x123, 0xFFF, 0x001, 0x800, 0x7FF]
+        );
+        // An even number of samples per row needs no padding
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&buf[..3], 12, 2),
+            vec![0xABC, 0x123]
+        );
+    }
+
+    #[test]
+    fn unpacks_narrow_samples_per_row() {
+        // Rows of three 4-bit samples, padded to two bytes
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[0x12, 0x3F, 0xAB, 0xCF], 4, 3),
+            vec![1, 2, 3, 0xA, 0xB, 0xC]
+        );
+        // Rows of five 2-bit samples
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[0b1110_0100, 0b1000_0000], 2, 5),
+            vec![3, 2, 1, 0, 2]
+        );
+        // Rows of three 1-bit samples
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[0b1011_1111, 0b0101_1111], 1, 3),
+            vec![1, 0, 1, 0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn unpacks_whole_bytes_in_byte_order() {
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[1, 2, 3], 8, 3),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[0x01, 0x02], 16, 1),
+            vec![0x0201]
+        );
+        assert_eq!(
+            unpack_samples::<BigEndian>(&[0x01, 0x02], 16, 1),
+            vec![0x0102]
+        );
+    }
+
+    #[test]
+    fn drops_unsupported_depths_and_cut_off_samples() {
+        assert!(unpack_samples::<LittleEndian>(&[0xFF; 4], 0, 2).is_empty());
+        assert!(unpack_samples::<LittleEndian>(&[0xFF; 4], 17, 1).is_empty());
+        assert!(unpack_samples::<LittleEndian>(&[0xFF; 4], 8, 0).is_empty());
+        // The row is cut off partway through its second 12-bit sample
+        assert_eq!(
+            unpack_samples::<LittleEndian>(&[0xAB, 0xC1], 12, 2),
+            vec![0xABC]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::lowlevel::Rational;
+#[cfg(feature = "std")]
+use crate::{
+    baseline::tags,
+    errors::{FieldExtractionError, ImageDataError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Luma coefficients of red, green and blue assumed when an image has no YCbCrCoefficients tag:
+/// those of CCIR Recommendation 601-1.
+pub const DEFAULT_COEFFICIENTS: [Rational; 3] = [
+    Rational {
+        num: 299,
+        den: 1000,
+    },
+    Rational {
+        num: 587,
+        den: 1000,
+    },
+    Rational {
+        num: 114,
+        den: 1000,
+    },
+];
+
+/// Black and white reference codes of Y, Cb and Cr assumed when an image has no
+/// ReferenceBlackWhite tag: the full range for Y, and chroma centered on 128.
+pub const DEFAULT_REFERENCE_BLACK_WHITE: [Rational; 6] = [
+    Rational { num: 0, den: 1 },
+    Rational { num: 255, den: 1 },
+    Rational { num: 128, den: 1 },
+    Rational { num: 255, den: 1 },
+    Rational { num: 128, den: 1 },
+    Rational { num: 255, den: 1 },
+];
+
+/// The tags describing how an image's YCbCr samples are encoded, from
+/// `YCbCrParams::from_ifd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YCbCrParams {
+    /// Luma coefficients of red, green and blue, from the YCbCrCoefficients tag.
+    pub coefficients: [Rational; 3],
+    /// Black and white reference codes of Y, Cb and Cr, from the ReferenceBlackWhite tag.
+    pub reference_black_white: [Rational; 6],
+    /// Horizontal and vertical factors by which the chroma samples are subsampled, from the
+    /// YCbCrSubSampling tag.
+    pub subsampling: [u16; 2],
+}
+
+impl YCbCrParams {
+    /// Read the YCbCrCoefficients, ReferenceBlackWhite and YCbCrSubSampling tags of `ifd`,
+    /// defaulting any which are missing as the specification does: to `DEFAULT_COEFFICIENTS`,
+    /// `DEFAULT_REFERENCE_BLACK_WHITE` and subsampling by 2 in each direction.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let mut params = Self {
+            coefficients: DEFAULT_COEFFICIENTS,
+            reference_black_white: DEFAULT_REFERENCE_BLACK_WHITE,
+            subsampling: [2, 2],
+        };
+        if let Some(field) = ifd.get(tags::Y_CBCR_COEFFICIENTS) {
+            copy_exact(
+                &field.as_rational_array::<E, R>(reader)?,
+                &mut params.coefficients,
+            )?;
+        }
+        if let Some(field) = ifd.get(tags::REFERENCE_BLACK_WHITE) {
+            copy_exact(
+                &field.as_rational_array::<E, R>(reader)?,
+                &mut params.reference_black_white,
+            )?;
+        }
+        if let Some(field) = ifd.get(tags::Y_CBCR_SUB_SAMPLING) {
+            copy_exact(
+                &field.as_u16_array::<E, R>(reader)?,
+                &mut params.subsampling,
+            )?;
+        }
+        Ok(params)
+    }
+
+    /// Fail with `ImageDataError::UnsupportedSubsampling` unless the chroma samples are at full
+    /// resolution, as `ycbcr_to_rgb` requires.
+    #[cfg(feature = "std")]
+    pub fn check_not_subsampled(&self) -> Result<(), TiffError> {
+        match self.subsampling {
+            [1, 1] => Ok(()),
+            [horizontal, vertical] => Err(ImageDataError::UnsupportedSubsampling {
+                horizontal,
+                vertical,
+            }
+            .into()),
+        }
+    }
+}
+
+/// Copy `values` into `into`, failing unless there are exactly enough of them.
+#[cfg(feature = "std")]
+fn copy_exact<T: Copy>(values: &[T], into: &mut [T]) -> Result<(), TiffError> {
+    if values.len() != into.len() {
+        return Err(FieldExtractionError::InsufficientData.into());
+    }
+    into.copy_from_slice(values);
+    Ok(())
+}
+
+/// Convert `buf`, holding interleaved 8-bit Y, Cb and Cr samples without subsampling, to RGB in
+/// place, as set out in section 21 of the TIFF 6.0 specification. `coefficients` are the luma
+/// coefficients of red, green and blue, and `reference_bw` the black and white reference codes
+/// of Y, Cb and Cr; `DEFAULT_REFERENCE_BLACK_WHITE` is used if fewer than six are given.
+pub fn ycbcr_to_rgb(buf: &mut [u8], coefficients: [Rational; 3], reference_bw: &[Rational]) {
+    let reference_bw = match reference_bw.get(..6) {
+        Some(reference_bw) => reference_bw,
+        None => &DEFAULT_REFERENCE_BLACK_WHITE,
+    };
+    let [luma_red, luma_green, luma_blue] = coefficients.map(Rational::as_f64);
+    let reference: [f64; 6] = core::array::from_fn(|idx| reference_bw[idx].as_f64());
+
+    // Scale each code to a luma of 0 to 255, or a chroma of -127 to 127
+    let scale = |code: u8, component: usize, range: f64| {
+        let (black, white) = (reference[component * 2], reference[component * 2 + 1]);
+        (f64::from(code) - black) * range / (white - black)
+    };
+    for pixel in buf.chunks_exact_mut(3) {
+        let y = scale(pixel[0], 0, 255.);
+        let cb = scale(pixel[1], 1, 127.);
+        let cr = scale(pixel[2], 2, 127.);
+
+        let red = cr * (2. - 2. * luma_red) + y;
+        let blue = cb * (2. - 2. * luma_blue) + y;
+        let green = (y - luma_blue * blue - luma_red * red) / luma_green;
+        for (sample, value) in pixel.iter_mut().zip([red, green, blue]) {
+            // Round to nearest, without `f64::round` which needs std
+            *sample = (value.clamp(0., 255.) + 0.5) as u8;
+        }
+    }
+}
@@ -0,0 +1,722 @@
+use crate::errors::CompressionError;
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    errors::TiffError,
+    lowlevel::RawIFD,
+};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// Longest code for a run, in bits.
+const MAX_CODE_LEN: u8 = 13;
+
+/// Runs of at least this length are followed by another code, continuing the run.
+const MAKEUP_MIN: u16 = 64;
+
+/// Zeros in an end-of-line code, before its final one.
+const EOL_ZEROS: usize = 11;
+
+/// Options for CCITT Group 3 coding, as given by the T4Options tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct T4Options {
+    /// Rows may be coded relative to the row above, as marked after each end-of-line code.
+    pub two_dimensional: bool,
+    /// Uncompressed mode may be used, which isn't supported here.
+    pub uncompressed: bool,
+    /// Zeros are inserted before each end-of-line code so that it ends on a byte boundary.
+    pub fill_bits: bool,
+}
+
+impl T4Options {
+    /// Interpret a T4Options value.
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            two_dimensional: value & 1 != 0,
+            uncompressed: value & 2 != 0,
+            fill_bits: value & 4 != 0,
+        }
+    }
+
+    /// The T4Options value for these options.
+    pub fn as_u32(self) -> u32 {
+        u32::from(self.two_dimensional)
+            | u32::from(self.uncompressed) << 1
+            | u32::from(self.fill_bits) << 2
+    }
+
+    /// Read the T4Options tag of `ifd`, or the default of one-dimensional coding without fill
+    /// bits if it is missing.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::T4_OPTIONS, 0)?;
+        Ok(Self::from_u32(value as u32))
+    }
+}
+
+/// Options for CCITT Group 4 coding, as given by the T6Options tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct T6Options {
+    /// Uncompressed mode may be used, which isn't supported here.
+    pub uncompressed: bool,
+}
+
+impl T6Options {
+    /// Interpret a T6Options value.
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            uncompressed: value & 2 != 0,
+        }
+    }
+
+    /// The T6Options value for these options.
+    pub fn as_u32(self) -> u32 {
+        u32::from(self.uncompressed) << 1
+    }
+
+    /// Read the T6Options tag of `ifd`, or the default of no uncompressed mode if it is missing.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::T6_OPTIONS, 0)?;
+        Ok(Self::from_u32(value as u32))
+    }
+}
+
+/// Options for the CCITT Group 3 and Group 4 decoders, read by `FaxOptions::from_ifd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FaxOptions {
+    /// Options for Group 3 coding, from the T4Options tag.
+    pub t4: T4Options,
+    /// Options for Group 4 coding, from the T6Options tag.
+    pub t6: T6Options,
+    /// The code for each row is padded with zeros to a byte boundary, so that the next row
+    /// begins on one. No tag records this, but some writers code rows this way, and the rows of
+    /// their data are shifted unless it is set.
+    pub byte_aligned_rows: bool,
+}
+
+impl FaxOptions {
+    /// Read the T4Options and T6Options tags of `ifd`, either of which may be missing, with rows
+    /// not byte-aligned.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        Ok(Self {
+            t4: T4Options::from_ifd::<E, R>(reader, ifd)?,
+            t6: T6Options::from_ifd::<E, R>(reader, ifd)?,
+            byte_aligned_rows: false,
+        })
+    }
+}
+
+/// Decompress CCITT Group 4 (T.6, compression 4) `input`, an image `width` pixels wide, into
+/// `rows` rows of one bit per pixel, most significant bit first, with each row starting on a byte
+/// boundary. White pixels are 0 and black pixels 1, as with a PhotometricInterpretation of
+/// WhiteIsZero. Every row is coded relative to the row above it, the first relative to an
+/// imaginary white row, and padded to a byte boundary of `input` if `options` says so. Fails if
+/// `input` ends early or contains an invalid code, or if `options` allows uncompressed mode.
+pub fn decompress_ccitt_g4(
+    input: &[u8],
+    width: usize,
+    rows: usize,
+    options: FaxOptions,
+) -> Result<Vec<u8>, CompressionError> {
+    if options.t6.uncompressed {
+        return Err(CompressionError::UnsupportedT6Options {
+            options: options.t6.as_u32(),
+        });
+    }
+
+    let row_len = width.div_ceil(8);
+    let mut output = vec![0; row_len * rows];
+    let mut bits = BitReader::new(input);
+    let mut reference = Vec::new();
+
+    for (row, pixels) in output
+        .chunks_exact_mut(row_len.max(1))
+        .take(rows)
+        .enumerate()
+    {
+        let changes = decode_2d_row(&mut bits, &reference, width, row)?;
+        if options.byte_aligned_rows {
+            bits.align();
+        }
+        fill_black_runs(&changes, width, pixels);
+        reference = changes;
+    }
+    Ok(output)
+}
+
+/// Decompress CCITT Group 3 (T.4, compression 3) `input` into rows of bits as for
+/// `decompress_ccitt_g4`, with the coding given by `options.t4`. Each row follows an end-of-line
+/// code, and any fill bits before it, though rows lacking one are accepted in one-dimensional
+/// coding. With two-dimensional coding, the bit after each end-of-line code says whether the row
+/// is coded by itself or relative to the row above. Rows are padded to a byte boundary if
+/// `options` says so. Fails if `input` ends early or contains an invalid code, or if `options`
+/// allows uncompressed mode.
+pub fn decompress_ccitt_g3(
+    input: &[u8],
+    width: usize,
+    rows: usize,
+    options: FaxOptions,
+) -> Result<Vec<u8>, CompressionError> {
+    if options.t4.uncompressed {
+        return Err(CompressionError::UnsupportedT4Options {
+            options: options.t4.as_u32(),
+        });
+    }
+
+    let row_len = width.div_ceil(8);
+    let mut output = vec![0; row_len * rows];
+    let mut bits = BitReader::new(input);
+    let mut reference = Vec::new();
+
+    for (row, pixels) in output
+        .chunks_exact_mut(row_len.max(1))
+        .take(rows)
+        .enumerate()
+    {
+        let has_eol = bits.skip_eol();
+        let one_dimensional = match (options.t4.two_dimensional, has_eol) {
+            (false, _) => true,
+            (true, true) => bits.next().ok_or(CompressionError::TruncatedInput)?,
+            (true, false) => return Err(CompressionError::InvalidFaxCode { row }),
+        };
+        let changes = if one_dimensional {
+            decode_1d_row(&mut bits, width, row)?
+        } else {
+            decode_2d_row(&mut bits, &reference, width, row)?
+        };
+        if options.byte_aligned_rows {
+            bits.align();
+        }
+        fill_black_runs(&changes, width, pixels);
+        reference = changes;
+    }
+    Ok(output)
+}
+
+/// Decompress CCITT modified Huffman (compression 2) `input` into rows of bits as for
+/// `decompress_ccitt_g4`. This is one-dimensional Group 3 coding without end-of-line codes, with
+/// each row starting on a byte boundary instead. Fails if `input` ends early or contains an
+/// invalid code.
+pub fn decompress_ccitt_rle(
+    input: &[u8],
+    width: usize,
+    rows: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let row_len = width.div_ceil(8);
+    let mut output = vec![0; row_len * rows];
+    let mut bits = BitReader::new(input);
+
+    for (row, pixels) in output
+        .chunks_exact_mut(row_len.max(1))
+        .take(rows)
+        .enumerate()
+    {
+        let changes = decode_1d_row(&mut bits, width, row)?;
+        fill_black_runs(&changes, width, pixels);
+        bits.align();
+    }
+    Ok(output)
+}
+
+/// Decode one row coded by itself, as alternating runs of white and black pixels, returning
+/// its changing elements as for `decode_2d_row`.
+fn decode_1d_row(
+    bits: &mut BitReader,
+    width: usize,
+    row: usize,
+) -> Result<Vec<usize>, CompressionError> {
+    let mut changes = Vec::new();
+    let mut position = 0;
+    let mut black = false;
+    while position < width {
+        position += read_run(bits, black, row)?;
+        if position > width {
+            return Err(CompressionError::InvalidFaxCode { row });
+        }
+        changes.push(position);
+        black = !black;
+    }
+    Ok(changes)
+}
+
+/// Decode one row coded relative to the changing elements of the row above, `reference`,
+/// returning the changing elements of this row: the positions of the pixels which differ in
+/// color from the pixel before them, starting from an imaginary white pixel before the row.
+/// `row` is the index of this row, for errors.
+pub(crate) fn decode_2d_row(
+    bits: &mut BitReader,
+    reference: &[usize],
+    width: usize,
+    row: usize,
+) -> Result<Vec<usize>, CompressionError> {
+    let invalid = || CompressionError::InvalidFaxCode { row };
+    let mut changes = Vec::new();
+    // Position of the last pixel coded, before the start of the row at first
+    let mut a0: Option<usize> = None;
+    let mut black = false;
+    // Index within `reference` of the first changing element to the right of a0
+    let mut next_reference = 0;
+
+    while a0.is_none_or(|a0| a0 < width) {
+        while a0.is_some_and(|a0| reference.get(next_reference).is_some_and(|&b| b <= a0)) {
+            next_reference += 1;
+        }
+        // b1 is the first changing element on the reference row to the right of a0 and of the
+        // opposite color, so to black for a white a0; elements alternate, starting with black
+        let b1_index = next_reference + usize::from((next_reference % 2 == 1) != black);
+        let b1 = reference.get(b1_index).copied().unwrap_or(width);
+        let b2 = reference.get(b1_index + 1).copied().unwrap_or(width);
+        let start = a0.unwrap_or(0);
+
+        match read_mode(bits).ok_or(CompressionError::TruncatedInput)? {
+            Mode::Pass => a0 = Some(b2),
+            Mode::Horizontal => {
+                let first = read_run(bits, black, row)?;
+                let second = read_run(bits, !black, row)?;
+                let a1 = (start + first).min(width);
+                let a2 = (a1 + second).min(width);
+                changes.extend_from_slice(&[a1, a2]);
+                a0 = Some(a2);
+            }
+            Mode::Vertical(offset) => {
+                let a1 = b1 as isize + isize::from(offset);
+                if a1 < start as isize || a1 > width as isize {
+                    return Err(invalid());
+                }
+                changes.push(a1 as usize);
+                a0 = Some(a1 as usize);
+                black = !black;
+            }
+            Mode::Invalid => return Err(invalid()),
+        }
+    }
+    Ok(changes)
+}
+
+/// Set the bits of the black pixels of a row, given its changing elements, in `pixels`, which
+/// must be zeroed.
+pub(crate) fn fill_black_runs(changes: &[usize], width: usize, pixels: &mut [u8]) {
+    for run in changes.chunks(2) {
+        let end = run.get(1).copied().unwrap_or(width).min(width);
+        for x in run[0]..end {
+            pixels[x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+}
+
+/// Read the code for a run of white or `black` pixels, including any makeup codes before the
+/// final terminating code, returning the total length of the run.
+pub(crate) fn read_run(
+    bits: &mut BitReader,
+    black: bool,
+    row: usize,
+) -> Result<usize, CompressionError> {
+    let codes: &[(u8, u16, u16)] = if black { &BLACK_CODES } else { &WHITE_CODES };
+    let mut total = 0;
+    loop {
+        let mut code = 0;
+        let mut run = None;
+        for len in 1..=MAX_CODE_LEN {
+            code = code << 1 | u16::from(bits.next().ok_or(CompressionError::TruncatedInput)?);
+            if let Some(&(_, _, found)) = codes.iter().find(|&&(l, c, _)| l == len && c == code) {
+                run = Some(found);
+                break;
+            }
+        }
+        let run = run.ok_or(CompressionError::InvalidFaxCode { row })?;
+        total += usize::from(run);
+        if run < MAKEUP_MIN {
+            return Ok(total);
+        }
+    }
+}
+
+/// A coding mode of a two-dimensionally coded row.
+enum Mode {
+    /// Skip to b2, below the end of the next run on the reference row.
+    Pass,
+    /// Two runs coded by their lengths.
+    Horizontal,
+    /// The next changing element, a1, is at this offset from b1.
+    Vertical(i8),
+    /// An extension or end-of-line code, which can't appear within a row.
+    Invalid,
+}
+
+/// Read the code for a coding mode, or `None` if the input ends first.
+fn read_mode(bits: &mut BitReader) -> Option<Mode> {
+    // Every code is some number of zeros followed by a one, then possibly a single bit
+    let mut zeros = 0;
+    while !bits.next()? {
+        zeros += 1;
+        if zeros > 6 {
+            return Some(Mode::Invalid);
+        }
+    }
+    Some(match zeros {
+        0 => Mode::Vertical(0),
+        1 if bits.next()? => Mode::Vertical(1),
+        1 => Mode::Vertical(-1),
+        2 => Mode::Horizontal,
+        3 => Mode::Pass,
+        4 if bits.next()? => Mode::Vertical(2),
+        4 => Mode::Vertical(-2),
+        5 if bits.next()? => Mode::Vertical(3),
+        5 => Mode::Vertical(-3),
+        _ => Mode::Invalid,
+    })
+}
+
+/// Reads single bits, most significant bit first.
+#[derive(Clone)]
+pub(crate) struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    /// Read the next bit, or `None` at the end of the input.
+    pub(crate) fn next(&mut self) -> Option<bool> {
+        let byte = self.input.get(self.position / 8)?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Some(bit)
+    }
+
+    /// Skip to the next byte boundary.
+    pub(crate) fn align(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+
+    /// Skip an end-of-line code, including any fill bits before it, if one comes next. Returns
+    /// whether there was one.
+    pub(crate) fn skip_eol(&mut self) -> bool {
+        let mut ahead = self.clone();
+        let mut zeros = 0;
+        loop {
+            match ahead.next() {
+                Some(false) => zeros += 1,
+                Some(true) if zeros >= EOL_ZEROS => {
+                    *self = ahead;
+                    return true;
+                }
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Codes for runs of white pixels, as (length in bits, code, run length).
+const WHITE_CODES: [(u8, u16, u16); 104] = [
+    (8, 0b00110101, 0),
+    (6, 0b000111, 1),
+    (4, 0b0111, 2),
+    (4, 0b1000, 3),
+    (4, 0b1011, 4),
+    (4, 0b1100, 5),
+    (4, 0b1110, 6),
+    (4, 0b1111, 7),
+    (5, 0b10011, 8),
+    (5, 0b10100, 9),
+    (5, 0b00111, 10),
+    (5, 0b01000, 11),
+    (6, 0b001000, 12),
+    (6, 0b000011, 13),
+    (6, 0b110100, 14),
+    (6, 0b110101, 15),
+    (6, 0b101010, 16),
+    (6, 0b101011, 17),
+    (7, 0b0100111, 18),
+    (7, 0b0001100, 19),
+    (7, 0b0001000, 20),
+    (7, 0b0010111, 21),
+    (7, 0b0000011, 22),
+    (7, 0b0000100, 23),
+    (7, 0b0101000, 24),
+    (7, 0b0101011, 25),
+    (7, 0b0010011, 26),
+    (7, 0b0100100, 27),
+    (7, 0b0011000, 28),
+    (8, 0b00000010, 29),
+    (8, 0b00000011, 30),
+    (8, 0b00011010, 31),
+    (8, 0b00011011, 32),
+    (8, 0b00010010, 33),
+    (8, 0b00010011, 34),
+    (8, 0b00010100, 35),
+    (8, 0b00010101, 36),
+    (8, 0b00010110, 37),
+    (8, 0b00010111, 38),
+    (8, 0b00101000, 39),
+    (8, 0b00101001, 40),
+    (8, 0b00101010, 41),
+    (8, 0b00101011, 42),
+    (8, 0b00101100, 43),
+    (8, 0b00101101, 44),
+    (8, 0b00000100, 45),
+    (8, 0b00000101, 46),
+    (8, 0b00001010, 47),
+    (8, 0b00001011, 48),
+    (8, 0b01010010, 49),
+    (8, 0b01010011, 50),
+    (8, 0b01010100, 51),
+    (8, 0b01010101, 52),
+    (8, 0b00100100, 53),
+    (8, 0b00100101, 54),
+    (8, 0b01011000, 55),
+    (8, 0b01011001, 56),
+    (8, 0b01011010, 57),
+    (8, 0b01011011, 58),
+    (8, 0b01001010, 59),
+    (8, 0b01001011, 60),
+    (8, 0b00110010, 61),
+    (8, 0b00110011, 62),
+    (8, 0b00110100, 63),
+    (5, 0b11011, 64),
+    (5, 0b10010, 128),
+    (6, 0b010111, 192),
+    (7, 0b0110111, 256),
+    (8, 0b00110110, 320),
+    (8, 0b00110111, 384),
+    (8, 0b01100100, 448),
+    (8, 0b01100101, 512),
+    (8, 0b01101000, 576),
+    (8, 0b01100111, 640),
+    (9, 0b011001100, 704),
+    (9, 0b011001101, 768),
+    (9, 0b011010010, 832),
+    (9, 0b011010011, 896),
+    (9, 0b011010100, 960),
+    (9, 0b011010101, 1024),
+    (9, 0b011010110, 1088),
+    (9, 0b011010111, 1152),
+    (9, 0b011011000, 1216),
+    (9, 0b011011001, 1280),
+    (9, 0b011011010, 1344),
+    (9, 0b011011011, 1408),
+    (9, 0b010011000, 1472),
+    (9, 0b010011001, 1536),
+    (9, 0b010011010, 1600),
+    (6, 0b011000, 1664),
+    (9, 0b010011011, 1728),
+    (11, 0b00000001000, 1792),
+    (11, 0b00000001100, 1856),
+    (11, 0b00000001101, 1920),
+    (12, 0b000000010010, 1984),
+    (12, 0b000000010011, 2048),
+    (12, 0b000000010100, 2112),
+    (12, 0b000000010101, 2176),
+    (12, 0b000000010110, 2240),
+    (12, 0b000000010111, 2304),
+    (12, 0b000000011100, 2368),
+    (12, 0b000000011101, 2432),
+    (12, 0b000000011110, 2496),
+    (12, 0b000000011111, 2560),
+];
+
+/// Codes for runs of black pixels, as for `WHITE_CODES`.
+const BLACK_CODES: [(u8, u16, u16); 104] = [
+    (10, 0b0000110111, 0),
+    (3, 0b010, 1),
+    (2, 0b11, 2),
+    (2, 0b10, 3),
+    (3, 0b011, 4),
+    (4, 0b0011, 5),
+    (4, 0b0010, 6),
+    (5, 0b00011, 7),
+    (6, 0b000101, 8),
+    (6, 0b000100, 9),
+    (7, 0b0000100, 10),
+    (7, 0b0000101, 11),
+    (7, 0b0000111, 12),
+    (8, 0b00000100, 13),
+    (8, 0b00000111, 14),
+    (9, 0b000011000, 15),
+    (10, 0b0000010111, 16),
+    (10, 0b0000011000, 17),
+    (10, 0b0000001000, 18),
+    (11, 0b00001100111, 19),
+    (11, 0b00001101000, 20),
+    (11, 0b00001101100, 21),
+    (11, 0b00000110111, 22),
+    (11, 0b00000101000, 23),
+    (11, 0b00000010111, 24),
+    (11, 0b00000011000, 25),
+    (12, 0b000011001010, 26),
+    (12, 0b000011001011, 27),
+    (12, 0b000011001100, 28),
+    (12, 0b000011001101, 29),
+    (12, 0b000001101000, 30),
+    (12, 0b000001101001, 31),
+    (12, 0b000001101010, 32),
+    (12, 0b000001101011, 33),
+    (12, 0b000011010010, 34),
+    (12, 0b000011010011, 35),
+    (12, 0b000011010100, 36),
+    (12, 0b000011010101, 37),
+    (12, 0b000011010110, 38),
+    (12, 0b000011010111, 39),
+    (12, 0b000001101100, 40),
+    (12, 0b000001101101, 41),
+    (12, 0b000011011010, 42),
+    (12, 0b000011011011, 43),
+    (12, 0b000001010100, 44),
+    (12, 0b000001010101, 45),
+    (12, 0b000001010110, 46),
+    (12, 0b000001010111, 47),
+    (12, 0b000001100100, 48),
+    (12, 0b000001100101, 49),
+    (12, 0b000001010010, 50),
+    (12, 0b000001010011, 51),
+    (12, 0b000000100100, 52),
+    (12, 0b000000110111, 53),
+    (12, 0b000000111000, 54),
+    (12, 0b000000100111, 55),
+    (12, 0b000000101000, 56),
+    (12, 0b000001011000, 57),
+    (12, 0b000001011001, 58),
+    (12, 0b000000101011, 59),
+    (12, 0b000000101100, 60),
+    (12, 0b000001011010, 61),
+    (12, 0b000001100110, 62),
+    (12, 0b000001100111, 63),
+    (10, 0b0000001111, 64),
+    (12, 0b000011001000, 128),
+    (12, 0b000011001001, 192),
+    (12, 0b000001011011, 256),
+    (12, 0b000000110011, 320),
+    (12, 0b000000110100, 384),
+    (12, 0b000000110101, 448),
+    (13, 0b0000001101100, 512),
+    (13, 0b0000001101101, 576),
+    (13, 0b0000001001010, 640),
+    (13, 0b0000001001011, 704),
+    (13, 0b0000001001100, 768),
+    (13, 0b0000001001101, 832),
+    (13, 0b0000001110010, 896),
+    (13, 0b0000001110011, 960),
+    (13, 0b0000001110100, 1024),
+    (13, 0b0000001110101, 1088),
+    (13, 0b0000001110110, 1152),
+    (13, 0b0000001110111, 1216),
+    (13, 0b0000001010010, 1280),
+    (13, 0b0000001010011, 1344),
+    (13, 0b0000001010100, 1408),
+    (13, 0b0000001010101, 1472),
+    (13, 0b0000001011010, 1536),
+    (13, 0b0000001011011, 1600),
+    (13, 0b0000001100100, 1664),
+    (13, 0b0000001100101, 1728),
+    (11, 0b00000001000, 1792),
+    (11, 0b00000001100, 1856),
+    (11, 0b00000001101, 1920),
+    (12, 0b000000010010, 1984),
+    (12, 0b000000010011, 2048),
+    (12, 0b000000010100, 2112),
+    (12, 0b000000010101, 2176),
+    (12, 0b000000010110, 2240),
+    (12, 0b000000010111, 2304),
+    (12, 0b000000011100, 2368),
+    (12, 0b000000011101, 2432),
+    (12, 0b000000011110, 2496),
+    (12, 0b000000011111, 2560),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two rows 16 pixels wide, each black from pixel 4 to 7, as decoded.
+    const TWO_BARS: [u8; 4] = [0x0F, 0x00, 0x0F, 0x00];
+
+    /// `TWO_BARS` coded in Group 4 with each row directly after the last: the first row in
+    /// horizontal mode (001, white 4 = 1011, black 4 = 011, then V0 = 1), and the second as three
+    /// V0 codes (111), relative to the first.
+    const TWO_BARS_G4: [u8; 2] = [0b0011_0110, 0b1111_1100];
+
+    /// The same codes as `TWO_BARS_G4`, with each row padded to a byte boundary.
+    const TWO_BARS_G4_ALIGNED: [u8; 3] = [0b0011_0110, 0b1110_0000, 0b1110_0000];
+
+    /// The single Group 4 strip of a 200x24 bilevel image, written by libtiff. Pixel `(x, y)` is
+    /// black if `x >= 70 + 3 * y` and `(x * x + 3 * y * y) / 97` is odd, so that every row
+    /// starts with a white run too long for a terminating code alone.
+    const LIBTIFF_G4: &[u8] = include_bytes!("../../testdata/libtiff_g4_strip.bin");
+
+    fn aligned(byte_aligned_rows: bool) -> FaxOptions {
+        FaxOptions {
+            byte_aligned_rows,
+            ..FaxOptions::default()
+        }
+    }
+
+    #[test]
+    fn g4_decodes_libtiff_strip() {
+        let mut expected = vec![0; 25 * 24];
+        for y in 0..24 {
+            for x in 0..200 {
+                if x >= 70 + 3 * y && (x * x + 3 * y * y) / 97 % 2 == 1 {
+                    expected[y * 25 + x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+        assert_eq!(
+            decompress_ccitt_g4(LIBTIFF_G4, 200, 24, FaxOptions::default()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn g4_decodes_rows_as_aligned_by_options() {
+        assert_eq!(
+            decompress_ccitt_g4(&TWO_BARS_G4, 16, 2, aligned(false)).unwrap(),
+            TWO_BARS
+        );
+        assert_eq!(
+            decompress_ccitt_g4(&TWO_BARS_G4_ALIGNED, 16, 2, aligned(true)).unwrap(),
+            TWO_BARS
+        );
+    }
+
+    #[test]
+    fn g4_misreads_rows_without_matching_alignment() {
+        // Either the padding is read as codes, or codes are skipped as padding
+        for (input, options) in [
+            (&TWO_BARS_G4_ALIGNED[..], aligned(false)),
+            (&TWO_BARS_G4[..], aligned(true)),
+        ] {
+            let decoded = decompress_ccitt_g4(input, 16, 2, options);
+            assert_ne!(decoded.ok(), Some(TWO_BARS.to_vec()));
+        }
+    }
+
+    #[test]
+    fn g4_rejects_uncompressed_mode() {
+        let options = FaxOptions {
+            t6: T6Options { uncompressed: true },
+            ..FaxOptions::default()
+        };
+        assert!(matches!(
+            decompress_ccitt_g4(&TWO_BARS_G4, 16, 2, options),
+            Err(CompressionError::UnsupportedT6Options { options: 2 })
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::baseline::constants::compression::{
+    ADOBE_DEFLATE, CCITT_1D, DEFLATE_PKZIP, GROUP_3_FAX, GROUP_4_FAX, JPEG, LZW, NEW_JPEG,
+    PACKBITS, UNCOMPRESSED,
+};
+#[cfg(not(feature = "flate2"))]
+use crate::compression::deflate::decompress_deflate;
+#[cfg(feature = "flate2")]
+use crate::compression::deflate::decompress_deflate_flate2;
+#[cfg(feature = "jpeg")]
+use crate::compression::jpeg::decompress_jpeg;
+#[cfg(feature = "std")]
+use crate::compression::jpeg::{read_jpeg_tables, with_jpeg_tables};
+use crate::compression::{
+    ccitt::{decompress_ccitt_g3, decompress_ccitt_g4, decompress_ccitt_rle, FaxOptions},
+    lzw::decompress_lzw,
+    packbits::decompress_packbits,
+};
+use crate::errors::CompressionError;
+#[cfg(feature = "std")]
+use crate::{
+    baseline::{ifd_values::read_optional_value, tags},
+    errors::{FieldExtractionError, TiffError},
+    lowlevel::RawIFD,
+};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// The compression scheme of image data, as given by the Compression tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    /// CCITT modified Huffman run-length encoding
+    CcittRle,
+    /// CCITT Group 3 fax (T.4)
+    CcittG3,
+    /// CCITT Group 4 fax (T.6)
+    CcittG4,
+    Lzw,
+    /// JPEG as specified by TIFF 6.0, since superseded
+    OldJpeg,
+    /// JPEG as specified by TIFF Technical Note 2
+    Jpeg,
+    /// zlib/DEFLATE, as specified by Adobe
+    Deflate,
+    PackBits,
+    /// zlib/DEFLATE, under its original number
+    DeflateOld,
+}
+
+impl Compression {
+    /// Interpret a Compression value, or None if it is unrecognized.
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            UNCOMPRESSED => Some(Compression::None),
+            CCITT_1D => Some(Compression::CcittRle),
+            GROUP_3_FAX => Some(Compression::CcittG3),
+            GROUP_4_FAX => Some(Compression::CcittG4),
+            LZW => Some(Compression::Lzw),
+            JPEG => Some(Compression::OldJpeg),
+            NEW_JPEG => Some(Compression::Jpeg),
+            ADOBE_DEFLATE => Some(Compression::Deflate),
+            PACKBITS => Some(Compression::PackBits),
+            DEFLATE_PKZIP => Some(Compression::DeflateOld),
+            _ => None,
+        }
+    }
+
+    /// The Compression value for this scheme.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            Compression::None => UNCOMPRESSED,
+            Compression::CcittRle => CCITT_1D,
+            Compression::CcittG3 => GROUP_3_FAX,
+            Compression::CcittG4 => GROUP_4_FAX,
+            Compression::Lzw => LZW,
+            Compression::OldJpeg => JPEG,
+            Compression::Jpeg => NEW_JPEG,
+            Compression::Deflate => ADOBE_DEFLATE,
+            Compression::PackBits => PACKBITS,
+            Compression::DeflateOld => DEFLATE_PKZIP,
+        }
+    }
+
+    /// Read the Compression tag of `ifd`, or the default of no compression if it is missing.
+    /// Fails with `CompressionError::UnsupportedCompression` if the value is unrecognized.
+    #[cfg(feature = "std")]
+    pub fn from_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        ifd: &RawIFD,
+    ) -> Result<Self, TiffError> {
+        let value = read_optional_value::<E, R>(reader, ifd, tags::COMPRESSION, 1)? as u16;
+        Self::from_u16(value)
+            .ok_or_else(|| CompressionError::UnsupportedCompression { compression: value }.into())
+    }
+
+    /// Decompress a strip or tile, `input`, into `expected_output_len` bytes. The fax schemes
+    /// also need the dimensions of the strip or tile, and the fax options, from `params`. JPEG
+    /// `input` must be a complete stream, with any JPEGTables already spliced in by
+    /// `splice_jpeg_tables`. Fails with `CompressionError::UnsupportedCompression` for old-style
+    /// JPEG, or for JPEG without the `jpeg` feature, or if `input` is truncated or corrupt.
+    pub fn decompress(
+        &self,
+        input: &[u8],
+        expected_output_len: usize,
+        params: &DecompressParams,
+    ) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            Compression::None => input
+                .get(..expected_output_len)
+                .map(<[u8]>::to_vec)
+                .ok_or(CompressionError::TruncatedInput),
+            Compression::CcittRle => decompress_ccitt_rle(input, params.width, params.rows),
+            Compression::CcittG3 => {
+                decompress_ccitt_g3(input, params.width, params.rows, params.fax_options)
+            }
+            Compression::CcittG4 => {
+                decompress_ccitt_g4(input, params.width, params.rows, params.fax_options)
+            }
+            Compression::Lzw => truncate_to(decompress_lzw(input)?, expected_output_len),
+            Compression::PackBits => decompress_packbits(input, expected_output_len),
+            Compression::Deflate | Compression::DeflateOld => {
+                #[cfg(feature = "flate2")]
+                let output = decompress_deflate_flate2(input, expected_output_len)?;
+                #[cfg(not(feature = "flate2"))]
+                let output = decompress_deflate(input, expected_output_len)?;
+                truncate_to(output, expected_output_len)
+            }
+            #[cfg(feature = "jpeg")]
+            Compression::Jpeg => truncate_to(
+                decompress_jpeg(input, expected_output_len)?,
+                expected_output_len,
+            ),
+            #[cfg(not(feature = "jpeg"))]
+            Compression::Jpeg => Err(CompressionError::UnsupportedCompression {
+                compression: self.as_u16(),
+            }),
+            Compression::OldJpeg => Err(CompressionError::UnsupportedCompression {
+                compression: self.as_u16(),
+            }),
+        }
+    }
+}
+
+/// What some decompressors need to know besides the length of their output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DecompressParams {
+    /// Width in pixels of the strip or tile.
+    pub width: usize,
+    /// Number of rows in the strip or tile.
+    pub rows: usize,
+    /// Options for CCITT Group 3 and Group 4 coding.
+    pub fax_options: FaxOptions,
+}
+
+/// Decompress a strip or tile, `input`, compressed with the scheme identified by `compression`
+/// (a value of the Compression tag), into `expected_output_len` bytes. Fails if the scheme is
+/// unsupported, or `input` is truncated or corrupt. The fax schemes are unsupported here, as they
+/// need the dimensions of the image; see `Compression::decompress`.
+pub fn decompress(
+    compression: u16,
+    input: &[u8],
+    expected_output_len: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    match Compression::from_u16(compression) {
+        Some(Compression::CcittRle | Compression::CcittG3 | Compression::CcittG4) | None => {
+            Err(CompressionError::UnsupportedCompression { compression })
+        }
+        Some(scheme) => scheme.decompress(input, expected_output_len, &DecompressParams::default()),
+    }
+}
+
+/// Shorten `output` to `len` bytes, failing if it is already shorter.
+fn truncate_to(mut output: Vec<u8>, len: usize) -> Result<Vec<u8>, CompressionError> {
+    if output.len() < len {
+        return Err(CompressionError::TruncatedInput);
+    }
+    output.truncate(len);
+    Ok(output)
+}
+
+/// Decompress a strip or tile, `input`, with the scheme given by the Compression tag of `ifd`
+/// (uncompressed if absent) into `expected_output_len` bytes. JPEG strips and tiles have the
+/// JPEGTables of `ifd` spliced in first, if it has any. See `decompress`.
+#[cfg(feature = "std")]
+pub fn decompress_strip<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+    input: &[u8],
+    expected_output_len: usize,
+) -> Result<Vec<u8>, TiffError> {
+    let compression = match ifd.get(tags::COMPRESSION) {
+        Some(field) => *field
+            .as_u16_array::<E, R>(reader)?
+            .first()
+            .ok_or(FieldExtractionError::InsufficientData)?,
+        None => UNCOMPRESSED,
+    };
+    if compression == NEW_JPEG {
+        let tables = read_jpeg_tables::<E, R>(reader, ifd)?;
+        let input = with_jpeg_tables(tables.as_deref(), input)?;
+        return Ok(decompress(compression, &input, expected_output_len)?);
+    }
+    Ok(decompress(compression, input, expected_output_len)?)
+}
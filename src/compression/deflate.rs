@@ -0,0 +1,452 @@
+use crate::errors::CompressionError;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+/// Longest Huffman code permitted by DEFLATE.
+const MAX_CODE_LENGTH: usize = 15;
+
+/// Base lengths for length symbols 257..=285.
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Extra bits for length symbols 257..=285.
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances for distance symbols 0..=29.
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Extra bits for distance symbols 0..=29.
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in a dynamic block header.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress zlib-wrapped DEFLATE `input`, as used by Adobe Deflate (compression 8) and the
+/// older Deflate (compression 32946). Decompression stops once `max_output_len` bytes have been
+/// produced, so that a small, malicious stream can't expand without limit; the output is then
+/// cut to that length and its checksum isn't checked. Fails if the stream is truncated or
+/// corrupt, or its checksum doesn't match.
+pub fn decompress_deflate(
+    input: &[u8],
+    max_output_len: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let (&cmf, &flg) = match input {
+        [cmf, flg, ..] => (cmf, flg),
+        _ => return Err(CompressionError::TruncatedInput),
+    };
+    let has_dictionary = flg & 0x20 != 0;
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 || has_dictionary {
+        return Err(CompressionError::InvalidZlibHeader);
+    }
+
+    let mut bits = BitReader::new(&input[2..]);
+    let mut output = Vec::new();
+    if !inflate(&mut bits, &mut output, max_output_len)? {
+        output.truncate(max_output_len);
+        return Ok(output);
+    }
+
+    // The Adler-32 checksum follows the DEFLATE stream, on a byte boundary
+    let checksum_bytes = bits
+        .remaining_bytes()
+        .get(..4)
+        .ok_or(CompressionError::TruncatedInput)?;
+    let checksum = u32::from_be_bytes([
+        checksum_bytes[0],
+        checksum_bytes[1],
+        checksum_bytes[2],
+        checksum_bytes[3],
+    ]);
+    if checksum != adler32(&output) {
+        return Err(CompressionError::ChecksumMismatch);
+    }
+    Ok(output)
+}
+
+/// Decompress zlib-wrapped DEFLATE `input` as `decompress_deflate` does, but with flate2. This
+/// is what `Compression::decompress` uses when the `flate2` feature is enabled.
+#[cfg(feature = "flate2")]
+pub fn decompress_deflate_flate2(
+    input: &[u8],
+    max_output_len: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    use std::io::{ErrorKind, Read};
+
+    let mut output = Vec::new();
+    flate2::read::ZlibDecoder::new(input)
+        .take(max_output_len as u64)
+        .read_to_end(&mut output)
+        .map_err(|err| match err.kind() {
+            ErrorKind::UnexpectedEof => CompressionError::TruncatedInput,
+            _ => CompressionError::InvalidDeflateBlock,
+        })?;
+    Ok(output)
+}
+
+/// Decompress a raw DEFLATE stream from `bits` onto `output`, leaving it just after the final
+/// block. Returns false if decompression stopped early because `output` grew past `limit`
+/// bytes, or true if the whole stream was decompressed.
+fn inflate(
+    bits: &mut BitReader,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> Result<bool, CompressionError> {
+    loop {
+        let is_final = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => inflate_stored(bits, output)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_codes(bits, output, &literals, &distances, limit)?
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(bits)?;
+                inflate_codes(bits, output, &literals, &distances, limit)?
+            }
+            _ => return Err(CompressionError::InvalidDeflateBlock),
+        }
+        if output.len() > limit {
+            return Ok(false);
+        }
+        if is_final {
+            return Ok(true);
+        }
+    }
+}
+
+/// Copy a stored (uncompressed) block.
+fn inflate_stored(bits: &mut BitReader, output: &mut Vec<u8>) -> Result<(), CompressionError> {
+    bits.align_to_byte();
+    let header = bits
+        .remaining_bytes()
+        .get(..4)
+        .ok_or(CompressionError::TruncatedInput)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let len_complement = u16::from_le_bytes([header[2], header[3]]);
+    if len != !len_complement {
+        return Err(CompressionError::InvalidDeflateBlock);
+    }
+    bits.skip_bytes(4);
+
+    let data = bits
+        .remaining_bytes()
+        .get(..len as usize)
+        .ok_or(CompressionError::TruncatedInput)?;
+    output.extend_from_slice(data);
+    bits.skip_bytes(len as usize);
+    Ok(())
+}
+
+/// Decode one block of literals and back-references with the given codes, stopping early
+/// once `output` grows past `limit` bytes.
+fn inflate_codes(
+    bits: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), CompressionError> {
+    while output.len() <= limit {
+        let symbol = literals.decode(bits)?;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let idx = (symbol - 257) as usize;
+                let len = LENGTH_BASES[idx] as usize + bits.read(LENGTH_EXTRA_BITS[idx])? as usize;
+
+                let idx = distances.decode(bits)? as usize;
+                if idx >= DISTANCE_BASES.len() {
+                    return Err(CompressionError::InvalidDeflateBlock);
+                }
+                let distance =
+                    DISTANCE_BASES[idx] as usize + bits.read(DISTANCE_EXTRA_BITS[idx])? as usize;
+                if distance > output.len() {
+                    return Err(CompressionError::InvalidDeflateBlock);
+                }
+
+                // The referenced bytes may overlap those being written, so copy one at a time
+                let start = output.len() - distance;
+                for idx in start..start + len {
+                    output.push(output[idx]);
+                }
+            }
+            _ => return Err(CompressionError::InvalidDeflateBlock),
+        }
+    }
+    Ok(())
+}
+
+/// The literal/length and distance codes used by fixed Huffman blocks.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].iter_mut().for_each(|len| *len = 8);
+    lengths[144..256].iter_mut().for_each(|len| *len = 9);
+    lengths[256..280].iter_mut().for_each(|len| *len = 7);
+    lengths[280..].iter_mut().for_each(|len| *len = 8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// Read the literal/length and distance codes from the header of a dynamic Huffman block.
+fn read_dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), CompressionError> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for &idx in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[idx] = bits.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    // Literal/length and distance code lengths are run-length coded as one sequence
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut idx = 0;
+    while idx < lengths.len() {
+        let (len, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *idx
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or(CompressionError::InvalidDeflateBlock)?;
+                (previous, 3 + bits.read(2)? as usize)
+            }
+            17 => (0, 3 + bits.read(3)? as usize),
+            _ => (0, 11 + bits.read(7)? as usize),
+        };
+        let run = lengths
+            .get_mut(idx..idx + repeat)
+            .ok_or(CompressionError::InvalidDeflateBlock)?;
+        run.iter_mut().for_each(|length| *length = len);
+        idx += repeat;
+    }
+
+    if lengths[256] == 0 {
+        // A block without an end-of-block code can't be terminated
+        return Err(CompressionError::InvalidDeflateBlock);
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+/// A canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build the code assigning each symbol the code length at its index in `lengths`, where
+    /// zero means the symbol is unused.
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; MAX_CODE_LENGTH + 2];
+        for len in 1..=MAX_CODE_LENGTH {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; offsets[MAX_CODE_LENGTH + 1] as usize];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    /// Read one symbol from `bits`.
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, CompressionError> {
+        // Codes of each length are consecutive, and follow on from those one bit shorter
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(CompressionError::InvalidDeflateBlock)
+    }
+}
+
+/// Reads bits least significant first, as DEFLATE packs them.
+struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    bit: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            position: 0,
+            bit: 0,
+        }
+    }
+
+    /// Read `count` (at most 16) bits as an integer.
+    fn read(&mut self, count: u8) -> Result<u32, CompressionError> {
+        let mut value = 0;
+        for shift in 0..count {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or(CompressionError::TruncatedInput)?;
+            value |= u32::from((byte >> self.bit) & 1) << shift;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.position += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Discard any bits remaining in the current byte.
+    fn align_to_byte(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+    }
+
+    /// Skip to the next byte boundary, and return the bytes from there on.
+    fn remaining_bytes(&mut self) -> &'a [u8] {
+        self.align_to_byte();
+        self.input.get(self.position..).unwrap_or(&[])
+    }
+
+    /// Skip `count` whole bytes. Must be at a byte boundary.
+    fn skip_bytes(&mut self, count: usize) {
+        self.position += count;
+    }
+}
+
+/// The Adler-32 checksum of `data`, as used by zlib.
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums of up to 5552 bytes can't overflow before being reduced
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::{Compression, DecompressParams};
+
+    const TEXT: &[u8] = b"TIFF strips, TIFF tiles, TIFF strips and tiles";
+
+    /// `TEXT` compressed by zlib.
+    const COMPRESSED_TEXT: [u8; 37] = [
+        0x78, 0xda, 0x0b, 0xf1, 0x74, 0x73, 0x53, 0x28, 0x2e, 0x29, 0xca, 0x2c, 0x28, 0xd6, 0x51,
+        0x08, 0x01, 0x71, 0x4a, 0x32, 0x73, 0x52, 0x61, 0x6c, 0x88, 0x84, 0x42, 0x62, 0x5e, 0x0a,
+        0x44, 0x18, 0x00, 0x5e, 0x3b, 0x0f, 0x73,
+    ];
+
+    /// 100,000 zero bytes compressed by zlib into 120 bytes.
+    fn zeros() -> Vec<u8> {
+        let mut stream = vec![
+            0x78, 0xda, 0xed, 0xc1, 0x31, 0x01, 0x00, 0x00, 0x00, 0xc2, 0xa0, 0xf5, 0x4f, 0x6d,
+            0x0d, 0x0f, 0xa0,
+        ];
+        stream.resize(stream.len() + 96, 0);
+        stream.extend_from_slice(&[0x80, 0x57, 0x03, 0x86, 0xaf, 0x00, 0x01]);
+        stream
+    }
+
+    #[test]
+    fn decompress_deflate_inflates_zlib_stream() {
+        assert_eq!(decompress_deflate(&COMPRESSED_TEXT, 1000).unwrap(), TEXT);
+        assert_eq!(
+            decompress_deflate(&zeros(), 100_000).unwrap(),
+            vec![0; 100_000]
+        );
+    }
+
+    #[test]
+    fn decompress_deflate_stops_at_output_limit() {
+        assert_eq!(decompress_deflate(&zeros(), 1000).unwrap(), vec![0; 1000]);
+        assert_eq!(decompress_deflate(&COMPRESSED_TEXT, 4).unwrap(), b"TIFF");
+    }
+
+    #[test]
+    fn decompress_deflate_rejects_corrupt_stream() {
+        let mut corrupt = COMPRESSED_TEXT;
+        corrupt[36] ^= 1;
+        assert!(matches!(
+            decompress_deflate(&corrupt, 1000),
+            Err(CompressionError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            decompress_deflate(&COMPRESSED_TEXT[..20], 1000),
+            Err(CompressionError::TruncatedInput)
+        ));
+        assert!(matches!(
+            decompress_deflate(&[0x78, 0x00], 1000),
+            Err(CompressionError::InvalidZlibHeader)
+        ));
+    }
+
+    #[test]
+    fn compression_decompress_bounds_deflate_output() {
+        let params = DecompressParams::default();
+        for compression in &[Compression::Deflate, Compression::DeflateOld] {
+            let output = compression.decompress(&zeros(), 64, &params).unwrap();
+            assert_eq!(output, vec![0; 64]);
+        }
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn decompress_deflate_flate2_matches_built_in_decoder() {
+        for limit in &[4, 1000] {
+            assert_eq!(
+                decompress_deflate_flate2(&COMPRESSED_TEXT, *limit).unwrap(),
+                decompress_deflate(&COMPRESSED_TEXT, *limit).unwrap()
+            );
+        }
+        assert_eq!(
+            decompress_deflate_flate2(&zeros(), 1000).unwrap(),
+            vec![0; 1000]
+        );
+        assert!(decompress_deflate_flate2(&COMPRESSED_TEXT[..20], 1000).is_err());
+    }
+}
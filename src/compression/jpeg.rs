@@ -0,0 +1,183 @@
+use crate::errors::CompressionError;
+#[cfg(feature = "std")]
+use crate::{baseline::tags, errors::TiffError, lowlevel::RawIFD};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::{borrow::Cow, io::Seek};
+
+/// Start of image marker, beginning every JPEG stream.
+const SOI: [u8; 2] = [0xFF, 0xD8];
+
+/// End of image marker, ending every JPEG stream.
+const EOI: [u8; 2] = [0xFF, 0xD9];
+
+/// Read the JPEGTables tag of `ifd`, an abbreviated JPEG stream holding the quantization and
+/// Huffman tables shared by every strip or tile of a JPEG-compressed (compression 7) image.
+/// Returns `None` if the tag is missing, in which case each strip or tile holds its own tables.
+#[cfg(feature = "std")]
+pub fn read_jpeg_tables<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Option<Vec<u8>>, TiffError> {
+    ifd.get(tags::JPEG_TABLES)
+        .map(|field| field.read_value_bytes::<E, R>(reader))
+        .transpose()
+}
+
+/// Combine `tables`, the value of the JPEGTables tag, with `stream`, an abbreviated JPEG stream
+/// from a strip or tile, into a complete JPEG stream that a decoder can read by itself. The
+/// tables' end of image marker and the stream's start of image marker are dropped, leaving the
+/// tables between them. Fails with `CompressionError::InvalidJpegStream` if either is missing
+/// those markers.
+pub fn splice_jpeg_tables(tables: &[u8], stream: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    if !tables.starts_with(&SOI) || !tables.ends_with(&EOI) || tables.len() < 4 {
+        return Err(CompressionError::InvalidJpegStream);
+    }
+    if !stream.starts_with(&SOI) {
+        return Err(CompressionError::InvalidJpegStream);
+    }
+    let tables = &tables[..tables.len() - EOI.len()];
+    let mut spliced = Vec::with_capacity(tables.len() + stream.len() - SOI.len());
+    spliced.extend_from_slice(tables);
+    spliced.extend_from_slice(&stream[SOI.len()..]);
+    Ok(spliced)
+}
+
+/// Make `stream`, a JPEG-compressed strip or tile, into a complete JPEG stream by splicing in
+/// `tables` as `splice_jpeg_tables` does, or leave it as it is if the image has no JPEGTables.
+#[cfg(feature = "std")]
+pub(crate) fn with_jpeg_tables<'a>(
+    tables: Option<&[u8]>,
+    stream: &'a [u8],
+) -> Result<Cow<'a, [u8]>, CompressionError> {
+    match tables {
+        Some(tables) => Ok(Cow::Owned(splice_jpeg_tables(tables, stream)?)),
+        None => Ok(Cow::Borrowed(stream)),
+    }
+}
+
+/// Decode `input`, a complete JPEG stream such as `splice_jpeg_tables` makes, into interleaved
+/// 8-bit samples. Three-component streams come out as RGB, with any chroma subsampling undone.
+/// This is what `Compression::decompress` uses for JPEG (compression 7) when the `jpeg` feature
+/// is enabled. Fails with `CompressionError::InvalidJpegData` if the stream is corrupt, uses a
+/// feature jpeg-decoder doesn't support, or would decode to more than `max_output_len` bytes.
+#[cfg(feature = "jpeg")]
+pub fn decompress_jpeg(input: &[u8], max_output_len: usize) -> Result<Vec<u8>, CompressionError> {
+    let mut decoder = jpeg_decoder::Decoder::new(input);
+    decoder
+        .read_info()
+        .map_err(|_| CompressionError::InvalidJpegData)?;
+    let info = decoder.info().ok_or(CompressionError::InvalidJpegData)?;
+    let len = usize::from(info.width) * usize::from(info.height) * info.pixel_format.pixel_bytes();
+    // Samples wider than 8 bits don't match the BitsPerSample of a JPEG-compressed image
+    if info.pixel_format == jpeg_decoder::PixelFormat::L16 || len > max_output_len {
+        return Err(CompressionError::InvalidJpegData);
+    }
+    decoder
+        .decode()
+        .map_err(|_| CompressionError::InvalidJpegData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 32x32 RGB image, written by libtiff in two JPEG-compressed strips sharing JPEGTables,
+    /// as chroma-subsampled YCbCr.
+    #[cfg(feature = "jpeg")]
+    const LIBTIFF_YCBCR: &[u8] = include_bytes!("../../testdata/libtiff_jpeg_ycbcr.tif");
+
+    /// The same image as `LIBTIFF_YCBCR`, stored as RGB.
+    #[cfg(feature = "jpeg")]
+    const LIBTIFF_RGB: &[u8] = include_bytes!("../../testdata/libtiff_jpeg_rgb.tif");
+
+    /// Decode the first image of `file` as RGB, checking that every sample is close to the
+    /// gradient both fixtures were made from.
+    #[cfg(feature = "jpeg")]
+    fn assert_decodes_to_gradient(file: &[u8]) {
+        use crate::baseline::decode::decode_rgb8;
+        use crate::lowlevel::{read_ifds_auto, LittleEndian};
+        use std::io::Cursor;
+
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(file)).unwrap();
+        let ifd = &ifds.into_classic().unwrap()[0];
+        let (width, height, pixels) =
+            decode_rgb8::<LittleEndian, _>(&mut Cursor::new(file), ifd).unwrap();
+        assert_eq!((width, height), (32, 32));
+        for (idx, pixel) in pixels.chunks(3).enumerate() {
+            let (x, y) = (idx % 32, idx / 32);
+            let expected = [x as u8 * 8, y as u8 * 8, 128];
+            for (actual, expected) in pixel.iter().zip(expected) {
+                assert!(
+                    actual.abs_diff(expected) <= 8,
+                    "pixel ({}, {}) is {:?}, expected about {:?}",
+                    x,
+                    y,
+                    pixel,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn decodes_libtiff_ycbcr_strips_with_shared_tables() {
+        assert_decodes_to_gradient(LIBTIFF_YCBCR);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn decodes_libtiff_rgb_strips_with_shared_tables() {
+        assert_decodes_to_gradient(LIBTIFF_RGB);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn decompress_jpeg_rejects_corrupt_stream() {
+        assert!(matches!(
+            decompress_jpeg(&[0xFF, 0xD8, 0xFF, 0xD9], 100),
+            Err(CompressionError::InvalidJpegData)
+        ));
+    }
+
+    #[cfg(not(feature = "jpeg"))]
+    #[test]
+    fn jpeg_is_unsupported_without_feature() {
+        use crate::compression::decompress;
+
+        assert!(matches!(
+            decompress(7, &[0xFF, 0xD8, 0xFF, 0xD9], 100),
+            Err(CompressionError::UnsupportedCompression { compression: 7 })
+        ));
+    }
+
+    #[test]
+    fn splice_jpeg_tables_joins_tables_and_stream() {
+        let tables = [0xFF, 0xD8, 0xFF, 0xDB, 1, 0xFF, 0xD9];
+        let stream = [0xFF, 0xD8, 0xFF, 0xDA, 2, 0xFF, 0xD9];
+        assert_eq!(
+            splice_jpeg_tables(&tables, &stream).unwrap(),
+            [0xFF, 0xD8, 0xFF, 0xDB, 1, 0xFF, 0xDA, 2, 0xFF, 0xD9]
+        );
+    }
+
+    #[test]
+    fn splice_jpeg_tables_rejects_missing_markers() {
+        let tables = [0xFF, 0xD8, 0xFF, 0xD9];
+        let stream = [0xFF, 0xD8, 0xFF, 0xD9];
+        for (tables, stream) in [
+            (&tables[..], &stream[1..]),
+            (&tables[1..], &stream[..]),
+            (&tables[..3], &stream[..]),
+        ] {
+            assert!(matches!(
+                splice_jpeg_tables(tables, stream),
+                Err(CompressionError::InvalidJpegStream)
+            ));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::errors::CompressionError;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Code which resets the dictionary and code width.
+const CLEAR_CODE: u16 = 256;
+
+/// Code which marks the end of the data.
+const EOI_CODE: u16 = 257;
+
+/// First code assigned to a dictionary entry.
+const FIRST_CODE: u16 = 258;
+
+/// Width of codes after a clear code.
+const MIN_CODE_WIDTH: u32 = 9;
+
+/// Width of the widest codes, limiting the dictionary to 4096 entries.
+const MAX_CODE_WIDTH: u32 = 12;
+
+/// Decompress TIFF-style LZW (compression 5) `input`, as written by libtiff: codes are packed
+/// most significant bit first, and widen one code earlier than in GIF's LZW. Decoding stops at
+/// the end-of-information code, or the end of `input` if that is missing.
+pub fn decompress_lzw(input: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let mut output = Vec::with_capacity(input.len() * 2);
+    let mut codes = CodeReader::new(input);
+
+    // Every entry is its prefix plus one byte, and output contains the prefix directly followed
+    // by that byte, so each entry is stored as the range of output it was first decoded into
+    let mut dictionary: Vec<(usize, usize)> = Vec::new();
+    let mut width = MIN_CODE_WIDTH;
+    // Where the previously decoded string was written to output
+    let mut previous: Option<(usize, usize)> = None;
+
+    while let Some(code) = codes.next(width) {
+        if code == CLEAR_CODE {
+            dictionary.clear();
+            width = MIN_CODE_WIDTH;
+            previous = None;
+            continue;
+        }
+        if code == EOI_CODE {
+            break;
+        }
+
+        let next_code = FIRST_CODE + dictionary.len() as u16;
+        let start = output.len();
+        match (code, previous) {
+            (0..=255, _) => output.push(code as u8),
+            (_, _) if code < next_code => {
+                let (entry_start, entry_len) = dictionary[(code - FIRST_CODE) as usize];
+                output.extend_from_within(entry_start..entry_start + entry_len);
+            }
+            // The code being defined by this very step: the previous string plus its first byte
+            (_, Some((previous_start, previous_len))) if code == next_code => {
+                output.extend_from_within(previous_start..previous_start + previous_len);
+                output.push(output[previous_start]);
+            }
+            _ => return Err(CompressionError::InvalidCode { code }),
+        }
+
+        if let Some((previous_start, previous_len)) = previous {
+            if next_code < 1 << MAX_CODE_WIDTH {
+                dictionary.push((previous_start, previous_len + 1));
+                // Widen a code early, as libtiff does
+                if u32::from(next_code) + 2 >= 1 << width && width < MAX_CODE_WIDTH {
+                    width += 1;
+                }
+            }
+        }
+        previous = Some((start, output.len() - start));
+    }
+
+    Ok(output)
+}
+
+/// Reads variable-width codes, most significant bit first.
+struct CodeReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    buffer: u32,
+    buffered_bits: u32,
+}
+
+impl<'a> CodeReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            position: 0,
+            buffer: 0,
+            buffered_bits: 0,
+        }
+    }
+
+    /// Read the next `width`-bit code, or `None` at the end of the input.
+    fn next(&mut self, width: u32) -> Option<u16> {
+        while self.buffered_bits < width {
+            let byte = *self.input.get(self.position)?;
+            self.position += 1;
+            self.buffer = (self.buffer << 8) | u32::from(byte);
+            self.buffered_bits += 8;
+        }
+        self.buffered_bits -= width;
+        let code = (self.buffer >> self.buffered_bits) & ((1 << width) - 1);
+        Some(code as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The single strip of a 64x128 8-bit grayscale image, LZW-compressed by libtiff. The image
+    /// needs several thousand codes, so the strip widens codes through every width and clears
+    /// the dictionary partway through.
+    const LIBTIFF_STRIP: &[u8] = include_bytes!("../../testdata/libtiff_lzw_strip.bin");
+
+    #[test]
+    fn decodes_libtiff_strip() {
+        let expected: Vec<u8> = (0..128u32)
+            .flat_map(|y| (0..64u32).map(move |x| ((x * y) ^ (x + 3 * y)) as u8))
+            .collect();
+        assert_eq!(decompress_lzw(LIBTIFF_STRIP).unwrap(), expected);
+    }
+
+    #[test]
+    fn stops_at_end_of_input_without_eoi() {
+        // A clear code, then 'A', then too few bits for another code
+        assert_eq!(decompress_lzw(&[0x80, 0x10, 0x40]).unwrap(), b"A");
+    }
+
+    #[test]
+    fn rejects_code_beyond_dictionary() {
+        // A clear code, then code 300 with nothing yet in the dictionary
+        assert!(matches!(
+            decompress_lzw(&[0x80, 0x4B, 0x00]),
+            Err(CompressionError::InvalidCode { code: 300 })
+        ));
+    }
+}
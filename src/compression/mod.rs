@@ -0,0 +1,27 @@
+/// CCITT fax decompression
+pub(crate) mod ccitt;
+pub use ccitt::*;
+
+/// Selecting a decompressor by the Compression tag
+pub(crate) mod decompress;
+pub use decompress::*;
+
+/// zlib/DEFLATE decompression
+pub(crate) mod deflate;
+pub use deflate::*;
+
+/// Preparing JPEG-compressed strips and tiles for a JPEG decoder
+pub(crate) mod jpeg;
+pub use jpeg::*;
+
+/// TIFF-style LZW decompression
+pub(crate) mod lzw;
+pub use lzw::*;
+
+/// PackBits run-length decompression
+pub(crate) mod packbits;
+pub use packbits::*;
+
+/// Undoing the differencing and floating point predictors
+pub(crate) mod predictor;
+pub use predictor::*;
@@ -0,0 +1,60 @@
+use crate::errors::CompressionError;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Decompress PackBits run-length encoded `input`, stopping once `expected_output_len` bytes
+/// have been produced. Fails if `input` ends first.
+pub fn decompress_packbits(
+    input: &[u8],
+    expected_output_len: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    // Each byte of input produces at most 128 bytes of output, however large the claimed length
+    let mut output = Vec::with_capacity(expected_output_len.min(input.len().saturating_mul(128)));
+    let mut input = input.iter();
+
+    while output.len() < expected_output_len {
+        let control = *input.next().ok_or(CompressionError::TruncatedInput)? as i8;
+        let remaining = expected_output_len - output.len();
+        match control {
+            // Copy the next n + 1 bytes literally
+            0..=127 => {
+                let len = control as usize + 1;
+                for _ in 0..len.min(remaining) {
+                    output.push(*input.next().ok_or(CompressionError::TruncatedInput)?);
+                }
+            }
+            // No-op
+            -128 => {}
+            // Repeat the next byte 1 - n times
+            _ => {
+                let len = (1 - control as isize) as usize;
+                let byte = *input.next().ok_or(CompressionError::TruncatedInput)?;
+                output.resize(output.len() + len.min(remaining), byte);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_literals_and_runs() {
+        let input = [0x02, 1, 2, 3, 0x80, 0xfd, 9];
+        assert_eq!(
+            decompress_packbits(&input, 7).unwrap(),
+            [1, 2, 3, 9, 9, 9, 9]
+        );
+    }
+
+    #[test]
+    fn huge_expected_length_fails_without_allocating_it() {
+        assert!(matches!(
+            decompress_packbits(&[0x81, 7], usize::MAX),
+            Err(CompressionError::TruncatedInput)
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::errors::CompressionError;
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+use byteorder::ByteOrder;
+
+/// Undo horizontal differencing (Predictor 2) in decompressed image data `buf`, holding rows of
+/// `width` pixels with `samples_per_pixel` interleaved samples each. Every sample was stored as
+/// the difference from the same sample of the pixel to its left, restarting at each row.
+/// Samples of 8, 16 or 32 bits are supported, with the wider ones in the byte order `E`.
+pub fn apply_predictor_horizontal<E: ByteOrder>(
+    buf: &mut [u8],
+    width: usize,
+    samples_per_pixel: usize,
+    bits_per_sample: u8,
+) -> Result<(), CompressionError> {
+    let sample_size = match bits_per_sample {
+        8 | 16 | 32 => bits_per_sample as usize / 8,
+        _ => return Err(CompressionError::UnsupportedPredictor { bits_per_sample }),
+    };
+    let row_len = width * samples_per_pixel * sample_size;
+    if row_len == 0 {
+        return Ok(());
+    }
+
+    // Samples are offset by one pixel from those they were differenced with
+    let stride = samples_per_pixel * sample_size;
+    for row in buf.chunks_mut(row_len) {
+        for idx in (stride..row.len() - row.len() % sample_size).step_by(sample_size) {
+            match sample_size {
+                1 => row[idx] = row[idx].wrapping_add(row[idx - stride]),
+                2 => {
+                    let sum =
+                        E::read_u16(&row[idx..]).wrapping_add(E::read_u16(&row[idx - stride..]));
+                    E::write_u16(&mut row[idx..], sum);
+                }
+                _ => {
+                    let sum =
+                        E::read_u32(&row[idx..]).wrapping_add(E::read_u32(&row[idx - stride..]));
+                    E::write_u32(&mut row[idx..], sum);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Undo the floating point predictor (Predictor 3) in decompressed image data `buf`, holding
+/// rows of `width` pixels with `samples_per_pixel` interleaved floating point samples each. Each
+/// row was stored with the bytes of its samples split into planes, most significant first, and
+/// then every byte differenced from the one `samples_per_pixel` before it. The samples are
+/// restored in the byte order `E`. Samples of 16, 32 or 64 bits are supported.
+pub fn apply_predictor_float<E: ByteOrder>(
+    buf: &mut [u8],
+    width: usize,
+    samples_per_pixel: usize,
+    bits_per_sample: u8,
+) -> Result<(), CompressionError> {
+    let sample_size = match bits_per_sample {
+        16 | 32 | 64 => bits_per_sample as usize / 8,
+        _ => return Err(CompressionError::UnsupportedPredictor { bits_per_sample }),
+    };
+    let samples_per_row = width * samples_per_pixel;
+    let row_len = samples_per_row * sample_size;
+    if row_len == 0 {
+        return Ok(());
+    }
+
+    // Whether the first byte of a sample in `E` is its most significant
+    let big_endian = {
+        let mut probe = [0; 2];
+        E::write_u16(&mut probe, 1);
+        probe[0] == 0
+    };
+
+    let mut planes = vec![0; row_len];
+    for row in buf.chunks_exact_mut(row_len) {
+        for idx in samples_per_pixel..row_len {
+            row[idx] = row[idx].wrapping_add(row[idx - samples_per_pixel]);
+        }
+
+        planes.copy_from_slice(row);
+        for (sample, bytes) in row.chunks_exact_mut(sample_size).enumerate() {
+            for (byte, value) in bytes.iter_mut().enumerate() {
+                let plane = if big_endian {
+                    byte
+                } else {
+                    sample_size - 1 - byte
+                };
+                *value = planes[plane * samples_per_row + sample];
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::{decompress_deflate, decompress_lzw};
+    use byteorder::LittleEndian;
+
+    /// The single strip of a 16x8 8-bit RGB image, written by libtiff with Predictor 2 and LZW.
+    const LIBTIFF_RGB8: &[u8] = include_bytes!("../../testdata/libtiff_predictor_rgb8_strip.bin");
+
+    /// The single strip of a 16x8 16-bit little-endian grayscale image, written by libtiff with
+    /// Predictor 2 and LZW.
+    const LIBTIFF_GRAY16: &[u8] =
+        include_bytes!("../../testdata/libtiff_predictor_gray16_strip.bin");
+
+    /// The single strip of a 20x6 little-endian image of two 32-bit float samples per pixel,
+    /// written by libtiff (as GDAL writes float rasters) with Predictor 3 and Deflate.
+    const LIBTIFF_FLOAT: &[u8] = include_bytes!("../../testdata/libtiff_predictor_float_strip.bin");
+
+    #[test]
+    fn restores_libtiff_rgb8() {
+        let mut buf = decompress_lzw(LIBTIFF_RGB8).unwrap();
+        apply_predictor_horizontal::<LittleEndian>(&mut buf, 16, 3, 8).unwrap();
+        let expected: Vec<u8> = (0..8u32)
+            .flat_map(|y| (0..16u32).flat_map(move |x| [x * 16, y * 32, x * y * 7]))
+            .map(|sample| sample as u8)
+            .collect();
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn restores_libtiff_gray16() {
+        let mut buf = decompress_lzw(LIBTIFF_GRAY16).unwrap();
+        apply_predictor_horizontal::<LittleEndian>(&mut buf, 16, 1, 16).unwrap();
+        let expected: Vec<u8> = (0..8u32)
+            .flat_map(|y| (0..16u32).map(move |x| (x * 4099 + y * 257) as u16))
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn restores_libtiff_float() {
+        let mut buf = decompress_deflate(LIBTIFF_FLOAT, usize::MAX).unwrap();
+        apply_predictor_float::<LittleEndian>(&mut buf, 20, 2, 32).unwrap();
+        let expected: Vec<u8> = (0..6)
+            .flat_map(|y| (0..20).flat_map(move |x| [(x, y, 0), (x, y, 1)]))
+            .map(|(x, y, s)| {
+                let (x, y, s) = (f64::from(x), f64::from(y), f64::from(s));
+                ((x * 0.5 - y * 1.25) * (1. + s) + 100. * s) as f32
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn restarts_at_each_row() {
+        // Two rows of two 2-sample pixels
+        let mut buf = [1, 2, 1, 1, 5, 6, 1, 1];
+        apply_predictor_horizontal::<LittleEndian>(&mut buf, 2, 2, 8).unwrap();
+        assert_eq!(buf, [1, 2, 2, 3, 5, 6, 6, 7]);
+    }
+
+    #[test]
+    fn rejects_unsupported_bit_depth() {
+        assert!(matches!(
+            apply_predictor_horizontal::<LittleEndian>(&mut [0; 4], 4, 1, 4),
+            Err(CompressionError::UnsupportedPredictor { bits_per_sample: 4 })
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::baseline::decode::{
+    decode_f32_with_limits, decode_gray16_with_limits, decode_gray8_with_limits,
+    decode_i16_with_limits, decode_i32_with_limits, decode_rgb8_with_limits,
+    decode_rgba8_with_limits, DecodeLimits,
+};
+use crate::errors::{ImageDataError, TiffError};
+use crate::lowlevel::{read_ifds_auto, BigEndian, Endianness, LittleEndian, RawIFD};
+use byteorder::ReadBytesExt;
+use std::io::Seek;
+use std::slice;
+
+/// A classic TIFF file viewed as a sequence of pages, one per IFD in the main chain.
+pub struct TiffDocument<R: ReadBytesExt + Seek> {
+    reader: R,
+    endianness: Endianness,
+    pages: Box<[RawIFD]>,
+    decode_limits: DecodeLimits,
+}
+
+impl<R: ReadBytesExt + Seek> TiffDocument<R> {
+    /// Read the header and every page's IFD from `reader`. Assumes the cursor is positioned at
+    /// the beginning of the file. Fails with `TiffError::UnexpectedBigTiff` if the file is a
+    /// BigTIFF, which the decoders don't handle.
+    pub fn read_from(mut reader: R) -> Result<Self, TiffError> {
+        let (endianness, pages) = read_ifds_auto(&mut reader)?;
+        let pages = pages.into_classic()?;
+        Ok(Self {
+            reader,
+            endianness,
+            pages,
+            decode_limits: DecodeLimits::default(),
+        })
+    }
+
+    /// Returns the byte order of the file, in which the pages' fields must be interpreted.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Number of pages in the file.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Returns the IFD of the page at `index`, or `None` if there is no such page.
+    pub fn page(&self, index: usize) -> Option<&RawIFD> {
+        self.pages.get(index)
+    }
+
+    /// Returns an iterator over the IFDs of the pages, in order.
+    pub fn pages(&self) -> slice::Iter<'_, RawIFD> {
+        self.pages.iter()
+    }
+
+    /// Borrow the underlying reader, for reading field values or image data of the pages.
+    pub fn reader(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns the bounds on the size of the pages decoded, which default to
+    /// `DecodeLimits::default()`.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        self.decode_limits
+    }
+
+    /// Bound the size of the pages decoded from now on by `limits`, as the `_with_limits`
+    /// decoders do.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_limits = limits;
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decode the page at `index` as `decode_rgb8` does, within the document's decode limits.
+    pub fn decode_rgb8(&mut self, index: usize) -> Result<(u32, u32, Vec<u8>), TiffError> {
+        self.decode_page(
+            index,
+            decode_rgb8_with_limits::<LittleEndian, R>,
+            decode_rgb8_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_rgba8` does, within the document's decode limits.
+    pub fn decode_rgba8(&mut self, index: usize) -> Result<(u32, u32, Vec<u8>), TiffError> {
+        self.decode_page(
+            index,
+            decode_rgba8_with_limits::<LittleEndian, R>,
+            decode_rgba8_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_gray8` does, within the document's decode limits.
+    pub fn decode_gray8(&mut self, index: usize) -> Result<(u32, u32, Vec<u8>), TiffError> {
+        self.decode_page(
+            index,
+            decode_gray8_with_limits::<LittleEndian, R>,
+            decode_gray8_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_gray16` does, within the document's decode limits.
+    pub fn decode_gray16(&mut self, index: usize) -> Result<(u32, u32, Vec<u16>), TiffError> {
+        self.decode_page(
+            index,
+            decode_gray16_with_limits::<LittleEndian, R>,
+            decode_gray16_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_f32` does, within the document's decode limits.
+    pub fn decode_f32(&mut self, index: usize) -> Result<(u32, u32, Vec<f32>), TiffError> {
+        self.decode_page(
+            index,
+            decode_f32_with_limits::<LittleEndian, R>,
+            decode_f32_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_i16` does, within the document's decode limits.
+    pub fn decode_i16(&mut self, index: usize) -> Result<(u32, u32, Vec<i16>), TiffError> {
+        self.decode_page(
+            index,
+            decode_i16_with_limits::<LittleEndian, R>,
+            decode_i16_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` as `decode_i32` does, within the document's decode limits.
+    pub fn decode_i32(&mut self, index: usize) -> Result<(u32, u32, Vec<i32>), TiffError> {
+        self.decode_page(
+            index,
+            decode_i32_with_limits::<LittleEndian, R>,
+            decode_i32_with_limits::<BigEndian, R>,
+        )
+    }
+
+    /// Decode the page at `index` with whichever of `little` or `big` matches the file's byte
+    /// order, passing them the document's decode limits. Fails if there is no such page.
+    fn decode_page<T>(
+        &mut self,
+        index: usize,
+        little: fn(&mut R, &RawIFD, DecodeLimits) -> Result<T, TiffError>,
+        big: fn(&mut R, &RawIFD, DecodeLimits) -> Result<T, TiffError>,
+    ) -> Result<T, TiffError> {
+        let page = self
+            .pages
+            .get(index)
+            .ok_or(ImageDataError::PageOutOfRange {
+                index,
+                count: self.pages.len(),
+            })?;
+        match self.endianness {
+            Endianness::Little => little(&mut self.reader, page, self.decode_limits),
+            Endianness::Big => big(&mut self.reader, page, self.decode_limits),
+        }
+    }
+}
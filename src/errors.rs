@@ -1,12 +1,701 @@
-use failure::Fail;
+use crate::lowlevel::{Endianness, RawIFD, TagType};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+
+/// An error encountered while reading or writing a TIFF file.
+#[derive(Debug)]
+pub enum TiffError {
+    /// An I/O error not covered by a more specific variant.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// The file ended in the middle of a structure or value.
+    UnexpectedEof,
+    /// The byte-order mark was neither `II` nor `MM`.
+    BadEndianMagic { culprit: [u8; 2] },
+    /// The magic number was neither 42 (TIFF) nor 43 (BigTIFF).
+    BadMagic { magic: u16 },
+    /// A BigTIFF header declared an offset size other than 8.
+    BadBigTiffOffsetSize { size: u16 },
+    /// A BigTIFF file was passed to a reader for classic TIFFs.
+    UnexpectedBigTiff,
+    /// A field's data type is unrecognized, so the size of its value is unknown.
+    UnknownTagType { tag_type: u16 },
+    /// An offset (plus the length of the data there) runs past the end of the file.
+    OffsetOutOfBounds { offset: u64, file_len: u64 },
+    /// A field's value is stored out-of-line at offset zero, which is the header rather than any
+    /// data, so the file is almost certainly corrupt.
+    NullOffset { tag: u16 },
+    /// Something was to be written at `position`, past the 4 GiB a classic TIFF's 32-bit
+    /// offsets can address, so the file needs to be a BigTIFF instead.
+    OffsetOverflow { position: u64 },
+    /// The file ended before all of the entries an IFD claims to have, leaving `partial` with
+    /// only the entries that were read.
+    TruncatedIFD {
+        expected_entries: usize,
+        read_entries: usize,
+        partial: RawIFD,
+    },
+    /// An existing file is in the byte order `file`, not the one it was to be modified in.
+    ByteOrderMismatch { file: Endianness },
+    /// A field's value could not be extracted.
+    Field(FieldExtractionError),
+    /// An IFD is malformed, or exceeds the limits placed on it.
+    IFD(IFDError),
+    /// The tags describing the image data are inconsistent, or request something unsupported.
+    ImageData(ImageDataError),
+    /// Image data could not be decompressed.
+    Compression(CompressionError),
+    /// A GeoTIFF key directory is malformed.
+    GeoKey(GeoKeyError),
+}
+
+impl fmt::Display for TiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            TiffError::Io(err) => write!(f, "I/O error: {}", err),
+            TiffError::UnexpectedEof => write!(f, "Unexpected end of file"),
+            TiffError::BadEndianMagic { culprit } => {
+                write!(f, "Bad endian magic number: {:?}", culprit)
+            }
+            TiffError::BadMagic { magic } => write!(f, "Bad magic number: {:?}", magic),
+            TiffError::BadBigTiffOffsetSize { size } => {
+                write!(f, "Bad BigTIFF offset size: {:?}", size)
+            }
+            TiffError::UnexpectedBigTiff => write!(
+                f,
+                "File is a BigTIFF, which requires the 64-bit IFD readers"
+            ),
+            TiffError::UnknownTagType { tag_type } => write!(f, "Unknown data type {}", tag_type),
+            TiffError::OffsetOutOfBounds { offset, file_len } => write!(
+                f,
+                "Offset {} runs past the end of the file ({} bytes)",
+                offset, file_len
+            ),
+            TiffError::NullOffset { tag } => {
+                write!(f, "Value of tag {} is out-of-line at offset zero", tag)
+            }
+            TiffError::OffsetOverflow { position } => write!(
+                f,
+                "Position {} is too large for a classic TIFF offset, use BigTIFF instead",
+                position
+            ),
+            TiffError::TruncatedIFD {
+                expected_entries,
+                read_entries,
+                ..
+            } => write!(
+                f,
+                "IFD claims {} entries, but the file ends after {}",
+                expected_entries, read_entries
+            ),
+            TiffError::ByteOrderMismatch { file } => write!(
+                f,
+                "File is {}, not in the byte order it was to be modified in",
+                match file {
+                    Endianness::Little => "little-endian",
+                    Endianness::Big => "big-endian",
+                }
+            ),
+            TiffError::Field(err) => err.fmt(f),
+            TiffError::IFD(err) => err.fmt(f),
+            TiffError::ImageData(err) => err.fmt(f),
+            TiffError::Compression(err) => err.fmt(f),
+            TiffError::GeoKey(err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for TiffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TiffError::Io(err) => Some(err),
+            TiffError::Field(err) => Some(err),
+            TiffError::IFD(err) => Some(err),
+            TiffError::ImageData(err) => Some(err),
+            TiffError::Compression(err) => Some(err),
+            TiffError::GeoKey(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for TiffError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            TiffError::UnexpectedEof
+        } else {
+            TiffError::Io(err)
+        }
+    }
+}
+
+impl From<FieldExtractionError> for TiffError {
+    fn from(err: FieldExtractionError) -> Self {
+        TiffError::Field(err)
+    }
+}
+
+impl From<IFDError> for TiffError {
+    fn from(err: IFDError) -> Self {
+        TiffError::IFD(err)
+    }
+}
+
+impl From<ImageDataError> for TiffError {
+    fn from(err: ImageDataError) -> Self {
+        TiffError::ImageData(err)
+    }
+}
+
+impl From<CompressionError> for TiffError {
+    fn from(err: CompressionError) -> Self {
+        TiffError::Compression(err)
+    }
+}
+
+impl From<GeoKeyError> for TiffError {
+    fn from(err: GeoKeyError) -> Self {
+        TiffError::GeoKey(err)
+    }
+}
 
 /// An error encountered during extraction of a field from from an IFD
-#[derive(Fail, Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum FieldExtractionError {
-    #[fail(display = "Tag has wrong data type")]
     WrongDataType,
-    #[fail(display = "Tag contains insufficient data")]
     InsufficientData,
-    #[fail(display = "Missing tag {:X}", tag)]
     MissingTag { tag: u16 },
+    MismatchedDataType { expected: TagType, actual: u16 },
+    InvalidText { tag: u16 },
+    InvalidDateTime { reason: &'static str },
+    ValueOutOfRange { tag: u16, value: u64 },
 }
+
+impl fmt::Display for FieldExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldExtractionError::WrongDataType => write!(f, "Tag has wrong data type"),
+            FieldExtractionError::InsufficientData => write!(f, "Tag contains insufficient data"),
+            FieldExtractionError::MissingTag { tag } => write!(f, "Missing tag {:X}", tag),
+            FieldExtractionError::MismatchedDataType { expected, actual } => write!(
+                f,
+                "Expected {:?} data, found data type {}",
+                expected, actual
+            ),
+            FieldExtractionError::InvalidText { tag } => {
+                write!(f, "Tag {:X} contains text which is not valid UTF-8", tag)
+            }
+            FieldExtractionError::InvalidDateTime { reason } => write!(
+                f,
+                "Date and time is not in the format YYYY:MM:DD HH:MM:SS: {}",
+                reason
+            ),
+            FieldExtractionError::ValueOutOfRange { tag, value } => {
+                write!(f, "Value {} of tag {:X} is out of range", value, tag)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for FieldExtractionError {}
+
+/// An error encountered due to the structure of an IFD
+#[derive(Debug, Clone)]
+pub enum IFDError {
+    UnorderedTags {
+        /// Tags appearing more than once
+        duplicates: Vec<u16>,
+        /// Tags appearing after a larger tag
+        out_of_order: Vec<u16>,
+    },
+    OffsetInHeader {
+        offset: u64,
+    },
+    TooManyEntries {
+        count: usize,
+        limit: usize,
+    },
+    TooManyIFDs {
+        limit: usize,
+    },
+    DataLengthMismatch {
+        tag: u16,
+        expected: usize,
+        actual: usize,
+    },
+    DataWithoutField {
+        tag: u16,
+    },
+    NoIFDs,
+    ChainLoop {
+        offset: u64,
+    },
+    ValueNotInline {
+        tag: u16,
+    },
+    OffsetTooLarge {
+        offset: u64,
+    },
+    ValueTooLarge {
+        tag: u16,
+        value: u64,
+    },
+    ByteCountMismatch {
+        tag: u16,
+        offsets: usize,
+        byte_counts: usize,
+    },
+    MergeConflict {
+        tag: u16,
+    },
+    EmptyIFD {
+        offset: u64,
+    },
+    InlineValueTooLong {
+        tag: u16,
+        len: usize,
+    },
+    ValueOutOfBounds {
+        tag: u16,
+        offset: u64,
+        len: u64,
+        file_len: u64,
+    },
+    TooManyValues {
+        tag: u16,
+        count: usize,
+    },
+}
+
+impl fmt::Display for IFDError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IFDError::UnorderedTags {
+                duplicates,
+                out_of_order,
+            } => write!(
+                f,
+                "Tags are not strictly ascending (duplicates: {:X?}, out of order: {:X?})",
+                duplicates, out_of_order
+            ),
+            IFDError::OffsetInHeader { offset } => {
+                write!(f, "IFD offset {} points inside the file header", offset)
+            }
+            IFDError::TooManyEntries { count, limit } => write!(
+                f,
+                "IFD has {} entries, more than the limit of {}",
+                count, limit
+            ),
+            IFDError::TooManyIFDs { limit } => {
+                write!(f, "File has more than the limit of {} IFDs", limit)
+            }
+            IFDError::DataLengthMismatch {
+                tag,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Data for tag {:X} is {} bytes long, but the field describes {} bytes",
+                tag, actual, expected
+            ),
+            IFDError::DataWithoutField { tag } => write!(
+                f,
+                "Data was supplied for tag {:X}, which is not in the IFD",
+                tag
+            ),
+            IFDError::NoIFDs => write!(f, "A TIFF file must contain at least one IFD"),
+            IFDError::ChainLoop { offset } => write!(
+                f,
+                "IFD chain loops back to offset {}, so has no last IFD",
+                offset
+            ),
+            IFDError::ValueNotInline { tag } => write!(
+                f,
+                "Value of tag {:X} is stored out-of-line, so can't be patched in place",
+                tag
+            ),
+            IFDError::OffsetTooLarge { offset } => {
+                write!(f, "Offset {} is too large for a classic TIFF", offset)
+            }
+            IFDError::ValueTooLarge { tag, value } => write!(
+                f,
+                "Value {} of tag {} is too large for a classic TIFF",
+                value, tag
+            ),
+            IFDError::ByteCountMismatch {
+                tag,
+                offsets,
+                byte_counts,
+            } => write!(
+                f,
+                "Tag {} has {} offsets but {} byte counts",
+                tag, offsets, byte_counts
+            ),
+            IFDError::MergeConflict { tag } => {
+                write!(f, "Both IFDs have differing fields for tag {}", tag)
+            }
+            IFDError::EmptyIFD { offset } => write!(f, "IFD at offset {} has no entries", offset),
+            IFDError::InlineValueTooLong { tag, len } => write!(
+                f,
+                "Value of tag {:X} is {} bytes long, too long to be stored inline",
+                tag, len
+            ),
+            IFDError::ValueOutOfBounds {
+                tag,
+                offset,
+                len,
+                file_len,
+            } => write!(
+                f,
+                "Value of tag {:X} ({} bytes at offset {}) runs past the end of the file ({} bytes)",
+                tag, len, offset, file_len
+            ),
+            IFDError::TooManyValues { tag, count } => write!(
+                f,
+                "Tag {:X} has {} values, more than a field's 32-bit count can hold",
+                tag, count
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for IFDError {}
+
+/// An error encountered while locating or interpreting image data
+#[derive(Debug, Clone, Copy)]
+pub enum ImageDataError {
+    StripCountMismatch {
+        offsets: usize,
+        byte_counts: usize,
+        expected: usize,
+    },
+    StripOutOfRange {
+        index: usize,
+        count: usize,
+    },
+    ShortStrip {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+    TileCountMismatch {
+        offsets: usize,
+        byte_counts: usize,
+    },
+    TooFewTiles {
+        expected: usize,
+        actual: usize,
+    },
+    TileOutOfRange {
+        col: u32,
+        row: u32,
+    },
+    InvalidTileSize {
+        width: u32,
+        length: u32,
+    },
+    InvalidRowsPerStrip {
+        rows_per_strip: u32,
+    },
+    UnknownPhotometric {
+        value: u16,
+    },
+    UnknownResolutionUnit {
+        value: u16,
+    },
+    UnknownOrientation {
+        value: u16,
+    },
+    UnknownFillOrder {
+        value: u16,
+    },
+    UnknownExtraSample {
+        value: u16,
+    },
+    UnknownSampleFormat {
+        value: u16,
+    },
+    UnsupportedSampleFormat {
+        sample_format: u16,
+    },
+    UnsupportedFormat {
+        photometric: u16,
+        samples_per_pixel: u16,
+        bits_per_sample: u16,
+    },
+    UnsupportedPlanarConfiguration {
+        planar_configuration: u16,
+    },
+    InvalidColorMap {
+        len: usize,
+    },
+    PageOutOfRange {
+        index: usize,
+        count: usize,
+    },
+    UnsupportedSubsampling {
+        horizontal: u16,
+        vertical: u16,
+    },
+    NotStripped,
+    ImageTooLarge {
+        bytes: u64,
+        limit: u64,
+    },
+    PixelBufferLength {
+        expected: u64,
+        actual: usize,
+    },
+    ChunkTooLarge {
+        len: usize,
+    },
+}
+
+impl fmt::Display for ImageDataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageDataError::StripCountMismatch {
+                offsets,
+                byte_counts,
+                expected,
+            } => write!(
+                f,
+                "Image has {} strip offsets and {} strip byte counts, but needs {} strips",
+                offsets, byte_counts, expected
+            ),
+            ImageDataError::StripOutOfRange { index, count } => {
+                write!(
+                    f,
+                    "Strip {} requested, but image has {} strips",
+                    index, count
+                )
+            }
+            ImageDataError::ShortStrip {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Strip {} decoded to {} bytes, but should hold {}",
+                index, actual, expected
+            ),
+            ImageDataError::TileCountMismatch {
+                offsets,
+                byte_counts,
+            } => write!(
+                f,
+                "Image has {} tile offsets but {} tile byte counts",
+                offsets, byte_counts
+            ),
+            ImageDataError::TooFewTiles { expected, actual } => write!(
+                f,
+                "Image needs {} tiles to cover it, but only has {}",
+                expected, actual
+            ),
+            ImageDataError::TileOutOfRange { col, row } => {
+                write!(
+                    f,
+                    "Tile at column {}, row {} is outside the image",
+                    col, row
+                )
+            }
+            ImageDataError::InvalidTileSize { width, length } => {
+                write!(f, "Invalid tile size {}x{}", width, length)
+            }
+            ImageDataError::InvalidRowsPerStrip { rows_per_strip } => {
+                write!(f, "Invalid RowsPerStrip {}", rows_per_strip)
+            }
+            ImageDataError::UnknownPhotometric { value } => {
+                write!(f, "Unknown photometric interpretation {}", value)
+            }
+            ImageDataError::UnknownResolutionUnit { value } => {
+                write!(f, "Unknown resolution unit {}", value)
+            }
+            ImageDataError::UnknownOrientation { value } => {
+                write!(f, "Unknown orientation {}", value)
+            }
+            ImageDataError::UnknownFillOrder { value } => {
+                write!(f, "Unknown fill order {}", value)
+            }
+            ImageDataError::UnknownExtraSample { value } => {
+                write!(f, "Unknown extra sample type {}", value)
+            }
+            ImageDataError::UnknownSampleFormat { value } => {
+                write!(f, "Unknown sample format {}", value)
+            }
+            ImageDataError::UnsupportedSampleFormat { sample_format } => {
+                write!(f, "Unsupported sample format {}", sample_format)
+            }
+            ImageDataError::UnsupportedFormat {
+                photometric,
+                samples_per_pixel,
+                bits_per_sample,
+            } => write!(
+                f,
+                "Unsupported image format: {} with {} samples of {} bits",
+                crate::baseline::constants::photometic_interpretation::to_string(*photometric),
+                samples_per_pixel,
+                bits_per_sample
+            ),
+            ImageDataError::UnsupportedPlanarConfiguration {
+                planar_configuration,
+            } => write!(
+                f,
+                "Unsupported planar configuration {}",
+                planar_configuration
+            ),
+            ImageDataError::InvalidColorMap { len } => write!(
+                f,
+                "Color map has {} values, which is not a multiple of three",
+                len
+            ),
+            ImageDataError::PageOutOfRange { index, count } => write!(
+                f,
+                "Page {} requested, but document has {} pages",
+                index, count
+            ),
+            ImageDataError::UnsupportedSubsampling {
+                horizontal,
+                vertical,
+            } => write!(
+                f,
+                "Unsupported YCbCr subsampling {}x{}",
+                horizontal, vertical
+            ),
+            ImageDataError::NotStripped => write!(f, "Image is stored in tiles, not strips"),
+            ImageDataError::ImageTooLarge { bytes, limit } => write!(
+                f,
+                "Image data is {} bytes once decoded, more than the limit of {}",
+                bytes, limit
+            ),
+            ImageDataError::PixelBufferLength { expected, actual } => write!(
+                f,
+                "Pixel buffer holds {} bytes, but the image needs {}",
+                actual, expected
+            ),
+            ImageDataError::ChunkTooLarge { len } => write!(
+                f,
+                "Strip or tile of {} bytes is too long for a classic TIFF's 32-bit byte counts",
+                len
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ImageDataError {}
+
+/// An error encountered while decompressing image data
+#[derive(Debug, Clone, Copy)]
+pub enum CompressionError {
+    UnsupportedCompression { compression: u16 },
+    TruncatedInput,
+    InvalidCode { code: u16 },
+    InvalidFaxCode { row: usize },
+    UnsupportedT4Options { options: u32 },
+    UnsupportedT6Options { options: u32 },
+    InvalidZlibHeader,
+    InvalidDeflateBlock,
+    ChecksumMismatch,
+    UnsupportedPredictor { bits_per_sample: u8 },
+    UnknownPredictor { predictor: u16 },
+    InvalidJpegStream,
+    InvalidJpegData,
+}
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressionError::UnsupportedCompression { compression } => write!(
+                f,
+                "Unsupported compression {} ({})",
+                compression,
+                crate::baseline::constants::compression::to_string(*compression)
+            ),
+            CompressionError::InvalidCode { code } => {
+                write!(f, "Invalid code {} in compressed data", code)
+            }
+            CompressionError::InvalidFaxCode { row } => {
+                write!(f, "Invalid code in row {} of CCITT fax data", row)
+            }
+            CompressionError::UnsupportedT4Options { options } => {
+                write!(f, "Unsupported CCITT Group 3 options {:#x}", options)
+            }
+            CompressionError::UnsupportedT6Options { options } => {
+                write!(f, "Unsupported CCITT Group 4 options {:#x}", options)
+            }
+            CompressionError::InvalidZlibHeader => write!(f, "Invalid zlib header"),
+            CompressionError::InvalidDeflateBlock => write!(f, "Invalid DEFLATE block"),
+            CompressionError::ChecksumMismatch => {
+                write!(f, "Checksum of decompressed data does not match")
+            }
+            CompressionError::UnsupportedPredictor { bits_per_sample } => write!(
+                f,
+                "Predictor is unsupported for {}-bit samples",
+                bits_per_sample
+            ),
+            CompressionError::UnknownPredictor { predictor } => {
+                write!(f, "Unknown predictor {}", predictor)
+            }
+            CompressionError::InvalidJpegStream => {
+                write!(
+                    f,
+                    "JPEG stream does not begin and end with the expected markers"
+                )
+            }
+            CompressionError::InvalidJpegData => write!(f, "Invalid or unsupported JPEG data"),
+            CompressionError::TruncatedInput => {
+                write!(
+                    f,
+                    "Compressed data ended before the expected output was produced"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for CompressionError {}
+
+/// An error encountered while parsing a GeoTIFF key directory
+#[derive(Debug, Clone, Copy)]
+pub enum GeoKeyError {
+    TruncatedDirectory { len: usize },
+    ValueOutOfRange { key: u16 },
+    UnknownLocation { key: u16, location: u16 },
+}
+
+impl fmt::Display for GeoKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeoKeyError::TruncatedDirectory { len } => write!(
+                f,
+                "GeoKey directory of {} values is too short for its keys",
+                len
+            ),
+            GeoKeyError::ValueOutOfRange { key } => write!(
+                f,
+                "Value of GeoKey {} lies outside of the parameters holding it",
+                key
+            ),
+            GeoKeyError::UnknownLocation { key, location } => write!(
+                f,
+                "Value of GeoKey {} is stored in unknown tag {}",
+                key, location
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for GeoKeyError {}
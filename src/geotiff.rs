@@ -0,0 +1,112 @@
+#[cfg(feature = "std")]
+use crate::{
+    baseline::tags,
+    errors::{FieldExtractionError, GeoKeyError, TiffError},
+    lowlevel::{RawIFD, RawIFDField},
+};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt};
+#[cfg(feature = "std")]
+use std::io::Seek;
+
+/// IDs of commonly used GeoKeys
+pub mod keys {
+    pub const GT_MODEL_TYPE: u16 = 1024;
+    pub const GT_RASTER_TYPE: u16 = 1025;
+    pub const GT_CITATION: u16 = 1026;
+    pub const GEOGRAPHIC_TYPE: u16 = 2048;
+    pub const GEOG_CITATION: u16 = 2049;
+    pub const GEOG_GEODETIC_DATUM: u16 = 2050;
+    pub const GEOG_ANGULAR_UNITS: u16 = 2054;
+    pub const PROJECTED_CS_TYPE: u16 = 3072;
+    pub const PCS_CITATION: u16 = 3073;
+    pub const PROJECTION: u16 = 3074;
+    pub const PROJ_LINEAR_UNITS: u16 = 3076;
+    pub const VERTICAL_CS_TYPE: u16 = 4096;
+    pub const VERTICAL_UNITS: u16 = 4099;
+}
+
+/// A single GeoKey from a GeoTIFF key directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoKey {
+    /// Key ID, such as `keys::PROJECTED_CS_TYPE`.
+    pub id: u16,
+    /// The key's value, from wherever in the GeoTIFF tags it was stored.
+    pub value: GeoKeyValue,
+}
+
+/// The value of a GeoKey.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoKeyValue {
+    /// SHORTs, stored within the key directory itself.
+    Short(Vec<u16>),
+    /// DOUBLEs, stored in the GeoDoubleParams tag.
+    Double(Vec<f64>),
+    /// Text, stored in the GeoAsciiParams tag, without its terminating `|`.
+    Ascii(String),
+}
+
+/// Read the GeoKeys in the GeoKeyDirectory tag of `ifd`, resolving values stored in the
+/// GeoDoubleParams and GeoAsciiParams tags. Returns an empty Vec if `ifd` has no GeoKeyDirectory
+/// tag. Fails if the directory is truncated, or a key's value lies outside the tag it refers to.
+#[cfg(feature = "std")]
+pub fn read_geo_keys<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Vec<GeoKey>, TiffError> {
+    let directory = match ifd.get(tags::GEO_KEY_DIRECTORY) {
+        Some(field) => field.as_u16_array::<E, R>(reader)?,
+        None => return Ok(Vec::new()),
+    };
+
+    // The header is the directory version, key revision, minor revision and number of keys,
+    // padded out to the same four SHORTs as each key entry
+    let truncated = GeoKeyError::TruncatedDirectory {
+        len: directory.len(),
+    };
+    let key_count = *directory.get(3).ok_or(truncated)? as usize;
+    let entries = directory.get(4..4 + key_count * 4).ok_or(truncated)?;
+
+    // The parameter tags are only needed, and so only read, if a key refers to them
+    let mut doubles = None;
+    let mut ascii = None;
+    let mut keys = Vec::with_capacity(key_count);
+    for entry in entries.chunks_exact(4) {
+        let (id, location, count, value_offset) = (entry[0], entry[1], entry[2], entry[3]);
+        let range = value_offset as usize..value_offset as usize + count as usize;
+        let out_of_range = GeoKeyError::ValueOutOfRange { key: id };
+        let value = match location {
+            // The value is the offset itself
+            0 => GeoKeyValue::Short(vec![value_offset]),
+            tags::GEO_KEY_DIRECTORY => {
+                GeoKeyValue::Short(directory.get(range).ok_or(out_of_range)?.to_vec())
+            }
+            tags::GEO_DOUBLE_PARAMS => {
+                if doubles.is_none() {
+                    doubles = Some(required_field(ifd, location)?.as_f64_array::<E, R>(reader)?);
+                }
+                let doubles = doubles.as_ref().unwrap();
+                GeoKeyValue::Double(doubles.get(range).ok_or(out_of_range)?.to_vec())
+            }
+            tags::GEO_ASCII_PARAMS => {
+                if ascii.is_none() {
+                    ascii = Some(required_field(ifd, location)?.as_ascii::<E, R>(reader)?);
+                }
+                let text = ascii.as_ref().unwrap().get(range).ok_or(out_of_range)?;
+                GeoKeyValue::Ascii(text.trim_end_matches('|').to_string())
+            }
+            location => return Err(GeoKeyError::UnknownLocation { key: id, location }.into()),
+        };
+        keys.push(GeoKey { id, value });
+    }
+    Ok(keys)
+}
+
+/// Find the field for `tag` in `ifd`, failing if it is missing.
+#[cfg(feature = "std")]
+fn required_field(ifd: &RawIFD, tag: u16) -> Result<&RawIFDField, TiffError> {
+    ifd.get(tag)
+        .ok_or_else(|| FieldExtractionError::MissingTag { tag }.into())
+}
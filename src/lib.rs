@@ -1,4 +1,8 @@
 #![forbid(unsafe_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
 
 /// Headers, IFDs, and helpers.
 pub mod lowlevel;
@@ -8,3 +12,13 @@ pub mod baseline;
 
 /// Error types
 pub mod errors;
+
+/// Decompression of strip and tile data
+pub mod compression;
+
+/// GeoTIFF georeferencing keys
+pub mod geotiff;
+
+/// Page-oriented access to whole TIFF files
+#[cfg(feature = "std")]
+pub mod document;
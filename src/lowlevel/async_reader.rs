@@ -0,0 +1,272 @@
+use crate::errors::{IFDError, TiffError};
+use crate::lowlevel::{
+    metadata_reader::ReadLimits,
+    raw_ifd::{RawIFD, HEADER_SIZE},
+};
+use byteorder::ByteOrder;
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// Size in bytes of each entry of a classic IFD.
+const ENTRY_SIZE: u64 = 12;
+
+impl RawIFD {
+    /// Read an entire IFD from `reader` excluding the offset to the next IFD, as `read_from`
+    /// does, without blocking. Fails with `TiffError::TruncatedIFD`, holding the entries read, if
+    /// the file ends partway through.
+    pub async fn from_async_reader<E: ByteOrder, R: AsyncRead + AsyncSeek + Unpin>(
+        reader: &mut R,
+    ) -> Result<Self, TiffError> {
+        Self::from_async_reader_limited::<E, R>(reader, usize::MAX).await
+    }
+
+    /// Read an entire IFD from `reader` as `from_async_reader` does, but fail without reading any
+    /// entries if the IFD claims to have more than `max_entries`.
+    async fn from_async_reader_limited<E: ByteOrder, R: AsyncRead + AsyncSeek + Unpin>(
+        reader: &mut R,
+        max_entries: usize,
+    ) -> Result<Self, TiffError> {
+        let mut count = [0; 2];
+        reader.read_exact(&mut count).await?;
+        let field_count = usize::from(E::read_u16(&count));
+        if field_count > max_entries {
+            return Err(IFDError::TooManyEntries {
+                count: field_count,
+                limit: max_entries,
+            }
+            .into());
+        }
+
+        // Fetch every entry at once, then parse them just as the synchronous reader would
+        let mut entries = Vec::new();
+        reader
+            .take(field_count as u64 * ENTRY_SIZE)
+            .read_to_end(&mut entries)
+            .await?;
+        Self::read_entries::<E, _>(&mut entries.as_slice(), field_count)
+    }
+}
+
+/// Read all IFDs from `reader` as `read_raw_ifds` does, without blocking. Reading stops at the
+/// first pointer to an already-read IFD, so a looping chain terminates.
+pub async fn read_raw_ifds_async<E: ByteOrder, R: AsyncRead + AsyncSeek + Unpin>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    read_raw_ifds_async_with_limits::<E, R>(reader, first_ifd_offset, ReadLimits::default()).await
+}
+
+/// Read all IFDs as `read_raw_ifds_async` does, but fail once the chain or any IFD in it exceeds
+/// `limits`, as `read_raw_ifds_with_limits` does.
+pub async fn read_raw_ifds_async_with_limits<E: ByteOrder, R: AsyncRead + AsyncSeek + Unpin>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    limits: ReadLimits,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    let file_len = reader.seek(SeekFrom::End(0)).await?;
+    let mut ifds = Vec::new();
+    let mut visited = HashSet::new();
+    let mut next_ifd_offset = first_ifd_offset;
+    while next_ifd_offset != 0 && visited.insert(next_ifd_offset) {
+        if next_ifd_offset < HEADER_SIZE {
+            return Err(IFDError::OffsetInHeader {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        if ifds.len() == limits.max_ifds {
+            return Err(IFDError::TooManyIFDs {
+                limit: limits.max_ifds,
+            }
+            .into());
+        }
+        if next_ifd_offset
+            .checked_add(2)
+            .is_none_or(|end| end > file_len)
+        {
+            return Err(TiffError::OffsetOutOfBounds {
+                offset: next_ifd_offset,
+                file_len,
+            });
+        }
+        reader.seek(SeekFrom::Start(next_ifd_offset)).await?;
+        let ifd =
+            RawIFD::from_async_reader_limited::<E, R>(reader, limits.max_entries_per_ifd).await?;
+        if limits.reject_empty_ifds && ifd.is_empty() {
+            return Err(IFDError::EmptyIFD {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        ifds.push(ifd);
+
+        let mut next = [0; 4];
+        reader.read_exact(&mut next).await?;
+        next_ifd_offset = E::read_u32(&next).into();
+    }
+    Ok(ifds.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::tags;
+    use crate::lowlevel::{read_raw_ifds_with_limits, IFDField, LittleEndian, MetadataWriter, IFD};
+    use std::io::Cursor;
+
+    /// A little-endian file of `pages` IFDs, each with one field.
+    fn file(pages: u32) -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        let mut writer = MetadataWriter::<LittleEndian>::write_header(&mut buf).unwrap();
+        for page in 0..pages {
+            let mut ifd = IFD::new();
+            ifd.entries
+                .insert(tags::IMAGE_WIDTH, IFDField::Long(vec![page + 1].into()));
+            writer.write_ifd(&ifd, &mut buf).unwrap();
+        }
+        buf.into_inner()
+    }
+
+    /// Point the IFD at `ifd_offset` in `buf`, which has `entries` entries, back at `target`.
+    fn link(buf: &mut [u8], ifd_offset: usize, entries: usize, target: u32) {
+        let next = ifd_offset + 2 + entries * ENTRY_SIZE as usize;
+        LittleEndian::write_u32(&mut buf[next..next + 4], target);
+    }
+
+    #[tokio::test]
+    async fn matches_sync_reader() {
+        let buf = file(3);
+        let first = u64::from(LittleEndian::read_u32(&buf[4..8]));
+        let ifds = read_raw_ifds_async::<LittleEndian, _>(&mut Cursor::new(&buf), first)
+            .await
+            .unwrap();
+        let expected = read_raw_ifds_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            first,
+            ReadLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(ifds.len(), 3);
+        assert_eq!(ifds, expected);
+    }
+
+    #[tokio::test]
+    async fn from_async_reader_reads_one_ifd() {
+        let buf = file(1);
+        let first = u64::from(LittleEndian::read_u32(&buf[4..8]));
+        let mut reader = Cursor::new(&buf);
+        reader.set_position(first);
+        let ifd = RawIFD::from_async_reader::<LittleEndian, _>(&mut reader)
+            .await
+            .unwrap();
+        assert_eq!(ifd.entries.len(), 1);
+        assert_eq!(ifd.entries[0].tag, tags::IMAGE_WIDTH);
+    }
+
+    #[tokio::test]
+    async fn from_async_reader_keeps_entries_of_truncated_ifd() {
+        // Two entries claimed, but the file ends partway through the second
+        let mut buf = vec![2, 0];
+        buf.extend_from_slice(&[0; 18]);
+        let result = RawIFD::from_async_reader::<LittleEndian, _>(&mut Cursor::new(&buf)).await;
+        match result {
+            Err(TiffError::TruncatedIFD {
+                expected_entries: 2,
+                read_entries: 1,
+                partial,
+            }) => assert_eq!(partial.entries.len(), 1),
+            other => panic!("expected TruncatedIFD, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_at_looping_chain() {
+        let mut buf = file(1);
+        let first = LittleEndian::read_u32(&buf[4..8]);
+        link(&mut buf, first as usize, 1, first);
+        let ifds = read_raw_ifds_async::<LittleEndian, _>(&mut Cursor::new(&buf), first.into())
+            .await
+            .unwrap();
+        assert_eq!(ifds.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn enforces_limits() {
+        let buf = file(3);
+        let first = u64::from(LittleEndian::read_u32(&buf[4..8]));
+        let limits = ReadLimits {
+            max_ifds: 2,
+            ..ReadLimits::default()
+        };
+        let result = read_raw_ifds_async_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            first,
+            limits,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(TiffError::IFD(IFDError::TooManyIFDs { limit: 2 }))
+        ));
+
+        let limits = ReadLimits {
+            max_entries_per_ifd: 0,
+            ..ReadLimits::default()
+        };
+        let result = read_raw_ifds_async_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            first,
+            limits,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(TiffError::IFD(IFDError::TooManyEntries {
+                count: 1,
+                limit: 0
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_ifds_when_asked() {
+        let mut buf = Cursor::new(Vec::new());
+        let mut writer = MetadataWriter::<LittleEndian>::write_header(&mut buf).unwrap();
+        let first = writer.write_ifd(&IFD::new(), &mut buf).unwrap();
+        let buf = buf.into_inner();
+
+        let ifds = read_raw_ifds_async::<LittleEndian, _>(&mut Cursor::new(&buf), first)
+            .await
+            .unwrap();
+        assert!(ifds[0].is_empty());
+        let limits = ReadLimits {
+            reject_empty_ifds: true,
+            ..ReadLimits::default()
+        };
+        let result = read_raw_ifds_async_with_limits::<LittleEndian, _>(
+            &mut Cursor::new(&buf),
+            first,
+            limits,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(TiffError::IFD(IFDError::EmptyIFD { offset })) if offset == first
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_offsets_in_header_or_past_end() {
+        let buf = file(1);
+        let mut reader = Cursor::new(&buf);
+        assert!(matches!(
+            read_raw_ifds_async::<LittleEndian, _>(&mut reader, 4).await,
+            Err(TiffError::IFD(IFDError::OffsetInHeader { offset: 4 }))
+        ));
+        assert!(matches!(
+            read_raw_ifds_async::<LittleEndian, _>(&mut reader, 1000).await,
+            Err(TiffError::OffsetOutOfBounds { offset: 1000, .. })
+        ));
+    }
+}
@@ -0,0 +1,643 @@
+use crate::baseline::tags;
+use crate::errors::{FieldExtractionError, IFDError, TiffError};
+use crate::lowlevel::{
+    constants::ifd_field_type_magic::*,
+    header::{write_bigtiff_header, write_header, Endianness, TiffHeader},
+    metadata_writer::write_padding,
+    raw_field_values::{check_in_bounds, read_bytes_at},
+    tag_type::TagType,
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Tags locating blocks of data, each paired with the tag giving the lengths of the blocks.
+const DATA_TAGS: &[(u16, u16)] = &[
+    (tags::STRIP_OFFSETS, tags::STRIP_BYTE_COUNTS),
+    (tags::FREE_OFFSETS, tags::FREE_BYTE_COUNTS),
+    (tags::TILE_OFFSETS, tags::TILE_BYTE_COUNTS),
+    (
+        tags::JPEG_INTERCHANGE_FORMAT,
+        tags::JPEG_INTERCHANGE_FORMAT_LNGTH,
+    ),
+];
+
+/// Tags locating unused space within the file, which `compact` drops.
+const FREE_SPACE_TAGS: &[u16] = &[tags::FREE_OFFSETS, tags::FREE_BYTE_COUNTS];
+
+/// Tags holding the offsets of further IFDs.
+const IFD_TAGS: &[u16] = &[tags::SUB_IFDS, tags::EXIF_IFD, tags::GPS_IFD];
+
+/// Classic TIFF or BigTIFF, which differ in the width of counts and offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Classic,
+    Big,
+}
+
+impl Format {
+    /// Size of the header, including the first IFD pointer.
+    fn header_size(self) -> u64 {
+        match self {
+            Format::Classic => 8,
+            Format::Big => 16,
+        }
+    }
+
+    /// Size of offsets, of the count of each field, and of the space for inline values.
+    fn offset_size(self) -> usize {
+        match self {
+            Format::Classic => 4,
+            Format::Big => 8,
+        }
+    }
+
+    /// Size of the count of entries at the start of an IFD.
+    fn entry_count_size(self) -> u64 {
+        match self {
+            Format::Classic => 2,
+            Format::Big => 8,
+        }
+    }
+
+    /// Size of each entry in an IFD.
+    fn entry_size(self) -> u64 {
+        4 + 2 * self.offset_size() as u64
+    }
+}
+
+/// A field read from the source file, with the whole of its value.
+struct Field {
+    tag: u16,
+    tag_type: u16,
+    count: u64,
+    value: Vec<u8>,
+}
+
+/// Copy the classic TIFF (or BigTIFF) in `reader` to `writer` as a BigTIFF, in the same byte
+/// order. Every IFD in the main chain is copied, along with those referenced by the SubIFDs,
+/// Exif IFD and GPS IFD tags, the values of their fields, and the blocks of data located by
+/// the StripOffsets, TileOffsets, FreeOffsets and JPEGInterchangeFormat tags, with every offset
+/// recomputed. The offset tags are widened to LONG8, and IFD offsets to IFD8. Fails if a
+/// field's type is unrecognized, as its value can't be located, or if the file is malformed.
+/// Note: Assumes both cursors are at the beginning of their files.
+pub fn convert_to_bigtiff<R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), TiffError> {
+    convert(reader, writer, Some(Format::Big), true)
+}
+
+/// Copy the BigTIFF (or classic TIFF) in `reader` to `writer` as a classic TIFF, as
+/// `convert_to_bigtiff` does in reverse. Fields of the 64-bit types LONG8, SLONG8 and IFD8 are
+/// narrowed to LONG, SLONG and IFD. Fails with `IFDError::OffsetTooLarge` if the copy would
+/// need an offset beyond 4 GiB, or `IFDError::ValueTooLarge` if a value or count won't fit in
+/// 32 bits, in which case `writer` is left holding a partial file.
+pub fn convert_to_classic<R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), TiffError> {
+    convert(reader, writer, Some(Format::Classic), true)
+}
+
+/// Copy the classic TIFF or BigTIFF in `reader` to `writer` in the same format and byte order,
+/// keeping only the data its IFDs refer to, as `convert_to_bigtiff` does. Values left behind
+/// when fields were rewritten, and anything else unreferenced, are dropped, as are the blocks
+/// located by FreeOffsets along with the FreeOffsets and FreeByteCounts tags, so the copy
+/// holds the same IFDs, values and image data with nothing between them but padding to word
+/// boundaries. Fails for the same reasons as `convert_to_bigtiff`.
+/// Note: Assumes both cursors are at the beginning of their files.
+pub fn compact<R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(), TiffError> {
+    convert(reader, writer, None, false)
+}
+
+/// Copy the file in `reader` to `writer` in the format `to`, or its own format if `None`,
+/// copying the blocks located by FreeOffsets only if `keep_free_space` is set.
+fn convert<R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+    to: Option<Format>,
+    keep_free_space: bool,
+) -> Result<(), TiffError> {
+    let header = TiffHeader::read_from(reader)?;
+    match header.endianness {
+        Endianness::Little => {
+            convert_endian::<LittleEndian, R, W>(reader, writer, header, to, keep_free_space)
+        }
+        Endianness::Big => {
+            convert_endian::<BigEndian, R, W>(reader, writer, header, to, keep_free_space)
+        }
+    }
+}
+
+fn convert_endian<E: ByteOrder, R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+    header: TiffHeader,
+    to: Option<Format>,
+    keep_free_space: bool,
+) -> Result<(), TiffError> {
+    let from = if header.is_bigtiff {
+        Format::Big
+    } else {
+        Format::Classic
+    };
+    let to = to.unwrap_or(from);
+    match to {
+        Format::Classic => write_header::<E, W>(writer)?,
+        Format::Big => write_bigtiff_header::<E, W>(writer)?,
+    }
+    let mut pointer_position = writer.stream_position()?;
+    write_offset::<E, W>(writer, to, 0)?;
+
+    // Stop at a looping chain, as the readers do
+    let mut chain = HashSet::new();
+    let mut copies = HashMap::new();
+    let mut next_ifd_offset = header.first_ifd_offset;
+    while next_ifd_offset != 0 && chain.insert(next_ifd_offset) {
+        let copied = convert_ifd::<E, R, W>(
+            reader,
+            writer,
+            from,
+            to,
+            next_ifd_offset,
+            keep_free_space,
+            &mut copies,
+        )?;
+
+        // Link the previous IFD (or the header) to this one
+        let end = writer.stream_position()?;
+        writer.seek(SeekFrom::Start(pointer_position))?;
+        write_offset::<E, W>(writer, to, copied.position)?;
+        writer.seek(SeekFrom::Start(end))?;
+
+        pointer_position = copied.next_pointer_position;
+        next_ifd_offset = copied.source_next_ifd_offset;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Where an IFD was copied to by `convert_ifd`.
+#[derive(Clone, Copy)]
+struct CopiedIFD {
+    /// Position of the copy
+    position: u64,
+    /// Position of the copy's 'next IFD' pointer, which is left as zero
+    next_pointer_position: u64,
+    /// The 'next IFD' pointer of the original
+    source_next_ifd_offset: u64,
+}
+
+/// Copy the IFD at `offset` in `reader` to the end of `writer`, converting it from the
+/// format `from` to `to`, preceded by the data and IFDs its fields refer to. Unless
+/// `keep_free_space` is set, the FreeOffsets and FreeByteCounts fields and the blocks they
+/// locate are dropped. `copies` records the IFDs copied so far by their offset in `reader`, or
+/// `None` while they are being copied; an IFD already copied, such as one which is both a
+/// SubIFD and the next page, isn't copied again. Fails if the IFD is still being copied, since
+/// IFDs referring to each other in a loop can't be copied.
+fn convert_ifd<E: ByteOrder, R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+    from: Format,
+    to: Format,
+    offset: u64,
+    keep_free_space: bool,
+    copies: &mut HashMap<u64, Option<CopiedIFD>>,
+) -> Result<CopiedIFD, TiffError> {
+    match copies.get(&offset) {
+        Some(Some(copied)) => return Ok(*copied),
+        Some(None) => return Err(IFDError::ChainLoop { offset }.into()),
+        None => {
+            copies.insert(offset, None);
+        }
+    }
+    let (fields, source_next_ifd_offset) = read_ifd::<E, R>(reader, from, offset)?;
+
+    let mut converted = Vec::with_capacity(fields.len());
+    for field in &fields {
+        if !keep_free_space && FREE_SPACE_TAGS.contains(&field.tag) {
+            continue;
+        }
+        if let Some(&(_, byte_counts_tag)) = DATA_TAGS.iter().find(|(tag, _)| *tag == field.tag) {
+            let offsets = unsigned_values::<E>(field)?;
+            let byte_counts = match fields.iter().find(|field| field.tag == byte_counts_tag) {
+                Some(byte_counts) => unsigned_values::<E>(byte_counts)?,
+                None => Vec::new(),
+            };
+            if offsets.len() != byte_counts.len() {
+                return Err(IFDError::ByteCountMismatch {
+                    tag: field.tag,
+                    offsets: offsets.len(),
+                    byte_counts: byte_counts.len(),
+                }
+                .into());
+            }
+            let mut new_offsets = Vec::with_capacity(offsets.len());
+            for (&offset, &len) in offsets.iter().zip(&byte_counts) {
+                new_offsets.push(copy_block(reader, writer, offset, len)?);
+            }
+            let tag_type = match to {
+                Format::Classic => IFD_TYPE_LONG,
+                Format::Big => IFD_TYPE_LONG8,
+            };
+            converted.push(offsets_field::<E>(field.tag, tag_type, to, &new_offsets)?);
+        } else if IFD_TAGS.contains(&field.tag) {
+            let mut new_offsets = Vec::new();
+            for offset in unsigned_values::<E>(field)? {
+                let copied = convert_ifd::<E, R, W>(
+                    reader,
+                    writer,
+                    from,
+                    to,
+                    offset,
+                    keep_free_space,
+                    copies,
+                )?;
+                new_offsets.push(copied.position);
+            }
+            let tag_type = match to {
+                Format::Classic => IFD_TYPE_IFD,
+                Format::Big => IFD_TYPE_IFD8,
+            };
+            converted.push(offsets_field::<E>(field.tag, tag_type, to, &new_offsets)?);
+        } else {
+            converted.push(narrow_field::<E>(field, to)?);
+        }
+    }
+
+    // Values too large to be inline come after the data blocks and IFDs referred to
+    let mut value_offsets = Vec::with_capacity(converted.len());
+    for field in &converted {
+        if field.value.len() > to.offset_size() {
+            value_offsets.push(Some(write_aligned(writer, &field.value)?));
+        } else {
+            value_offsets.push(None);
+        }
+    }
+
+    let position = align(writer)?;
+    check_offset(to, position)?;
+    match to {
+        Format::Classic => {
+            if converted.len() > u16::MAX as usize {
+                return Err(IFDError::TooManyEntries {
+                    count: converted.len(),
+                    limit: u16::MAX as usize,
+                }
+                .into());
+            }
+            writer.write_u16::<E>(converted.len() as u16)?;
+        }
+        Format::Big => writer.write_u64::<E>(converted.len() as u64)?,
+    }
+    for (field, value_offset) in converted.iter().zip(value_offsets) {
+        writer.write_u16::<E>(field.tag)?;
+        writer.write_u16::<E>(field.tag_type)?;
+        write_offset::<E, W>(writer, to, field.count)?;
+        match value_offset {
+            Some(value_offset) => write_offset::<E, W>(writer, to, value_offset)?,
+            None => {
+                let mut value_or_offset = [0; 8];
+                value_or_offset[..field.value.len()].copy_from_slice(&field.value);
+                writer.write_all(&value_or_offset[..to.offset_size()])?;
+            }
+        }
+    }
+    let next_pointer_position = writer.stream_position()?;
+    write_offset::<E, W>(writer, to, 0)?;
+
+    let copied = CopiedIFD {
+        position,
+        next_pointer_position,
+        source_next_ifd_offset,
+    };
+    copies.insert(offset, Some(copied));
+    Ok(copied)
+}
+
+/// Read the IFD at `offset` in `reader`, along with the whole of each field's value. Returns
+/// the fields and the offset of the next IFD.
+fn read_ifd<E: ByteOrder, R: Read + Seek>(
+    reader: &mut R,
+    format: Format,
+    offset: u64,
+) -> Result<(Vec<Field>, u64), TiffError> {
+    if offset < format.header_size() {
+        return Err(IFDError::OffsetInHeader { offset }.into());
+    }
+    check_in_bounds(reader, offset, format.entry_count_size())?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let entry_count = match format {
+        Format::Classic => u64::from(reader.read_u16::<E>()?),
+        Format::Big => reader.read_u64::<E>()?,
+    };
+
+    // Check the entries are present before allocating for them, as a bogus count could be huge
+    let table_len = entry_count
+        .checked_mul(format.entry_size())
+        .and_then(|len| len.checked_add(format.offset_size() as u64))
+        .unwrap_or(u64::MAX);
+    check_in_bounds(reader, offset + format.entry_count_size(), table_len)?;
+
+    let mut entries = Vec::with_capacity(entry_count as usize);
+    for _ in 0..entry_count {
+        let tag = reader.read_u16::<E>()?;
+        let tag_type = reader.read_u16::<E>()?;
+        let count = read_offset::<E, R>(reader, format)?;
+        let mut value_or_offset = [0; 8];
+        reader.read_exact(&mut value_or_offset[..format.offset_size()])?;
+        entries.push((tag, tag_type, count, value_or_offset));
+    }
+    let next_ifd_offset = read_offset::<E, R>(reader, format)?;
+
+    let mut fields = Vec::with_capacity(entries.len());
+    for (tag, tag_type, count, value_or_offset) in entries {
+        let unit_size = TagType::from_u16(tag_type)
+            .ok_or(TiffError::UnknownTagType { tag_type })?
+            .byte_size() as u64;
+        let len = count.saturating_mul(unit_size);
+        let value = if len <= format.offset_size() as u64 {
+            value_or_offset[..len as usize].to_vec()
+        } else {
+            let value_offset = match format {
+                Format::Classic => u64::from(E::read_u32(&value_or_offset)),
+                Format::Big => E::read_u64(&value_or_offset),
+            };
+            read_bytes_at(reader, value_offset, len)?
+        };
+        fields.push(Field {
+            tag,
+            tag_type,
+            count,
+            value,
+        });
+    }
+    Ok((fields, next_ifd_offset))
+}
+
+/// Read the value of `field` as unsigned integers, such as offsets. Fails unless it is of an
+/// unsigned integer type.
+fn unsigned_values<E: ByteOrder>(field: &Field) -> Result<Vec<u64>, TiffError> {
+    let values = match field.tag_type {
+        IFD_TYPE_SHORT => field
+            .value
+            .chunks_exact(2)
+            .map(|bytes| u64::from(E::read_u16(bytes)))
+            .collect(),
+        IFD_TYPE_LONG | IFD_TYPE_IFD => field
+            .value
+            .chunks_exact(4)
+            .map(|bytes| u64::from(E::read_u32(bytes)))
+            .collect(),
+        IFD_TYPE_LONG8 | IFD_TYPE_IFD8 => field.value.chunks_exact(8).map(E::read_u64).collect(),
+        tag_type => {
+            return Err(FieldExtractionError::MismatchedDataType {
+                expected: TagType::Long,
+                actual: tag_type,
+            }
+            .into())
+        }
+    };
+    Ok(values)
+}
+
+/// A field of `tag_type` (LONG, IFD, LONG8 or IFD8) holding `offsets` within the new file.
+fn offsets_field<E: ByteOrder>(
+    tag: u16,
+    tag_type: u16,
+    to: Format,
+    offsets: &[u64],
+) -> Result<Field, TiffError> {
+    let mut value = Vec::with_capacity(offsets.len() * to.offset_size());
+    for &offset in offsets {
+        check_offset(to, offset)?;
+        write_offset::<E, _>(&mut value, to, offset)?;
+    }
+    Ok(Field {
+        tag,
+        tag_type,
+        count: offsets.len() as u64,
+        value,
+    })
+}
+
+/// Convert `field` for a file in the format `to`. BigTIFF can hold any field, but for classic
+/// TIFF the 64-bit types are narrowed to their 32-bit counterparts. Fails if a value or the
+/// count won't fit in 32 bits.
+fn narrow_field<E: ByteOrder>(field: &Field, to: Format) -> Result<Field, TiffError> {
+    let too_large = |value| IFDError::ValueTooLarge {
+        tag: field.tag,
+        value,
+    };
+    let narrowed_type = match field.tag_type {
+        _ if to == Format::Big => None,
+        IFD_TYPE_LONG8 => Some(IFD_TYPE_LONG),
+        IFD_TYPE_SLONG8 => Some(IFD_TYPE_SLONG),
+        IFD_TYPE_IFD8 => Some(IFD_TYPE_IFD),
+        _ => None,
+    };
+    if to == Format::Classic && field.count > u64::from(u32::MAX) {
+        return Err(too_large(field.count).into());
+    }
+
+    let tag_type = match narrowed_type {
+        Some(tag_type) => tag_type,
+        None => {
+            return Ok(Field {
+                tag: field.tag,
+                tag_type: field.tag_type,
+                count: field.count,
+                value: field.value.clone(),
+            })
+        }
+    };
+    let mut value = vec![0; field.value.len() / 2];
+    for (wide, narrow) in field.value.chunks_exact(8).zip(value.chunks_exact_mut(4)) {
+        if field.tag_type == IFD_TYPE_SLONG8 {
+            let signed = E::read_i64(wide);
+            let signed = i32::try_from(signed).map_err(|_| too_large(signed as u64))?;
+            E::write_i32(narrow, signed);
+        } else {
+            let unsigned = E::read_u64(wide);
+            let unsigned = u32::try_from(unsigned).map_err(|_| too_large(unsigned))?;
+            E::write_u32(narrow, unsigned);
+        }
+    }
+    Ok(Field {
+        tag: field.tag,
+        tag_type,
+        count: field.count,
+        value,
+    })
+}
+
+/// Copy the `len` bytes at `offset` in `reader` to the next word boundary in `writer`,
+/// returning the position they were copied to.
+fn copy_block<R: Read + Seek, W: Write + Seek>(
+    reader: &mut R,
+    writer: &mut W,
+    offset: u64,
+    len: u64,
+) -> Result<u64, TiffError> {
+    check_in_bounds(reader, offset, len)?;
+    let position = align(writer)?;
+    reader.seek(SeekFrom::Start(offset))?;
+    if io::copy(&mut reader.by_ref().take(len), writer)? != len {
+        return Err(TiffError::UnexpectedEof);
+    }
+    Ok(position)
+}
+
+/// Write `data` at the next word boundary in `writer`, returning its position.
+fn write_aligned<W: Write + Seek>(writer: &mut W, data: &[u8]) -> Result<u64, TiffError> {
+    let position = align(writer)?;
+    writer.write_all(data)?;
+    Ok(position)
+}
+
+/// Advance `writer` to the next word (even byte) boundary, returning the position.
+fn align<W: Write + Seek>(writer: &mut W) -> Result<u64, TiffError> {
+    let mut position = writer.stream_position()?;
+    let target = position + position % 2;
+    write_padding(writer, &mut position, target)?;
+    Ok(position)
+}
+
+/// Fail if `offset` can't be stored in a file of the format `to`.
+fn check_offset(to: Format, offset: u64) -> Result<(), IFDError> {
+    if to == Format::Classic && offset > u64::from(u32::MAX) {
+        return Err(IFDError::OffsetTooLarge { offset });
+    }
+    Ok(())
+}
+
+/// Read an offset or count of the width used by `format`.
+fn read_offset<E: ByteOrder, R: Read>(reader: &mut R, format: Format) -> Result<u64, TiffError> {
+    Ok(match format {
+        Format::Classic => u64::from(reader.read_u32::<E>()?),
+        Format::Big => reader.read_u64::<E>()?,
+    })
+}
+
+/// Write an offset or count of the width used by `format`, which must already be known to fit.
+fn write_offset<E: ByteOrder, W: Write>(
+    writer: &mut W,
+    format: Format,
+    value: u64,
+) -> Result<(), TiffError> {
+    match format {
+        Format::Classic => writer.write_u32::<E>(value as u32)?,
+        Format::Big => writer.write_u64::<E>(value)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::decode::decode_gray8;
+    use crate::lowlevel::metadata_writer::Sink;
+    use crate::lowlevel::tiff_builder::{image_ifd, single_page_file};
+    use crate::lowlevel::{
+        read_ifds_auto, IfdDiff, RawIFD, RawIFDChain, RawIFDFieldBuilder, TiffWriter,
+    };
+    use std::io::Cursor;
+
+    /// A little-endian file holding a 4 x 3 grayscale image whose pixels count up from 0, in one
+    /// strip, with its IFD.
+    fn gray_file() -> (Vec<u8>, RawIFD) {
+        let pixels = (0..12).collect();
+        single_page_file(image_ifd(4, 3, &[8], 1), vec![pixels], Endianness::Little)
+    }
+
+    /// The only IFD of the classic TIFF in `buf`.
+    fn only_ifd(buf: &[u8]) -> RawIFD {
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(buf)).unwrap();
+        ifds.into_classic().unwrap()[0].clone()
+    }
+
+    fn decode(buf: &[u8]) -> (u32, u32, Vec<u8>) {
+        decode_gray8::<LittleEndian, _>(&mut Cursor::new(buf), &only_ifd(buf)).unwrap()
+    }
+
+    #[test]
+    fn classic_to_bigtiff_and_back_keeps_pixels() {
+        let (classic, _) = gray_file();
+        let mut big = Cursor::new(Vec::new());
+        convert_to_bigtiff(&mut Cursor::new(&classic), &mut big).unwrap();
+        let big = big.into_inner();
+        let ifds = match read_ifds_auto(&mut Cursor::new(&big)).unwrap() {
+            (_, RawIFDChain::Big(ifds)) => ifds,
+            (_, chain) => panic!("expected a BigTIFF, got {:?}", chain),
+        };
+        let strip_offsets = ifds[0]
+            .entries
+            .iter()
+            .find(|field| field.tag == tags::STRIP_OFFSETS);
+        assert_eq!(strip_offsets.unwrap().tag_type, IFD_TYPE_LONG8);
+
+        let mut back = Cursor::new(Vec::new());
+        convert_to_classic(&mut Cursor::new(&big), &mut back).unwrap();
+        let back = back.into_inner();
+        assert_eq!(decode(&back), (4, 3, (0..12).collect()));
+        assert_eq!(decode(&back), decode(&classic));
+    }
+
+    #[test]
+    fn classic_offsets_past_4_gib_fail() {
+        let (classic, _) = gray_file();
+        let mut big = Cursor::new(Vec::new());
+        convert_to_bigtiff(&mut Cursor::new(&classic), &mut big).unwrap();
+
+        // The header ends just past 4 GiB, so the strip after it can't be addressed
+        let mut sink = Sink {
+            position: u64::from(u32::MAX) - 4,
+        };
+        assert!(matches!(
+            convert_to_classic(&mut Cursor::new(big.get_ref()), &mut sink),
+            Err(TiffError::IFD(IFDError::OffsetTooLarge { offset })) if offset > u64::from(u32::MAX)
+        ));
+    }
+
+    #[test]
+    fn compact_drops_orphaned_data() {
+        // plan_layout and write_planned can't make the input, as everything they lay out is
+        // referenced, so the page is rewritten with TiffWriter after 99 bytes nothing refers to
+        let (original, mut ifd) = gray_file();
+        let pixels: Vec<u8> = (0..12).collect();
+        let mut writer =
+            TiffWriter::write_header(Cursor::new(Vec::new()), Endianness::Little).unwrap();
+        writer.write_data(&[0xAA; 99]).unwrap();
+        let offset = writer.write_data(&pixels).unwrap();
+        let mut field_data = HashMap::new();
+        RawIFDFieldBuilder::<LittleEndian>::long(tags::STRIP_OFFSETS, &[offset as u32])
+            .unwrap()
+            .set_into(&mut ifd, &mut field_data);
+        writer.add_ifd(&ifd, &field_data).unwrap();
+        let orphaned = writer.finish().unwrap().into_inner();
+
+        let mut compacted = Cursor::new(Vec::new());
+        compact(&mut Cursor::new(&orphaned), &mut compacted).unwrap();
+        let compacted = compacted.into_inner();
+
+        // The orphan and the padding after it are gone, and only the strip has moved
+        assert_eq!(compacted.len(), orphaned.len() - 100);
+        let diff = only_ifd(&orphaned).diff(&only_ifd(&compacted));
+        assert!(matches!(
+            diff[..],
+            [IfdDiff::ValueDiffers {
+                tag: tags::STRIP_OFFSETS,
+                ..
+            }]
+        ));
+        assert_eq!(decode(&compacted), decode(&orphaned));
+        assert_eq!(decode(&compacted), (4, 3, pixels));
+        // Which leaves the file as TiffBuilder laid it out to begin with
+        assert_eq!(compacted, original);
+    }
+}
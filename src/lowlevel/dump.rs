@@ -0,0 +1,228 @@
+use crate::baseline::tags::{self, tag_name};
+use crate::errors::TiffError;
+use crate::lowlevel::{
+    header::{Endianness, TiffHeader},
+    ifd_field::IFDField,
+    raw_field_values::read_bytes_at,
+    raw_ifd::{RawIFD, RawIFDField},
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+use std::collections::HashSet;
+use std::io::{Seek, Write};
+
+/// Bytes of an out-of-line value shown by `dump_tiff`.
+const PREVIEW_LEN: usize = 16;
+
+/// Tags holding the offsets of further IFDs, and what to call those IFDs.
+const IFD_TAGS: &[(u16, &str)] = &[
+    (tags::SUB_IFDS, "SubIFD"),
+    (tags::EXIF_IFD, "Exif IFD"),
+    (tags::GPS_IFD, "GPS IFD"),
+];
+
+/// Write a `tiffdump`-style description of the TIFF in `reader` to `out`: the header, then each
+/// IFD with its position and every entry's tag, type and count. Inline values are shown decoded,
+/// and out-of-line values as `@offset` followed by a hex preview of their first bytes. The IFDs
+/// referenced by SubIFDs, Exif IFD and GPS IFD tags are shown indented beneath the entry
+/// pointing to them. The byte order is taken from the header. An IFD referenced more than once
+/// is only shown the first time. Fails with `TiffError::UnexpectedBigTiff` after the header if
+/// the file is a BigTIFF, or if an IFD can't be read; values that can't be read are reported in
+/// place and don't stop the dump.
+/// Note: Assumes the cursor is at the beginning of the file.
+pub fn dump_tiff<R: ReadBytesExt + Seek, W: Write>(
+    reader: &mut R,
+    out: &mut W,
+) -> Result<(), TiffError> {
+    let header = TiffHeader::read_from(reader)?;
+    let byte_order = match header.endianness {
+        Endianness::Little => "little-endian (II)",
+        Endianness::Big => "big-endian (MM)",
+    };
+    let magic = if header.is_bigtiff { 43 } else { 42 };
+    writeln!(out, "Byte order: {}, magic: {}", byte_order, magic)?;
+    if header.is_bigtiff {
+        return Err(TiffError::UnexpectedBigTiff);
+    }
+
+    let mut visited = HashSet::new();
+    match header.endianness {
+        Endianness::Little => dump_chain::<LittleEndian, R, W>(
+            reader,
+            out,
+            header.first_ifd_offset,
+            ("IFD", 0),
+            0,
+            &mut visited,
+        ),
+        Endianness::Big => dump_chain::<BigEndian, R, W>(
+            reader,
+            out,
+            header.first_ifd_offset,
+            ("IFD", 0),
+            0,
+            &mut visited,
+        ),
+    }
+}
+
+/// Dump the chain of IFDs beginning at `offset`, indented by `depth` levels. The IFDs are
+/// called by the label of `name`, and numbered from its index.
+fn dump_chain<E: ByteOrder, R: ReadBytesExt + Seek, W: Write>(
+    reader: &mut R,
+    out: &mut W,
+    mut offset: u64,
+    name: (&str, usize),
+    depth: usize,
+    visited: &mut HashSet<u64>,
+) -> Result<(), TiffError> {
+    let indent = "  ".repeat(depth);
+    let (label, mut index) = name;
+    while offset != 0 {
+        if !visited.insert(offset) {
+            writeln!(
+                out,
+                "{}{} {} @ {}: shown above",
+                indent, label, index, offset
+            )?;
+            break;
+        }
+        let ifd = RawIFD::read_from_at::<E, R>(reader, offset)?;
+        let next_offset = reader.read_u32::<E>()?;
+        writeln!(
+            out,
+            "{}{} {} @ {}: {} entries, next IFD @ {}",
+            indent,
+            label,
+            index,
+            offset,
+            ifd.len(),
+            next_offset
+        )?;
+
+        for field in &ifd {
+            dump_field::<E, R, W>(reader, out, field, &indent)?;
+            if let Some(&(_, child_label)) = IFD_TAGS.iter().find(|(tag, _)| *tag == field.tag) {
+                let child_offsets = match field.as_offset_array::<E, R>(reader) {
+                    Ok(child_offsets) => child_offsets,
+                    Err(err) => {
+                        writeln!(out, "{}    (unreadable IFD offsets: {})", indent, err)?;
+                        continue;
+                    }
+                };
+                for (child_index, child_offset) in child_offsets.into_iter().enumerate() {
+                    dump_chain::<E, R, W>(
+                        reader,
+                        out,
+                        child_offset.into(),
+                        (child_label, child_index),
+                        depth + 2,
+                        visited,
+                    )?;
+                }
+            }
+        }
+
+        offset = next_offset.into();
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Dump a single entry, indented one level beyond `indent`.
+fn dump_field<E: ByteOrder, R: ReadBytesExt + Seek, W: Write>(
+    reader: &mut R,
+    out: &mut W,
+    field: &RawIFDField,
+    indent: &str,
+) -> Result<(), TiffError> {
+    write!(out, "{}  ", indent)?;
+    match tag_name(field.tag) {
+        Some(name) => write!(out, "{} ({})", name, field.tag)?,
+        None => write!(out, "Tag {}", field.tag)?,
+    }
+    match field.tag_type_enum() {
+        Some(tag_type) => write!(out, " {} x{}", tag_type.name(), field.count)?,
+        None => write!(out, " type {} x{}", field.tag_type, field.count)?,
+    }
+
+    match (field.is_inline(), field.value_byte_len()) {
+        (Some(false), Some(len)) => {
+            write!(out, " @{}:", E::read_u32(&field.value_or_offset))?;
+            let preview = field.offset::<E>().and_then(|offset| {
+                read_bytes_at(reader, offset.into(), len.min(PREVIEW_LEN) as u64)
+            });
+            match preview {
+                Ok(preview) => {
+                    write_hex(out, &preview)?;
+                    if len > PREVIEW_LEN {
+                        write!(out, " ...")?;
+                    }
+                }
+                Err(err) => write!(out, " ({})", err)?,
+            }
+        }
+        (Some(true), _) => {
+            write!(out, ":")?;
+            match IFDField::read_from::<E, R>(reader, field) {
+                Ok(value) => write_values(out, &value)?,
+                Err(err) => write!(out, " ({})", err)?,
+            }
+        }
+        // The type is unrecognized, so the size of the value is unknown
+        _ => {
+            write!(out, ":")?;
+            write_hex(out, &field.value_or_offset)?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Write each of `bytes` in hex, preceded by a space.
+fn write_hex<W: Write>(out: &mut W, bytes: &[u8]) -> Result<(), TiffError> {
+    for byte in bytes {
+        write!(out, " {:02X}", byte)?;
+    }
+    Ok(())
+}
+
+/// Write each of the values of `field`, preceded by a space.
+fn write_values<W: Write>(out: &mut W, field: &IFDField) -> Result<(), TiffError> {
+    match field {
+        IFDField::Undefined(bytes) | IFDField::Byte(bytes) => write_hex(out, bytes)?,
+        IFDField::Ascii(strings) => {
+            for string in strings.iter() {
+                write!(out, " {:?}", string)?;
+            }
+        }
+        IFDField::Short(values) => write_each(out, values)?,
+        IFDField::Long(values) => write_each(out, values)?,
+        IFDField::Rational(values) => {
+            for (numerator, denominator) in values.iter() {
+                write!(out, " {}/{}", numerator, denominator)?;
+            }
+        }
+        IFDField::SRational(values) => {
+            for (numerator, denominator) in values.iter() {
+                write!(out, " {}/{}", numerator, denominator)?;
+            }
+        }
+        IFDField::SByte(values) => write_each(out, values)?,
+        IFDField::SShort(values) => write_each(out, values)?,
+        IFDField::SLong(values) => write_each(out, values)?,
+        IFDField::Float(values) => write_each(out, values)?,
+        IFDField::Double(values) => write_each(out, values)?,
+        IFDField::Unrecognized {
+            value_or_offset, ..
+        } => write_hex(out, value_or_offset)?,
+    }
+    Ok(())
+}
+
+/// Write each of `values`, preceded by a space.
+fn write_each<T: std::fmt::Display, W: Write>(out: &mut W, values: &[T]) -> Result<(), TiffError> {
+    for value in values {
+        write!(out, " {}", value)?;
+    }
+    Ok(())
+}
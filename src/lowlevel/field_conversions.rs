@@ -51,3 +51,9 @@ impl_ifdfield_conv!(u16, IFDField::Short);
 impl_ifdfield_conv!(u32, IFDField::Long);
 impl_ifdfield_conv!(String, IFDField::Ascii);
 impl_ifdfield_conv!((u32, u32), IFDField::Rational);
+impl_ifdfield_conv!(i8, IFDField::SByte);
+impl_ifdfield_conv!(i16, IFDField::SShort);
+impl_ifdfield_conv!(i32, IFDField::SLong);
+impl_ifdfield_conv!((i32, i32), IFDField::SRational);
+impl_ifdfield_conv!(f32, IFDField::Float);
+impl_ifdfield_conv!(f64, IFDField::Double);
@@ -1,35 +1,163 @@
+use crate::errors::{IFDError, TiffError};
+#[cfg(feature = "std")]
 use crate::lowlevel::constants::header_magic::{
-    BIG_ENDIAN_MAGIC, LITTLE_ENDIAN_MAGIC, VERSION_MAGIC,
+    BIGTIFF_OFFSET_BYTESIZE, BIGTIFF_VERSION_MAGIC, BIG_ENDIAN_MAGIC, LITTLE_ENDIAN_MAGIC,
+    VERSION_MAGIC,
 };
-use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use failure::{Fail, Fallible};
-use std::io::Write;
+use byteorder::ByteOrder;
+#[cfg(feature = "std")]
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
 
-/// Represents an error encountered when reading a TIFF header.
-#[derive(Fail, Debug)]
-pub enum HeaderError {
-    #[fail(display = "Bad endian magic number: {:?}", culprit)]
-    BadEndianMagic { culprit: [u8; 2] },
-    #[fail(display = "Bad magic number: {:?}", magic)]
-    BadMagic { magic: u16 },
+/// Byte order of a TIFF file, as declared by its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// `II`
+    Little,
+    /// `MM`
+    Big,
+}
+
+impl Endianness {
+    /// Determine the endian of the file in `reader` from its byte-order mark.
+    /// Assumes the cursor is positioned at the beginning of a TIFF file, and consumes the mark.
+    #[cfg(feature = "std")]
+    pub fn detect<R: Read>(reader: &mut R) -> Result<Self, TiffError> {
+        if read_header_endian(reader)? {
+            Ok(Endianness::Little)
+        } else {
+            Ok(Endianness::Big)
+        }
+    }
+
+    /// The endianness corresponding to the ByteOrder `E`.
+    pub fn of<E: ByteOrder>() -> Self {
+        if endian_type_is_little::<E>() {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }
+    }
+
+    /// Returns true if this is little-endian byte order.
+    pub fn is_little(self) -> bool {
+        self == Endianness::Little
+    }
+}
+
+/// The header found at the beginning of every TIFF file; 8 bytes long for classic TIFF, or 16
+/// bytes long for BigTIFF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TiffHeader {
+    /// Byte order of the file.
+    pub endianness: Endianness,
+    /// Whether the file is a BigTIFF (magic 43, 64-bit offsets) rather than a classic TIFF.
+    pub is_bigtiff: bool,
+    /// File position of the first IFD.
+    pub first_ifd_offset: u64,
+}
+
+impl TiffHeader {
+    /// Read and validate the byte-order mark, magic number and first IFD offset from `reader`.
+    /// Fails with `IFDError::OffsetInHeader` if the first IFD offset points within the header.
+    /// Assumes the cursor is positioned at the beginning of a TIFF file, and leaves it just
+    /// after the header.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, TiffError> {
+        let endianness = Endianness::detect(reader)?;
+        let (is_bigtiff, first_ifd_offset) = match endianness {
+            Endianness::Little => read_header_version::<LittleEndian, _>(reader)?,
+            Endianness::Big => read_header_version::<BigEndian, _>(reader)?,
+        };
+
+        let header = Self {
+            endianness,
+            is_bigtiff,
+            first_ifd_offset,
+        };
+        header.check_first_ifd_offset()?;
+        Ok(header)
+    }
+
+    /// Length in bytes of the header: 8 for classic TIFF, or 16 for BigTIFF.
+    pub fn size(&self) -> u64 {
+        if self.is_bigtiff {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Check that the first IFD offset doesn't point within the header, where an IFD would
+    /// overlap the byte-order mark and magic number. Zero, meaning there are no IFDs, is allowed.
+    pub(crate) fn check_first_ifd_offset(&self) -> Result<(), TiffError> {
+        let offset = self.first_ifd_offset;
+        if offset != 0 && offset < self.size() {
+            return Err(IFDError::OffsetInHeader { offset }.into());
+        }
+        Ok(())
+    }
+
+    /// Write the byte-order mark, magic number and first IFD offset to `writer`.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match self.endianness {
+            Endianness::Little => self.write_to_endian::<LittleEndian, _>(writer),
+            Endianness::Big => self.write_to_endian::<BigEndian, _>(writer),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn write_to_endian<E: ByteOrder, W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        if self.is_bigtiff {
+            write_bigtiff_header::<E, _>(writer)?;
+            writer.write_u64::<E>(self.first_ifd_offset)
+        } else {
+            write_header::<E, _>(writer)?;
+            writer.write_u32::<E>(self.first_ifd_offset as u32)
+        }
+    }
+}
+
+/// Read the magic number, and for BigTIFF the offset size and padding, followed by the first
+/// IFD offset. Returns whether the file is a BigTIFF, and the first IFD offset.
+#[cfg(feature = "std")]
+fn read_header_version<E: ByteOrder, R: ReadBytesExt>(
+    reader: &mut R,
+) -> Result<(bool, u64), TiffError> {
+    match reader.read_u16::<E>()? {
+        VERSION_MAGIC => Ok((false, reader.read_u32::<E>()?.into())),
+        BIGTIFF_VERSION_MAGIC => {
+            let size = reader.read_u16::<E>()?;
+            if size != BIGTIFF_OFFSET_BYTESIZE {
+                return Err(TiffError::BadBigTiffOffsetSize { size });
+            }
+            let _padding = reader.read_u16::<E>()?;
+            Ok((true, reader.read_u64::<E>()?))
+        }
+        magic => Err(TiffError::BadMagic { magic }),
+    }
 }
 
 /// Determine the endian of the file in `reader`. Returns `true` if the file is little-endian.
-pub fn read_header_endian<R: ReadBytesExt>(reader: &mut R) -> Fallible<bool> {
+#[cfg(feature = "std")]
+pub fn read_header_endian<R: ReadBytesExt>(reader: &mut R) -> Result<bool, TiffError> {
     let mut endian_magic = [0u8; 2];
     reader.read_exact(&mut endian_magic)?;
     match endian_magic {
         LITTLE_ENDIAN_MAGIC => Ok(true),
         BIG_ENDIAN_MAGIC => Ok(false),
-        culprit => Err(HeaderError::BadEndianMagic { culprit }.into()),
+        culprit => Err(TiffError::BadEndianMagic { culprit }),
     }
 }
 
 /// Read and check the magic number from `reader`.
-pub fn read_header_magic<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Fallible<()> {
+#[cfg(feature = "std")]
+pub fn read_header_magic<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<(), TiffError> {
     let magic = reader.read_u16::<E>()?;
     if magic != VERSION_MAGIC {
-        Err(HeaderError::BadMagic { magic }.into())
+        Err(TiffError::BadMagic { magic })
     } else {
         Ok(())
     }
@@ -41,11 +169,97 @@ fn endian_type_is_little<E: ByteOrder>() -> bool {
 }
 
 /// Write the TIFF endian header and magic number to `writer`.
+#[cfg(feature = "std")]
 pub fn write_header<E: ByteOrder, W: Write>(writer: &mut W) -> Result<(), std::io::Error> {
     if endian_type_is_little::<E>() {
-        writer.write(&LITTLE_ENDIAN_MAGIC)
+        writer.write_all(&LITTLE_ENDIAN_MAGIC)
     } else {
-        writer.write(&BIG_ENDIAN_MAGIC)
+        writer.write_all(&BIG_ENDIAN_MAGIC)
     }?;
     writer.write_u16::<E>(VERSION_MAGIC)
 }
+
+/// Write the BigTIFF endian header, magic number, offset size, and padding to `writer`.
+#[cfg(feature = "std")]
+pub fn write_bigtiff_header<E: ByteOrder, W: Write>(writer: &mut W) -> Result<(), io::Error> {
+    if endian_type_is_little::<E>() {
+        writer.write_all(&LITTLE_ENDIAN_MAGIC)
+    } else {
+        writer.write_all(&BIG_ENDIAN_MAGIC)
+    }?;
+    writer.write_u16::<E>(BIGTIFF_VERSION_MAGIC)?;
+    writer.write_u16::<E>(BIGTIFF_OFFSET_BYTESIZE)?;
+    writer.write_u16::<E>(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::{read_ifds_auto, read_raw_ifds_bytes};
+    use std::io::Cursor;
+
+    /// The bytes of a header in `endianness` whose first IFD offset is `first_ifd_offset`.
+    fn header(endianness: Endianness, is_bigtiff: bool, first_ifd_offset: u64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        TiffHeader {
+            endianness,
+            is_bigtiff,
+            first_ifd_offset,
+        }
+        .write_to(&mut buf)
+        .unwrap();
+        buf
+    }
+
+    fn read(buf: &[u8]) -> Result<TiffHeader, TiffError> {
+        let from_reader = TiffHeader::read_from(&mut Cursor::new(buf));
+        let from_bytes = TiffHeader::from_bytes(buf);
+        assert_eq!(format!("{:?}", from_reader), format!("{:?}", from_bytes));
+        from_reader
+    }
+
+    #[test]
+    fn rejects_first_ifd_offset_within_header() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            // Offset 2 would read the magic number as the IFD's entry count
+            let mut buf = header(endianness, false, 2);
+            buf.extend_from_slice(&[0; 16]);
+            assert!(matches!(
+                read(&buf),
+                Err(TiffError::IFD(IFDError::OffsetInHeader { offset: 2 }))
+            ));
+            assert!(matches!(
+                read_ifds_auto(&mut Cursor::new(&buf)),
+                Err(TiffError::IFD(IFDError::OffsetInHeader { offset: 2 }))
+            ));
+
+            for offset in [4, 7] {
+                assert!(matches!(
+                    read(&header(endianness, false, offset)),
+                    Err(TiffError::IFD(IFDError::OffsetInHeader { offset: o })) if o == offset
+                ));
+            }
+            assert!(matches!(
+                read(&header(endianness, true, 8)),
+                Err(TiffError::IFD(IFDError::OffsetInHeader { offset: 8 }))
+            ));
+        }
+
+        // The slice reader checks the offset it's given too
+        assert!(matches!(
+            read_raw_ifds_bytes::<LittleEndian>(&header(Endianness::Little, false, 8), 2),
+            Err(TiffError::IFD(IFDError::OffsetInHeader { offset: 2 }))
+        ));
+    }
+
+    #[test]
+    fn accepts_first_ifd_offset_after_header_or_zero() {
+        for (is_bigtiff, offset) in [(false, 0), (false, 8), (true, 0), (true, 16)] {
+            let header = read(&header(Endianness::Big, is_bigtiff, offset)).unwrap();
+            assert_eq!(header.endianness, Endianness::Big);
+            assert_eq!(header.is_bigtiff, is_bigtiff);
+            assert_eq!(header.first_ifd_offset, offset);
+            assert_eq!(header.size(), if is_bigtiff { 16 } else { 8 });
+        }
+    }
+}
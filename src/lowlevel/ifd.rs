@@ -1,3 +1,4 @@
+use crate::errors::TiffError;
 use crate::lowlevel::ifd_field::IFDField;
 use crate::lowlevel::raw_ifd::{RawIFD, RawIFDField};
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
@@ -23,15 +24,14 @@ impl IFD {
     pub fn read_from<E: ByteOrder, R: ReadBytesExt + Seek>(
         reader: &mut R,
         raw_ifd: &RawIFD,
-    ) -> Result<Self, io::Error> {
+    ) -> Result<Self, TiffError> {
         Ok(Self {
             entries: raw_ifd
-                .entries
                 .iter()
                 .map(|field| {
                     IFDField::read_from::<E, R>(reader, field).map(|data| (field.tag, data))
                 })
-                .collect::<Result<HashMap<u16, IFDField>, io::Error>>()?,
+                .collect::<Result<HashMap<u16, IFDField>, TiffError>>()?,
         })
     }
 
@@ -1,4 +1,8 @@
-use crate::lowlevel::{constants::ifd_field_type_magic::*, raw_ifd::RawIFDField};
+use crate::errors::TiffError;
+use crate::lowlevel::{
+    constants::ifd_field_type_magic::*, raw_field_values::check_in_bounds, raw_ifd::RawIFDField,
+    tag_type::TagType,
+};
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
 use std::io::{self, Cursor, Seek, SeekFrom};
 
@@ -22,12 +26,12 @@ pub enum IFDField {
         /// Either the tag's value, or a pointer to a location within the file.
         value_or_offset: [u8; 4],
     },
-    /* Sbyte(Box<[i8]>),
+    SByte(Box<[i8]>),
     SShort(Box<[i16]>),
     SLong(Box<[i32]>),
     SRational(Box<[(i32, i32)]>),
+    Float(Box<[f32]>),
     Double(Box<[f64]>),
-    Float(Box<[f32]>), */
 }
 
 impl IFDField {
@@ -35,9 +39,12 @@ impl IFDField {
     pub fn read_from<E: ByteOrder, R: ReadBytesExt + Seek>(
         reader: &mut R,
         field: &RawIFDField,
-    ) -> Result<Self, io::Error> {
+    ) -> Result<Self, TiffError> {
         if tag_exceeds_ifd_field(field.tag_type, field.count) {
-            let tag_data_offset = field.value_or_offset.as_ref().read_u32::<E>()?;
+            let tag_data_offset = field.offset::<E>()?;
+            // Check the data is present before allocating for it, as a bogus count could be huge
+            let len = field.value_byte_len().map_or(u64::MAX, |len| len as u64);
+            check_in_bounds(reader, tag_data_offset.into(), len)?;
             reader.seek(SeekFrom::Start(tag_data_offset.into()))?;
             Self::from_raw_field_reader::<E, R>(reader, field.tag_type, field.count)
         } else {
@@ -50,7 +57,7 @@ impl IFDField {
         reader: &mut R,
         tag_type: u16,
         count: u32,
-    ) -> Result<Self, io::Error> {
+    ) -> Result<Self, TiffError> {
         Ok(match tag_type {
             IFD_TYPE_BYTE => {
                 let mut buffer = vec![0; count as usize];
@@ -88,12 +95,38 @@ impl IFDField {
                 reader.read_exact(&mut buffer)?;
                 IFDField::Undefined(buffer.into_boxed_slice())
             }
-            IFD_TYPE_SBYTE => unimplemented!("SByte IFD values"),
-            IFD_TYPE_SSHORT => unimplemented!("SShort IFD values"),
-            IFD_TYPE_SLONG => unimplemented!("SLong IFD values"),
-            IFD_TYPE_SRATIONAL => unimplemented!("SRational IFD values"),
-            IFD_TYPE_FLOAT => unimplemented!("Float IFD values"),
-            IFD_TYPE_DOUBLE => unimplemented!("Double IFD values"),
+            IFD_TYPE_SBYTE => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i8_into(&mut buffer)?;
+                IFDField::SByte(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SSHORT => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i16_into::<E>(&mut buffer)?;
+                IFDField::SShort(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SLONG => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i32_into::<E>(&mut buffer)?;
+                IFDField::SLong(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SRATIONAL => {
+                let mut rational_buffer = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    rational_buffer.push((reader.read_i32::<E>()?, reader.read_i32::<E>()?));
+                }
+                IFDField::SRational(rational_buffer.into_boxed_slice())
+            }
+            IFD_TYPE_FLOAT => {
+                let mut buffer = vec![0.0; count as usize];
+                reader.read_f32_into::<E>(&mut buffer)?;
+                IFDField::Float(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_DOUBLE => {
+                let mut buffer = vec![0.0; count as usize];
+                reader.read_f64_into::<E>(&mut buffer)?;
+                IFDField::Double(buffer.into_boxed_slice())
+            }
             _ => {
                 let mut value_or_offset = [0u8; 4];
                 reader.read_exact(&mut value_or_offset)?;
@@ -118,7 +151,7 @@ impl IFDField {
         let tag_type = self.type_number();
         let count = self.count() as u32;
         if tag_exceeds_ifd_field(tag_type, count) {
-            let data_offset = writer.seek(SeekFrom::Current(0))? as u32;
+            let data_offset = writer.stream_position()? as u32;
             cursor.write_u32::<E>(data_offset)?;
             self.write_field_into::<E, _>(writer)?;
         } else {
@@ -139,12 +172,12 @@ impl IFDField {
         writer: &mut W,
     ) -> Result<(), io::Error> {
         match self {
-            Self::Undefined(bytes) => writer.write_all(&bytes),
-            Self::Byte(bytes) => writer.write_all(&bytes),
+            Self::Undefined(bytes) => writer.write_all(bytes),
+            Self::Byte(bytes) => writer.write_all(bytes),
             Self::Ascii(strings) => {
                 for string in strings.iter() {
                     writer.write_all(string.as_bytes())?;
-                    writer.write_all(&[b'\0'])?;
+                    writer.write_all(b"\0")?;
                 }
                 Ok(())
             }
@@ -159,6 +192,24 @@ impl IFDField {
                     .write_u32::<E>(*a)
                     .and_then(|()| writer.write_u32::<E>(*b))
             }),
+            Self::SByte(sbytes) => sbytes.iter().try_for_each(|sbyte| writer.write_i8(*sbyte)),
+            Self::SShort(sshorts) => sshorts
+                .iter()
+                .try_for_each(|sshort| writer.write_i16::<E>(*sshort)),
+            Self::SLong(slongs) => slongs
+                .iter()
+                .try_for_each(|slong| writer.write_i32::<E>(*slong)),
+            Self::SRational(srationals) => srationals.iter().try_for_each(|(a, b)| {
+                writer
+                    .write_i32::<E>(*a)
+                    .and_then(|()| writer.write_i32::<E>(*b))
+            }),
+            Self::Float(floats) => floats
+                .iter()
+                .try_for_each(|float| writer.write_f32::<E>(*float)),
+            Self::Double(doubles) => doubles
+                .iter()
+                .try_for_each(|double| writer.write_f64::<E>(*double)),
             Self::Unrecognized {
                 value_or_offset, ..
             } => writer.write_all(value_or_offset),
@@ -173,7 +224,7 @@ impl IFDField {
             Self::Ascii(strings) => {
                 let mut length: usize = 0;
                 for string in strings.iter() {
-                    length += string.as_bytes().len();
+                    length += string.len();
                     length += 1; // For null character
                 }
                 length
@@ -181,6 +232,12 @@ impl IFDField {
             Self::Short(data) => data.len(),
             Self::Long(data) => data.len(),
             Self::Rational(data) => data.len(),
+            Self::SByte(data) => data.len(),
+            Self::SShort(data) => data.len(),
+            Self::SLong(data) => data.len(),
+            Self::SRational(data) => data.len(),
+            Self::Float(data) => data.len(),
+            Self::Double(data) => data.len(),
             Self::Unrecognized { count, .. } => *count as usize,
         }
     }
@@ -193,6 +250,12 @@ impl IFDField {
             Self::Short(_) => IFD_TYPE_SHORT,
             Self::Long(_) => IFD_TYPE_LONG,
             Self::Rational(_) => IFD_TYPE_RATIONAL,
+            Self::SByte(_) => IFD_TYPE_SBYTE,
+            Self::SShort(_) => IFD_TYPE_SSHORT,
+            Self::SLong(_) => IFD_TYPE_SLONG,
+            Self::SRational(_) => IFD_TYPE_SRATIONAL,
+            Self::Float(_) => IFD_TYPE_FLOAT,
+            Self::Double(_) => IFD_TYPE_DOUBLE,
             Self::Unrecognized { tag_type, .. } => *tag_type,
         }
     }
@@ -201,29 +264,14 @@ impl IFDField {
 /// Decide whether or not the specified count of this tag type exceeds the 4-byte
 /// 'value_or_offset' field within the IFD tag field.
 pub fn tag_exceeds_ifd_field(tag_type: u16, count: u32) -> bool {
-    match tag_type {
-        // Single byte per unit fields
-        IFD_TYPE_BYTE if count > 4 => true,
-        IFD_TYPE_ASCII if count > 4 => true,
-        IFD_TYPE_SBYTE if count > 4 => true,
-        IFD_TYPE_UNDEFINED if count > 4 => true,
-
-        // Two byte per unit fields
-        IFD_TYPE_SHORT if count > 2 => true,
-        IFD_TYPE_SSHORT if count > 2 => true,
-
-        // Four byte per unit fields
-        IFD_TYPE_LONG if count > 1 => true,
-        IFD_TYPE_SLONG if count > 1 => true,
-        IFD_TYPE_FLOAT if count > 1 => true,
-
-        // 5+ byte per unit fields
-        IFD_TYPE_RATIONAL => true,
-        IFD_TYPE_SRATIONAL => true,
-        IFD_TYPE_DOUBLE => true,
-
+    match TagType::from_u16(tag_type) {
+        // Types without an IFDField variant are read as Unrecognized, keeping the raw value
+        Some(TagType::IFD) | Some(TagType::Long8) | Some(TagType::SLong8) | Some(TagType::IFD8) => {
+            false
+        }
+        Some(tag_type) => tag_type.byte_size() as u64 * u64::from(count) > 4,
         // Otherwise, assume it fits (As it is unrecognized and custom-defined)
-        _ => false,
+        None => false,
     }
 }
 
@@ -0,0 +1,140 @@
+use crate::errors::TiffError;
+use crate::lowlevel::{
+    raw_ifd::{RawIFD, RawIFDField},
+    raw_ifd64::{RawIFD64, RawIFDField64},
+};
+use byteorder::ByteOrder;
+
+/// The parts common to the fields of classic TIFF and BigTIFF IFDs, `RawIFDField` and
+/// `RawIFDField64`, so that code inspecting fields can be written once for both.
+pub trait IfdFieldLike {
+    /// Width of counts and offsets: `u32` for classic TIFF and `u64` for BigTIFF.
+    type Offset: Copy + Into<u64>;
+
+    /// Tag ID.
+    fn tag(&self) -> u16;
+
+    /// Tag data type.
+    fn tag_type(&self) -> u16;
+
+    /// Quantity (not byte count) of data in the field.
+    fn count(&self) -> Self::Offset;
+
+    /// Either the value of the tag, if it is small enough, or the file offset of its data.
+    fn value_or_offset(&self) -> &[u8];
+
+    /// Whether the value is stored inline within `value_or_offset`, rather than at the file
+    /// offset it contains. Returns `None` if the type is unrecognized.
+    fn is_inline(&self) -> Option<bool>;
+
+    /// Decode `value_or_offset` as the file offset of the value, in the file's byte order `E`.
+    /// Only meaningful if the value isn't inline. Fails with `TiffError::NullOffset` if it is
+    /// zero, which points at the header rather than any value.
+    fn offset<E: ByteOrder>(&self) -> Result<Self::Offset, TiffError>;
+}
+
+/// The parts common to classic TIFF and BigTIFF IFDs, `RawIFD` and `RawIFD64`, so that code
+/// walking the fields of an IFD can be written once for both. The concrete types have more
+/// methods of their own, and can be used directly when only one format matters.
+pub trait IfdLike {
+    /// Width of counts and offsets: `u32` for classic TIFF and `u64` for BigTIFF.
+    type Offset: Copy + Into<u64>;
+
+    /// Type of the fields of the IFD.
+    type Field: IfdFieldLike<Offset = Self::Offset>;
+
+    /// The fields of the IFD, in the order they are stored.
+    fn entries(&self) -> &[Self::Field];
+
+    /// Number of fields in the IFD.
+    fn entry_count(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Find the field for `tag`.
+    fn get(&self, tag: u16) -> Option<&Self::Field> {
+        self.entries().iter().find(|field| field.tag() == tag)
+    }
+}
+
+impl IfdFieldLike for RawIFDField {
+    type Offset = u32;
+
+    fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    fn tag_type(&self) -> u16 {
+        self.tag_type
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    fn value_or_offset(&self) -> &[u8] {
+        &self.value_or_offset
+    }
+
+    fn is_inline(&self) -> Option<bool> {
+        RawIFDField::is_inline(self)
+    }
+
+    fn offset<E: ByteOrder>(&self) -> Result<u32, TiffError> {
+        RawIFDField::offset::<E>(self)
+    }
+}
+
+impl IfdFieldLike for RawIFDField64 {
+    type Offset = u64;
+
+    fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    fn tag_type(&self) -> u16 {
+        self.tag_type
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn value_or_offset(&self) -> &[u8] {
+        &self.value_or_offset
+    }
+
+    fn is_inline(&self) -> Option<bool> {
+        RawIFDField64::is_inline(self)
+    }
+
+    fn offset<E: ByteOrder>(&self) -> Result<u64, TiffError> {
+        match E::read_u64(&self.value_or_offset) {
+            0 => Err(TiffError::NullOffset { tag: self.tag }),
+            offset => Ok(offset),
+        }
+    }
+}
+
+impl IfdLike for RawIFD {
+    type Offset = u32;
+    type Field = RawIFDField;
+
+    fn entries(&self) -> &[RawIFDField] {
+        &self.entries
+    }
+
+    fn get(&self, tag: u16) -> Option<&RawIFDField> {
+        // Use the binary search of the inherent method
+        RawIFD::get(self, tag)
+    }
+}
+
+impl IfdLike for RawIFD64 {
+    type Offset = u64;
+    type Field = RawIFDField64;
+
+    fn entries(&self) -> &[RawIFDField64] {
+        &self.entries
+    }
+}
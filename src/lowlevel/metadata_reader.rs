@@ -1,34 +1,74 @@
+use crate::baseline::tags;
+use crate::errors::{FieldExtractionError, IFDError, TiffError};
 use crate::lowlevel::{
-    header::{read_header_endian, read_header_magic},
+    header::{Endianness, TiffHeader},
     ifd::IFD,
+    raw_field_values::check_in_bounds,
     raw_ifd::*,
+    raw_ifd64::*,
 };
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
-use failure::Fallible;
+use std::collections::{HashMap, HashSet};
 use std::io::{Seek, SeekFrom};
+use std::marker::PhantomData;
+
+/// Size of a BigTIFF header, before which no IFD may begin.
+const BIGTIFF_HEADER_SIZE: u64 = 16;
+
+/// Caps on the number of structures read from a file, bounding the memory used to parse
+/// untrusted input, and stricter checks on what is read. The default places no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// Maximum number of IFDs to read from a chain.
+    pub max_ifds: usize,
+    /// Maximum number of entries a single IFD may claim to have.
+    pub max_entries_per_ifd: usize,
+    /// Fail with `IFDError::EmptyIFD` on reaching an IFD with no entries. These are legal, but
+    /// in practice more often mean the offset to the IFD was wrong and it was read from the
+    /// wrong place.
+    pub reject_empty_ifds: bool,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_ifds: usize::MAX,
+            max_entries_per_ifd: usize::MAX,
+            reject_empty_ifds: false,
+        }
+    }
+}
 
 /// A TIFF metadata (header/IFD) reader.
 pub struct MetadataReader {
-    /// Whether the file is in little endian byte order.
-    is_little_endian: bool,
+    /// Byte order of the file.
+    endianness: Endianness,
     /// Table of IFDs read.
     ifd_table: Box<[IFD]>,
 }
 
 impl MetadataReader {
     /// Create a new MetadataReader from `reader`, reading the entire IFD table from the file.
-    /// Assumes the cursor is positioned at a 32-bit pointer to the first valid IFD.
-    pub fn read_header<R: ReadBytesExt + Seek>(reader: &mut R) -> Fallible<Self> {
-        let is_little_endian = read_header_endian(reader)?;
-
-        let ifd_table = if is_little_endian {
-            read_ifd_table_endian::<LittleEndian, R>(reader)?
-        } else {
-            read_ifd_table_endian::<BigEndian, R>(reader)?
+    /// Assumes the cursor is positioned at the beginning of a TIFF file.
+    pub fn read_header<R: ReadBytesExt + Seek>(reader: &mut R) -> Result<Self, TiffError> {
+        let TiffHeader {
+            endianness,
+            is_bigtiff,
+            first_ifd_offset,
+        } = TiffHeader::read_from(reader)?;
+        if is_bigtiff {
+            return Err(TiffError::UnexpectedBigTiff);
+        }
+
+        let ifd_table = match endianness {
+            Endianness::Little => {
+                read_ifd_table_endian::<LittleEndian, R>(reader, first_ifd_offset)?
+            }
+            Endianness::Big => read_ifd_table_endian::<BigEndian, R>(reader, first_ifd_offset)?,
         };
 
         Ok(Self {
-            is_little_endian,
+            endianness,
             ifd_table,
         })
     }
@@ -38,18 +78,21 @@ impl MetadataReader {
         &mut self,
         offset: u64,
         reader: &mut R,
-    ) -> Fallible<Box<[IFD]>> {
-        reader.seek(SeekFrom::Start(offset))?;
-        if self.is_little_endian {
-            read_ifd_table_endian::<LittleEndian, R>(reader)
-        } else {
-            read_ifd_table_endian::<BigEndian, R>(reader)
+    ) -> Result<Box<[IFD]>, TiffError> {
+        match self.endianness {
+            Endianness::Little => read_ifd_table_endian::<LittleEndian, R>(reader, offset),
+            Endianness::Big => read_ifd_table_endian::<BigEndian, R>(reader, offset),
         }
     }
 
     /// Returns true if the file is in little-endian byte order.
     pub fn is_little_endian(&self) -> bool {
-        self.is_little_endian
+        self.endianness.is_little()
+    }
+
+    /// Returns the byte order of the file.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
     }
 
     /// Returns an iterator over references to this file's IFDs in the order they were read.
@@ -58,35 +101,847 @@ impl MetadataReader {
     }
 }
 
-/// Read all of the IFDs with the specified endian.
-/// Assumes the cursor is positioned at the beginning of a TIFF file.
+/// The chain of raw IFDs read by `read_ifds_auto`, in whichever form the file's header calls for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RawIFDChain {
+    /// The IFDs of a classic TIFF, with 32-bit counts and offsets.
+    Classic(Box<[RawIFD]>),
+    /// The IFDs of a BigTIFF, with 64-bit counts and offsets.
+    Big(Box<[RawIFD64]>),
+}
+
+impl RawIFDChain {
+    /// Number of IFDs in the chain.
+    pub fn len(&self) -> usize {
+        match self {
+            RawIFDChain::Classic(ifds) => ifds.len(),
+            RawIFDChain::Big(ifds) => ifds.len(),
+        }
+    }
+
+    /// Whether the chain has no IFDs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the IFDs were read from a BigTIFF.
+    pub fn is_bigtiff(&self) -> bool {
+        matches!(self, RawIFDChain::Big(_))
+    }
+
+    /// Returns the IFDs of a classic TIFF, for code which doesn't handle BigTIFF. Fails with
+    /// `TiffError::UnexpectedBigTiff` if they were read from a BigTIFF.
+    pub fn into_classic(self) -> Result<Box<[RawIFD]>, TiffError> {
+        match self {
+            RawIFDChain::Classic(ifds) => Ok(ifds),
+            RawIFDChain::Big(_) => Err(TiffError::UnexpectedBigTiff),
+        }
+    }
+}
+
+/// Read the header from `reader`, then read all raw IFDs in the file with whichever byte order
+/// the header declares: with `read_raw_ifds` for a classic TIFF, or `read_raw_ifds_64` for a
+/// BigTIFF. The endianness is returned so that the entries (which are still in the file's byte
+/// order) can be interpreted, or written back out the same way. Assumes the cursor is
+/// positioned at the beginning of the file.
+pub fn read_ifds_auto<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+) -> Result<(Endianness, RawIFDChain), TiffError> {
+    read_ifds_auto_with_limits(reader, ReadLimits::default())
+}
+
+/// Read the header and all raw IFDs as `read_ifds_auto` does, but fail once the chain or any IFD
+/// in it exceeds `limits`, whether the file is a classic TIFF or a BigTIFF.
+pub fn read_ifds_auto_with_limits<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    limits: ReadLimits,
+) -> Result<(Endianness, RawIFDChain), TiffError> {
+    let header = TiffHeader::read_from(reader)?;
+    let offset = header.first_ifd_offset;
+    let chain = match (header.endianness, header.is_bigtiff) {
+        (Endianness::Little, false) => {
+            let ifds = read_raw_ifds_with_limits::<LittleEndian, R>(reader, offset, limits)?;
+            RawIFDChain::Classic(ifds)
+        }
+        (Endianness::Big, false) => {
+            let ifds = read_raw_ifds_with_limits::<BigEndian, R>(reader, offset, limits)?;
+            RawIFDChain::Classic(ifds)
+        }
+        (Endianness::Little, true) => {
+            let ifds = read_raw_ifds_64_with_limits::<LittleEndian, R>(reader, offset, limits)?;
+            RawIFDChain::Big(ifds)
+        }
+        (Endianness::Big, true) => {
+            let ifds = read_raw_ifds_64_with_limits::<BigEndian, R>(reader, offset, limits)?;
+            RawIFDChain::Big(ifds)
+        }
+    };
+    Ok((header.endianness, chain))
+}
+
+/// Read all of the IFDs in the chain beginning at `first_ifd_offset` with the specified endian.
 pub fn read_ifd_table_endian<E: ByteOrder, R: ReadBytesExt + Seek>(
     reader: &mut R,
-) -> Fallible<Box<[IFD]>> {
-    read_header_magic::<E, _>(reader)?;
-    let raw_ifds = read_raw_ifds::<E, R>(reader)?;
+    first_ifd_offset: u64,
+) -> Result<Box<[IFD]>, TiffError> {
+    let raw_ifds = read_raw_ifds::<E, R>(reader, first_ifd_offset)?;
     let mut ifds = Vec::with_capacity(raw_ifds.len());
     for raw_ifd in raw_ifds.iter() {
-        ifds.push(IFD::read_from::<E, _>(reader, &raw_ifd)?);
+        ifds.push(IFD::read_from::<E, _>(reader, raw_ifd)?);
     }
     Ok(ifds.into_boxed_slice())
 }
 
-/// Read all IFDs from `reader` table into memory sequentially.
-/// Assumes the cursor is positioned at a 32-bit pointer to the first valid IFD.
+/// Read all IFDs from `reader` table into memory sequentially, following the chain of
+/// 'next IFD' pointers beginning at `first_ifd_offset` (as found in the `TiffHeader`).
+/// Reading stops at the first pointer to an already-read IFD, so a looping chain terminates.
 pub fn read_raw_ifds<E: ByteOrder, R: ReadBytesExt + Seek>(
     reader: &mut R,
-) -> Fallible<Box<[RawIFD]>> {
+    first_ifd_offset: u64,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    read_raw_ifds_with_limits::<E, R>(reader, first_ifd_offset, ReadLimits::default())
+}
+
+/// Read all IFDs from a little-endian `reader`, as `read_raw_ifds` does.
+pub fn read_raw_ifds_le<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    read_raw_ifds::<LittleEndian, R>(reader, first_ifd_offset)
+}
+
+/// Read all IFDs from a big-endian `reader`, as `read_raw_ifds` does.
+pub fn read_raw_ifds_be<R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    read_raw_ifds::<BigEndian, R>(reader, first_ifd_offset)
+}
+
+/// Read only the first IFD, at `first_ifd_offset` (as found in the `TiffHeader`), without
+/// following the chain beyond it. The cursor is left just after the IFD, so that the next 4
+/// bytes are the offset of the one following it. Fails with `IFDError::NoIFDs` if
+/// `first_ifd_offset` is zero, or as `RawIFD::read_from_at` does.
+pub fn read_first_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<RawIFD, TiffError> {
+    if first_ifd_offset == 0 {
+        return Err(IFDError::NoIFDs.into());
+    }
+    RawIFD::read_from_at::<E, R>(reader, first_ifd_offset)
+}
+
+/// Reads the IFDs of a chain one at a time, following each 'next IFD' pointer only when the
+/// next IFD is asked for, so that a caller can stop early without reading the rest of a large
+/// file. It yields the same IFDs as `read_raw_ifds`, also stopping at the first pointer to an
+/// already-read IFD so that a looping chain terminates. After an error, nothing more is
+/// yielded; if only the pointer following an IFD couldn't be read, the IFD is yielded first.
+pub struct IfdChainIter<'r, E: ByteOrder, R: ReadBytesExt + Seek> {
+    reader: &'r mut R,
+    next_offset: u64,
+    visited: HashSet<u64>,
+    pending_error: Option<TiffError>,
+    _phantomdata: PhantomData<E>,
+}
+
+impl<'r, E: ByteOrder, R: ReadBytesExt + Seek> IfdChainIter<'r, E, R> {
+    /// Begin walking the chain from `first_ifd_offset` (as found in the `TiffHeader`).
+    pub fn new(reader: &'r mut R, first_ifd_offset: u64) -> Self {
+        Self {
+            reader,
+            next_offset: first_ifd_offset,
+            visited: HashSet::new(),
+            pending_error: None,
+            _phantomdata: PhantomData,
+        }
+    }
+
+    /// Position within the file of the IFD the next call to `next` will read, or zero if the
+    /// chain has ended.
+    pub fn next_offset(&self) -> u64 {
+        self.next_offset
+    }
+}
+
+impl<'r, E: ByteOrder, R: ReadBytesExt + Seek> Iterator for IfdChainIter<'r, E, R> {
+    type Item = Result<RawIFD, TiffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.pending_error.take() {
+            return Some(Err(err));
+        }
+        let offset = core::mem::replace(&mut self.next_offset, 0);
+        if offset == 0 || !self.visited.insert(offset) {
+            return None;
+        }
+
+        let ifd = match RawIFD::read_from_at::<E, R>(self.reader, offset) {
+            Ok(ifd) => ifd,
+            Err(err) => return Some(Err(err)),
+        };
+        match self.reader.read_u32::<E>() {
+            Ok(next) => self.next_offset = next.into(),
+            Err(err) => self.pending_error = Some(err.into()),
+        }
+        Some(Ok(ifd))
+    }
+}
+
+/// Read all IFDs as `read_raw_ifds` does, but fail once the chain or any IFD in it exceeds
+/// `limits`.
+pub fn read_raw_ifds_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    limits: ReadLimits,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    Ok(
+        read_raw_ifds_with_offsets_limited::<E, R>(reader, first_ifd_offset, limits)?
+            .into_iter()
+            .map(|(_, ifd)| ifd)
+            .collect(),
+    )
+}
+
+/// Read all IFDs as `read_raw_ifds` does, pairing each with the position within the file it
+/// begins at (as needed by `patch_inline_value`, for example).
+pub fn read_raw_ifds_with_offsets<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Vec<(u64, RawIFD)>, TiffError> {
+    read_raw_ifds_with_offsets_limited::<E, R>(reader, first_ifd_offset, ReadLimits::default())
+}
+
+/// Read all IFDs and their positions as `read_raw_ifds_with_offsets` does, but fail once the
+/// chain or any IFD in it exceeds `limits`.
+fn read_raw_ifds_with_offsets_limited<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    limits: ReadLimits,
+) -> Result<Vec<(u64, RawIFD)>, TiffError> {
+    let mut ifds = Vec::new();
+    read_raw_ifd_chain::<E, R>(
+        reader,
+        first_ifd_offset,
+        limits,
+        &mut HashSet::new(),
+        &mut ifds,
+    )?;
+    Ok(ifds
+        .into_iter()
+        .map(|linked| (linked.offset, linked.ifd))
+        .collect())
+}
+
+/// Read all IFDs as `read_raw_ifds` does, but rather than discarding them on failure, return
+/// every IFD read before the chain broke, along with the error that broke it, if any. Useful for
+/// recovering the early pages of a file whose tail is truncated or corrupt. An IFD is kept if
+/// it was read in full, even if the pointer to the next one couldn't be. A truncated IFD is not
+/// kept, but its partial entries are held by the `TiffError::TruncatedIFD` returned.
+pub fn read_raw_ifds_lenient<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> (Vec<RawIFD>, Option<TiffError>) {
+    let mut ifds = Vec::new();
+    let result = read_raw_ifd_chain::<E, R>(
+        reader,
+        first_ifd_offset,
+        ReadLimits::default(),
+        &mut HashSet::new(),
+        &mut ifds,
+    );
+    (
+        ifds.into_iter().map(|linked| linked.ifd).collect(),
+        result.err(),
+    )
+}
+
+/// An IFD along with its position within the file and the 'next IFD' pointer following it, as
+/// stored, so that the links between IFDs can be followed or reproduced exactly.
+#[derive(Debug, Clone)]
+pub struct LinkedIfd {
+    pub ifd: RawIFD,
+    /// Position within the file of the IFD.
+    pub offset: u64,
+    /// The 'next IFD' pointer following the IFD, or zero if it ends its chain.
+    pub next_offset: u64,
+}
+
+/// Read every IFD in the file reachable from `first_ifd_offset`: the main chain, then the IFDs
+/// referenced by the SubIFDs, Exif IFD and GPS IFD tags of any IFD read, along with the chains
+/// beginning at each of them. Each IFD is read once, however many times it is referenced, and
+/// keeps the links found on disk, so the true structure of the file can be reconstructed:
+/// the main chain comes first, in order, and the rest follow in the order they were found.
+/// `read_raw_ifds` is a convenience for reading just the main chain.
+pub fn read_ifd_graph<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Vec<LinkedIfd>, TiffError> {
     let mut ifds = Vec::new();
-    let mut pointers_encountered = Vec::new(); // Break if a loop is found within the IFD pointers
-    loop {
-        let next_ifd_offset = reader.read_u32::<E>()?;
-        if next_ifd_offset == 0 || pointers_encountered.contains(&next_ifd_offset) {
-            break;
+    let mut visited = HashSet::new();
+    read_raw_ifd_chain::<E, R>(
+        reader,
+        first_ifd_offset,
+        ReadLimits::default(),
+        &mut visited,
+        &mut ifds,
+    )?;
+
+    // IFDs are appended as they're found, so this also visits their children
+    let mut index = 0;
+    while index < ifds.len() {
+        for (_, offset) in child_ifd_offsets::<E, R>(reader, &ifds[index].ifd)? {
+            read_raw_ifd_chain::<E, R>(
+                reader,
+                offset,
+                ReadLimits::default(),
+                &mut visited,
+                &mut ifds,
+            )?;
         }
-        pointers_encountered.push(next_ifd_offset);
-        reader.seek(SeekFrom::Start(next_ifd_offset.into()))?;
-        ifds.push(RawIFD::read_from::<E, R>(reader)?);
+        index += 1;
+    }
+    Ok(ifds)
+}
+
+/// A pointer to an IFD from the header or another IFD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IfdReference {
+    /// The first IFD offset of the header.
+    Header,
+    /// The 'next IFD' pointer following the IFD at `from`.
+    NextIfd { from: u64 },
+    /// An offset in the field with `tag` (SubIFDs, Exif IFD or GPS IFD) of the IFD at `from`.
+    Field { from: u64, tag: u16 },
+}
+
+/// An IFD along with its position within the file and every pointer to it, as read by
+/// `read_ifd_references`.
+#[derive(Debug, Clone)]
+pub struct ReferencedIfd {
+    pub ifd: RawIFD,
+    /// Position within the file of the IFD.
+    pub offset: u64,
+    /// Every pointer to the IFD, in the order of the IFDs holding them, and with the 'next IFD'
+    /// pointer before any fields of the same IFD.
+    pub references: Vec<IfdReference>,
+}
+
+/// Read every IFD reachable from `first_ifd_offset` as `read_ifd_graph` does, in the same order,
+/// pairing each with every pointer to it. An IFD both in a chain and referenced by a field, as
+/// when a writer points the main chain and a SubIFDs offset at the same place, is returned
+/// once with both references, rather than being read twice.
+pub fn read_ifd_references<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Vec<ReferencedIfd>, TiffError> {
+    let linked = read_ifd_graph::<E, R>(reader, first_ifd_offset)?;
+    let indices: HashMap<u64, usize> = linked
+        .iter()
+        .enumerate()
+        .map(|(index, linked)| (linked.offset, index))
+        .collect();
+
+    let mut references = vec![Vec::new(); linked.len()];
+    if let Some(&index) = indices.get(&first_ifd_offset) {
+        references[index].push(IfdReference::Header);
+    }
+    for from in &linked {
+        if let Some(&index) = indices.get(&from.next_offset) {
+            references[index].push(IfdReference::NextIfd { from: from.offset });
+        }
+        for (tag, offset) in child_ifd_offsets::<E, R>(reader, &from.ifd)? {
+            if let Some(&index) = indices.get(&offset) {
+                references[index].push(IfdReference::Field {
+                    from: from.offset,
+                    tag,
+                });
+            }
+        }
+    }
+
+    Ok(linked
+        .into_iter()
+        .zip(references)
+        .map(|(linked, references)| ReferencedIfd {
+            ifd: linked.ifd,
+            offset: linked.offset,
+            references,
+        })
+        .collect())
+}
+
+/// Read the offsets in the SubIFDs, Exif IFD and GPS IFD fields of `ifd`, each paired with the
+/// tag of the field holding it, in that order.
+fn child_ifd_offsets<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    ifd: &RawIFD,
+) -> Result<Vec<(u16, u64)>, TiffError> {
+    let mut offsets = Vec::new();
+    for &tag in &[tags::SUB_IFDS, tags::EXIF_IFD, tags::GPS_IFD] {
+        if let Some(field) = ifd.get(tag) {
+            offsets.extend(
+                field
+                    .as_offset_array::<E, R>(reader)?
+                    .into_iter()
+                    .map(|offset| (tag, u64::from(offset))),
+            );
+        }
+    }
+    Ok(offsets)
+}
+
+/// Find every field with `tag` in the IFDs `read_ifd_graph` reads from `first_ifd_offset`: the
+/// main chain and the IFDs referenced by the SubIFDs, Exif IFD and GPS IFD tags. Each field is
+/// paired with the index of the IFD it was found in, within the Vec `read_ifd_graph` returns.
+/// Each IFD is searched once, so looping files terminate.
+pub fn find_tag_anywhere<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    tag: u16,
+) -> Result<Vec<(usize, RawIFDField)>, TiffError> {
+    Ok(read_ifd_graph::<E, R>(reader, first_ifd_offset)?
+        .iter()
+        .enumerate()
+        .filter_map(|(index, linked)| linked.ifd.get(tag).map(|field| (index, *field)))
+        .collect())
+}
+
+/// Read the chain of IFDs beginning at `first_ifd_offset` into `ifds`, stopping at the first
+/// pointer to an IFD in `visited`, and adding the position of each IFD read to it. On failure,
+/// `ifds` holds those read before it.
+fn read_raw_ifd_chain<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    limits: ReadLimits,
+    visited: &mut HashSet<u64>,
+    ifds: &mut Vec<LinkedIfd>,
+) -> Result<(), TiffError> {
+    let mut next_ifd_offset = first_ifd_offset;
+    while next_ifd_offset != 0 && visited.insert(next_ifd_offset) {
+        if next_ifd_offset < HEADER_SIZE {
+            return Err(IFDError::OffsetInHeader {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        if ifds.len() == limits.max_ifds {
+            return Err(IFDError::TooManyIFDs {
+                limit: limits.max_ifds,
+            }
+            .into());
+        }
+        check_in_bounds(reader, next_ifd_offset, 2)?;
+        reader.seek(SeekFrom::Start(next_ifd_offset))?;
+        let ifd = RawIFD::read_from_limited::<E, R>(reader, limits.max_entries_per_ifd)?;
+        if limits.reject_empty_ifds && ifd.is_empty() {
+            return Err(IFDError::EmptyIFD {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        // Keep the IFD even if the pointer after it is missing
+        let next = reader.read_u32::<E>();
+        ifds.push(LinkedIfd {
+            ifd,
+            offset: next_ifd_offset,
+            next_offset: next.as_ref().map_or(0, |&next| next.into()),
+        });
+        next_ifd_offset = next?.into();
+    }
+    Ok(())
+}
+
+/// Read the IFDs referenced by the SubIFDs tag of `parent`, such as thumbnails or reduced
+/// resolution versions of the image, in the order their offsets appear. Returns an empty Vec if
+/// `parent` has no SubIFDs tag. Only the referenced IFDs are read, not any chain beyond them.
+/// The cursor position of `reader` is restored afterwards.
+pub fn read_sub_ifds<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    parent: &RawIFD,
+) -> Result<Vec<RawIFD>, TiffError> {
+    let field = match parent.get(tags::SUB_IFDS) {
+        Some(field) => field,
+        None => return Ok(Vec::new()),
+    };
+
+    let offsets = field.as_offset_array::<E, R>(reader)?;
+    let position = reader.stream_position()?;
+    let sub_ifds = offsets
+        .into_iter()
+        .map(|offset| read_raw_ifd_at::<E, R>(reader, offset.into()))
+        .collect();
+    reader.seek(SeekFrom::Start(position))?;
+    sub_ifds
+}
+
+/// Read the private EXIF IFD referenced by the Exif IFD tag of `parent`, holding camera
+/// settings such as exposure time and ISO speed. Returns `None` if `parent` has no such tag.
+/// The cursor position of `reader` is restored afterwards.
+pub fn read_exif_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    parent: &RawIFD,
+) -> Result<Option<RawIFD>, TiffError> {
+    read_pointed_ifd::<E, R>(reader, parent, tags::EXIF_IFD)
+}
+
+/// Read the GPS IFD referenced by the GPS IFD tag of `parent`, holding geotags such as latitude
+/// and longitude. Returns `None` if `parent` has no such tag. The cursor position of `reader` is
+/// restored afterwards.
+pub fn read_gps_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    parent: &RawIFD,
+) -> Result<Option<RawIFD>, TiffError> {
+    read_pointed_ifd::<E, R>(reader, parent, tags::GPS_IFD)
+}
+
+/// Decode the latitude and longitude in `gps_ifd` (as read by `read_gps_ifd`) into signed
+/// decimal degrees, with south and west negative. Returns `None` if either coordinate or its
+/// reference is missing.
+pub fn read_gps_coordinates<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    gps_ifd: &RawIFD,
+) -> Result<Option<(f64, f64)>, TiffError> {
+    let latitude = read_gps_degrees::<E, R>(
+        reader,
+        gps_ifd,
+        tags::gps::GPS_LATITUDE_REF,
+        tags::gps::GPS_LATITUDE,
+    )?;
+    let longitude = read_gps_degrees::<E, R>(
+        reader,
+        gps_ifd,
+        tags::gps::GPS_LONGITUDE_REF,
+        tags::gps::GPS_LONGITUDE,
+    )?;
+    Ok(latitude.and_then(|latitude| longitude.map(|longitude| (latitude, longitude))))
+}
+
+/// Decode a degrees, minutes, seconds RATIONAL triplet in `gps_ifd`, negated if its reference
+/// is `S` or `W`.
+fn read_gps_degrees<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    gps_ifd: &RawIFD,
+    ref_tag: u16,
+    tag: u16,
+) -> Result<Option<f64>, TiffError> {
+    let (reference, triplet) = match (gps_ifd.get(ref_tag), gps_ifd.get(tag)) {
+        (Some(reference), Some(triplet)) => (reference, triplet),
+        _ => return Ok(None),
+    };
+
+    let reference = reference.as_ascii::<E, R>(reader)?;
+    let triplet = triplet.as_rational_array::<E, R>(reader)?;
+    if triplet.len() < 3 {
+        return Err(FieldExtractionError::InsufficientData.into());
+    }
+
+    let degrees = triplet[0].as_f64() + triplet[1].as_f64() / 60. + triplet[2].as_f64() / 3600.;
+    match reference.as_str() {
+        "S" | "W" => Ok(Some(-degrees)),
+        _ => Ok(Some(degrees)),
+    }
+}
+
+/// Read the IFD referenced by the single offset in the `tag` field of `parent`, if present.
+fn read_pointed_ifd<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    parent: &RawIFD,
+    tag: u16,
+) -> Result<Option<RawIFD>, TiffError> {
+    let field = match parent.get(tag) {
+        Some(field) => field,
+        None => return Ok(None),
+    };
+
+    let offset = *field
+        .as_offset_array::<E, R>(reader)?
+        .first()
+        .ok_or(FieldExtractionError::InsufficientData)?;
+    let position = reader.stream_position()?;
+    let ifd = read_raw_ifd_at::<E, R>(reader, offset.into());
+    reader.seek(SeekFrom::Start(position))?;
+    ifd.map(Some)
+}
+
+/// Read the single IFD at `offset`, excluding the offset to the next IFD.
+fn read_raw_ifd_at<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> Result<RawIFD, TiffError> {
+    RawIFD::read_from_at::<E, R>(reader, offset)
+}
+
+/// Read all BigTIFF IFDs from `reader` table into memory sequentially, following the chain of
+/// 64-bit 'next IFD' pointers beginning at `first_ifd_offset` (as found in the `TiffHeader`).
+/// Reading stops at the first pointer to an already-read IFD, so a looping chain terminates.
+pub fn read_raw_ifds_64<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+) -> Result<Box<[RawIFD64]>, TiffError> {
+    read_raw_ifds_64_with_limits::<E, R>(reader, first_ifd_offset, ReadLimits::default())
+}
+
+/// Read all BigTIFF IFDs as `read_raw_ifds_64` does, but fail once the chain or any IFD in it
+/// exceeds `limits`, as `read_raw_ifds_with_limits` does for a classic TIFF.
+pub fn read_raw_ifds_64_with_limits<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    limits: ReadLimits,
+) -> Result<Box<[RawIFD64]>, TiffError> {
+    let mut ifds = Vec::new();
+    let mut pointers_encountered = HashSet::new(); // Break if a loop is found within the IFD pointers
+    let mut next_ifd_offset = first_ifd_offset;
+    while next_ifd_offset != 0 && pointers_encountered.insert(next_ifd_offset) {
+        if next_ifd_offset < BIGTIFF_HEADER_SIZE {
+            return Err(IFDError::OffsetInHeader {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        if ifds.len() == limits.max_ifds {
+            return Err(IFDError::TooManyIFDs {
+                limit: limits.max_ifds,
+            }
+            .into());
+        }
+        check_in_bounds(reader, next_ifd_offset, 8)?;
+        reader.seek(SeekFrom::Start(next_ifd_offset))?;
+        let ifd = RawIFD64::read_from_limited::<E, R>(reader, limits.max_entries_per_ifd)?;
+        if limits.reject_empty_ifds && ifd.entries.is_empty() {
+            return Err(IFDError::EmptyIFD {
+                offset: next_ifd_offset,
+            }
+            .into());
+        }
+        ifds.push(ifd);
+        next_ifd_offset = reader.read_u64::<E>()?;
     }
     Ok(ifds.into_boxed_slice())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::raw_ifd::XorShift;
+    use crate::lowlevel::{write_raw_ifds_64, IFDField, TiffBuilder};
+    use std::io::Cursor;
+
+    /// A BigTIFF in the byte order `E` with a chain of two IFDs, each with a single SHORT field.
+    fn bigtiff<E: ByteOrder>() -> (Vec<u8>, Vec<RawIFD64>) {
+        let ifds: Vec<_> = [1, 2]
+            .iter()
+            .map(|&width| {
+                let mut value_or_offset = [0; 8];
+                E::write_u16(&mut value_or_offset, width);
+                RawIFD64 {
+                    entries: vec![RawIFDField64 {
+                        tag: tags::IMAGE_WIDTH,
+                        tag_type: 3,
+                        count: 1,
+                        value_or_offset,
+                    }],
+                }
+            })
+            .collect();
+        let mut writer = Cursor::new(Vec::new());
+        TiffHeader {
+            endianness: Endianness::of::<E>(),
+            is_bigtiff: true,
+            first_ifd_offset: BIGTIFF_HEADER_SIZE,
+        }
+        .write_to(&mut writer)
+        .unwrap();
+        write_raw_ifds_64::<E, _>(&mut writer, &ifds).unwrap();
+        (writer.into_inner(), ifds)
+    }
+
+    #[test]
+    fn read_ifds_auto_reads_bigtiff_chain() {
+        let (buf, ifds) = bigtiff::<LittleEndian>();
+        let (endianness, chain) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(endianness, Endianness::Little);
+        assert!(chain.is_bigtiff());
+        assert_eq!(chain, RawIFDChain::Big(ifds.into()));
+
+        let (buf, ifds) = bigtiff::<BigEndian>();
+        let (endianness, chain) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+        assert_eq!(endianness, Endianness::Big);
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain, RawIFDChain::Big(ifds.into()));
+        assert!(matches!(
+            chain.into_classic(),
+            Err(TiffError::UnexpectedBigTiff)
+        ));
+    }
+
+    #[test]
+    fn bigtiff_chains_are_read_within_limits() {
+        let (buf, ifds) = bigtiff::<LittleEndian>();
+        let read = |limits| {
+            read_raw_ifds_64_with_limits::<LittleEndian, _>(
+                &mut Cursor::new(&buf),
+                BIGTIFF_HEADER_SIZE,
+                limits,
+            )
+        };
+        let too_many_ifds = ReadLimits {
+            max_ifds: 1,
+            ..ReadLimits::default()
+        };
+        assert!(matches!(
+            read(too_many_ifds),
+            Err(TiffError::IFD(IFDError::TooManyIFDs { limit: 1 }))
+        ));
+        let too_many_entries = ReadLimits {
+            max_entries_per_ifd: 0,
+            ..ReadLimits::default()
+        };
+        assert!(matches!(
+            read(too_many_entries),
+            Err(TiffError::IFD(IFDError::TooManyEntries {
+                count: 1,
+                limit: 0
+            }))
+        ));
+        let exact = ReadLimits {
+            max_ifds: 2,
+            max_entries_per_ifd: 1,
+            ..ReadLimits::default()
+        };
+        assert_eq!(read(exact).unwrap(), ifds.clone().into_boxed_slice());
+
+        // read_ifds_auto applies the same limits
+        assert!(matches!(
+            read_ifds_auto_with_limits(&mut Cursor::new(&buf), too_many_ifds),
+            Err(TiffError::IFD(IFDError::TooManyIFDs { limit: 1 }))
+        ));
+        let (_, chain) = read_ifds_auto_with_limits(&mut Cursor::new(&buf), exact).unwrap();
+        assert_eq!(chain, RawIFDChain::Big(ifds.into()));
+    }
+
+    #[test]
+    fn read_ifds_auto_reads_classic_chain() {
+        let mut ifd = IFD::new();
+        ifd.entries
+            .insert(tags::IMAGE_WIDTH, IFDField::Short(vec![7].into()));
+        let mut builder = TiffBuilder::new();
+        builder
+            .add_page(ifd.clone(), Vec::new())
+            .add_page(ifd, Vec::new());
+
+        for buf in [builder.build_le().unwrap(), builder.build_be().unwrap()] {
+            let (_, chain) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+            assert!(!chain.is_bigtiff());
+            let ifds = chain.into_classic().unwrap();
+            assert_eq!(ifds.len(), 2);
+            assert!(ifds.iter().all(|ifd| ifd.get(tags::IMAGE_WIDTH).is_some()));
+        }
+    }
+
+    #[test]
+    fn empty_ifd_keeps_chain_in_step() {
+        // An empty IFD between two others, whose next-IFD offset directly follows its count
+        let mut ifd = IFD::new();
+        ifd.entries
+            .insert(tags::IMAGE_WIDTH, IFDField::Short(vec![7].into()));
+        let buf = TiffBuilder::new()
+            .add_page(ifd.clone(), Vec::new())
+            .add_page(IFD::new(), Vec::new())
+            .add_page(ifd, Vec::new())
+            .build_le()
+            .unwrap();
+        let first = u64::from(LittleEndian::read_u32(&buf[4..8]));
+
+        let ifds =
+            read_raw_ifds_with_offsets::<LittleEndian, _>(&mut Cursor::new(&buf), first).unwrap();
+        assert_eq!(ifds.len(), 3);
+        assert_eq!(ifds[1].1.entries.len(), 0);
+        let empty_at = ifds[1].0 as usize;
+        assert_eq!(&buf[empty_at..empty_at + 2], [0, 0]);
+        assert_eq!(
+            LittleEndian::read_u32(&buf[empty_at + 2..empty_at + 6]),
+            ifds[2].0 as u32
+        );
+        assert!(ifds[2].1.get(tags::IMAGE_WIDTH).is_some());
+
+        let limits = ReadLimits {
+            reject_empty_ifds: true,
+            ..ReadLimits::default()
+        };
+        let result =
+            read_raw_ifds_with_limits::<LittleEndian, _>(&mut Cursor::new(&buf), first, limits);
+        assert!(matches!(
+            result,
+            Err(TiffError::IFD(IFDError::EmptyIFD { offset })) if offset == empty_at as u64
+        ));
+    }
+
+    /// Parse `buf` the ways a malformed file might be parsed, discarding the results. The point
+    /// is that none of them panic.
+    fn parse_all(buf: &[u8]) {
+        let _ = RawIFD::read_from::<LittleEndian, _>(&mut Cursor::new(buf));
+        let _ = RawIFD::read_from::<BigEndian, _>(&mut Cursor::new(buf));
+        let _ = read_raw_ifds_lenient::<LittleEndian, _>(&mut Cursor::new(buf), HEADER_SIZE);
+        let ifds = match read_ifds_auto(&mut Cursor::new(buf)) {
+            Ok((endianness, RawIFDChain::Classic(ifds))) => (endianness, ifds),
+            _ => return,
+        };
+        for ifd in ifds.1.iter() {
+            for field in &ifd.entries {
+                let mut reader = Cursor::new(buf);
+                match ifds.0 {
+                    Endianness::Little => {
+                        let _ = field.read_value_bytes::<LittleEndian, _>(&mut reader);
+                        let _ = field.as_unsigned_array::<LittleEndian, _>(&mut reader);
+                    }
+                    Endianness::Big => {
+                        let _ = field.read_value_bytes::<BigEndian, _>(&mut reader);
+                        let _ = field.as_unsigned_array::<BigEndian, _>(&mut reader);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let mut ifd = IFD::new();
+        ifd.entries
+            .insert(tags::IMAGE_WIDTH, IFDField::Short(vec![7].into()));
+        ifd.entries
+            .insert(tags::STRIP_OFFSETS, IFDField::Long(vec![8, 9, 10].into()));
+        let mut builder = TiffBuilder::new();
+        builder
+            .add_page(ifd.clone(), vec![vec![1, 2, 3]])
+            .add_page(ifd, Vec::new());
+        let valid = [
+            builder.build_le().unwrap(),
+            builder.build_be().unwrap(),
+            bigtiff::<LittleEndian>().0,
+        ];
+
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..2000 {
+            // Overwrite a few bytes of a valid file, and sometimes cut it short
+            let mut buf = valid[rng.below(valid.len())].clone();
+            for _ in 0..1 + rng.below(8) {
+                let idx = rng.below(buf.len());
+                buf[idx] = rng.next() as u8;
+            }
+            if rng.below(4) == 0 {
+                buf.truncate(rng.below(buf.len()));
+            }
+            parse_all(&buf);
+        }
+        for _ in 0..2000 {
+            // Random bytes, behind a valid header half of the time
+            let mut buf = match rng.below(2) {
+                0 => b"II*\0".to_vec(),
+                _ => Vec::new(),
+            };
+            buf.extend((0..rng.below(64)).map(|_| rng.next() as u8));
+            parse_all(&buf);
+        }
+    }
+}
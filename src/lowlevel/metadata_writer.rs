@@ -1,9 +1,20 @@
-use crate::lowlevel::{header::write_header, ifd::IFD};
-use byteorder::{ByteOrder, WriteBytesExt};
-use failure::Fallible;
-use std::io::{Seek, SeekFrom};
+use crate::errors::{FieldExtractionError, IFDError, TiffError};
+use crate::lowlevel::{
+    header::{write_bigtiff_header, write_header, Endianness, TiffHeader},
+    ifd::IFD,
+    raw_ifd::{RawIFD, HEADER_SIZE},
+    raw_ifd64::RawIFD64,
+    tiff_writer::WriterOptions,
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 
+/// Position of the first IFD pointer within a classic TIFF header.
+const FIRST_IFD_POINTER_POSITION: u64 = 4;
+
 /// A TIFF metadata (header/IFD) writer.
 pub struct MetadataWriter<E: ByteOrder> {
     /// File position of the last written IFD pointer
@@ -14,12 +25,12 @@ pub struct MetadataWriter<E: ByteOrder> {
 impl<E: ByteOrder> MetadataWriter<E> {
     /// Create a new MetadataWriter, writing a header to `writer`.
     /// Note: Assumes the cursor is in a position ready for writing the new file.
-    pub fn write_header<W: WriteBytesExt + Seek>(writer: &mut W) -> Fallible<Self> {
+    pub fn write_header<W: WriteBytesExt + Seek>(writer: &mut W) -> Result<Self, TiffError> {
         // Write the header
         write_header::<E, _>(writer)?;
 
         // Write zero for the first IFD pointer, and remember where you were
-        let last_ifd_pointer_position = writer.seek(SeekFrom::Current(0))?;
+        let last_ifd_pointer_position = writer.stream_position()?;
         writer.write_u32::<E>(0)?;
 
         Ok(Self {
@@ -35,31 +46,88 @@ impl<E: ByteOrder> MetadataWriter<E> {
         &mut self,
         ifd: &IFD,
         writer: &mut W,
-    ) -> Fallible<u64> {
+    ) -> Result<u64, TiffError> {
         // Write out the ifd's long-form data
         let raw_ifd = ifd.write_to::<E, _>(writer)?;
 
+        // Write the IFD describing the data into the file
+        self.write_raw_ifd(&raw_ifd, writer)
+    }
+
+    /// Write a single raw IFD into `writer` along with the data for its fields, keyed by tag in
+    /// `field_data`. Data small enough to fit is packed into its field's `value_or_offset`, and
+    /// the rest is laid out after the IFD with each field's offset set to match. The IFD and each
+    /// piece of data begin on a word (even byte) boundary, as TIFF requires, and fields without
+    /// an entry in `field_data` are written verbatim. Fails if data is supplied for a tag not in
+    /// `raw_ifd`, or whose length doesn't match its field's type and count. Note: the cursor
+    /// shall be advanced to a position after the IFD and data, ready for another write. Returns
+    /// the position within the file of the beginning of the IFD just written.
+    pub fn write_raw_ifd_with_data<W: WriteBytesExt + Seek>(
+        &mut self,
+        raw_ifd: &RawIFD,
+        field_data: &HashMap<u16, Vec<u8>>,
+        writer: &mut W,
+    ) -> Result<u64, TiffError> {
+        self.write_raw_ifd_with_options(raw_ifd, field_data, WriterOptions::default(), writer)
+    }
+
+    /// Write a single raw IFD into `writer` along with the data for its fields, as for
+    /// `write_raw_ifd_with_data`, but with the layout controlled by `options`. Unless
+    /// `options.align_values` is set, neither the IFD nor its data are padded to a word boundary.
+    pub fn write_raw_ifd_with_options<W: WriteBytesExt + Seek>(
+        &mut self,
+        raw_ifd: &RawIFD,
+        field_data: &HashMap<u16, Vec<u8>>,
+        options: WriterOptions,
+        writer: &mut W,
+    ) -> Result<u64, TiffError> {
+        let mut position = writer.stream_position()?;
+        let placed = place_field_data::<E>(raw_ifd, field_data, position, options.align_values)?;
+
+        write_padding(writer, &mut position, placed.position)?;
+        self.write_raw_ifd(&placed.raw_ifd, writer)?;
+        position = writer.stream_position()?;
+        for (data_position, data) in placed.data {
+            write_padding(writer, &mut position, data_position)?;
+            writer.write_all(data)?;
+            position += data.len() as u64;
+        }
+
+        Ok(placed.position)
+    }
+
+    /// Write a single raw IFD into `writer` at the current position, and link it to the
+    /// previously written IFD (or the header). Any long-form data the IFD points to must already
+    /// have been written, and may be anywhere in the file. Note: the cursor shall be advanced to
+    /// a position after the IFD, ready for another write. Returns the position within the file of
+    /// the beginning of the IFD just written.
+    pub fn write_raw_ifd<W: WriteBytesExt + Seek>(
+        &mut self,
+        raw_ifd: &RawIFD,
+        writer: &mut W,
+    ) -> Result<u64, TiffError> {
         // Save the current cursor position as it will become the pointer to the next IFD
-        let ifd_table_position = writer.seek(SeekFrom::Current(0))?;
+        let ifd_table_position = writer.stream_position()?;
+        let ifd_table_offset = classic_offset(ifd_table_position)?;
 
-        // Write the IFD describing the data into the file
+        // Write the IFD into the file
         raw_ifd.write_to::<E, _>(writer)?;
 
         // Create a pointer to the 'next IFD' pointer
-        let next_ifd_table_pointer_position = writer.seek(SeekFrom::Current(0))?;
+        let next_ifd_table_pointer_position = writer.stream_position()?;
 
         // Write zero to that pointer for now
         writer.write_u32::<E>(0)?;
 
         // Save the position after the end of the table to restore it so this function seems to
-        // write only the table and data sequentially
-        let position_after_table = writer.seek(SeekFrom::Current(0))?;
+        // write only the table sequentially
+        let position_after_table = writer.stream_position()?;
 
         // Seek to the last pointer
         let _ = writer.seek(SeekFrom::Start(self.last_ifd_pointer_position));
 
         // Write the position of the IFD we just wrote to it
-        writer.write_u32::<E>(ifd_table_position as u32)?;
+        writer.write_u32::<E>(ifd_table_offset)?;
 
         // Save the pointer to the 'next IFD' in our struct
         self.last_ifd_pointer_position = next_ifd_table_pointer_position;
@@ -70,3 +138,524 @@ impl<E: ByteOrder> MetadataWriter<E> {
         Ok(ifd_table_position)
     }
 }
+
+/// A BigTIFF metadata (header/IFD) writer.
+pub struct MetadataWriter64<E: ByteOrder> {
+    /// File position of the last written IFD pointer
+    last_ifd_pointer_position: u64,
+    _phantomdata: PhantomData<E>,
+}
+
+impl<E: ByteOrder> MetadataWriter64<E> {
+    /// Create a new MetadataWriter64, writing a BigTIFF header to `writer`.
+    /// Note: Assumes the cursor is in a position ready for writing the new file.
+    pub fn write_header<W: WriteBytesExt + Seek>(writer: &mut W) -> Result<Self, TiffError> {
+        // Write the header
+        write_bigtiff_header::<E, _>(writer)?;
+
+        // Write zero for the first IFD pointer, and remember where you were
+        let last_ifd_pointer_position = writer.stream_position()?;
+        writer.write_u64::<E>(0)?;
+
+        Ok(Self {
+            last_ifd_pointer_position,
+            _phantomdata: PhantomData,
+        })
+    }
+
+    /// Write a single raw BigTIFF IFD into `writer` at the current position, and link it to the
+    /// previously written IFD (or the header). Any long-form data the IFD points to must already
+    /// have been written, and may be anywhere in the file. Note: the cursor shall be advanced to
+    /// a position after the IFD, ready for another write. Returns the position within the file of
+    /// the beginning of the IFD just written.
+    pub fn write_raw_ifd<W: WriteBytesExt + Seek>(
+        &mut self,
+        raw_ifd: &RawIFD64,
+        writer: &mut W,
+    ) -> Result<u64, TiffError> {
+        let ifd_table_position = writer.stream_position()?;
+        raw_ifd.write_to::<E, _>(writer)?;
+
+        // Terminate the chain here for now
+        let next_ifd_table_pointer_position = writer.stream_position()?;
+        writer.write_u64::<E>(0)?;
+        let position_after_table = writer.stream_position()?;
+
+        // Link the previous IFD to this one
+        writer.seek(SeekFrom::Start(self.last_ifd_pointer_position))?;
+        writer.write_u64::<E>(ifd_table_position)?;
+        self.last_ifd_pointer_position = next_ifd_table_pointer_position;
+
+        writer.seek(SeekFrom::Start(position_after_table))?;
+        Ok(ifd_table_position)
+    }
+}
+
+/// Append an IFD, along with the data for its fields keyed by tag in `field_data`, to the end of
+/// the existing classic TIFF `file`, making it the last page. The chain of IFDs is walked to its
+/// end, and only the terminating 'next IFD' pointer found there is overwritten, to point at the
+/// new IFD; the rest of the file is left untouched. The new IFD's fields are sorted by tag, as
+/// TIFF requires, and it terminates the chain in turn. Fails with `TiffError::UnexpectedBigTiff`
+/// if `file` is a BigTIFF, or `TiffError::ByteOrderMismatch` if its byte order isn't `E`, before
+/// anything is written. Fails too if a tag appears more than once, or the chain loops and so has
+/// no end. Returns the position within the file of the new IFD.
+pub fn append_ifd<E: ByteOrder, F: ReadBytesExt + WriteBytesExt + Seek>(
+    file: &mut F,
+    raw_ifd: &RawIFD,
+    field_data: &HashMap<u16, Vec<u8>>,
+) -> Result<u64, TiffError> {
+    let mut sorted = raw_ifd.clone();
+    sorted.sort_entries();
+    sorted.validate()?;
+
+    file.seek(SeekFrom::Start(0))?;
+    let header = TiffHeader::read_from(file)?;
+    if header.is_bigtiff {
+        return Err(TiffError::UnexpectedBigTiff);
+    }
+    if header.endianness != Endianness::of::<E>() {
+        return Err(TiffError::ByteOrderMismatch {
+            file: header.endianness,
+        });
+    }
+
+    // Follow the chain from the header's first IFD pointer to the one holding zero
+    let mut pointer_position = FIRST_IFD_POINTER_POSITION;
+    let mut visited = HashSet::new();
+    loop {
+        file.seek(SeekFrom::Start(pointer_position))?;
+        let offset = u64::from(file.read_u32::<E>()?);
+        if offset == 0 {
+            break;
+        }
+        if offset < HEADER_SIZE {
+            return Err(IFDError::OffsetInHeader { offset }.into());
+        }
+        if !visited.insert(offset) {
+            return Err(IFDError::ChainLoop { offset }.into());
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let entry_count = u64::from(file.read_u16::<E>()?);
+        pointer_position = offset + 2 + 12 * entry_count;
+    }
+
+    file.seek(SeekFrom::End(0))?;
+    let mut writer = MetadataWriter::<E> {
+        last_ifd_pointer_position: pointer_position,
+        _phantomdata: PhantomData,
+    };
+    writer.write_raw_ifd_with_data(&sorted, field_data, file)
+}
+
+/// Overwrite the `value_or_offset` of the `tag` field in the IFD at `ifd_offset` in `file` with
+/// `new_value`, changing nothing else. `new_value` must already be in the byte order `E`, and
+/// left-justified if the value is shorter than four bytes. Fails if the IFD has no such field,
+/// or its value is stored out-of-line, since `value_or_offset` is then an offset rather than
+/// the value.
+pub fn patch_inline_value<E: ByteOrder, F: ReadBytesExt + WriteBytesExt + Seek>(
+    file: &mut F,
+    ifd_offset: u64,
+    tag: u16,
+    new_value: [u8; 4],
+) -> Result<(), TiffError> {
+    file.seek(SeekFrom::Start(ifd_offset))?;
+    let raw_ifd = RawIFD::read_from::<E, F>(file)?;
+    let (idx, field) = raw_ifd
+        .iter()
+        .enumerate()
+        .find(|(_, field)| field.tag == tag)
+        .ok_or(FieldExtractionError::MissingTag { tag })?;
+    let is_inline = field.is_inline().ok_or(TiffError::UnknownTagType {
+        tag_type: field.tag_type,
+    })?;
+    if !is_inline {
+        return Err(IFDError::ValueNotInline { tag }.into());
+    }
+
+    // Skip the entry count, preceding fields, and this field's tag, type and count
+    file.seek(SeekFrom::Start(ifd_offset + 2 + 12 * idx as u64 + 8))?;
+    file.write_all(&new_value)?;
+    Ok(())
+}
+
+/// Where an IFD and the data for its fields go within a file, from `place_field_data`.
+pub(crate) struct PlacedIFD<'a> {
+    /// Position of the IFD itself
+    pub position: u64,
+
+    /// The IFD, with its fields pointing at their data
+    pub raw_ifd: RawIFD,
+
+    /// Position of each piece of data after the IFD, in order
+    pub data: Vec<(u64, &'a [u8])>,
+
+    /// Position just after the IFD and its data
+    pub end: u64,
+}
+
+/// Lay out an IFD to be written at `position`, followed by the data for its fields keyed by tag
+/// in `field_data`, as `MetadataWriter::write_raw_ifd_with_data` does. If `align` is set, the IFD
+/// and each piece of out-of-line data are moved up to the next word boundary; otherwise they are
+/// packed with no padding between them. Fails if `raw_ifd` has too many fields, or data is
+/// supplied for a tag not in `raw_ifd` or of the wrong length.
+pub(crate) fn place_field_data<'a, E: ByteOrder>(
+    raw_ifd: &RawIFD,
+    field_data: &'a HashMap<u16, Vec<u8>>,
+    position: u64,
+    align: bool,
+) -> Result<PlacedIFD<'a>, TiffError> {
+    raw_ifd.check_entry_count()?;
+    for (&tag, data) in field_data {
+        let field = raw_ifd.get(tag).ok_or(IFDError::DataWithoutField { tag })?;
+        if let Some(expected) = field.value_byte_len() {
+            if expected != data.len() {
+                return Err(IFDError::DataLengthMismatch {
+                    tag,
+                    expected,
+                    actual: data.len(),
+                }
+                .into());
+            }
+        }
+    }
+
+    // The IFD itself is 2 bytes of length, 12 per field, and the 4 byte next IFD pointer
+    let padding = |position: u64| if align { position % 2 } else { 0 };
+    let ifd_table_position = position + padding(position);
+    let mut data_position = ifd_table_position + 2 + 12 * raw_ifd.len() as u64 + 4;
+
+    // Place the data, pointing the fields at it
+    let mut raw_ifd = raw_ifd.clone();
+    let mut data_layout = Vec::new();
+    for field in raw_ifd.iter_mut() {
+        let data = match field_data.get(&field.tag) {
+            Some(data) => data,
+            None => continue,
+        };
+        if data.len() <= field.value_or_offset.len() {
+            field.value_or_offset = [0; 4];
+            field.value_or_offset[..data.len()].copy_from_slice(data);
+        } else {
+            data_position += padding(data_position);
+            E::write_u32(&mut field.value_or_offset, classic_offset(data_position)?);
+            data_layout.push((data_position, data.as_slice()));
+            data_position += data.len() as u64;
+        }
+    }
+    Ok(PlacedIFD {
+        position: ifd_table_position,
+        raw_ifd,
+        data: data_layout,
+        end: data_position,
+    })
+}
+
+/// Narrow `position` to an offset within a classic TIFF, failing with
+/// `TiffError::OffsetOverflow` if it lies beyond what 32 bits can address.
+pub(crate) fn classic_offset(position: u64) -> Result<u32, TiffError> {
+    u32::try_from(position).map_err(|_| TiffError::OffsetOverflow { position })
+}
+
+/// Write zeroes into `writer` to advance `position` to `target`.
+pub(crate) fn write_padding<W: Write>(
+    writer: &mut W,
+    position: &mut u64,
+    target: u64,
+) -> Result<(), TiffError> {
+    while *position < target {
+        writer.write_all(&[0])?;
+        *position += 1;
+    }
+    Ok(())
+}
+
+/// Write a chain of IFDs into `writer`, linking each to the position the next one was actually
+/// written at and terminating the chain with zero. The entries are written verbatim, so any
+/// long-form data they point to must already be in place; see `MetadataWriter` for
+/// interleaving data with the IFDs. Each IFD begins on a word boundary, with a byte of padding
+/// before it if necessary. Returns the position within the file of the first IFD, for use as
+/// the `TiffHeader`'s first IFD offset. Fails if `ifds` is empty, or an IFD would begin past
+/// 4 GiB.
+pub fn write_raw_ifds<E: ByteOrder, W: WriteBytesExt + Seek>(
+    writer: &mut W,
+    ifds: &[RawIFD],
+) -> Result<u64, TiffError> {
+    if ifds.is_empty() {
+        return Err(IFDError::NoIFDs.into());
+    }
+    let mut offsets = Vec::with_capacity(ifds.len());
+    let mut next_pointer_positions = Vec::with_capacity(ifds.len());
+    for ifd in ifds {
+        let mut position = writer.stream_position()?;
+        let target = position + position % 2;
+        write_padding(writer, &mut position, target)?;
+        offsets.push(classic_offset(position)?);
+        ifd.write_to::<E, _>(writer)?;
+        next_pointer_positions.push(writer.stream_position()?);
+        writer.write_u32::<E>(0)?;
+    }
+    let position_after_chain = writer.stream_position()?;
+
+    // Patch each 'next IFD' pointer with the offset the following IFD was written at
+    for (pointer_position, next_offset) in next_pointer_positions.iter().zip(&offsets[1..]) {
+        writer.seek(SeekFrom::Start(*pointer_position))?;
+        writer.write_u32::<E>(*next_offset)?;
+    }
+    writer.seek(SeekFrom::Start(position_after_chain))?;
+
+    Ok(u64::from(offsets[0]))
+}
+
+/// Write a chain of IFDs into `writer` in little-endian byte order, as `write_raw_ifds` does.
+pub fn write_raw_ifds_le<W: WriteBytesExt + Seek>(
+    writer: &mut W,
+    ifds: &[RawIFD],
+) -> Result<u64, TiffError> {
+    write_raw_ifds::<LittleEndian, W>(writer, ifds)
+}
+
+/// Write a chain of IFDs into `writer` in big-endian byte order, as `write_raw_ifds` does.
+pub fn write_raw_ifds_be<W: WriteBytesExt + Seek>(
+    writer: &mut W,
+    ifds: &[RawIFD],
+) -> Result<u64, TiffError> {
+    write_raw_ifds::<BigEndian, W>(writer, ifds)
+}
+
+/// Write a chain of BigTIFF IFDs into `writer`, linking each to the position the next one was
+/// actually written at and terminating the chain with zero. The entries are written verbatim,
+/// so any long-form data they point to must already be in place; see `MetadataWriter64` for
+/// interleaving data with the IFDs. Returns the position within the file of the first IFD, for
+/// use as the `TiffHeader`'s first IFD offset. Fails if `ifds` is empty.
+pub fn write_raw_ifds_64<E: ByteOrder, W: WriteBytesExt + Seek>(
+    writer: &mut W,
+    ifds: &[RawIFD64],
+) -> Result<u64, TiffError> {
+    if ifds.is_empty() {
+        return Err(IFDError::NoIFDs.into());
+    }
+    let first_ifd_position = writer.stream_position()?;
+    let mut positions = Vec::with_capacity(ifds.len());
+    let mut next_pointer_positions = Vec::with_capacity(ifds.len());
+    for ifd in ifds {
+        positions.push(writer.stream_position()?);
+        ifd.write_to::<E, _>(writer)?;
+        next_pointer_positions.push(writer.stream_position()?);
+        writer.write_u64::<E>(0)?;
+    }
+    let position_after_chain = writer.stream_position()?;
+
+    // Patch each 'next IFD' pointer with the position the following IFD was written at
+    for (pointer_position, next_position) in next_pointer_positions.iter().zip(&positions[1..]) {
+        writer.seek(SeekFrom::Start(*pointer_position))?;
+        writer.write_u64::<E>(*next_position)?;
+    }
+    writer.seek(SeekFrom::Start(position_after_chain))?;
+
+    Ok(first_ifd_position)
+}
+
+/// A sink which discards what is written to it, but tracks the position as if it hadn't,
+/// starting from `position`, for testing what happens near the 4 GiB limit of a classic TIFF.
+#[cfg(test)]
+pub(crate) struct Sink {
+    pub(crate) position: u64,
+}
+
+#[cfg(test)]
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl Seek for Sink {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        if let SeekFrom::Start(position) = pos {
+            self.position = position;
+        }
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::tags;
+    use crate::lowlevel::{
+        read_ifds_auto, read_raw_ifds_with_offsets, RawIFD, RawIFDField, RawIFDFieldBuilder,
+    };
+    use std::io::Cursor;
+
+    fn ifd(tags: &[u16]) -> RawIFD {
+        RawIFD {
+            entries: tags
+                .iter()
+                .map(|&tag| RawIFDField::pack_inline_u16::<LittleEndian>(tag, 3, &[1]).unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn write_raw_ifds_rejects_empty_chain() {
+        let mut writer = Cursor::new(Vec::new());
+        assert!(matches!(
+            write_raw_ifds_le(&mut writer, &[]),
+            Err(TiffError::IFD(IFDError::NoIFDs))
+        ));
+    }
+
+    #[test]
+    fn write_raw_ifds_64_rejects_empty_chain() {
+        let mut writer = Cursor::new(Vec::new());
+        assert!(matches!(
+            write_raw_ifds_64::<LittleEndian, _>(&mut writer, &[]),
+            Err(TiffError::IFD(IFDError::NoIFDs))
+        ));
+    }
+
+    #[test]
+    fn write_raw_ifds_aligns_and_links_ifds() {
+        let mut writer = Cursor::new(vec![0; 9]);
+        writer.set_position(9);
+        let ifds = vec![ifd(&[256]), ifd(&[256, 257])];
+        let first = write_raw_ifds_le(&mut writer, &ifds).unwrap();
+        assert_eq!(first, 10);
+
+        let mut reader = Cursor::new(writer.into_inner());
+        let read = read_raw_ifds_with_offsets::<LittleEndian, _>(&mut reader, first).unwrap();
+        assert_eq!(read, vec![(10, ifds[0].clone()), (28, ifds[1].clone())]);
+    }
+
+    /// A classic TIFF in the byte order `E` holding a single IFD with one field.
+    fn one_page<E: ByteOrder>() -> Cursor<Vec<u8>> {
+        let mut file = Cursor::new(Vec::new());
+        let mut writer = MetadataWriter::<E>::write_header(&mut file).unwrap();
+        writer
+            .write_raw_ifd_with_data(&ifd(&[256]), &HashMap::new(), &mut file)
+            .unwrap();
+        file
+    }
+
+    #[test]
+    fn append_ifd_twice_keeps_existing_pages() {
+        let mut file = one_page::<LittleEndian>();
+        let original = file.get_ref().clone();
+        let mut field_data = HashMap::new();
+        field_data.insert(305, b"appended\0".to_vec());
+        let mut software = ifd(&[256, 257]);
+        software.entries.push(RawIFDField {
+            tag: 305,
+            tag_type: 2,
+            count: 9,
+            value_or_offset: [0; 4],
+        });
+
+        let second =
+            append_ifd::<LittleEndian, _>(&mut file, &ifd(&[257]), &HashMap::new()).unwrap();
+        let third = append_ifd::<LittleEndian, _>(&mut file, &software, &field_data).unwrap();
+        assert!(third > second && second >= original.len() as u64);
+
+        let appended = file.into_inner();
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(&appended)).unwrap();
+        let ifds = ifds.into_classic().unwrap();
+        assert_eq!(ifds.len(), 3);
+        assert_eq!(ifds[0], ifd(&[256]));
+        assert_eq!(ifds[1], ifd(&[257]));
+        let value = ifds[2]
+            .get(305)
+            .unwrap()
+            .read_value_bytes::<LittleEndian, _>(&mut Cursor::new(&appended))
+            .unwrap();
+        assert_eq!(value, b"appended\0");
+        // Of the original bytes, only its terminating 'next IFD' pointer changed
+        let unchanged = original.len() - 4;
+        assert_eq!(appended[..unchanged], original[..unchanged]);
+    }
+
+    #[test]
+    fn append_ifd_rejects_other_byte_order() {
+        let mut file = one_page::<BigEndian>();
+        let original = file.get_ref().clone();
+        assert!(matches!(
+            append_ifd::<LittleEndian, _>(&mut file, &ifd(&[257]), &HashMap::new()),
+            Err(TiffError::ByteOrderMismatch {
+                file: Endianness::Big
+            })
+        ));
+        assert_eq!(file.into_inner(), original);
+    }
+
+    #[test]
+    fn append_ifd_rejects_bigtiff() {
+        let mut file = Cursor::new(Vec::new());
+        write_bigtiff_header::<LittleEndian, _>(&mut file).unwrap();
+        file.write_u64::<LittleEndian>(0).unwrap();
+        let original = file.get_ref().clone();
+        assert!(matches!(
+            append_ifd::<LittleEndian, _>(&mut file, &ifd(&[257]), &HashMap::new()),
+            Err(TiffError::UnexpectedBigTiff)
+        ));
+        assert_eq!(file.into_inner(), original);
+    }
+
+    /// An IFD of one field whose 6 bytes of ASCII must be stored out-of-line, with that data.
+    fn ifd_with_blob() -> (RawIFD, HashMap<u16, Vec<u8>>) {
+        let mut ifd = RawIFD {
+            entries: Vec::new(),
+        };
+        let mut blobs = HashMap::new();
+        RawIFDFieldBuilder::<LittleEndian>::ascii(tags::SOFTWARE, "tiffy")
+            .unwrap()
+            .set_into(&mut ifd, &mut blobs);
+        (ifd, blobs)
+    }
+
+    #[test]
+    fn place_field_data_rejects_offsets_past_4_gib() {
+        let (ifd, blobs) = ifd_with_blob();
+        let limit = u64::from(u32::MAX);
+
+        // The IFD takes 18 bytes, so its data starts just inside the limit
+        let placed = place_field_data::<LittleEndian>(&ifd, &blobs, limit - 19, false).unwrap();
+        assert_eq!(placed.position, limit - 19);
+        assert_eq!(placed.data[0].0, limit - 1);
+
+        // One word further on, it lands past the limit
+        assert!(matches!(
+            place_field_data::<LittleEndian>(&ifd, &blobs, limit - 17, false),
+            Err(TiffError::OffsetOverflow { position }) if position == limit + 1
+        ));
+    }
+
+    #[test]
+    fn write_raw_ifd_rejects_ifds_past_4_gib() {
+        let mut sink = Sink { position: 0 };
+        let mut writer = MetadataWriter::<LittleEndian>::write_header(&mut sink).unwrap();
+
+        // Data pushed past the limit by the IFD before it
+        let (blob_ifd, blobs) = ifd_with_blob();
+        sink.position = u64::from(u32::MAX) - 10;
+        assert!(matches!(
+            writer.write_raw_ifd_with_data(&blob_ifd, &blobs, &mut sink),
+            Err(TiffError::OffsetOverflow {
+                position: 4_294_967_304
+            })
+        ));
+
+        // An IFD itself past the limit, which fails before anything is written
+        let start = u64::from(u32::MAX) + 1;
+        sink.position = start;
+        assert!(matches!(
+            writer.write_raw_ifd(&ifd(&[257]), &mut sink),
+            Err(TiffError::OffsetOverflow { position }) if position == start
+        ));
+        assert_eq!(sink.position, start);
+    }
+}
@@ -1,26 +1,96 @@
 /// Conversions between some primitive types and IFDFieldData
+#[cfg(feature = "std")]
 pub(crate) mod field_conversions;
-pub use field_conversions::*;
+
+/// Fractional field values
+pub(crate) mod rational;
+pub use rational::*;
+
+/// Reading and decoding the values of RawIFDFields
+#[cfg(feature = "std")]
+pub(crate) mod raw_field_values;
+
+/// Constructing RawIFDFields from typed values
+pub(crate) mod raw_field_builder;
+pub use raw_field_builder::*;
 
 /// TIFF metadata reader
+#[cfg(feature = "std")]
 pub(crate) mod metadata_reader;
+#[cfg(feature = "std")]
 pub use metadata_reader::*;
 
+/// Reading IFDs asynchronously, over tokio's AsyncRead and AsyncSeek
+#[cfg(feature = "tokio")]
+pub(crate) mod async_reader;
+#[cfg(feature = "tokio")]
+pub use async_reader::*;
+
+/// Buffering a whole stream in memory so it can be parsed without seeking the source
+#[cfg(feature = "std")]
+pub(crate) mod seekable_buffer;
+#[cfg(feature = "std")]
+pub use seekable_buffer::*;
+
+/// Parsing headers and IFDs directly from byte slices, without copying
+pub(crate) mod slice_reader;
+pub use slice_reader::*;
+
 /// TIFF metadata writer
+#[cfg(feature = "std")]
 pub(crate) mod metadata_writer;
+#[cfg(feature = "std")]
 pub use metadata_writer::*;
 
+/// Writing complete TIFF files
+#[cfg(feature = "std")]
+pub(crate) mod tiff_writer;
+#[cfg(feature = "std")]
+pub use tiff_writer::*;
+
+/// Writing complete TIFF files in one forward pass, with every offset computed up front
+#[cfg(feature = "std")]
+pub(crate) mod planned_writer;
+#[cfg(feature = "std")]
+pub use planned_writer::*;
+
+/// Building complete TIFF files in memory from pages of typed fields
+#[cfg(feature = "std")]
+pub(crate) mod tiff_builder;
+#[cfg(feature = "std")]
+pub use tiff_builder::*;
+
+/// Describing the structure of a whole file, for debugging
+#[cfg(feature = "std")]
+pub(crate) mod dump;
+#[cfg(feature = "std")]
+pub use dump::*;
+
+/// Converting files between classic TIFF and BigTIFF, and compacting them
+#[cfg(feature = "std")]
+pub(crate) mod convert;
+#[cfg(feature = "std")]
+pub use convert::*;
+
 /// Non-tag magic numbers
 pub mod constants;
 
+/// Field data types
+pub(crate) mod tag_type;
+pub use tag_type::*;
+
 /// Header reading/writing
 pub(crate) mod header;
 pub use header::*;
 
 /// High-level IFD abstrations
+#[cfg(feature = "std")]
 pub(crate) mod ifd;
+#[cfg(feature = "std")]
 pub(crate) mod ifd_field;
+#[cfg(feature = "std")]
 pub use ifd::*;
+#[cfg(feature = "std")]
 pub use ifd_field::*;
 
 /// RawIFDs are the non-dereferenced low-level versions of their high-level counterparts -
@@ -28,5 +98,13 @@ pub use ifd_field::*;
 pub(crate) mod raw_ifd;
 pub use raw_ifd::*;
 
+/// BigTIFF counterparts of the RawIFDs, with 64-bit counts and offsets.
+pub(crate) mod raw_ifd64;
+pub use raw_ifd64::*;
+
+/// Traits over both RawIFDs and their BigTIFF counterparts
+pub(crate) mod ifd_like;
+pub use ifd_like::*;
+
 /// Byteorder
 pub use byteorder::{BigEndian, LittleEndian, NativeEndian};
@@ -0,0 +1,316 @@
+use crate::baseline::tags;
+use crate::errors::{IFDError, TiffError};
+use crate::lowlevel::{
+    header::write_header,
+    metadata_writer::{classic_offset, place_field_data, write_padding},
+    raw_field_builder::RawIFDFieldBuilder,
+    raw_ifd::{RawIFD, HEADER_SIZE},
+};
+use byteorder::{ByteOrder, WriteBytesExt};
+use std::collections::HashMap;
+use std::io::Write;
+use std::marker::PhantomData;
+
+/// The position of every IFD, piece of field data and chunk of image data within a classic TIFF
+/// file, computed up front by `plan_layout` so that `write_planned` can emit the file without
+/// seeking.
+#[derive(Debug, Clone)]
+pub struct Layout<E: ByteOrder> {
+    ifds: Vec<PlannedIFD>,
+    file_len: u64,
+    _phantomdata: PhantomData<E>,
+}
+
+/// The image data of a page laid out by `plan_layout`, divided into strips or tiles, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageChunks {
+    /// Strips, located by the StripOffsets and StripByteCounts fields.
+    Strips(Vec<Vec<u8>>),
+    /// Tiles, located by the TileOffsets and TileByteCounts fields.
+    Tiles(Vec<Vec<u8>>),
+}
+
+impl ImageChunks {
+    /// Tags of the fields locating the chunks: their offsets, then their byte counts.
+    fn tags(&self) -> (u16, u16) {
+        match self {
+            ImageChunks::Strips(_) => (tags::STRIP_OFFSETS, tags::STRIP_BYTE_COUNTS),
+            ImageChunks::Tiles(_) => (tags::TILE_OFFSETS, tags::TILE_BYTE_COUNTS),
+        }
+    }
+
+    /// The chunks themselves.
+    fn chunks(&self) -> &[Vec<u8>] {
+        match self {
+            ImageChunks::Strips(chunks) | ImageChunks::Tiles(chunks) => chunks,
+        }
+    }
+}
+
+/// An IFD with its fields pointing at their data, and where it, that data and the image data
+/// before it go.
+#[derive(Debug, Clone)]
+struct PlannedIFD {
+    image_data: Vec<(u64, Vec<u8>)>,
+    position: u64,
+    raw_ifd: RawIFD,
+    next_ifd: u32,
+    data: Vec<(u64, Vec<u8>)>,
+}
+
+impl<E: ByteOrder> Layout<E> {
+    /// Position within the file of the beginning of each IFD, in order.
+    pub fn ifd_positions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.ifds.iter().map(|ifd| ifd.position)
+    }
+
+    /// The IFDs as they will be written, sorted by tag with their fields pointing at their data.
+    pub fn ifds(&self) -> impl Iterator<Item = &RawIFD> + '_ {
+        self.ifds.iter().map(|ifd| &ifd.raw_ifd)
+    }
+
+    /// Total length in bytes of the file `write_planned` will write.
+    pub fn file_len(&self) -> u64 {
+        self.file_len
+    }
+}
+
+/// Lay out a classic TIFF file holding a chain of `ifds`, in order, without writing anything.
+/// `blobs[i]` holds the data for the fields of `ifds[i]`, keyed by tag, as for
+/// `TiffWriter::add_ifd`, and `images[i]` holds its strips or tiles of image data; IFDs past
+/// the end of either have none. Each page's chunks of image data are placed just before its
+/// IFD, each on a word boundary as `TiffWriter::write_data` places them, and the offsets and
+/// byte counts fields locating them are set to match, replacing any in the IFD, as
+/// `TiffBuilder` does. Each IFD is then sorted by tag and placed, with its data after it,
+/// exactly where `TiffWriter` would put it with the default `WriterOptions`, so the file
+/// `write_planned` emits is byte-for-byte the same. Fails if `ifds` is empty, if any chunk
+/// would lie beyond what a classic TIFF can address, or for the same reasons as
+/// `TiffWriter::add_ifd`.
+pub fn plan_layout<E: ByteOrder>(
+    ifds: &[RawIFD],
+    blobs: &[HashMap<u16, Vec<u8>>],
+    images: &[ImageChunks],
+) -> Result<Layout<E>, TiffError> {
+    if ifds.is_empty() {
+        return Err(IFDError::NoIFDs.into());
+    }
+
+    let no_data = HashMap::new();
+    let mut planned: Vec<PlannedIFD> = Vec::with_capacity(ifds.len());
+    let mut position = HEADER_SIZE;
+    for (index, raw_ifd) in ifds.iter().enumerate() {
+        let mut sorted = raw_ifd.clone();
+        let mut field_data = blobs.get(index).unwrap_or(&no_data).clone();
+
+        let mut image_data = Vec::new();
+        if let Some(image) = images.get(index) {
+            let chunks = image.chunks();
+            let mut offsets = Vec::with_capacity(chunks.len());
+            let mut byte_counts = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                position += position % 2;
+                offsets.push(classic_offset(position)?);
+                // The whole chunk must lie within reach of a classic offset
+                classic_offset(position + chunk.len() as u64)?;
+                byte_counts.push(chunk.len() as u32);
+                image_data.push((position, chunk.clone()));
+                position += chunk.len() as u64;
+            }
+            let (offsets_tag, byte_counts_tag) = image.tags();
+            RawIFDFieldBuilder::<E>::long(offsets_tag, &offsets)?
+                .set_into(&mut sorted, &mut field_data);
+            RawIFDFieldBuilder::<E>::long(byte_counts_tag, &byte_counts)?
+                .set_into(&mut sorted, &mut field_data);
+        }
+        sorted.sort_entries();
+        sorted.validate()?;
+
+        let placed = place_field_data::<E>(&sorted, &field_data, position, true)?;
+        if let Some(previous) = planned.last_mut() {
+            previous.next_ifd = classic_offset(placed.position)?;
+        }
+        planned.push(PlannedIFD {
+            image_data,
+            position: placed.position,
+            raw_ifd: placed.raw_ifd,
+            next_ifd: 0,
+            data: placed
+                .data
+                .into_iter()
+                .map(|(data_position, data)| (data_position, data.to_vec()))
+                .collect(),
+        });
+        position = placed.end;
+    }
+
+    Ok(Layout {
+        ifds: planned,
+        file_len: position,
+        _phantomdata: PhantomData,
+    })
+}
+
+/// Write the file planned by `plan_layout` to `writer` in a single forward pass, so `writer`
+/// needn't support seeking. Note: Assumes the cursor is at the beginning of the new file.
+pub fn write_planned<E: ByteOrder, W: Write>(
+    writer: &mut W,
+    layout: &Layout<E>,
+) -> Result<(), TiffError> {
+    write_header::<E, _>(writer)?;
+    let first_ifd = layout
+        .ifds
+        .first()
+        .map_or(Ok(0), |ifd| classic_offset(ifd.position))?;
+    writer.write_u32::<E>(first_ifd)?;
+
+    let mut position = HEADER_SIZE;
+    for ifd in &layout.ifds {
+        for (chunk_position, chunk) in &ifd.image_data {
+            write_padding(writer, &mut position, *chunk_position)?;
+            writer.write_all(chunk)?;
+            position += chunk.len() as u64;
+        }
+        write_padding(writer, &mut position, ifd.position)?;
+        ifd.raw_ifd.write_to::<E, _>(writer)?;
+        writer.write_u32::<E>(ifd.next_ifd)?;
+        position += 2 + 12 * ifd.raw_ifd.len() as u64 + 4;
+        for (data_position, data) in &ifd.data {
+            write_padding(writer, &mut position, *data_position)?;
+            writer.write_all(data)?;
+            position += data.len() as u64;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::decode::decode_gray8;
+    use crate::lowlevel::{read_ifds_auto, Endianness, LittleEndian, TiffWriter};
+    use std::io::Cursor;
+
+    /// A grayscale page of 3 x 3 pixels in rows of one strip each, with its field data.
+    fn page() -> (RawIFD, HashMap<u16, Vec<u8>>) {
+        let mut ifd = RawIFD {
+            entries: Vec::new(),
+        };
+        let mut blobs = HashMap::new();
+        for (tag, value) in [
+            (tags::IMAGE_WIDTH, 3),
+            (tags::IMAGE_LENGTH, 3),
+            (tags::BITS_PER_SAMPLE, 8),
+            (tags::PHOTOMETRIC_INTERPRETATION, 1),
+            (tags::ROWS_PER_STRIP, 1),
+        ] {
+            RawIFDFieldBuilder::<LittleEndian>::short(tag, &[value])
+                .unwrap()
+                .set_into(&mut ifd, &mut blobs);
+        }
+        RawIFDFieldBuilder::<LittleEndian>::ascii(tags::SOFTWARE, "tiffy")
+            .unwrap()
+            .set_into(&mut ifd, &mut blobs);
+        (ifd, blobs)
+    }
+
+    fn strips() -> Vec<Vec<u8>> {
+        vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]
+    }
+
+    fn write(layout: &Layout<LittleEndian>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_planned(&mut buf, layout).unwrap();
+        assert_eq!(buf.len() as u64, layout.file_len());
+        buf
+    }
+
+    #[test]
+    fn planned_strips_match_seeking_writer() {
+        let (ifd, blobs) = page();
+        let layout = plan_layout::<LittleEndian>(
+            &[ifd.clone(), ifd.clone()],
+            &[blobs.clone(), blobs.clone()],
+            &[ImageChunks::Strips(strips()), ImageChunks::Strips(strips())],
+        )
+        .unwrap();
+        let planned = write(&layout);
+
+        let mut writer =
+            TiffWriter::write_header(Cursor::new(Vec::new()), Endianness::Little).unwrap();
+        for _ in 0..2 {
+            let (mut ifd, mut blobs) = page();
+            let offsets = strips()
+                .iter()
+                .map(|strip| writer.write_data(strip).unwrap() as u32)
+                .collect::<Vec<_>>();
+            RawIFDFieldBuilder::<LittleEndian>::long(tags::STRIP_OFFSETS, &offsets)
+                .unwrap()
+                .set_into(&mut ifd, &mut blobs);
+            RawIFDFieldBuilder::<LittleEndian>::long(tags::STRIP_BYTE_COUNTS, &[3, 3, 3])
+                .unwrap()
+                .set_into(&mut ifd, &mut blobs);
+            writer.add_ifd(&ifd, &blobs).unwrap();
+        }
+        assert_eq!(planned, writer.finish().unwrap().into_inner());
+    }
+
+    #[test]
+    fn planned_strips_decode() {
+        let (mut ifd, mut blobs) = page();
+        // Stale offsets are replaced by those of the planned strips
+        RawIFDFieldBuilder::<LittleEndian>::long(tags::STRIP_OFFSETS, &[1, 2, 3])
+            .unwrap()
+            .set_into(&mut ifd, &mut blobs);
+        let layout =
+            plan_layout::<LittleEndian>(&[ifd], &[blobs], &[ImageChunks::Strips(strips())])
+                .unwrap();
+        let buf = write(&layout);
+
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+        let ifds = ifds.into_classic().unwrap();
+        let decoded = decode_gray8::<LittleEndian, _>(&mut Cursor::new(&buf), &ifds[0]).unwrap();
+        assert_eq!(decoded, (3, 3, (1..=9).collect()));
+    }
+
+    #[test]
+    fn planned_chunks_are_word_aligned() {
+        let (ifd, blobs) = page();
+        let layout = plan_layout::<LittleEndian>(
+            &[ifd],
+            &[blobs],
+            &[ImageChunks::Tiles(vec![vec![1; 3], vec![2; 5]])],
+        )
+        .unwrap();
+        let buf = write(&layout);
+
+        let (_, ifds) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+        let ifds = ifds.into_classic().unwrap();
+        let read_longs = |tag| {
+            ifds[0]
+                .get(tag)
+                .unwrap()
+                .as_u32_array::<LittleEndian, _>(&mut Cursor::new(&buf))
+                .unwrap()
+        };
+        assert_eq!(read_longs(tags::TILE_OFFSETS), [8, 12]);
+        assert_eq!(read_longs(tags::TILE_BYTE_COUNTS), [3, 5]);
+        assert!(ifds[0].get(tags::STRIP_OFFSETS).is_none());
+        assert_eq!(&buf[8..18], &[1, 1, 1, 0, 2, 2, 2, 2, 2, 0]);
+        assert_eq!(layout.ifd_positions().collect::<Vec<_>>(), [18]);
+    }
+
+    #[test]
+    fn pages_without_image_data_have_no_chunks() {
+        let (ifd, blobs) = page();
+        let layout = plan_layout::<LittleEndian>(&[ifd], &[blobs], &[]).unwrap();
+        assert_eq!(layout.ifd_positions().collect::<Vec<_>>(), [8]);
+        assert!(layout
+            .ifds()
+            .all(|ifd| ifd.get(tags::STRIP_OFFSETS).is_none()));
+        assert!(matches!(
+            plan_layout::<LittleEndian>(&[], &[], &[]),
+            Err(TiffError::IFD(IFDError::NoIFDs))
+        ));
+    }
+}
@@ -0,0 +1,39 @@
+/// An unsigned fraction, as stored in RATIONAL fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl Rational {
+    /// The value of this fraction. A zero denominator gives infinity or NaN.
+    pub fn as_f64(self) -> f64 {
+        f64::from(self.num) / f64::from(self.den)
+    }
+}
+
+impl From<(u32, u32)> for Rational {
+    fn from((num, den): (u32, u32)) -> Self {
+        Self { num, den }
+    }
+}
+
+/// A signed fraction, as stored in SRATIONAL fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SRational {
+    pub num: i32,
+    pub den: i32,
+}
+
+impl SRational {
+    /// The value of this fraction. A zero denominator gives infinity or NaN.
+    pub fn as_f64(self) -> f64 {
+        f64::from(self.num) / f64::from(self.den)
+    }
+}
+
+impl From<(i32, i32)> for SRational {
+    fn from((num, den): (i32, i32)) -> Self {
+        Self { num, den }
+    }
+}
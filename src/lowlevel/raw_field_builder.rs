@@ -0,0 +1,385 @@
+use crate::errors::{IFDError, TiffError};
+use crate::lowlevel::{
+    raw_ifd::{RawIFD, RawIFDField},
+    tag_type::TagType,
+};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use byteorder::ByteOrder;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// A RawIFDField built from typed values, along with the encoded value if it is too large to be
+/// stored inline and must be placed elsewhere in the file.
+#[derive(Debug, Clone)]
+pub struct BuiltField {
+    /// The field. If `data` is present, `value_or_offset` is zero until an offset is assigned.
+    pub field: RawIFDField,
+    /// The encoded value which doesn't fit within `value_or_offset`, if any.
+    pub data: Option<Vec<u8>>,
+}
+
+impl BuiltField {
+    /// Insert the field into `raw_ifd` at the position that keeps a sorted IFD sorted, replacing
+    /// any field with the same tag, as `RawIFD::set_field` does. Its data (if any) is added to
+    /// `field_data`, ready for `MetadataWriter::write_raw_ifd_with_data`, and any data for the
+    /// field replaced is removed.
+    #[cfg(feature = "std")]
+    pub fn insert_into(self, raw_ifd: &mut RawIFD, field_data: &mut HashMap<u16, Vec<u8>>) {
+        self.set_into(raw_ifd, field_data);
+    }
+
+    /// Set the field in `raw_ifd` as `RawIFD::set` does, replacing any field with the same tag,
+    /// and update `field_data` to match: its data is added if it isn't inline, and any data for
+    /// the field replaced is removed otherwise.
+    #[cfg(feature = "std")]
+    pub fn set_into(self, raw_ifd: &mut RawIFD, field_data: &mut HashMap<u16, Vec<u8>>) {
+        let tag = self.field.tag;
+        match raw_ifd.set(self) {
+            Some(data) => field_data.insert(tag, data),
+            None => field_data.remove(&tag),
+        };
+    }
+}
+
+impl RawIFD {
+    /// Insert the field of `built` as `set_field` does, replacing any field with the same tag.
+    /// Returns the field's data if it isn't inline, which must be placed in the file with the
+    /// field pointing at it, such as by `MetadataWriter::write_raw_ifd_with_data`.
+    pub fn set(&mut self, built: BuiltField) -> Option<Vec<u8>> {
+        self.set_field(built.field);
+        built.data
+    }
+
+    /// Set a BYTE field, as `set` does. Fails as `RawIFDFieldBuilder::byte` does.
+    pub fn set_byte<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        values: &[u8],
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::byte(tag, values)?))
+    }
+
+    /// Set an UNDEFINED field, as `set` does. Fails as `RawIFDFieldBuilder::undefined` does.
+    pub fn set_undefined<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        values: &[u8],
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::undefined(tag, values)?))
+    }
+
+    /// Set an ASCII field holding `text`, as `set` does. Fails as `RawIFDFieldBuilder::ascii` does.
+    pub fn set_ascii<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        text: &str,
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::ascii(tag, text)?))
+    }
+
+    /// Set a SHORT field, as `set` does. Fails as `RawIFDFieldBuilder::short` does.
+    pub fn set_short<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        values: &[u16],
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::short(tag, values)?))
+    }
+
+    /// Set a LONG field, as `set` does. Fails as `RawIFDFieldBuilder::long` does.
+    pub fn set_long<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        values: &[u32],
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::long(tag, values)?))
+    }
+
+    /// Set a RATIONAL field from (numerator, denominator) pairs, as `set` does. Fails as
+    /// `RawIFDFieldBuilder::rational` does.
+    pub fn set_rational<E: ByteOrder>(
+        &mut self,
+        tag: u16,
+        values: &[(u32, u32)],
+    ) -> Result<Option<Vec<u8>>, TiffError> {
+        Ok(self.set(RawIFDFieldBuilder::<E>::rational(tag, values)?))
+    }
+}
+
+impl RawIFDField {
+    /// A field of `tag_type` BYTE, SBYTE, ASCII or UNDEFINED holding `values` inline. Returns
+    /// `None` if there are more than 4 values, or `tag_type` isn't one of a byte.
+    pub fn pack_inline_u8(tag: u16, tag_type: u16, values: &[u8]) -> Option<Self> {
+        Self::pack_inline(tag, tag_type, values.len(), 1, |bytes| {
+            bytes.copy_from_slice(values)
+        })
+    }
+
+    /// A field of `tag_type` SHORT or SSHORT holding `values` inline, encoded in the byte order
+    /// `E`. Returns `None` if there are more than 2 values, or `tag_type` isn't two bytes.
+    pub fn pack_inline_u16<E: ByteOrder>(tag: u16, tag_type: u16, values: &[u16]) -> Option<Self> {
+        Self::pack_inline(tag, tag_type, values.len(), 2, |bytes| {
+            E::write_u16_into(values, bytes)
+        })
+    }
+
+    /// A field of `tag_type` LONG, SLONG, FLOAT or IFD holding a single value inline, encoded in
+    /// the byte order `E`. Returns `None` if there is more than 1 value, or `tag_type` isn't four
+    /// bytes.
+    pub fn pack_inline_u32<E: ByteOrder>(tag: u16, tag_type: u16, values: &[u32]) -> Option<Self> {
+        Self::pack_inline(tag, tag_type, values.len(), 4, |bytes| {
+            E::write_u32_into(values, bytes)
+        })
+    }
+
+    /// A field of `count` values of `tag_type` whose encoded value, `bytes`, is stored
+    /// left-justified in `value_or_offset`. Fails if `tag_type` is unknown, if `bytes` isn't the
+    /// length `count` values of `tag_type` take, or if it is longer than 4 bytes and so can't be
+    /// stored inline.
+    pub fn new_inline(
+        tag: u16,
+        tag_type: u16,
+        count: u32,
+        bytes: &[u8],
+    ) -> Result<Self, TiffError> {
+        let size = TagType::from_u16(tag_type)
+            .ok_or(TiffError::UnknownTagType { tag_type })?
+            .byte_size();
+        let expected = count as usize * size;
+        if bytes.len() != expected {
+            return Err(IFDError::DataLengthMismatch {
+                tag,
+                expected,
+                actual: bytes.len(),
+            }
+            .into());
+        }
+        if expected > 4 {
+            return Err(IFDError::InlineValueTooLong { tag, len: expected }.into());
+        }
+        let mut value_or_offset = [0; 4];
+        value_or_offset[..expected].copy_from_slice(bytes);
+        Ok(Self {
+            tag,
+            tag_type,
+            count,
+            value_or_offset,
+        })
+    }
+
+    /// Build a field of `count` values of `size` bytes, left-justified in `value_or_offset` by
+    /// `write`, if `tag_type` has values of that size and they fit.
+    fn pack_inline<F: FnOnce(&mut [u8])>(
+        tag: u16,
+        tag_type: u16,
+        count: usize,
+        size: usize,
+        write: F,
+    ) -> Option<Self> {
+        if TagType::from_u16(tag_type)?.byte_size() != size || count * size > 4 {
+            return None;
+        }
+        let mut value_or_offset = [0; 4];
+        write(&mut value_or_offset[..count * size]);
+        Some(Self {
+            tag,
+            tag_type,
+            count: count as u32,
+            value_or_offset,
+        })
+    }
+}
+
+/// Constructs RawIFDFields from typed values encoded in the byte order `E`, choosing the correct
+/// `tag_type` and `count`, and packing values of up to 4 bytes inline. Each fails with
+/// `IFDError::TooManyValues` if there are more values than a field's 32-bit count can hold.
+pub struct RawIFDFieldBuilder<E: ByteOrder> {
+    _phantomdata: PhantomData<E>,
+}
+
+impl<E: ByteOrder> RawIFDFieldBuilder<E> {
+    /// A BYTE field.
+    pub fn byte(tag: u16, values: &[u8]) -> Result<BuiltField, TiffError> {
+        Self::build(tag, TagType::Byte, values.len(), values.to_vec())
+    }
+
+    /// An UNDEFINED field, holding arbitrary bytes.
+    pub fn undefined(tag: u16, values: &[u8]) -> Result<BuiltField, TiffError> {
+        Self::build(tag, TagType::Undefined, values.len(), values.to_vec())
+    }
+
+    /// An ASCII field holding `text`, with the NUL terminator TIFF requires appended.
+    pub fn ascii(tag: u16, text: &str) -> Result<BuiltField, TiffError> {
+        let mut bytes = Vec::with_capacity(text.len() + 1);
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.push(0);
+        Self::build(tag, TagType::Ascii, bytes.len(), bytes)
+    }
+
+    /// A SHORT field.
+    pub fn short(tag: u16, values: &[u16]) -> Result<BuiltField, TiffError> {
+        let mut bytes = vec![0; values.len() * 2];
+        E::write_u16_into(values, &mut bytes);
+        Self::build(tag, TagType::Short, values.len(), bytes)
+    }
+
+    /// A LONG field.
+    pub fn long(tag: u16, values: &[u32]) -> Result<BuiltField, TiffError> {
+        let mut bytes = vec![0; values.len() * 4];
+        E::write_u32_into(values, &mut bytes);
+        Self::build(tag, TagType::Long, values.len(), bytes)
+    }
+
+    /// A RATIONAL field, from (numerator, denominator) pairs.
+    pub fn rational(tag: u16, values: &[(u32, u32)]) -> Result<BuiltField, TiffError> {
+        let mut bytes = vec![0; values.len() * 8];
+        for ((num, den), chunk) in values.iter().zip(bytes.chunks_exact_mut(8)) {
+            E::write_u32(&mut chunk[..4], *num);
+            E::write_u32(&mut chunk[4..], *den);
+        }
+        Self::build(tag, TagType::Rational, values.len(), bytes)
+    }
+
+    fn build(
+        tag: u16,
+        tag_type: TagType,
+        count: usize,
+        bytes: Vec<u8>,
+    ) -> Result<BuiltField, TiffError> {
+        let mut field = RawIFDField {
+            tag,
+            tag_type: tag_type.as_u16(),
+            count: u32::try_from(count).map_err(|_| IFDError::TooManyValues { tag, count })?,
+            value_or_offset: [0; 4],
+        };
+
+        let data = if bytes.len() <= field.value_or_offset.len() {
+            field.value_or_offset[..bytes.len()].copy_from_slice(&bytes);
+            None
+        } else {
+            Some(bytes)
+        };
+
+        Ok(BuiltField { field, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{BigEndian, LittleEndian};
+
+    type Builder = RawIFDFieldBuilder<LittleEndian>;
+
+    #[test]
+    fn builds_inline_and_out_of_line_fields() {
+        let short = Builder::short(256, &[640, 480]).unwrap();
+        assert_eq!(short.field.tag_type, TagType::Short.as_u16());
+        assert_eq!(short.field.count, 2);
+        assert_eq!(short.field.value_or_offset, [0x80, 0x02, 0xe0, 0x01]);
+        assert_eq!(short.data, None);
+
+        let long = RawIFDFieldBuilder::<BigEndian>::long(273, &[1, 2]).unwrap();
+        assert_eq!(long.field.count, 2);
+        assert_eq!(long.field.value_or_offset, [0; 4]);
+        assert_eq!(long.data, Some(vec![0, 0, 0, 1, 0, 0, 0, 2]));
+
+        let ascii = Builder::ascii(305, "tiffy").unwrap();
+        assert_eq!(ascii.field.count, 6);
+        assert_eq!(ascii.data.as_deref(), Some(&b"tiffy\0"[..]));
+    }
+
+    #[test]
+    fn insert_into_keeps_fields_sorted_and_replaces_tags() {
+        let mut raw_ifd = RawIFD {
+            entries: Vec::new(),
+        };
+        let mut field_data = HashMap::new();
+        for tag in [305, 256, 273, 257] {
+            Builder::long(tag, &[1, 2])
+                .unwrap()
+                .insert_into(&mut raw_ifd, &mut field_data);
+        }
+        // Replacing a field with an inline one drops its stale data
+        Builder::short(273, &[7])
+            .unwrap()
+            .insert_into(&mut raw_ifd, &mut field_data);
+
+        let tags: Vec<u16> = raw_ifd.iter().map(|field| field.tag).collect();
+        assert_eq!(tags, [256, 257, 273, 305]);
+        assert_eq!(raw_ifd.get(273).unwrap().count, 1);
+        let mut data_tags: Vec<u16> = field_data.keys().copied().collect();
+        data_tags.sort_unstable();
+        assert_eq!(data_tags, [256, 257, 305]);
+    }
+
+    #[test]
+    fn set_methods_return_out_of_line_data() {
+        let mut raw_ifd = RawIFD {
+            entries: Vec::new(),
+        };
+        assert_eq!(
+            raw_ifd
+                .set_rational::<LittleEndian>(282, &[(72, 1)])
+                .unwrap(),
+            Some(vec![72, 0, 0, 0, 1, 0, 0, 0])
+        );
+        assert_eq!(raw_ifd.set_byte::<LittleEndian>(1, &[1, 2]).unwrap(), None);
+        assert_eq!(raw_ifd.len(), 2);
+        assert_eq!(raw_ifd.iter().next().unwrap().tag, 1);
+    }
+
+    #[test]
+    fn pack_inline_follows_byte_order() {
+        let short = TagType::Short.as_u16();
+        let le = RawIFDField::pack_inline_u16::<LittleEndian>(256, short, &[0x0102]).unwrap();
+        let be = RawIFDField::pack_inline_u16::<BigEndian>(256, short, &[0x0102]).unwrap();
+        assert_eq!(le.value_or_offset, [0x02, 0x01, 0, 0]);
+        assert_eq!(be.value_or_offset, [0x01, 0x02, 0, 0]);
+        assert_eq!((le.count, be.count), (1, 1));
+        assert_eq!(le.inline_as_u32::<LittleEndian>(), Some(0x0102));
+        assert_eq!(be.inline_as_u32::<BigEndian>(), Some(0x0102));
+
+        let pair = RawIFDField::pack_inline_u16::<BigEndian>(256, short, &[1, 2]).unwrap();
+        assert_eq!(pair.value_or_offset, [0, 1, 0, 2]);
+
+        let long = TagType::Long.as_u16();
+        let le = RawIFDField::pack_inline_u32::<LittleEndian>(273, long, &[0x0102_0304]).unwrap();
+        let be = RawIFDField::pack_inline_u32::<BigEndian>(273, long, &[0x0102_0304]).unwrap();
+        assert_eq!(le.value_or_offset, [4, 3, 2, 1]);
+        assert_eq!(be.value_or_offset, [1, 2, 3, 4]);
+
+        let bytes = RawIFDField::pack_inline_u8(1, TagType::Byte.as_u16(), &[7, 8, 9]).unwrap();
+        assert_eq!((bytes.count, bytes.value_or_offset), (3, [7, 8, 9, 0]));
+    }
+
+    #[test]
+    fn pack_inline_rejects_values_that_dont_fit() {
+        let short = TagType::Short.as_u16();
+        assert!(RawIFDField::pack_inline_u16::<LittleEndian>(256, short, &[1, 2, 3]).is_none());
+        assert!(
+            RawIFDField::pack_inline_u32::<LittleEndian>(273, TagType::Long.as_u16(), &[1, 2])
+                .is_none()
+        );
+        assert!(RawIFDField::pack_inline_u8(1, TagType::Byte.as_u16(), &[0; 5]).is_none());
+        // The type must have values of the size being packed
+        assert!(
+            RawIFDField::pack_inline_u16::<LittleEndian>(256, TagType::Long.as_u16(), &[1])
+                .is_none()
+        );
+        assert!(RawIFDField::pack_inline_u8(1, 99, &[1]).is_none());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn rejects_too_many_values_for_count() {
+        let count = u32::MAX as usize + 1;
+        assert!(matches!(
+            Builder::build(273, TagType::Long, count, Vec::new()),
+            Err(TiffError::IFD(IFDError::TooManyValues { tag: 273, count: c })) if c == count
+        ));
+    }
+}
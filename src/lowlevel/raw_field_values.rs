@@ -0,0 +1,502 @@
+use crate::errors::{FieldExtractionError, TiffError};
+use crate::lowlevel::{
+    rational::{Rational, SRational},
+    raw_ifd::{RawIFD, RawIFDField},
+    tag_type::TagType,
+};
+use byteorder::{ByteOrder, ReadBytesExt};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+
+impl RawIFDField {
+    /// Read the bytes of this field's value, either directly from `value_or_offset` if the value
+    /// is inline, or from `reader` at the offset it contains. The cursor position of `reader` is
+    /// restored afterwards, so this may be used while iterating over fields. A field with a
+    /// count of zero has an empty value, whatever its type, and the typed accessors below return
+    /// empty results for it accordingly.
+    pub fn read_value_bytes<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, TiffError> {
+        if self.count == 0 {
+            return Ok(Vec::new());
+        }
+        let len = self.value_byte_len().ok_or(TiffError::UnknownTagType {
+            tag_type: self.tag_type,
+        })?;
+
+        if let Some(value) = self.inline_value_slice() {
+            return Ok(value.to_vec());
+        }
+
+        read_bytes_at(reader, self.offset::<E>()?.into(), len as u64)
+    }
+
+    /// Read this field's value as an array of unsigned integers, widened to u64. Fails if the
+    /// field is not of type BYTE, SHORT or LONG, as used interchangeably by many tags such as
+    /// StripOffsets.
+    pub fn as_unsigned_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u64>, TiffError> {
+        match TagType::from_u16(self.tag_type) {
+            Some(TagType::Byte) => Ok(self
+                .read_value_bytes::<E, R>(reader)?
+                .into_iter()
+                .map(u64::from)
+                .collect()),
+            Some(TagType::Short) => Ok(self
+                .as_u16_array::<E, R>(reader)?
+                .into_iter()
+                .map(u64::from)
+                .collect()),
+            Some(TagType::Long) => Ok(self
+                .as_u32_array::<E, R>(reader)?
+                .into_iter()
+                .map(u64::from)
+                .collect()),
+            _ => Err(FieldExtractionError::MismatchedDataType {
+                expected: TagType::Long,
+                actual: self.tag_type,
+            }
+            .into()),
+        }
+    }
+
+    /// Read this field's value as an array of BYTEs, exactly `count` long, so that the padding
+    /// of a short inline value is never included. Fails if the field is not of type BYTE.
+    pub fn as_u8_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, TiffError> {
+        self.read_typed_value_bytes::<E, R>(reader, TagType::Byte)
+    }
+
+    /// Read this field's value as an array of SBYTEs, exactly `count` long. Fails if the field is
+    /// not of type SBYTE.
+    pub fn as_i8_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<i8>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::SByte)?;
+        Ok(bytes.into_iter().map(|byte| byte as i8).collect())
+    }
+
+    /// Read the raw bytes of this field's value whatever its declared type, as for UNDEFINED
+    /// blobs, without any byte order conversion. Fails only if the type is unknown, as its
+    /// length can't then be determined.
+    pub fn as_u8_array_any<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u8>, TiffError> {
+        self.read_value_bytes::<E, R>(reader)
+    }
+
+    /// Read this field's value as an array of SHORTs. Fails if the field is not of type SHORT.
+    pub fn as_u16_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u16>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Short)?;
+        let mut values = vec![0; bytes.len() / 2];
+        E::read_u16_into(&bytes, &mut values);
+        Ok(values)
+    }
+
+    /// Read this field's value as an array of LONGs. Fails if the field is not of type LONG.
+    pub fn as_u32_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u32>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Long)?;
+        let mut values = vec![0; bytes.len() / 4];
+        E::read_u32_into(&bytes, &mut values);
+        Ok(values)
+    }
+
+    /// Read this field's value as an array of file offsets, such as those pointing to sub-IFDs.
+    /// Fails if the field is not of type LONG or IFD.
+    pub fn as_offset_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<u32>, TiffError> {
+        let expected = if self.tag_type == TagType::IFD.as_u16() {
+            TagType::IFD
+        } else {
+            TagType::Long
+        };
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, expected)?;
+        let mut values = vec![0; bytes.len() / 4];
+        E::read_u32_into(&bytes, &mut values);
+        Ok(values)
+    }
+
+    /// Read this field's value as an array of FLOATs. Fails if the field is not of type FLOAT.
+    pub fn as_f32_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<f32>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Float)?;
+        let mut values = vec![0.; bytes.len() / 4];
+        E::read_f32_into(&bytes, &mut values);
+        Ok(values)
+    }
+
+    /// Read this field's value as an array of DOUBLEs. Fails if the field is not of type DOUBLE.
+    pub fn as_f64_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<f64>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Double)?;
+        let mut values = vec![0.; bytes.len() / 8];
+        E::read_f64_into(&bytes, &mut values);
+        Ok(values)
+    }
+
+    /// Read this field's value as an array of RATIONALs. Fails if the field is not of type
+    /// RATIONAL.
+    pub fn as_rational_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<Rational>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Rational)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|pair| Rational {
+                num: E::read_u32(&pair[..4]),
+                den: E::read_u32(&pair[4..]),
+            })
+            .collect())
+    }
+
+    /// Read this field's value as an array of SRATIONALs. Fails if the field is not of type
+    /// SRATIONAL.
+    pub fn as_srational_array<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<SRational>, TiffError> {
+        let bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::SRational)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|pair| SRational {
+                num: E::read_i32(&pair[..4]),
+                den: E::read_i32(&pair[4..]),
+            })
+            .collect())
+    }
+
+    /// Read this field's value as a single NUL-terminated ASCII string, with the terminator (and
+    /// any NUL padding) removed. Fails if the field is not of type ASCII or is not valid UTF-8.
+    /// Fields holding several strings should be read with `as_ascii_multi` instead.
+    pub fn as_ascii<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<String, TiffError> {
+        let mut bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Ascii)?;
+        while bytes.last() == Some(&b'\0') {
+            bytes.pop();
+        }
+        String::from_utf8(bytes)
+            .map_err(|_| FieldExtractionError::InvalidText { tag: self.tag }.into())
+    }
+
+    /// Read this field's value as a sequence of NUL-separated ASCII strings. Fails if the field
+    /// is not of type ASCII or any of the strings are not valid UTF-8.
+    pub fn as_ascii_multi<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<Vec<String>, TiffError> {
+        let mut bytes = self.read_typed_value_bytes::<E, R>(reader, TagType::Ascii)?;
+        while bytes.last() == Some(&b'\0') {
+            bytes.pop();
+        }
+        if bytes.is_empty() {
+            return Ok(Vec::new());
+        }
+        bytes
+            .split(|byte| *byte == b'\0')
+            .map(|string| {
+                String::from_utf8(string.to_vec())
+                    .map_err(|_| FieldExtractionError::InvalidText { tag: self.tag }.into())
+            })
+            .collect()
+    }
+
+    /// Check that this field is of type `expected`, then read its value bytes.
+    fn read_typed_value_bytes<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+        expected: TagType,
+    ) -> Result<Vec<u8>, TiffError> {
+        if self.tag_type != expected.as_u16() {
+            return Err(FieldExtractionError::MismatchedDataType {
+                expected,
+                actual: self.tag_type,
+            }
+            .into());
+        }
+        self.read_value_bytes::<E, R>(reader)
+    }
+}
+
+impl RawIFD {
+    /// Read the value of every field of this IFD which isn't stored inline, keyed by tag, as
+    /// `RawIFDField::read_value_bytes` does. The result is in the form `TiffWriter::add_ifd` and
+    /// `plan_layout` take, so an IFD can be copied by reading all of its values and writing them
+    /// out again, laid out afresh. Fields sharing an offset each get their own copy of the value.
+    /// Only values are read: the image data located by fields such as StripOffsets isn't, and
+    /// neither are any IFDs pointed to. Fails if any value can't be read, or a field's type is
+    /// unrecognized so that it can't be told whether its value is inline.
+    pub fn collect_external_blobs<E: ByteOrder, R: ReadBytesExt + Seek>(
+        &self,
+        reader: &mut R,
+    ) -> Result<HashMap<u16, Vec<u8>>, TiffError> {
+        let mut blobs = HashMap::new();
+        for field in self {
+            let is_inline = field.is_inline().ok_or(TiffError::UnknownTagType {
+                tag_type: field.tag_type,
+            })?;
+            if !is_inline {
+                blobs.insert(field.tag, field.read_value_bytes::<E, R>(reader)?);
+            }
+        }
+        Ok(blobs)
+    }
+}
+
+/// Read `len` bytes from `reader` at `offset`, failing if they run past the end of the file. The
+/// cursor position of `reader` is restored afterwards.
+pub(crate) fn read_bytes_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, TiffError> {
+    check_in_bounds(reader, offset, len)?;
+    let position = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(offset))?;
+
+    // Grow the buffer as data arrives rather than trusting `len` for the allocation
+    let mut buffer = Vec::new();
+    let read_result = reader.by_ref().take(len).read_to_end(&mut buffer);
+    reader.seek(SeekFrom::Start(position))?;
+    read_result?;
+
+    if buffer.len() as u64 != len {
+        return Err(TiffError::UnexpectedEof);
+    }
+    Ok(buffer)
+}
+
+/// Check that `len` bytes at `offset` lie within the file in `reader`, failing with
+/// `TiffError::OffsetOutOfBounds` otherwise. The cursor position of `reader` is restored
+/// afterwards.
+pub(crate) fn check_in_bounds<R: Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+) -> Result<(), TiffError> {
+    let position = reader.stream_position()?;
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    if offset.checked_add(len).is_none_or(|end| end > file_len) {
+        return Err(TiffError::OffsetOutOfBounds { offset, file_len });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::tiff_builder::single_page_file;
+    use crate::lowlevel::{BigEndian, Endianness, IFDField, LittleEndian, IFD};
+    use std::io::Cursor;
+
+    const DOUBLES: u16 = 65000;
+    const FLOAT: u16 = 65001;
+    const BYTES: u16 = 65002;
+    const SBYTES: u16 = 65003;
+    const BLOB: u16 = 65004;
+
+    /// A file in byte order `E` whose only IFD holds three DOUBLEs and a single FLOAT, along with
+    /// that IFD.
+    fn float_file<E: ByteOrder>() -> (Vec<u8>, RawIFD) {
+        let mut ifd = IFD::new();
+        ifd.entries.extend([
+            (DOUBLES, IFDField::Double(vec![1.5, -0.25, 1e300].into())),
+            (FLOAT, IFDField::Float(vec![3.75].into())),
+        ]);
+        single_page_file(ifd, Vec::new(), Endianness::of::<E>())
+    }
+
+    fn reads_floats_back<E: ByteOrder>() {
+        let (buf, ifd) = float_file::<E>();
+        let mut reader = Cursor::new(&buf);
+
+        let doubles = ifd.get(DOUBLES).unwrap();
+        assert_eq!(doubles.is_inline(), Some(false));
+        assert_eq!(
+            doubles.as_f64_array::<E, _>(&mut reader).unwrap(),
+            vec![1.5, -0.25, 1e300]
+        );
+
+        let float = ifd.get(FLOAT).unwrap();
+        assert_eq!(float.is_inline(), Some(true));
+        assert_eq!(float.as_f32_array::<E, _>(&mut reader).unwrap(), vec![3.75]);
+        assert_eq!(reader.position(), 0);
+    }
+
+    #[test]
+    fn reads_out_of_line_doubles_and_inline_float() {
+        reads_floats_back::<LittleEndian>();
+        reads_floats_back::<BigEndian>();
+    }
+
+    #[test]
+    fn float_accessors_reject_other_types() {
+        let (buf, ifd) = float_file::<LittleEndian>();
+        let mut reader = Cursor::new(&buf);
+        assert!(matches!(
+            ifd.get(FLOAT)
+                .unwrap()
+                .as_f64_array::<LittleEndian, _>(&mut reader),
+            Err(TiffError::Field(FieldExtractionError::MismatchedDataType {
+                expected: TagType::Double,
+                actual: 11
+            }))
+        ));
+        assert!(matches!(
+            ifd.get(DOUBLES)
+                .unwrap()
+                .as_f32_array::<LittleEndian, _>(&mut reader),
+            Err(TiffError::Field(FieldExtractionError::MismatchedDataType {
+                expected: TagType::Float,
+                actual: 12
+            }))
+        ));
+    }
+
+    #[test]
+    fn byte_arrays_are_exactly_count_long() {
+        let mut ifd = IFD::new();
+        ifd.entries.extend([
+            (BYTES, IFDField::Byte(vec![1, 2, 3].into())),
+            (SBYTES, IFDField::SByte(vec![-1, -128, 127].into())),
+            (BLOB, IFDField::Undefined((0..10).collect())),
+        ]);
+        let (buf, ifd) = single_page_file(ifd, Vec::new(), Endianness::Little);
+        let mut reader = Cursor::new(&buf);
+        type E = LittleEndian;
+
+        // Three inline BYTEs, without the padding byte after them
+        let bytes = ifd.get(BYTES).unwrap();
+        assert_eq!(bytes.is_inline(), Some(true));
+        assert_eq!(
+            bytes.as_u8_array::<E, _>(&mut reader).unwrap(),
+            vec![1, 2, 3]
+        );
+        let padded = RawIFDField {
+            value_or_offset: [1, 2, 3, 0xFF],
+            ..*bytes
+        };
+        assert_eq!(
+            padded.as_u8_array::<E, _>(&mut reader).unwrap(),
+            vec![1, 2, 3]
+        );
+
+        let sbytes = ifd.get(SBYTES).unwrap();
+        assert_eq!(
+            sbytes.as_i8_array::<E, _>(&mut reader).unwrap(),
+            vec![-1, -128, 127]
+        );
+        assert!(sbytes.as_u8_array::<E, _>(&mut reader).is_err());
+        assert!(bytes.as_i8_array::<E, _>(&mut reader).is_err());
+
+        // Any type reads as its raw bytes, whether inline or not
+        let blob = ifd.get(BLOB).unwrap();
+        assert_eq!(blob.is_inline(), Some(false));
+        assert_eq!(
+            blob.as_u8_array_any::<E, _>(&mut reader).unwrap(),
+            (0..10).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            sbytes.as_u8_array_any::<E, _>(&mut reader).unwrap(),
+            vec![0xFF, 0x80, 0x7F]
+        );
+        assert!(blob.as_u8_array::<E, _>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn empty_fields_read_as_empty_values() {
+        // Were the garbage offset followed, every read would run past the end of the file
+        let empty = |tag_type: TagType| RawIFDField {
+            tag: DOUBLES,
+            tag_type: tag_type.as_u16(),
+            count: 0,
+            value_or_offset: [0xFF; 4],
+        };
+        let mut reader = Cursor::new(vec![0; 8]);
+        let reader = &mut reader;
+        type E = LittleEndian;
+
+        assert!(empty(TagType::Byte)
+            .as_u8_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::SByte)
+            .as_i8_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Short)
+            .as_u16_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Long)
+            .as_u32_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Long)
+            .as_unsigned_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::IFD)
+            .as_offset_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Float)
+            .as_f32_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Double)
+            .as_f64_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Rational)
+            .as_rational_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::SRational)
+            .as_srational_array::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert_eq!(empty(TagType::Ascii).as_ascii::<E, _>(reader).unwrap(), "");
+        assert!(empty(TagType::Ascii)
+            .as_ascii_multi::<E, _>(reader)
+            .unwrap()
+            .is_empty());
+        assert!(empty(TagType::Ascii)
+            .value_bytes::<E>(&[])
+            .unwrap()
+            .is_empty());
+
+        // Even a type of unknown size has an empty value when there are none of it
+        let unknown = RawIFDField {
+            tag_type: 99,
+            ..empty(TagType::Byte)
+        };
+        assert!(unknown.read_value_bytes::<E, _>(reader).unwrap().is_empty());
+        assert!(unknown.as_u8_array_any::<E, _>(reader).unwrap().is_empty());
+
+        // The type is still checked
+        assert!(empty(TagType::Short).as_u8_array::<E, _>(reader).is_err());
+    }
+}
@@ -1,8 +1,49 @@
-use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::io::Error;
+use crate::baseline::tags::{self, tag_name};
+use crate::errors::{IFDError, TiffError};
+#[cfg(feature = "std")]
+use crate::lowlevel::raw_field_values::check_in_bounds;
+use crate::lowlevel::{
+    constants::ifd_field_type_magic::{IFD_TYPE_BYTE, IFD_TYPE_LONG, IFD_TYPE_SHORT},
+    tag_type::TagType,
+};
+#[cfg(not(feature = "std"))]
+use alloc::vec::{self, Vec};
+use byteorder::ByteOrder;
+#[cfg(feature = "std")]
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use core::fmt;
+use core::iter::FromIterator;
+#[cfg(feature = "std")]
+use std::io::{Seek, SeekFrom};
+#[cfg(feature = "std")]
+use std::vec;
+
+/// Size of a classic TIFF header, before which no IFD may begin.
+pub(crate) const HEADER_SIZE: u64 = 8;
+
+/// Most entries to allocate room for before reading them. The entry count of an IFD is
+/// untrusted, so entries beyond this are only allocated for as they are actually read.
+#[cfg(feature = "std")]
+pub(crate) const MAX_PREALLOCATED_ENTRIES: usize = 256;
+
+/// Tags locating image data, or tables shared by the strips or tiles of compressed image data,
+/// along with the tags giving their lengths.
+const IMAGE_DATA_TAGS: &[u16] = &[
+    tags::STRIP_OFFSETS,
+    tags::STRIP_BYTE_COUNTS,
+    tags::TILE_OFFSETS,
+    tags::TILE_BYTE_COUNTS,
+    tags::JPEG_TABLES,
+    tags::JPEG_INTERCHANGE_FORMAT,
+    tags::JPEG_INTERCHANGE_FORMAT_LNGTH,
+    tags::JPEGQ_TABLES,
+    tags::JPEGDC_TABLES,
+    tags::JPEGAC_TABLES,
+];
 
 /// A struct representing a low-level IFD value.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawIFDField {
     /// Tag ID.
     pub tag: u16,
@@ -20,7 +61,8 @@ pub struct RawIFDField {
 
 impl RawIFDField {
     /// Read the field value from `reader`.
-    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
+    #[cfg(feature = "std")]
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
         Ok(Self {
             tag: reader.read_u16::<E>()?,
             tag_type: reader.read_u16::<E>()?,
@@ -34,6 +76,7 @@ impl RawIFDField {
     }
 
     /// Write the field value to `writer`.
+    #[cfg(feature = "std")]
     pub fn write_to<E: ByteOrder, W: WriteBytesExt>(
         &self,
         writer: &mut W,
@@ -43,31 +86,201 @@ impl RawIFDField {
         writer.write_u32::<E>(self.count)?;
         writer.write_all(&self.value_or_offset)
     }
+
+    /// Number of bytes occupied by this field's value (`count` times the size of `tag_type`),
+    /// or `None` if the type is unrecognized.
+    pub fn value_byte_len(&self) -> Option<usize> {
+        let unit_size = TagType::from_u16(self.tag_type)?.byte_size();
+        (self.count as usize).checked_mul(unit_size)
+    }
+
+    /// Whether this field's value is stored inline within `value_or_offset`, rather than at the
+    /// file offset it contains. Returns `None` if the type is unrecognized.
+    pub fn is_inline(&self) -> Option<bool> {
+        self.value_byte_len()
+            .map(|len| len <= self.value_or_offset.len())
+    }
+
+    /// Borrow exactly the bytes of this field's value from `value_or_offset`, if it is inline.
+    /// Values shorter than four bytes are left-justified, so only the first `value_byte_len`
+    /// bytes are returned, excluding the padding after them. Returns `None` if the value isn't
+    /// inline, or the type is unrecognized.
+    pub fn inline_value_slice(&self) -> Option<&[u8]> {
+        self.value_or_offset.get(..self.value_byte_len()?)
+    }
+
+    /// Decode `value_or_offset` as the file offset of this field's value, in the file's byte
+    /// order `E`. Only meaningful if the value isn't inline, which is checked in debug builds;
+    /// see `offset_checked`. Fails with `TiffError::NullOffset` if it is zero, as reading the
+    /// value there would read the header instead.
+    pub fn offset<E: ByteOrder>(&self) -> Result<u32, TiffError> {
+        debug_assert_ne!(
+            self.is_inline(),
+            Some(true),
+            "value of tag {} is inline",
+            self.tag
+        );
+        match E::read_u32(&self.value_or_offset) {
+            0 => Err(TiffError::NullOffset { tag: self.tag }),
+            offset => Ok(offset),
+        }
+    }
+
+    /// Decode `value_or_offset` as an offset as `offset` does, returning `None` if the value is
+    /// inline, or the type is unrecognized so that it can't be told. Fails as `offset` does if
+    /// the offset is zero.
+    pub fn offset_checked<E: ByteOrder>(&self) -> Result<Option<u32>, TiffError> {
+        match self.is_inline() {
+            Some(false) => self.offset::<E>().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Decode the first value of this field, widened to u32, if it is an inline BYTE, SHORT or
+    /// LONG, in the file's byte order `E`. Returns `None` for any other type, or if the field is
+    /// empty or its value isn't inline.
+    pub fn inline_as_u32<E: ByteOrder>(&self) -> Option<u32> {
+        let value = self.inline_value_slice()?;
+        match self.tag_type {
+            IFD_TYPE_BYTE => value.first().map(|&value| value.into()),
+            IFD_TYPE_SHORT => value.get(..2).map(|value| E::read_u16(value).into()),
+            IFD_TYPE_LONG => value.get(..4).map(E::read_u32),
+            _ => None,
+        }
+    }
 }
 
-/// A struct representing a low-level IFD.
-#[derive(Debug, Clone)]
+/// Shows the tag and type by name where known, e.g.
+/// `ImageWidth (256) SHORT x1: 80 02 00 00`. The bytes are those of `value_or_offset`, so for
+/// values which aren't inline they are the offset of the data.
+impl fmt::Display for RawIFDField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match tag_name(self.tag) {
+            Some(name) => write!(f, "{} ({})", name, self.tag)?,
+            None => write!(f, "Tag {}", self.tag)?,
+        }
+        match self.tag_type_enum() {
+            Some(tag_type) => write!(f, " {}", tag_type.name())?,
+            None => write!(f, " type {}", self.tag_type)?,
+        }
+        write!(f, " x{}:", self.count)?;
+        for byte in &self.value_or_offset {
+            write!(f, " {:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// A struct representing a low-level IFD. IFDs compare equal if their entries are the same and in
+/// the same order; out-of-line values are compared by offset, not by the data there.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawIFD {
     pub entries: Vec<RawIFDField>,
 }
 
 impl RawIFD {
-    /// Read an entire IFD from `reader` excluding the offset to the next IFD.
-    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
+    /// Read an entire IFD from `reader` excluding the offset to the next IFD. Fails with
+    /// `TiffError::TruncatedIFD`, holding the entries read, if the file ends partway through.
+    #[cfg(feature = "std")]
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
         // Read length header
         let field_count = reader.read_u16::<E>()? as usize;
+        Self::read_entries::<E, R>(reader, field_count)
+    }
+
+    /// Read an entire IFD from a little-endian `reader`, as `read_from` does.
+    #[cfg(feature = "std")]
+    pub fn read_from_le<R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
+        Self::read_from::<LittleEndian, R>(reader)
+    }
+
+    /// Read an entire IFD from a big-endian `reader`, as `read_from` does.
+    #[cfg(feature = "std")]
+    pub fn read_from_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
+        Self::read_from::<BigEndian, R>(reader)
+    }
 
-        // Read entries
-        let mut entries = Vec::with_capacity(field_count);
+    /// Read an entire IFD at `offset` within `reader`, excluding the offset to the next IFD, and
+    /// leave the cursor just after it, so that the next 4 bytes are that offset. Fails if `offset`
+    /// lies within the header or past the end of the file, or with `TiffError::TruncatedIFD` if
+    /// the IFD runs past the end.
+    #[cfg(feature = "std")]
+    pub fn read_from_at<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        offset: u64,
+    ) -> Result<Self, TiffError> {
+        if offset < HEADER_SIZE {
+            return Err(IFDError::OffsetInHeader { offset }.into());
+        }
+        check_in_bounds(reader, offset, 2)?;
+        reader.seek(SeekFrom::Start(offset))?;
+        Self::read_from::<E, R>(reader)
+    }
+
+    /// Read an entire IFD from `reader` as `read_from` does, but fail without reading any entries
+    /// if the IFD claims to have more than `max_entries`.
+    #[cfg(feature = "std")]
+    pub fn read_from_limited<E: ByteOrder, R: ReadBytesExt>(
+        reader: &mut R,
+        max_entries: usize,
+    ) -> Result<Self, TiffError> {
+        // Read length header
+        let field_count = reader.read_u16::<E>()? as usize;
+        if field_count > max_entries {
+            return Err(IFDError::TooManyEntries {
+                count: field_count,
+                limit: max_entries,
+            }
+            .into());
+        }
+
+        Self::read_entries::<E, R>(reader, field_count)
+    }
+
+    /// Read `field_count` entries from `reader`. Fails with `TiffError::TruncatedIFD`, holding
+    /// the entries read so far, if the file ends first.
+    #[cfg(feature = "std")]
+    pub(crate) fn read_entries<E: ByteOrder, R: ReadBytesExt>(
+        reader: &mut R,
+        field_count: usize,
+    ) -> Result<Self, TiffError> {
+        let mut entries = Vec::with_capacity(field_count.min(MAX_PREALLOCATED_ENTRIES));
         for _ in 0..field_count {
-            entries.push(RawIFDField::read_from::<E, R>(reader)?);
+            match RawIFDField::read_from::<E, R>(reader) {
+                Ok(field) => entries.push(field),
+                Err(TiffError::UnexpectedEof) => {
+                    return Err(TiffError::TruncatedIFD {
+                        expected_entries: field_count,
+                        read_entries: entries.len(),
+                        partial: Self { entries },
+                    })
+                }
+                Err(err) => return Err(err),
+            }
         }
         Ok(Self { entries })
     }
 
-    /// Write an entire IFD to `writer` excluding the offset to the next IFD.
-    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), Error> {
-        assert!(self.entries.len() < std::u16::MAX as usize);
+    /// Read an entire IFD from `reader` as `read_from` does, then check it with `validate`.
+    #[cfg(feature = "std")]
+    pub fn read_from_validated<E: ByteOrder, R: ReadBytesExt>(
+        reader: &mut R,
+    ) -> Result<Self, TiffError> {
+        let ifd = Self::read_from::<E, R>(reader)?;
+        ifd.validate()?;
+        Ok(ifd)
+    }
+
+    /// Write an entire IFD to `writer` excluding the offset to the next IFD. Fields are written
+    /// in their current order; see `write_to_sorted` for writing a spec-compliant IFD. Fails
+    /// without writing anything if there are more fields than the 16-bit count can hold.
+    #[cfg(feature = "std")]
+    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), TiffError> {
+        self.check_entry_count()?;
 
         // Write length header
         writer.write_u16::<E>(self.entries.len() as u16)?;
@@ -78,4 +291,692 @@ impl RawIFD {
         }
         Ok(())
     }
+
+    /// Write an entire IFD to `writer` in little-endian byte order, as `write_to` does.
+    #[cfg(feature = "std")]
+    pub fn write_to_le<W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), TiffError> {
+        self.write_to::<LittleEndian, W>(writer)
+    }
+
+    /// Write an entire IFD to `writer` in big-endian byte order, as `write_to` does.
+    #[cfg(feature = "std")]
+    pub fn write_to_be<W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), TiffError> {
+        self.write_to::<BigEndian, W>(writer)
+    }
+
+    /// Write an entire IFD to `writer` excluding the offset to the next IFD, with its fields
+    /// sorted in ascending order by tag as TIFF requires. Fails if a tag appears more than once.
+    #[cfg(feature = "std")]
+    pub fn write_to_sorted<E: ByteOrder, W: WriteBytesExt>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), TiffError> {
+        let mut sorted = self.clone();
+        sorted.sort_entries();
+        sorted.validate()?;
+        sorted.write_to::<E, W>(writer)
+    }
+
+    /// Check that the fields fit within the 16-bit count of a classic TIFF IFD.
+    pub fn check_entry_count(&self) -> Result<(), IFDError> {
+        if self.entries.len() > u16::MAX as usize {
+            return Err(IFDError::TooManyEntries {
+                count: self.entries.len(),
+                limit: u16::MAX as usize,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that the fields are in strictly ascending order by tag, as TIFF requires, listing
+    /// any duplicated or out-of-order tags otherwise.
+    pub fn validate(&self) -> Result<(), IFDError> {
+        let out_of_order: Vec<u16> = self
+            .entries
+            .windows(2)
+            .filter(|pair| pair[1].tag < pair[0].tag)
+            .map(|pair| pair[1].tag)
+            .collect();
+
+        let mut sorted_tags: Vec<u16> = self.entries.iter().map(|field| field.tag).collect();
+        sorted_tags.sort_unstable();
+        let mut duplicates: Vec<u16> = sorted_tags
+            .windows(2)
+            .filter(|pair| pair[0] == pair[1])
+            .map(|pair| pair[0])
+            .collect();
+        duplicates.dedup();
+
+        if out_of_order.is_empty() && duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(IFDError::UnorderedTags {
+                duplicates,
+                out_of_order,
+            })
+        }
+    }
+
+    /// Sort the fields in ascending order by tag, as TIFF requires. Fields with the same tag
+    /// keep their relative order.
+    pub fn sort_entries(&mut self) {
+        self.entries.sort_by_key(|field| field.tag);
+    }
+
+    /// Find the field for `tag`. TIFF requires fields to be sorted by tag, so this uses a binary
+    /// search, falling back to a linear search in case the IFD isn't sorted.
+    pub fn get(&self, tag: u16) -> Option<&RawIFDField> {
+        self.position(tag).map(|idx| &self.entries[idx])
+    }
+
+    /// Find the field for `tag`, see `get`.
+    pub fn get_mut(&mut self, tag: u16) -> Option<&mut RawIFDField> {
+        self.position(tag).map(move |idx| &mut self.entries[idx])
+    }
+
+    /// Returns true if this IFD contains a field for `tag`.
+    pub fn contains_tag(&self, tag: u16) -> bool {
+        self.position(tag).is_some()
+    }
+
+    fn position(&self, tag: u16) -> Option<usize> {
+        self.entries
+            .binary_search_by_key(&tag, |field| field.tag)
+            .ok()
+            .or_else(|| self.entries.iter().position(|field| field.tag == tag))
+    }
+
+    /// Insert `field`, replacing any fields with the same tag, in the position that keeps a sorted
+    /// IFD sorted. Returns the field replaced, if any. The caller is responsible for `field`'s
+    /// data; see `set` for inserting a field built from typed values.
+    pub fn set_field(&mut self, field: RawIFDField) -> Option<RawIFDField> {
+        let replaced = self.get(field.tag).copied();
+        self.entries.retain(|existing| existing.tag != field.tag);
+        let idx = self
+            .entries
+            .partition_point(|existing| existing.tag < field.tag);
+        self.entries.insert(idx, field);
+        replaced
+    }
+
+    /// Returns an iterator over references to the fields in their current order.
+    pub fn iter(&self) -> core::slice::Iter<'_, RawIFDField> {
+        self.entries.iter()
+    }
+
+    /// Returns an iterator over mutable references to the fields in their current order.
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, RawIFDField> {
+        self.entries.iter_mut()
+    }
+
+    /// Number of fields in this IFD.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if this IFD has no fields.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of bytes this IFD occupies on disk: the entry count, 12 bytes per entry and the
+    /// offset to the next IFD, plus the value of every field too large to be stored inline, each
+    /// padded to a word boundary. The image data the IFD locates, such as its strips, isn't
+    /// included. Values are counted once per field, so fields sharing an offset are counted
+    /// twice, as they would be when written out again. Fails with `TiffError::UnknownTagType`
+    /// if any field's type is unrecognized, as its length is then unknown.
+    pub fn on_disk_size(&self) -> Result<u64, TiffError> {
+        let mut size = 2 + 12 * self.entries.len() as u64 + 4;
+        for field in &self.entries {
+            let unit_size = TagType::from_u16(field.tag_type)
+                .ok_or(TiffError::UnknownTagType {
+                    tag_type: field.tag_type,
+                })?
+                .byte_size() as u64;
+            let len = u64::from(field.count) * unit_size;
+            if len > field.value_or_offset.len() as u64 {
+                size += len + len % 2;
+            }
+        }
+        Ok(size)
+    }
+
+    /// Check that the value of every field stored out-of-line lies within the file in `reader`,
+    /// reading offsets in the file's byte order `E`, so that a truncated or corrupt file is
+    /// caught before anything is decoded. Fields are checked in order, failing with
+    /// `IFDError::ValueOutOfBounds` or `TiffError::NullOffset` for the first bad one. Fields of
+    /// unrecognized type are skipped, as the length of their values is unknown. The image data
+    /// the IFD locates, such as its strips, isn't checked. The cursor position of `reader` is
+    /// restored afterwards.
+    #[cfg(feature = "std")]
+    pub fn verify_offsets<E: ByteOrder, R: Seek>(&self, reader: &mut R) -> Result<(), TiffError> {
+        for field in &self.entries {
+            let len = match field.value_byte_len() {
+                Some(len) if len > field.value_or_offset.len() => len as u64,
+                _ => continue,
+            };
+            let offset = u64::from(field.offset::<E>()?);
+            check_in_bounds(reader, offset, len).map_err(|err| match err {
+                TiffError::OffsetOutOfBounds { offset, file_len } => IFDError::ValueOutOfBounds {
+                    tag: field.tag,
+                    offset,
+                    len,
+                    file_len,
+                }
+                .into(),
+                err => err,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// List the tags of this IFD which locate its image data, or tables shared by its strips or
+    /// tiles, in ascending order: StripOffsets and TileOffsets, JPEGTables, the offsets of the
+    /// old-style JPEG interchange format stream and tables, and the byte counts paired with them.
+    /// These are the fields whose data must be copied, and offsets updated, to move the image
+    /// to another file.
+    pub fn data_tags(&self) -> Vec<u16> {
+        let mut data_tags: Vec<u16> = self
+            .iter()
+            .map(|field| field.tag)
+            .filter(|tag| IMAGE_DATA_TAGS.contains(tag))
+            .collect();
+        data_tags.sort_unstable();
+        data_tags.dedup();
+        data_tags
+    }
+
+    /// Compare the fields of this IFD with those of `other`, matching them up by tag regardless
+    /// of their order. Fields are compared as stored, so values which aren't inline compare by
+    /// offset rather than by data. Returns the differences in ascending order of tag.
+    pub fn diff(&self, other: &RawIFD) -> Vec<IfdDiff> {
+        let mut tags: Vec<u16> = self.iter().chain(other).map(|field| field.tag).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags.into_iter()
+            .filter_map(|tag| match (self.get(tag), other.get(tag)) {
+                (Some(_), None) => Some(IfdDiff::TagOnlyInSelf(tag)),
+                (None, Some(_)) => Some(IfdDiff::TagOnlyInOther(tag)),
+                (Some(self_entry), Some(other_entry)) if self_entry != other_entry => {
+                    Some(IfdDiff::ValueDiffers {
+                        tag,
+                        self_entry: *self_entry,
+                        other_entry: *other_entry,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Copy the fields of `other` into this IFD, keeping it sorted and free of duplicates. Tags
+    /// in only one of the IFDs are always kept; `policy` decides between differing fields for
+    /// the same tag. With `MergePolicy::Error`, fails with `IFDError::MergeConflict` for the
+    /// first such tag, leaving this IFD unchanged. Like `set_field`, this copies fields as
+    /// stored, so the caller is responsible for the data of any which aren't inline.
+    pub fn merge(&mut self, other: &RawIFD, policy: MergePolicy) -> Result<(), IFDError> {
+        if policy == MergePolicy::Error {
+            let conflict = self.diff(other).into_iter().find_map(|diff| match diff {
+                IfdDiff::ValueDiffers { tag, .. } => Some(tag),
+                _ => None,
+            });
+            if let Some(tag) = conflict {
+                return Err(IFDError::MergeConflict { tag });
+            }
+        }
+        for field in other {
+            if policy == MergePolicy::PreferOther || !self.contains_tag(field.tag) {
+                self.set_field(*field);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How `RawIFD::merge` treats a tag with differing fields in both IFDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the field already in the IFD being merged into.
+    PreferSelf,
+    /// Replace it with the field from the other IFD.
+    PreferOther,
+    /// Fail without changing either IFD.
+    Error,
+}
+
+/// A difference between two IFDs, as found by `RawIFD::diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfdDiff {
+    /// The first IFD has a field for this tag, but the second doesn't.
+    TagOnlyInSelf(u16),
+    /// The second IFD has a field for this tag, but the first doesn't.
+    TagOnlyInOther(u16),
+    /// Both IFDs have a field for this tag, differing in type, count or value.
+    ValueDiffers {
+        tag: u16,
+        self_entry: RawIFDField,
+        other_entry: RawIFDField,
+    },
+}
+
+impl fmt::Display for IfdDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IfdDiff::TagOnlyInSelf(tag) => write!(f, "Tag {} only in first IFD", tag),
+            IfdDiff::TagOnlyInOther(tag) => write!(f, "Tag {} only in second IFD", tag),
+            IfdDiff::ValueDiffers {
+                self_entry,
+                other_entry,
+                ..
+            } => write!(f, "{} differs from {}", self_entry, other_entry),
+        }
+    }
+}
+
+impl IntoIterator for RawIFD {
+    type Item = RawIFDField;
+    type IntoIter = vec::IntoIter<RawIFDField>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a RawIFD {
+    type Item = &'a RawIFDField;
+    type IntoIter = core::slice::Iter<'a, RawIFDField>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut RawIFD {
+    type Item = &'a mut RawIFDField;
+    type IntoIter = core::slice::IterMut<'a, RawIFDField>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter_mut()
+    }
+}
+
+impl FromIterator<RawIFDField> for RawIFD {
+    fn from_iter<I: IntoIterator<Item = RawIFDField>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Seeded xorshift generator, for random test inputs that are the same on every run.
+#[cfg(test)]
+pub(crate) struct XorShift(pub(crate) u64);
+
+#[cfg(test)]
+impl XorShift {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number less than `n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::{IfdFieldLike, RawIFD64, RawIFDField64};
+    use std::io::Cursor;
+
+    /// A field of two LONGs, too large to be inline, whose value is at `offset`.
+    fn out_of_line(offset: u32) -> RawIFDField {
+        let mut value_or_offset = [0; 4];
+        LittleEndian::write_u32(&mut value_or_offset, offset);
+        RawIFDField {
+            tag: tags::STRIP_OFFSETS,
+            tag_type: IFD_TYPE_LONG,
+            count: 2,
+            value_or_offset,
+        }
+    }
+
+    fn is_null<T>(result: Result<T, TiffError>) -> bool {
+        matches!(result, Err(TiffError::NullOffset { tag }) if tag == tags::STRIP_OFFSETS)
+    }
+
+    #[test]
+    fn value_is_inline_up_to_four_bytes() {
+        let field = |tag_type, count| RawIFDField {
+            tag_type,
+            count,
+            ..out_of_line(0)
+        };
+        // Exactly four bytes, whether as four BYTEs, two SHORTs or one LONG
+        for (tag_type, count) in [(IFD_TYPE_BYTE, 4), (IFD_TYPE_SHORT, 2), (IFD_TYPE_LONG, 1)] {
+            assert_eq!(field(tag_type, count).value_byte_len(), Some(4));
+            assert_eq!(field(tag_type, count).is_inline(), Some(true));
+        }
+
+        assert_eq!(field(IFD_TYPE_BYTE, 5).value_byte_len(), Some(5));
+        assert_eq!(field(IFD_TYPE_BYTE, 5).is_inline(), Some(false));
+        assert_eq!(field(IFD_TYPE_SHORT, 3).is_inline(), Some(false));
+        assert_eq!(field(IFD_TYPE_LONG, 0).value_byte_len(), Some(0));
+        assert_eq!(field(IFD_TYPE_LONG, 0).is_inline(), Some(true));
+
+        // The size of an unknown type can't be known
+        assert_eq!(field(0x7777, 1).value_byte_len(), None);
+        assert_eq!(field(0x7777, 1).is_inline(), None);
+    }
+
+    #[test]
+    fn bigtiff_value_is_inline_up_to_eight_bytes() {
+        let field = |tag_type, count| RawIFDField64 {
+            tag: tags::STRIP_OFFSETS,
+            tag_type,
+            count,
+            value_or_offset: [0; 8],
+        };
+        assert_eq!(field(IFD_TYPE_LONG, 2).value_byte_len(), Some(8));
+        assert_eq!(field(IFD_TYPE_LONG, 2).is_inline(), Some(true));
+        assert_eq!(field(IFD_TYPE_BYTE, 9).is_inline(), Some(false));
+        assert_eq!(field(IFD_TYPE_LONG, 0).is_inline(), Some(true));
+        assert_eq!(field(0x7777, 1).is_inline(), None);
+    }
+
+    #[test]
+    fn offset_decodes_out_of_line_offset() {
+        let field = out_of_line(16);
+        assert_eq!(field.offset::<LittleEndian>().unwrap(), 16);
+        assert_eq!(field.offset_checked::<LittleEndian>().unwrap(), Some(16));
+        assert_eq!(IfdFieldLike::offset::<LittleEndian>(&field).unwrap(), 16u32);
+    }
+
+    #[test]
+    fn zero_offset_is_rejected() {
+        let field = out_of_line(0);
+        assert!(is_null(field.offset::<LittleEndian>()));
+        assert!(is_null(field.offset_checked::<LittleEndian>()));
+        assert!(is_null(IfdFieldLike::offset::<LittleEndian>(&field)));
+
+        // Reading the value fails rather than returning the header's bytes
+        let mut reader = Cursor::new(vec![0x49, 0x49, 42, 0, 8, 0, 0, 0]);
+        assert!(is_null(
+            field.read_value_bytes::<LittleEndian, _>(&mut reader)
+        ));
+
+        let field64 = RawIFDField64 {
+            tag: tags::STRIP_OFFSETS,
+            tag_type: IFD_TYPE_LONG,
+            count: 4,
+            value_or_offset: [0; 8],
+        };
+        assert!(is_null(IfdFieldLike::offset::<LittleEndian>(&field64)));
+    }
+
+    #[test]
+    fn offset_checked_ignores_inline_and_unknown_values() {
+        let inline =
+            RawIFDField::pack_inline_u16::<LittleEndian>(tags::IMAGE_WIDTH, 3, &[0]).unwrap();
+        assert_eq!(inline.offset_checked::<LittleEndian>().unwrap(), None);
+
+        let unknown = RawIFDField {
+            tag_type: 0x7777,
+            ..out_of_line(0)
+        };
+        assert_eq!(unknown.offset_checked::<LittleEndian>().unwrap(), None);
+    }
+
+    #[test]
+    fn offset_decodes_in_either_byte_order() {
+        let field = RawIFDField {
+            value_or_offset: [0, 0, 1, 2],
+            ..out_of_line(0)
+        };
+        assert_eq!(field.offset::<BigEndian>().unwrap(), 0x0102);
+        assert_eq!(field.offset::<LittleEndian>().unwrap(), 0x0201_0000);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "value of tag 256 is inline")]
+    fn offset_asserts_value_is_out_of_line() {
+        let inline =
+            RawIFDField::pack_inline_u16::<LittleEndian>(tags::IMAGE_WIDTH, 3, &[1, 2]).unwrap();
+        let _ = inline.offset::<LittleEndian>();
+    }
+
+    #[test]
+    fn inline_as_u32_widens_first_inline_value() {
+        let short =
+            RawIFDField::pack_inline_u16::<BigEndian>(tags::IMAGE_WIDTH, 3, &[0x0102]).unwrap();
+        assert_eq!(short.inline_as_u32::<BigEndian>(), Some(0x0102));
+
+        let byte = RawIFDField {
+            tag_type: IFD_TYPE_BYTE,
+            count: 3,
+            value_or_offset: [7, 8, 9, 0],
+            ..out_of_line(0)
+        };
+        assert_eq!(byte.inline_as_u32::<LittleEndian>(), Some(7));
+
+        let long = RawIFDField {
+            count: 1,
+            ..out_of_line(0x0403_0201)
+        };
+        assert_eq!(long.inline_as_u32::<LittleEndian>(), Some(0x0403_0201));
+
+        // Values stored elsewhere, and empty fields, have no inline value
+        assert_eq!(out_of_line(16).inline_as_u32::<LittleEndian>(), None);
+        let empty = RawIFDField {
+            count: 0,
+            ..out_of_line(0)
+        };
+        assert_eq!(empty.inline_as_u32::<LittleEndian>(), None);
+    }
+
+    #[test]
+    fn huge_entry_count_allocates_only_for_entries_read() {
+        // 65535 entries claimed, but only one present
+        let mut buf = vec![0xFF, 0xFF];
+        buf.extend_from_slice(&[0; 18]);
+        match RawIFD::read_from::<LittleEndian, _>(&mut Cursor::new(&buf)) {
+            Err(TiffError::TruncatedIFD {
+                expected_entries: 65535,
+                read_entries: 1,
+                partial,
+            }) => assert!(partial.entries.capacity() <= MAX_PREALLOCATED_ENTRIES),
+            other => panic!("expected TruncatedIFD, got {:?}", other),
+        }
+
+        // A BigTIFF count that no allocation could hold
+        let mut buf = vec![0xFF; 8];
+        buf.extend_from_slice(&[0; 12]);
+        assert!(matches!(
+            RawIFD64::read_from::<LittleEndian, _>(&mut Cursor::new(&buf)),
+            Err(TiffError::UnexpectedEof)
+        ));
+    }
+
+    /// A sorted, duplicate-free IFD of up to 40 fields with arbitrary tags, types (including
+    /// unknown ones), counts and value bytes.
+    fn random_ifd(rng: &mut XorShift) -> RawIFD {
+        let mut tags: Vec<u16> = (0..rng.next() % 41).map(|_| rng.next() as u16).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        let entries = tags
+            .into_iter()
+            .map(|tag| RawIFDField {
+                tag,
+                tag_type: match rng.next() % 4 {
+                    0 => rng.next() as u16,
+                    _ => 1 + (rng.next() % 18) as u16,
+                },
+                count: match rng.next() % 4 {
+                    0 => 0,
+                    1 => 1,
+                    2 => (rng.next() % 8) as u32,
+                    _ => rng.next() as u32,
+                },
+                value_or_offset: (rng.next() as u32).to_ne_bytes(),
+            })
+            .collect();
+        RawIFD { entries }
+    }
+
+    #[test]
+    fn short_values_exclude_trailing_padding() {
+        // Garbage after the meaningful bytes, which must never be read as part of the value
+        let short = RawIFDField {
+            tag_type: IFD_TYPE_SHORT,
+            count: 1,
+            value_or_offset: [0x12, 0x34, 0xAA, 0xBB],
+            ..out_of_line(0)
+        };
+        assert_eq!(short.inline_value_slice(), Some(&[0x12, 0x34][..]));
+        let mut reader = Cursor::new(Vec::new());
+        assert_eq!(
+            short.as_u16_array::<BigEndian, _>(&mut reader).unwrap(),
+            vec![0x1234]
+        );
+        assert_eq!(
+            short.as_u16_array::<LittleEndian, _>(&mut reader).unwrap(),
+            vec![0x3412]
+        );
+        assert_eq!(short.value_bytes::<BigEndian>(&[]).unwrap(), [0x12, 0x34]);
+
+        let bytes = RawIFDField {
+            tag_type: IFD_TYPE_BYTE,
+            count: 2,
+            value_or_offset: [1, 2, 0xAA, 0xBB],
+            ..out_of_line(0)
+        };
+        assert_eq!(bytes.inline_value_slice(), Some(&[1, 2][..]));
+        assert_eq!(
+            bytes.as_u8_array::<LittleEndian, _>(&mut reader).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            bytes
+                .as_unsigned_array::<LittleEndian, _>(&mut reader)
+                .unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(bytes.value_bytes::<LittleEndian>(&[]).unwrap(), [1, 2]);
+
+        assert_eq!(out_of_line(16).inline_value_slice(), None);
+    }
+
+    #[test]
+    fn write_then_read_is_identity() {
+        let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+        for _ in 0..5000 {
+            let ifd = random_ifd(&mut rng);
+            assert!(ifd.validate().is_ok());
+
+            let mut buf = Vec::new();
+            ifd.write_to_le(&mut buf).unwrap();
+            assert_eq!(buf.len(), 2 + ifd.entries.len() * 12);
+            let read = RawIFD::read_from_validated::<LittleEndian, _>(&mut Cursor::new(&buf));
+            assert_eq!(read.unwrap(), ifd);
+
+            let mut buf = Vec::new();
+            ifd.write_to_be(&mut buf).unwrap();
+            let read = RawIFD::read_from_validated::<BigEndian, _>(&mut Cursor::new(&buf));
+            assert_eq!(read.unwrap(), ifd);
+        }
+    }
+
+    #[test]
+    fn write_sorted_then_read_restores_order() {
+        let mut rng = XorShift(0xD1B5_4A32_D192_ED03);
+        for _ in 0..1000 {
+            let ifd = random_ifd(&mut rng);
+            let mut shuffled = ifd.clone();
+            // Fisher-Yates, so every order of the fields is tried
+            for idx in (1..shuffled.entries.len()).rev() {
+                let other = (rng.next() % (idx as u64 + 1)) as usize;
+                shuffled.entries.swap(idx, other);
+            }
+
+            let mut buf = Vec::new();
+            shuffled
+                .write_to_sorted::<LittleEndian, _>(&mut buf)
+                .unwrap();
+            let read = RawIFD::read_from_validated::<LittleEndian, _>(&mut Cursor::new(&buf));
+            assert_eq!(read.unwrap(), ifd);
+        }
+    }
+
+    #[test]
+    fn diff_matches_fields_by_tag_in_any_order() {
+        let short = |tag, value| {
+            RawIFDField::pack_inline_u16::<LittleEndian>(tag, IFD_TYPE_SHORT, &[value]).unwrap()
+        };
+        let first = RawIFD {
+            entries: vec![
+                short(tags::IMAGE_WIDTH, 16),
+                short(tags::IMAGE_LENGTH, 8),
+                short(tags::COMPRESSION, 1),
+            ],
+        };
+        let reordered = RawIFD {
+            entries: first.entries.iter().rev().copied().collect(),
+        };
+        assert_eq!(first.diff(&reordered), []);
+
+        let second = RawIFD {
+            entries: vec![
+                short(tags::PHOTOMETRIC_INTERPRETATION, 1),
+                short(tags::COMPRESSION, 5),
+                short(tags::IMAGE_WIDTH, 16),
+            ],
+        };
+        assert_eq!(
+            first.diff(&second),
+            [
+                IfdDiff::TagOnlyInSelf(tags::IMAGE_LENGTH),
+                IfdDiff::ValueDiffers {
+                    tag: tags::COMPRESSION,
+                    self_entry: short(tags::COMPRESSION, 1),
+                    other_entry: short(tags::COMPRESSION, 5),
+                },
+                IfdDiff::TagOnlyInOther(tags::PHOTOMETRIC_INTERPRETATION),
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_round_trip() {
+        let ifd = RawIFD {
+            entries: vec![
+                RawIFDField {
+                    tag: tags::IMAGE_WIDTH,
+                    tag_type: IFD_TYPE_SHORT,
+                    count: 1,
+                    value_or_offset: [16, 0, 0, 0],
+                },
+                // A type this crate doesn't know survives as its number
+                RawIFDField {
+                    tag: 0xC000,
+                    tag_type: 99,
+                    count: 4,
+                    value_or_offset: [1, 2, 3, 255],
+                },
+            ],
+        };
+        let json = serde_json::to_string(&ifd).unwrap();
+        assert_eq!(
+            json,
+            r#"{"entries":[{"tag":256,"tag_type":3,"count":1,"value_or_offset":[16,0,0,0]},"#
+                .to_owned()
+                + r#"{"tag":49152,"tag_type":99,"count":4,"value_or_offset":[1,2,3,255]}]}"#
+        );
+        assert_eq!(serde_json::from_str::<RawIFD>(&json).unwrap(), ifd);
+    }
 }
@@ -0,0 +1,123 @@
+#[cfg(feature = "std")]
+use crate::errors::{IFDError, TiffError};
+#[cfg(feature = "std")]
+use crate::lowlevel::raw_ifd::MAX_PREALLOCATED_ENTRIES;
+use crate::lowlevel::tag_type::TagType;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "std")]
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::io::Error;
+
+/// A struct representing a low-level BigTIFF IFD value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawIFDField64 {
+    /// Tag ID.
+    pub tag: u16,
+
+    /// Tag data type.
+    pub tag_type: u16,
+
+    /// Quantity (not byte count) of data in the field.
+    pub count: u64,
+
+    /// Field representing either the value of the tag (if it is small enough)
+    /// or the file offset of the tag's data.
+    pub value_or_offset: [u8; 8],
+}
+
+impl RawIFDField64 {
+    /// Read the field value from `reader`.
+    #[cfg(feature = "std")]
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
+        Ok(Self {
+            tag: reader.read_u16::<E>()?,
+            tag_type: reader.read_u16::<E>()?,
+            count: reader.read_u64::<E>()?,
+            value_or_offset: {
+                let mut buffer = [0; 8];
+                reader.read_exact(&mut buffer)?;
+                buffer
+            },
+        })
+    }
+
+    /// Write the field value to `writer`.
+    #[cfg(feature = "std")]
+    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u16::<E>(self.tag)?;
+        writer.write_u16::<E>(self.tag_type)?;
+        writer.write_u64::<E>(self.count)?;
+        writer.write_all(&self.value_or_offset)
+    }
+
+    /// Number of bytes occupied by this field's value (`count` times the size of `tag_type`),
+    /// or `None` if the type is unrecognized.
+    pub fn value_byte_len(&self) -> Option<u64> {
+        let unit_size = TagType::from_u16(self.tag_type)?.byte_size();
+        self.count.checked_mul(unit_size as u64)
+    }
+
+    /// Whether this field's value is stored inline within `value_or_offset`, rather than at the
+    /// file offset it contains. Returns `None` if the type is unrecognized.
+    pub fn is_inline(&self) -> Option<bool> {
+        self.value_byte_len()
+            .map(|len| len <= self.value_or_offset.len() as u64)
+    }
+}
+
+/// A struct representing a low-level BigTIFF IFD, compared in the same way as a `RawIFD`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawIFD64 {
+    pub entries: Vec<RawIFDField64>,
+}
+
+impl RawIFD64 {
+    /// Read an entire IFD from `reader` excluding the offset to the next IFD.
+    #[cfg(feature = "std")]
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, TiffError> {
+        Self::read_from_limited::<E, R>(reader, usize::MAX)
+    }
+
+    /// Read an entire IFD from `reader` as `read_from` does, but fail without reading any entries
+    /// if the IFD claims to have more than `max_entries`.
+    #[cfg(feature = "std")]
+    pub fn read_from_limited<E: ByteOrder, R: ReadBytesExt>(
+        reader: &mut R,
+        max_entries: usize,
+    ) -> Result<Self, TiffError> {
+        // Read length header, which may not even fit in a usize
+        let field_count = reader.read_u64::<E>()?;
+        if field_count > max_entries as u64 {
+            return Err(IFDError::TooManyEntries {
+                count: usize::try_from(field_count).unwrap_or(usize::MAX),
+                limit: max_entries,
+            }
+            .into());
+        }
+        let field_count = field_count as usize;
+
+        // Read entries, not trusting the count for how much to allocate up front
+        let mut entries = Vec::with_capacity(field_count.min(MAX_PREALLOCATED_ENTRIES));
+        for _ in 0..field_count {
+            entries.push(RawIFDField64::read_from::<E, R>(reader)?);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Write an entire IFD to `writer` excluding the offset to the next IFD.
+    #[cfg(feature = "std")]
+    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), Error> {
+        // Write length header
+        writer.write_u64::<E>(self.entries.len() as u64)?;
+
+        // Write entries
+        for field in &self.entries {
+            field.write_to::<E, W>(writer)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::errors::TiffError;
+use std::io::{self, BufRead, Cursor, Read, Seek, SeekFrom};
+
+/// A whole file held in memory, so that it can be parsed by the seek-based readers such as
+/// `read_raw_ifds` when it only arrives as a `Read`, like the output of a decompressor. The
+/// entire stream is read up front, so the memory used is the full length of the file; callers
+/// able to seek the underlying source should do so rather than buffering it.
+#[derive(Debug, Clone, Default)]
+pub struct SeekableBuffer {
+    cursor: Cursor<Vec<u8>>,
+}
+
+impl SeekableBuffer {
+    /// Read everything remaining in `reader` into memory, with the cursor at the beginning.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, TiffError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(Self::from(bytes))
+    }
+
+    /// Length in bytes of the buffered file.
+    pub fn len(&self) -> usize {
+        self.cursor.get_ref().len()
+    }
+
+    /// Returns true if the buffered file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.cursor.get_ref().is_empty()
+    }
+
+    /// The bytes of the buffered file.
+    pub fn as_slice(&self) -> &[u8] {
+        self.cursor.get_ref()
+    }
+
+    /// Return the bytes of the buffered file.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.cursor.into_inner()
+    }
+}
+
+impl From<Vec<u8>> for SeekableBuffer {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self {
+            cursor: Cursor::new(bytes),
+        }
+    }
+}
+
+impl Read for SeekableBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.cursor.read(buf)
+    }
+}
+
+impl BufRead for SeekableBuffer {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.cursor.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.cursor.consume(amt)
+    }
+}
+
+impl Seek for SeekableBuffer {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
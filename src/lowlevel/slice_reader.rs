@@ -0,0 +1,151 @@
+use crate::errors::{IFDError, TiffError};
+use crate::lowlevel::constants::header_magic::{
+    BIGTIFF_OFFSET_BYTESIZE, BIGTIFF_VERSION_MAGIC, BIG_ENDIAN_MAGIC, LITTLE_ENDIAN_MAGIC,
+    VERSION_MAGIC,
+};
+use crate::lowlevel::{
+    header::{Endianness, TiffHeader},
+    raw_ifd::{RawIFD, RawIFDField, HEADER_SIZE},
+};
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::BTreeSet, vec::Vec};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+/// Size of a single field within an IFD.
+const FIELD_SIZE: usize = 12;
+
+impl TiffHeader {
+    /// Parse the byte-order mark, magic number and first IFD offset from the start of `buf`, as
+    /// `read_from` does.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, TiffError> {
+        let endianness = match slice_at(buf, 0, 2)? {
+            magic if magic == LITTLE_ENDIAN_MAGIC => Endianness::Little,
+            magic if magic == BIG_ENDIAN_MAGIC => Endianness::Big,
+            culprit => {
+                return Err(TiffError::BadEndianMagic {
+                    culprit: [culprit[0], culprit[1]],
+                })
+            }
+        };
+        let (is_bigtiff, first_ifd_offset) = match endianness {
+            Endianness::Little => header_version_from_bytes::<LittleEndian>(buf)?,
+            Endianness::Big => header_version_from_bytes::<BigEndian>(buf)?,
+        };
+
+        let header = Self {
+            endianness,
+            is_bigtiff,
+            first_ifd_offset,
+        };
+        header.check_first_ifd_offset()?;
+        Ok(header)
+    }
+}
+
+/// Parse the magic number, and for BigTIFF the offset size, followed by the first IFD offset.
+/// Returns whether the file is a BigTIFF, and the first IFD offset.
+fn header_version_from_bytes<E: ByteOrder>(buf: &[u8]) -> Result<(bool, u64), TiffError> {
+    match E::read_u16(slice_at(buf, 2, 2)?) {
+        VERSION_MAGIC => Ok((false, E::read_u32(slice_at(buf, 4, 4)?).into())),
+        BIGTIFF_VERSION_MAGIC => {
+            let size = E::read_u16(slice_at(buf, 4, 2)?);
+            if size != BIGTIFF_OFFSET_BYTESIZE {
+                return Err(TiffError::BadBigTiffOffsetSize { size });
+            }
+            Ok((true, E::read_u64(slice_at(buf, 8, 8)?)))
+        }
+        magic => Err(TiffError::BadMagic { magic }),
+    }
+}
+
+impl RawIFDField {
+    /// Parse a field from the 12 bytes at `offset` in `buf`.
+    pub fn from_bytes<E: ByteOrder>(buf: &[u8], offset: usize) -> Result<Self, TiffError> {
+        let bytes = slice_at(buf, offset, FIELD_SIZE)?;
+        let mut value_or_offset = [0; 4];
+        value_or_offset.copy_from_slice(&bytes[8..]);
+        Ok(Self {
+            tag: E::read_u16(&bytes[..2]),
+            tag_type: E::read_u16(&bytes[2..4]),
+            count: E::read_u32(&bytes[4..8]),
+            value_or_offset,
+        })
+    }
+
+    /// Borrow the bytes of this field's value, either from `value_or_offset` if the value is
+    /// inline, or from `buf` (the whole file) at the offset it contains. A field with a count of
+    /// zero has an empty value, whatever its type.
+    pub fn value_bytes<'a, E: ByteOrder>(&'a self, buf: &'a [u8]) -> Result<&'a [u8], TiffError> {
+        if self.count == 0 {
+            return Ok(&[]);
+        }
+        let len = self.value_byte_len().ok_or(TiffError::UnknownTagType {
+            tag_type: self.tag_type,
+        })?;
+
+        match self.inline_value_slice() {
+            Some(value) => Ok(value),
+            None => slice_at(buf, self.offset::<E>()? as usize, len),
+        }
+    }
+}
+
+impl RawIFD {
+    /// Parse an entire IFD at `offset` in `buf`, excluding the offset to the next IFD. Fails with
+    /// `TiffError::TruncatedIFD`, holding the entries present, if `buf` ends partway through.
+    pub fn from_bytes<E: ByteOrder>(buf: &[u8], offset: usize) -> Result<Self, TiffError> {
+        let field_count = E::read_u16(slice_at(buf, offset, 2)?) as usize;
+
+        // Only parse the entries actually present, so a bogus count can't cause a huge allocation
+        let available = (buf.len() - offset - 2) / FIELD_SIZE;
+        let entries = (0..field_count.min(available))
+            .map(|idx| RawIFDField::from_bytes::<E>(buf, offset + 2 + idx * FIELD_SIZE))
+            .collect::<Result<Vec<_>, _>>()?;
+        if entries.len() < field_count {
+            return Err(TiffError::TruncatedIFD {
+                expected_entries: field_count,
+                read_entries: entries.len(),
+                partial: Self { entries },
+            });
+        }
+        Ok(Self { entries })
+    }
+}
+
+/// Parse all IFDs in `buf` (the whole file), following the chain of 'next IFD' pointers beginning
+/// at `first_ifd_offset` (as found in the `TiffHeader`). Parsing stops at the first pointer to an
+/// already-parsed IFD, so a looping chain terminates.
+pub fn read_raw_ifds_bytes<E: ByteOrder>(
+    buf: &[u8],
+    first_ifd_offset: usize,
+) -> Result<Box<[RawIFD]>, TiffError> {
+    let mut ifds = Vec::new();
+    let mut pointers_encountered = BTreeSet::new(); // Break if a loop is found within the IFD pointers
+    let mut next_ifd_offset = first_ifd_offset;
+    while next_ifd_offset != 0 && pointers_encountered.insert(next_ifd_offset) {
+        if next_ifd_offset < HEADER_SIZE as usize {
+            return Err(IFDError::OffsetInHeader {
+                offset: next_ifd_offset as u64,
+            }
+            .into());
+        }
+        let ifd = RawIFD::from_bytes::<E>(buf, next_ifd_offset)?;
+        let next_pointer_offset = next_ifd_offset + 2 + ifd.len() * FIELD_SIZE;
+        next_ifd_offset = E::read_u32(slice_at(buf, next_pointer_offset, 4)?) as usize;
+        ifds.push(ifd);
+    }
+    Ok(ifds.into_boxed_slice())
+}
+
+/// Borrow `len` bytes at `offset` in `buf`, failing if they run past its end.
+fn slice_at(buf: &[u8], offset: usize, len: usize) -> Result<&[u8], TiffError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| buf.get(offset..end))
+        .ok_or(TiffError::OffsetOutOfBounds {
+            offset: offset as u64,
+            file_len: buf.len() as u64,
+        })
+}
@@ -0,0 +1,160 @@
+use crate::lowlevel::constants::ifd_field_type_magic::*;
+use crate::lowlevel::{raw_ifd::RawIFDField, raw_ifd64::RawIFDField64};
+
+/// The data type of an IFD field, as stored in its `tag_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagType {
+    Byte,
+    Ascii,
+    Short,
+    Long,
+    Rational,
+    SByte,
+    Undefined,
+    SShort,
+    SLong,
+    SRational,
+    Float,
+    Double,
+    /// Offset to a sub-IFD, otherwise identical to `Long`
+    IFD,
+    /// BigTIFF only
+    Long8,
+    /// BigTIFF only
+    SLong8,
+    /// BigTIFF only
+    IFD8,
+}
+
+impl TagType {
+    /// Interpret a `tag_type` integer, returning `None` if it is unrecognized.
+    pub fn from_u16(tag_type: u16) -> Option<Self> {
+        Some(match tag_type {
+            IFD_TYPE_BYTE => TagType::Byte,
+            IFD_TYPE_ASCII => TagType::Ascii,
+            IFD_TYPE_SHORT => TagType::Short,
+            IFD_TYPE_LONG => TagType::Long,
+            IFD_TYPE_RATIONAL => TagType::Rational,
+            IFD_TYPE_SBYTE => TagType::SByte,
+            IFD_TYPE_UNDEFINED => TagType::Undefined,
+            IFD_TYPE_SSHORT => TagType::SShort,
+            IFD_TYPE_SLONG => TagType::SLong,
+            IFD_TYPE_SRATIONAL => TagType::SRational,
+            IFD_TYPE_FLOAT => TagType::Float,
+            IFD_TYPE_DOUBLE => TagType::Double,
+            IFD_TYPE_IFD => TagType::IFD,
+            IFD_TYPE_LONG8 => TagType::Long8,
+            IFD_TYPE_SLONG8 => TagType::SLong8,
+            IFD_TYPE_IFD8 => TagType::IFD8,
+            _ => return None,
+        })
+    }
+
+    /// The `tag_type` integer for this type.
+    pub fn as_u16(self) -> u16 {
+        match self {
+            TagType::Byte => IFD_TYPE_BYTE,
+            TagType::Ascii => IFD_TYPE_ASCII,
+            TagType::Short => IFD_TYPE_SHORT,
+            TagType::Long => IFD_TYPE_LONG,
+            TagType::Rational => IFD_TYPE_RATIONAL,
+            TagType::SByte => IFD_TYPE_SBYTE,
+            TagType::Undefined => IFD_TYPE_UNDEFINED,
+            TagType::SShort => IFD_TYPE_SSHORT,
+            TagType::SLong => IFD_TYPE_SLONG,
+            TagType::SRational => IFD_TYPE_SRATIONAL,
+            TagType::Float => IFD_TYPE_FLOAT,
+            TagType::Double => IFD_TYPE_DOUBLE,
+            TagType::IFD => IFD_TYPE_IFD,
+            TagType::Long8 => IFD_TYPE_LONG8,
+            TagType::SLong8 => IFD_TYPE_SLONG8,
+            TagType::IFD8 => IFD_TYPE_IFD8,
+        }
+    }
+
+    /// The name of this type as written in the TIFF specification, such as `SHORT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TagType::Byte => "BYTE",
+            TagType::Ascii => "ASCII",
+            TagType::Short => "SHORT",
+            TagType::Long => "LONG",
+            TagType::Rational => "RATIONAL",
+            TagType::SByte => "SBYTE",
+            TagType::Undefined => "UNDEFINED",
+            TagType::SShort => "SSHORT",
+            TagType::SLong => "SLONG",
+            TagType::SRational => "SRATIONAL",
+            TagType::Float => "FLOAT",
+            TagType::Double => "DOUBLE",
+            TagType::IFD => "IFD",
+            TagType::Long8 => "LONG8",
+            TagType::SLong8 => "SLONG8",
+            TagType::IFD8 => "IFD8",
+        }
+    }
+
+    /// Size in bytes of a single unit (not the whole field) of this type.
+    pub fn byte_size(self) -> usize {
+        match self {
+            TagType::Byte | TagType::Ascii | TagType::SByte | TagType::Undefined => 1,
+            TagType::Short | TagType::SShort => 2,
+            TagType::Long | TagType::SLong | TagType::Float | TagType::IFD => 4,
+            TagType::Rational | TagType::SRational | TagType::Double => 8,
+            TagType::Long8 | TagType::SLong8 | TagType::IFD8 => 8,
+        }
+    }
+
+    /// Returns true for the integer types, signed or unsigned.
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            TagType::Byte
+                | TagType::Short
+                | TagType::Long
+                | TagType::Long8
+                | TagType::SByte
+                | TagType::SShort
+                | TagType::SLong
+                | TagType::SLong8
+                | TagType::IFD
+                | TagType::IFD8
+        )
+    }
+
+    /// Returns true for the signed types.
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            TagType::SByte
+                | TagType::SShort
+                | TagType::SLong
+                | TagType::SLong8
+                | TagType::SRational
+                | TagType::Float
+                | TagType::Double
+        )
+    }
+
+    /// Returns true for the rational and floating-point types.
+    pub fn is_fractional(self) -> bool {
+        matches!(
+            self,
+            TagType::Rational | TagType::SRational | TagType::Float | TagType::Double
+        )
+    }
+}
+
+impl RawIFDField {
+    /// Interpret this field's `tag_type`, returning `None` if it is unrecognized.
+    pub fn tag_type_enum(&self) -> Option<TagType> {
+        TagType::from_u16(self.tag_type)
+    }
+}
+
+impl RawIFDField64 {
+    /// Interpret this field's `tag_type`, returning `None` if it is unrecognized.
+    pub fn tag_type_enum(&self) -> Option<TagType> {
+        TagType::from_u16(self.tag_type)
+    }
+}
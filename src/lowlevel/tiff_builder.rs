@@ -0,0 +1,160 @@
+use crate::baseline::tags;
+use crate::errors::TiffError;
+use crate::lowlevel::{
+    header::Endianness,
+    ifd::IFD,
+    ifd_field::IFDField,
+    metadata_writer::classic_offset,
+    raw_ifd::{RawIFD, RawIFDField},
+    tiff_writer::TiffWriter,
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// Builds a complete classic TIFF file in memory from pages of typed fields and image data,
+/// laying out the header, the chain of IFDs and all of their data as `TiffWriter` does.
+#[derive(Debug, Clone, Default)]
+pub struct TiffBuilder {
+    pages: Vec<Page>,
+}
+
+/// The fields and strips of image data of a page added to a `TiffBuilder`.
+#[derive(Debug, Clone)]
+struct Page {
+    ifd: IFD,
+    strips: Vec<Vec<u8>>,
+}
+
+impl TiffBuilder {
+    /// Create a builder with no pages.
+    pub fn new() -> Self {
+        Self { pages: Vec::new() }
+    }
+
+    /// Append a page with the fields of `ifd`, and the image data in `strips`, in order. Unless
+    /// `strips` is empty, the StripOffsets and StripByteCounts fields are set to locate them,
+    /// replacing any in `ifd`; the other fields describing the image, such as ImageWidth and
+    /// RowsPerStrip, are written as given.
+    pub fn add_page(&mut self, ifd: IFD, strips: Vec<Vec<u8>>) -> &mut Self {
+        self.pages.push(Page { ifd, strips });
+        self
+    }
+
+    /// Number of pages added so far.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Encode the file in little-endian byte order, as `build` does.
+    pub fn build_le(&self) -> Result<Vec<u8>, TiffError> {
+        self.build(Endianness::Little)
+    }
+
+    /// Encode the file in big-endian byte order, as `build` does.
+    pub fn build_be(&self) -> Result<Vec<u8>, TiffError> {
+        self.build(Endianness::Big)
+    }
+
+    /// Encode the file in the byte order `endianness`, returning its bytes. Each page's strips
+    /// are written just before its IFD. Fields of unrecognized type are written verbatim. Fails
+    /// if no pages were added, or the file would be larger than a classic TIFF can address.
+    pub fn build(&self, endianness: Endianness) -> Result<Vec<u8>, TiffError> {
+        let mut writer = TiffWriter::write_header(Cursor::new(Vec::new()), endianness)?;
+        for page in &self.pages {
+            let mut ifd = page.ifd.clone();
+            if !page.strips.is_empty() {
+                let mut offsets = Vec::with_capacity(page.strips.len());
+                let mut byte_counts = Vec::with_capacity(page.strips.len());
+                for strip in &page.strips {
+                    let position = writer.write_data(strip)?;
+                    offsets.push(classic_offset(position)?);
+                    // The whole strip must lie within reach of a classic offset
+                    classic_offset(position + strip.len() as u64)?;
+                    byte_counts.push(strip.len() as u32);
+                }
+                ifd.entries
+                    .insert(tags::STRIP_OFFSETS, IFDField::Long(offsets.into()));
+                ifd.entries
+                    .insert(tags::STRIP_BYTE_COUNTS, IFDField::Long(byte_counts.into()));
+            }
+
+            let (raw_ifd, field_data) = match endianness {
+                Endianness::Little => encode_fields::<LittleEndian>(&ifd)?,
+                Endianness::Big => encode_fields::<BigEndian>(&ifd)?,
+            };
+            writer.add_ifd(&raw_ifd, &field_data)?;
+        }
+        Ok(writer.finish()?.into_inner())
+    }
+}
+
+/// Encode the fields of `ifd` in the byte order `E`, as a RawIFD along with the value of each
+/// field keyed by tag, ready for `TiffWriter::add_ifd` to place.
+fn encode_fields<E: ByteOrder>(ifd: &IFD) -> Result<(RawIFD, HashMap<u16, Vec<u8>>), TiffError> {
+    let mut entries = Vec::with_capacity(ifd.entries.len());
+    let mut field_data = HashMap::new();
+    for (&tag, field) in &ifd.entries {
+        let mut raw_field = RawIFDField {
+            tag,
+            tag_type: field.type_number(),
+            count: field.count() as u32,
+            value_or_offset: [0; 4],
+        };
+        match field {
+            IFDField::Unrecognized {
+                value_or_offset, ..
+            } => raw_field.value_or_offset = *value_or_offset,
+            _ => {
+                let mut value = Cursor::new(Vec::new());
+                field.write_field_into::<E, _>(&mut value)?;
+                field_data.insert(tag, value.into_inner());
+            }
+        }
+        entries.push(raw_field);
+    }
+    Ok((RawIFD { entries }, field_data))
+}
+
+/// The fields of an uncompressed image `width` by `height` pixels in a single strip, with one
+/// sample per entry of `bits`, each of that many bits, and `photometric` as its
+/// PhotometricInterpretation. Tests add to or remove from them before `single_page_file`.
+#[cfg(test)]
+pub(crate) fn image_ifd(width: u32, height: u32, bits: &[u16], photometric: u16) -> IFD {
+    let mut ifd = IFD::new();
+    ifd.entries.extend([
+        (tags::IMAGE_WIDTH, IFDField::Long(vec![width].into())),
+        (tags::IMAGE_LENGTH, IFDField::Long(vec![height].into())),
+        (tags::BITS_PER_SAMPLE, IFDField::Short(bits.to_vec().into())),
+        (tags::COMPRESSION, IFDField::Short(vec![1].into())),
+        (
+            tags::PHOTOMETRIC_INTERPRETATION,
+            IFDField::Short(vec![photometric].into()),
+        ),
+        (
+            tags::SAMPLES_PER_PIXEL,
+            IFDField::Short(vec![bits.len() as u16].into()),
+        ),
+        (tags::ROWS_PER_STRIP, IFDField::Long(vec![height].into())),
+    ]);
+    ifd
+}
+
+/// A file in the byte order `endianness` whose only page has the fields of `ifd` and the image
+/// data in `strips`, built by `TiffBuilder`, along with that page's IFD as read back from it.
+#[cfg(test)]
+pub(crate) fn single_page_file(
+    ifd: IFD,
+    strips: Vec<Vec<u8>>,
+    endianness: Endianness,
+) -> (Vec<u8>, RawIFD) {
+    use crate::lowlevel::metadata_reader::read_ifds_auto;
+
+    let buf = TiffBuilder::new()
+        .add_page(ifd, strips)
+        .build(endianness)
+        .unwrap();
+    let (_, ifds) = read_ifds_auto(&mut Cursor::new(&buf)).unwrap();
+    let ifd = ifds.into_classic().unwrap()[0].clone();
+    (buf, ifd)
+}
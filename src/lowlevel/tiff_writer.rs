@@ -0,0 +1,171 @@
+use crate::errors::{IFDError, TiffError};
+use crate::lowlevel::{header::Endianness, metadata_writer::MetadataWriter, raw_ifd::RawIFD};
+use byteorder::{BigEndian, LittleEndian};
+use std::collections::HashMap;
+use std::io::{Seek, SeekFrom, Write};
+
+/// Writes a complete classic TIFF file: the header, followed by a chain of IFDs each laid out
+/// with its field data.
+pub struct TiffWriter<W: Write + Seek> {
+    writer: W,
+    metadata: Metadata,
+    options: WriterOptions,
+    ifd_count: usize,
+}
+
+/// Choices about how a `TiffWriter` lays out a file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WriterOptions {
+    /// Begin each IFD, each out-of-line field value and each piece of data from `write_data` on a
+    /// word (even byte) boundary, as TIFF requires, by inserting a single zero byte before any
+    /// that would otherwise begin at an odd offset. If unset, everything is packed tightly with
+    /// no padding, which some minimal encoders do and lenient readers accept.
+    pub align_values: bool,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        Self { align_values: true }
+    }
+}
+
+/// A `MetadataWriter` in the byte order chosen when the header was written.
+enum Metadata {
+    Little(MetadataWriter<LittleEndian>),
+    Big(MetadataWriter<BigEndian>),
+}
+
+impl<W: Write + Seek> TiffWriter<W> {
+    /// Create a new TiffWriter, writing a header in the byte order `endianness` to `writer`.
+    /// Note: Assumes the cursor is at the beginning of the new file.
+    pub fn write_header(writer: W, endianness: Endianness) -> Result<Self, TiffError> {
+        Self::write_header_with_options(writer, endianness, WriterOptions::default())
+    }
+
+    /// Create a new TiffWriter, as for `write_header`, which lays out the file according to
+    /// `options`.
+    pub fn write_header_with_options(
+        mut writer: W,
+        endianness: Endianness,
+        options: WriterOptions,
+    ) -> Result<Self, TiffError> {
+        let metadata = match endianness {
+            Endianness::Little => Metadata::Little(MetadataWriter::write_header(&mut writer)?),
+            Endianness::Big => Metadata::Big(MetadataWriter::write_header(&mut writer)?),
+        };
+        Ok(Self {
+            writer,
+            metadata,
+            options,
+            ifd_count: 0,
+        })
+    }
+
+    /// Append an IFD to the file, along with the data for its fields, keyed by tag in
+    /// `field_data`, as for `MetadataWriter::write_raw_ifd_with_data`. The fields are sorted by
+    /// tag, as TIFF requires. Fails if a tag appears more than once. Returns the position within
+    /// the file of the beginning of the IFD.
+    pub fn add_ifd(
+        &mut self,
+        raw_ifd: &RawIFD,
+        field_data: &HashMap<u16, Vec<u8>>,
+    ) -> Result<u64, TiffError> {
+        let mut sorted = raw_ifd.clone();
+        sorted.sort_entries();
+        sorted.validate()?;
+
+        let position = match &mut self.metadata {
+            Metadata::Little(metadata) => metadata.write_raw_ifd_with_options(
+                &sorted,
+                field_data,
+                self.options,
+                &mut self.writer,
+            )?,
+            Metadata::Big(metadata) => metadata.write_raw_ifd_with_options(
+                &sorted,
+                field_data,
+                self.options,
+                &mut self.writer,
+            )?,
+        };
+        self.ifd_count += 1;
+        Ok(position)
+    }
+
+    /// Write `data` not belonging to any field, such as a strip of image data, at the next word
+    /// boundary, or immediately if `WriterOptions::align_values` is unset. Returns its position
+    /// within the file, for use in fields such as StripOffsets.
+    pub fn write_data(&mut self, data: &[u8]) -> Result<u64, TiffError> {
+        let mut position = self.writer.stream_position()?;
+        if self.options.align_values && position % 2 != 0 {
+            self.writer.write_all(&[0])?;
+            position += 1;
+        }
+        self.writer.write_all(data)?;
+        Ok(position)
+    }
+
+    /// Finish the file, leaving the cursor at its end and returning the underlying writer. The
+    /// last IFD written terminates the chain. Fails if no IFDs were added, as every TIFF must
+    /// have at least one.
+    pub fn finish(mut self) -> Result<W, TiffError> {
+        if self.ifd_count == 0 {
+            return Err(IFDError::NoIFDs.into());
+        }
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::tags;
+    use crate::lowlevel::RawIFDFieldBuilder;
+    use std::io::Cursor;
+
+    /// Write a piece of data of odd length, one of even length and then an IFD with `options`,
+    /// returning the position of each and the file.
+    fn write_odd_lengths(options: WriterOptions) -> ([u64; 3], Vec<u8>) {
+        let mut writer = TiffWriter::write_header_with_options(
+            Cursor::new(Vec::new()),
+            Endianness::Little,
+            options,
+        )
+        .unwrap();
+        let first = writer.write_data(&[1, 2, 3]).unwrap();
+        let second = writer.write_data(&[4, 5]).unwrap();
+
+        let mut ifd = RawIFD {
+            entries: Vec::new(),
+        };
+        let mut field_data = HashMap::new();
+        RawIFDFieldBuilder::<LittleEndian>::short(tags::IMAGE_WIDTH, &[1])
+            .unwrap()
+            .set_into(&mut ifd, &mut field_data);
+        let ifd_position = writer.add_ifd(&ifd, &field_data).unwrap();
+        let buf = writer.finish().unwrap().into_inner();
+        ([first, second, ifd_position], buf)
+    }
+
+    #[test]
+    fn aligns_values_to_even_offsets_by_default() {
+        let (positions, buf) = write_odd_lengths(WriterOptions::default());
+        assert_eq!(positions, [8, 12, 14]);
+        // The padding byte is zero
+        assert_eq!(&buf[8..14], &[1, 2, 3, 0, 4, 5]);
+    }
+
+    #[test]
+    fn packs_values_tightly_without_alignment() {
+        let options = WriterOptions {
+            align_values: false,
+        };
+        let (positions, buf) = write_odd_lengths(options);
+        assert_eq!(positions, [8, 11, 13]);
+        assert_eq!(&buf[8..13], &[1, 2, 3, 4, 5]);
+        // The header points at the unaligned IFD
+        assert_eq!(&buf[4..8], &[13, 0, 0, 0]);
+    }
+}